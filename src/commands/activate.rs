@@ -2,15 +2,14 @@
 //!
 //! Enter the Nix development shell for the current flake.
 
-use crate::commands::profile_cache::profile_cache_inputs;
+use crate::commands::profile_cache::{
+    profile_cache_is_fresh, profile_cache_path, profile_cache_stamp_path,
+};
 use anyhow::{Context, Result};
 use colored::Colorize;
-use flk::flake::parsers::utils::resolve_profile;
+use flk::flake::parsers::utils::resolve_profile_ref;
+use std::env;
 use std::process::Command;
-use std::{
-    env, fs,
-    path::{Path, PathBuf},
-};
 
 /// Enter the Nix development shell for the resolved profile.
 ///
@@ -18,11 +17,14 @@ use std::{
 /// files are unchanged, and refreshes that cache when the environment
 /// definition changes.
 ///
+/// The profile may be a combination such as `rust+python`, which enters the
+/// merged shell generated for those profiles.
+///
 /// # Arguments
 ///
-/// * `current_profile` - Optional profile override
+/// * `current_profile` - Optional profile override or combination
 pub fn run_activate(current_profile: Option<String>) -> Result<()> {
-    let profile = resolve_profile(current_profile)?;
+    let profile = resolve_profile_ref(current_profile)?;
 
     println!(
         "Activating nix develop shell with profile: {}.",
@@ -81,53 +83,3 @@ pub fn run_activate(current_profile: Option<String>) -> Result<()> {
         ))
     }
 }
-
-fn profile_cache_path(profile: &str) -> PathBuf {
-    Path::new(".flk").join(format!(".nix-profile-{profile}"))
-}
-
-fn profile_cache_stamp_path(profile: &str) -> PathBuf {
-    Path::new(".flk").join(format!(".nix-profile-{profile}.stamp"))
-}
-
-fn profile_cache_is_fresh(profile: &str, profile_path: &Path, stamp_path: &Path) -> Result<bool> {
-    if !profile_path.exists() || !stamp_path.exists() {
-        return Ok(false);
-    }
-
-    let stamp_modified = fs::metadata(stamp_path)
-        .with_context(|| {
-            format!(
-                "Failed to read metadata for profile cache stamp '{}'",
-                stamp_path.display()
-            )
-        })?
-        .modified()
-        .with_context(|| {
-            format!(
-                "Failed to read modification time for profile cache stamp '{}'",
-                stamp_path.display()
-            )
-        })?;
-
-    for path in profile_cache_inputs(profile) {
-        if !path.exists() {
-            continue;
-        }
-
-        let modified = fs::metadata(&path)
-            .with_context(|| format!("Failed to read metadata for '{}'", path.display()))?
-            .modified()
-            .with_context(|| {
-                format!("Failed to read modification time for '{}'", path.display())
-            })?;
-
-        // Treat equal mtimes as fresh — the stamp is written after a successful
-        // build, so inputs touched in the same clock tick were already incorporated.
-        if modified > stamp_modified {
-            return Ok(false);
-        }
-    }
-
-    Ok(true)
-}
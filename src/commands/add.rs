@@ -8,6 +8,7 @@ use flk::flake::parsers::overlays::add_pinned_package;
 use std::{fs, path};
 
 use crate::nix::run_nix_command;
+use flk::config::Config;
 use flk::flake::parsers::{packages::parse_packages_section, utils::resolve_profile};
 use flk::utils::visual::with_spinner;
 
@@ -21,10 +22,12 @@ use flk::utils::visual::with_spinner;
 /// * `package` - Package name to add (e.g., "ripgrep", "nodejs")
 /// * `version` - Optional version to pin (e.g., "15.1.0")
 /// * `target_profile` - Optional profile override
+/// * `config` - Resolved settings
 pub fn run_add(
     package: &str,
     version: Option<String>,
     target_profile: Option<String>,
+    config: &Config,
 ) -> Result<()> {
     let profile = resolve_profile(target_profile)?;
     let flake_path = path::Path::new(".flk/profiles/").join(format!("{}.nix", profile));
@@ -100,7 +103,11 @@ pub fn run_add(
     }
 
     // Add the package to buildInputs
-    let updated_content = section.add_package(&flake_content, &package_to_add, None);
+    let updated_content = if config.sort_packages() {
+        section.add_package_sorted(&flake_content, &package_to_add, None)
+    } else {
+        section.add_package(&flake_content, &package_to_add, None)
+    };
 
     // Write back to file
     fs::write(flake_path, updated_content).context("Failed to write flake.nix")?;
@@ -0,0 +1,329 @@
+//! # Channel Command Handler
+//!
+//! Show and switch the nixpkgs branch the project follows.
+//!
+//! Switching rewrites the `nixpkgs` input in `flake.nix`, relocks it, and
+//! reports the profile packages that disappeared or changed version. The
+//! previous `flake.lock` is backed up first, so `flk lock restore` can undo
+//! the switch.
+
+use anyhow::{bail, Context, Result};
+use colored::Colorize;
+use serde_json::Value;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+use std::path::Path;
+
+use crate::nix::{check_nix_available, run_nix_command};
+use flk::config::Config;
+use flk::flake::channel::{
+    channel_from_url, channel_url, compare_versions, is_release_channel, is_valid_channel,
+    locked_input, version_probe_expr, LockedInput, NIXPKGS_INPUT, STABLE_SOURCE,
+};
+use flk::flake::parsers::{
+    flake::parse_inputs_section,
+    overlays::{parse_sources_section, set_source_reference},
+    profile::parse_profile,
+    utils::list_profiles,
+};
+use flk::utils::{backup, visual::with_spinner};
+
+const FLAKE_FILE: &str = "flake.nix";
+const LOCK_FILE: &str = "flake.lock";
+const PINS_FILE: &str = ".flk/pins.nix";
+
+/// Show the nixpkgs branch and the revision it is locked to.
+pub fn run_show() -> Result<()> {
+    let url = current_nixpkgs_url()?;
+
+    match channel_from_url(&url) {
+        Some(channel) => println!("{} nixpkgs channel: {}", "ℹ".blue(), channel.green()),
+        None => println!("{} nixpkgs input: {}", "ℹ".blue(), url.green()),
+    }
+
+    match read_locked_nixpkgs()? {
+        Some(locked) => {
+            let short_rev = if locked.rev.len() >= 12 {
+                &locked.rev[..12]
+            } else {
+                &locked.rev
+            };
+            let date = locked
+                .last_modified
+                .and_then(|t| chrono::DateTime::from_timestamp(t, 0))
+                .map(|dt| format!(" ({})", dt.format("%Y-%m-%d")))
+                .unwrap_or_default();
+            println!("  {} {}{}", "Locked:".dimmed(), short_rev.yellow(), date);
+        }
+        None => println!("  {} {}", "Locked:".dimmed(), "not locked yet".yellow()),
+    }
+
+    if let Some(stable) = stable_source()? {
+        println!("  {} {}", "Pins stable source:".dimmed(), stable);
+    }
+
+    Ok(())
+}
+
+/// Switch the nixpkgs input to another branch and relock it.
+///
+/// # Arguments
+///
+/// * `channel` - nixpkgs branch, e.g. `nixos-unstable` or `nixos-25.11`
+/// * `config` - Resolved settings
+pub fn run_set(channel: String, config: &Config) -> Result<()> {
+    if !is_valid_channel(&channel) {
+        bail!(
+            "Invalid channel '{}'. Use nixos-unstable, a release branch such as nixos-25.11, or master.",
+            channel
+        );
+    }
+
+    let flake_content = fs::read_to_string(FLAKE_FILE)
+        .context("Failed to read flake.nix. Have you run 'flk init'?")?;
+    let inputs = parse_inputs_section(&flake_content)?;
+    let current_url = inputs
+        .entries
+        .iter()
+        .find(|e| e.name == NIXPKGS_INPUT)
+        .map(|e| e.url.clone())
+        .context("flake.nix has no nixpkgs input")?;
+
+    let previous = channel_from_url(&current_url)
+        .unwrap_or(&current_url)
+        .to_string();
+    if previous == channel {
+        println!("{} Already following {}", "ℹ".blue(), channel.cyan());
+        return Ok(());
+    }
+
+    let profile_packages = profile_packages()?;
+    let all_packages: Vec<String> = profile_packages
+        .values()
+        .flatten()
+        .cloned()
+        .collect::<BTreeSet<_>>()
+        .into_iter()
+        .collect();
+
+    let nix_available = check_nix_available();
+    let before = match (nix_available, read_locked_nixpkgs()?) {
+        (true, Some(locked)) if !all_packages.is_empty() => probe_versions(
+            &locked,
+            &all_packages,
+            "Reading current package versions...",
+        ),
+        _ => None,
+    };
+
+    // Rewrite the inputs, keeping the originals to roll back on failure
+    let new_flake = inputs.update_input(&flake_content, NIXPKGS_INPUT, &channel_url(&channel))?;
+    fs::write(FLAKE_FILE, &new_flake).context("Failed to write flake.nix")?;
+
+    let pins_content = fs::read_to_string(PINS_FILE).ok();
+    if let Some(pins) = &pins_content {
+        if is_release_channel(&channel) && stable_source()?.is_some() {
+            let updated = set_source_reference(pins, STABLE_SOURCE, &channel_url(&channel))?;
+            fs::write(PINS_FILE, updated).context("Failed to write pins.nix")?;
+        }
+    }
+
+    println!(
+        "{} Switched nixpkgs from {} to {}",
+        "✓".green().bold(),
+        previous.yellow(),
+        channel.green()
+    );
+
+    let lock_content = fs::read_to_string(LOCK_FILE).ok();
+    if lock_content.is_some() {
+        backup::ensure_flk_dir()?;
+        let backup_path = backup::create_backup(Path::new(LOCK_FILE))?;
+        println!(
+            "{} Created backup: {}",
+            "→".blue().bold(),
+            backup_path.file_name().unwrap().to_string_lossy().dimmed()
+        );
+        backup::prune_backups(Path::new(LOCK_FILE), config.backup_retention())?;
+    }
+
+    if !nix_available {
+        println!(
+            "{} Nix is not available, so flake.lock was not refreshed. Run {} once Nix is installed.",
+            "⚠".yellow(),
+            "nix flake update nixpkgs".cyan()
+        );
+        return Ok(());
+    }
+
+    let (_, stderr, success) = with_spinner("Locking nixpkgs...", || {
+        run_nix_command(&["flake", "update", NIXPKGS_INPUT])
+            .context("Failed to execute nix flake update")
+    })?;
+
+    if !success {
+        fs::write(FLAKE_FILE, &flake_content).context("Failed to restore flake.nix")?;
+        if let Some(pins) = &pins_content {
+            fs::write(PINS_FILE, pins).context("Failed to restore pins.nix")?;
+        }
+        if let Some(lock) = &lock_content {
+            fs::write(LOCK_FILE, lock).context("Failed to restore flake.lock")?;
+        }
+        bail!(
+            "Failed to lock nixpkgs to {}; flake.nix was left unchanged: {}",
+            channel,
+            stderr.trim()
+        );
+    }
+
+    println!(
+        "{} Locked nixpkgs to {}",
+        "✓".green().bold(),
+        channel.green()
+    );
+
+    let Some(before) = before else {
+        return Ok(());
+    };
+    let Some(after) = read_locked_nixpkgs()?
+        .and_then(|locked| probe_versions(&locked, &all_packages, "Comparing package versions..."))
+    else {
+        return Ok(());
+    };
+
+    report_changes(&profile_packages, &before, &after);
+
+    Ok(())
+}
+
+/// URL of the nixpkgs input in `flake.nix`.
+fn current_nixpkgs_url() -> Result<String> {
+    let content = fs::read_to_string(FLAKE_FILE)
+        .context("Failed to read flake.nix. Have you run 'flk init'?")?;
+    parse_inputs_section(&content)?
+        .entries
+        .into_iter()
+        .find(|e| e.name == NIXPKGS_INPUT)
+        .map(|e| e.url)
+        .context("flake.nix has no nixpkgs input")
+}
+
+/// The revision nixpkgs is locked to, if there is a lock file.
+fn read_locked_nixpkgs() -> Result<Option<LockedInput>> {
+    let Ok(content) = fs::read_to_string(LOCK_FILE) else {
+        return Ok(None);
+    };
+    let lock: Value = serde_json::from_str(&content).context("Failed to parse flake.lock")?;
+    Ok(locked_input(&lock, NIXPKGS_INPUT))
+}
+
+/// Reference of the `stable` source in `pins.nix`, if any.
+fn stable_source() -> Result<Option<String>> {
+    let Ok(content) = fs::read_to_string(PINS_FILE) else {
+        return Ok(None);
+    };
+    Ok(parse_sources_section(&content)?
+        .entries
+        .into_iter()
+        .find(|e| e.name == STABLE_SOURCE)
+        .map(|e| e.reference))
+}
+
+/// Unpinned packages of each profile; pinned ones do not follow the channel.
+fn profile_packages() -> Result<BTreeMap<String, Vec<String>>> {
+    let mut packages = BTreeMap::new();
+    for path in list_profiles()? {
+        let name = path
+            .file_stem()
+            .context("Failed to get profile name")?
+            .to_string_lossy()
+            .to_string();
+        let content = fs::read_to_string(&path)
+            .with_context(|| format!("Failed to read profile file: {}", path.display()))?;
+        let profile = parse_profile(&name, &content)
+            .with_context(|| format!("Failed to parse profile: {}", path.display()))?;
+        packages.insert(
+            name,
+            profile
+                .packages
+                .into_iter()
+                .filter(|p| p.pinned_version().is_none())
+                .map(|p| p.name)
+                .collect(),
+        );
+    }
+    Ok(packages)
+}
+
+/// Evaluate the version of each package at a locked revision.
+///
+/// Evaluation problems only cost the version report, so they are shown as a
+/// warning instead of failing the switch.
+fn probe_versions(
+    locked: &LockedInput,
+    packages: &[String],
+    message: &str,
+) -> Option<BTreeMap<String, Option<String>>> {
+    let expr = version_probe_expr(&locked.reference, packages);
+    let result = with_spinner(message, || {
+        run_nix_command(&["eval", "--impure", "--json", "--expr", &expr])
+    })
+    .and_then(|(stdout, stderr, success)| {
+        if !success {
+            bail!("{}", stderr.trim());
+        }
+        serde_json::from_str(&stdout).context("Unexpected output from nix eval")
+    });
+
+    match result {
+        Ok(versions) => Some(versions),
+        Err(e) => {
+            println!("{} Could not compare package versions: {}", "⚠".yellow(), e);
+            None
+        }
+    }
+}
+
+/// Print the packages that disappeared or changed version, per profile.
+fn report_changes(
+    profile_packages: &BTreeMap<String, Vec<String>>,
+    before: &BTreeMap<String, Option<String>>,
+    after: &BTreeMap<String, Option<String>>,
+) {
+    let changes = compare_versions(before, after);
+    if changes.is_empty() {
+        println!("{} No profile package changed version", "✓".green().bold());
+        return;
+    }
+
+    println!("{} Package changes:", "ℹ".blue());
+    for (profile, packages) in profile_packages {
+        let affected: Vec<_> = changes
+            .iter()
+            .filter(|c| packages.contains(&c.package))
+            .collect();
+        if affected.is_empty() {
+            continue;
+        }
+
+        println!("  {}", profile.cyan().bold());
+        for change in affected {
+            match &change.to {
+                Some(to) => println!(
+                    "    {} {} {} → {}",
+                    "•".yellow(),
+                    change.package,
+                    change.from.dimmed(),
+                    to.green()
+                ),
+                None => println!(
+                    "    {} {} {} → {}",
+                    "✗".red(),
+                    change.package,
+                    change.from.dimmed(),
+                    "no longer available".red()
+                ),
+            }
+        }
+    }
+}
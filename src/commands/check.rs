@@ -0,0 +1,632 @@
+//! # Check Command Handler
+//!
+//! Statically lint the `.flk` project: profiles, `pins.nix` and `default.nix`.
+
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use anyhow::{bail, Context, Result};
+use clap::ValueEnum;
+use colored::Colorize;
+use regex::Regex;
+use serde::Serialize;
+
+use flk::flake::inheritance::effective_profile;
+use flk::flake::interfaces::overlays::{OverlaysSection, SourcesSection};
+use flk::flake::interfaces::profiles::Profile;
+use flk::flake::interfaces::shellhooks::ShellHookSection;
+use flk::flake::parsers::{
+    overlays::{parse_overlay_section, parse_sources_section, render_file},
+    packages::{parse_packages_section, PackagesSection},
+    profile::parse_profile,
+    utils::{is_valid_env_var_name, list_profiles},
+};
+
+/// Output format for machine-readable reports.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+#[value(rename_all = "lowercase")]
+pub enum OutputFormat {
+    /// Human-readable, colored output
+    #[default]
+    Text,
+    /// JSON document on stdout
+    Json,
+}
+
+/// Shell builtins that a custom command should not shadow.
+const SHELL_BUILTINS: &[&str] = &[
+    "alias", "bg", "bind", "break", "builtin", "caller", "cd", "command", "compgen", "complete",
+    "continue", "declare", "dirs", "disown", "echo", "enable", "eval", "exec", "exit", "export",
+    "false", "fc", "fg", "getopts", "hash", "help", "history", "jobs", "kill", "let", "local",
+    "logout", "popd", "printf", "pushd", "pwd", "read", "readonly", "return", "set", "shift",
+    "shopt", "source", "suspend", "test", "times", "trap", "true", "type", "typeset", "ulimit",
+    "umask", "unalias", "unset", "wait",
+];
+
+const PINS_PATH: &str = ".flk/pins.nix";
+const IMPORTER_PATH: &str = ".flk/default.nix";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+enum Severity {
+    Error,
+    Warning,
+}
+
+/// A single problem found in the project.
+#[derive(Debug, Serialize)]
+struct Finding {
+    severity: Severity,
+    /// Stable identifier of the lint (e.g. "missing-pin")
+    code: &'static str,
+    file: String,
+    message: String,
+    /// Whether `--fix` can resolve it
+    fixable: bool,
+}
+
+#[derive(Serialize)]
+struct Report<'a> {
+    findings: &'a [Finding],
+    fixed: &'a [String],
+    errors: usize,
+    warnings: usize,
+}
+
+/// A profile file and the result of parsing it.
+struct ProfileFile {
+    name: String,
+    path: PathBuf,
+    parsed: Result<Profile>,
+}
+
+/// Everything the lints look at, loaded once.
+struct Project {
+    profiles: Vec<ProfileFile>,
+    pins: Result<(SourcesSection, OverlaysSection)>,
+    default_shell: Option<String>,
+}
+
+impl Project {
+    fn load() -> Result<Self> {
+        let mut paths = list_profiles()?;
+        paths.sort();
+        let profiles = paths
+            .into_iter()
+            .map(|path| {
+                let name = path
+                    .file_stem()
+                    .map(|s| s.to_string_lossy().to_string())
+                    .unwrap_or_default();
+                let parsed = fs::read_to_string(&path)
+                    .with_context(|| format!("Failed to read '{}'", path.display()))
+                    .and_then(|content| parse_profile(&name, &content));
+                ProfileFile { name, path, parsed }
+            })
+            .collect();
+
+        let pins = fs::read_to_string(PINS_PATH)
+            .with_context(|| format!("Failed to read '{}'", PINS_PATH))
+            .and_then(|content| {
+                Ok((
+                    parse_sources_section(&content)?,
+                    parse_overlay_section(&content)?,
+                ))
+            });
+
+        let importer = fs::read_to_string(IMPORTER_PATH).unwrap_or_default();
+        let default_shell = default_shell_re()
+            .captures(&importer)
+            .map(|c| c[1].to_string())
+            .filter(|name| !name.is_empty());
+
+        Ok(Self {
+            profiles,
+            pins,
+            default_shell,
+        })
+    }
+
+    /// Pinned package aliases (`name@version`) referenced by any profile.
+    fn referenced_pins(&self) -> HashSet<String> {
+        self.profiles
+            .iter()
+            .filter_map(|p| p.parsed.as_ref().ok())
+            .flat_map(|p| &p.packages)
+            .filter_map(|pkg| {
+                pkg.pinned_version()
+                    .map(|version| format!("{}@{}", pkg.name, version))
+            })
+            .collect()
+    }
+
+    /// Whether every profile parsed, so unreferenced pins can be trusted.
+    fn all_profiles_parsed(&self) -> bool {
+        self.profiles.iter().all(|p| p.parsed.is_ok())
+    }
+}
+
+fn default_shell_re() -> Regex {
+    Regex::new(r#"defaultShell\s*=\s*"([^"]*)"\s*;"#).unwrap()
+}
+
+/// Lint the project and report problems.
+///
+/// With `fix`, safe problems are repaired first: duplicate packages are
+/// removed, unreferenced pins and sources are dropped from `pins.nix`, and a
+/// `defaultShell` naming a missing profile is reset. The remaining problems
+/// are then reported.
+///
+/// # Arguments
+///
+/// * `fix` - Apply safe fixes before reporting
+/// * `format` - Text or JSON output
+///
+/// # Errors
+///
+/// Returns an error when at least one error-level problem remains, so CI
+/// jobs fail.
+pub fn run_check(fix: bool, format: OutputFormat) -> Result<()> {
+    if !Path::new(".flk/profiles").exists() {
+        bail!("No .flk/profiles directory found. Have you run 'flk init'?");
+    }
+
+    let fixed = if fix { apply_fixes()? } else { Vec::new() };
+    let findings = lint(&Project::load()?);
+
+    let errors = findings
+        .iter()
+        .filter(|f| f.severity == Severity::Error)
+        .count();
+    let warnings = findings.len() - errors;
+
+    match format {
+        OutputFormat::Json => {
+            let report = Report {
+                findings: &findings,
+                fixed: &fixed,
+                errors,
+                warnings,
+            };
+            println!(
+                "{}",
+                serde_json::to_string_pretty(&report).context("Failed to serialize report")?
+            );
+        }
+        OutputFormat::Text => print_text(&findings, &fixed, errors, warnings),
+    }
+
+    if errors > 0 {
+        bail!("flk check found {} error(s)", errors);
+    }
+    Ok(())
+}
+
+fn print_text(findings: &[Finding], fixed: &[String], errors: usize, warnings: usize) {
+    for fix in fixed {
+        println!("{} Fixed: {}", "✓".green().bold(), fix);
+    }
+
+    for finding in findings {
+        let label = match finding.severity {
+            Severity::Error => format!("{} error[{}]", "✗".red().bold(), finding.code).red(),
+            Severity::Warning => {
+                format!("{} warning[{}]", "⚠".yellow().bold(), finding.code).yellow()
+            }
+        };
+        let hint = if finding.fixable {
+            format!(" {}", "(fixable with --fix)".dimmed())
+        } else {
+            String::new()
+        };
+        println!("{} {}: {}{}", label, finding.file, finding.message, hint);
+    }
+
+    if findings.is_empty() {
+        println!("{} No problems found", "✓".green().bold());
+    } else {
+        println!(
+            "\n{} {} error(s), {} warning(s)",
+            "ℹ".blue(),
+            errors,
+            warnings
+        );
+    }
+}
+
+fn lint(project: &Project) -> Vec<Finding> {
+    let mut findings = Vec::new();
+
+    for profile in &project.profiles {
+        let file = profile.path.display().to_string();
+        match &profile.parsed {
+            Ok(parsed) => lint_profile(parsed, &file, &mut findings),
+            Err(err) => findings.push(Finding {
+                severity: Severity::Error,
+                code: "parse-error",
+                file,
+                message: format!("Profile '{}' cannot be parsed: {:#}", profile.name, err),
+                fixable: false,
+            }),
+        }
+    }
+
+    lint_inheritance(project, &mut findings);
+    lint_command_deps(project, &mut findings);
+    lint_pins(project, &mut findings);
+
+    if let Some(default_shell) = &project.default_shell {
+        if !project.profiles.iter().any(|p| &p.name == default_shell) {
+            findings.push(Finding {
+                severity: Severity::Error,
+                code: "missing-default-shell",
+                file: IMPORTER_PATH.to_string(),
+                message: format!(
+                    "defaultShell is '{}' but no such profile exists",
+                    default_shell
+                ),
+                fixable: true,
+            });
+        }
+    }
+
+    findings
+}
+
+fn lint_profile(profile: &Profile, file: &str, findings: &mut Vec<Finding>) {
+    let mut counts: BTreeMap<&str, Vec<Option<&str>>> = BTreeMap::new();
+    for package in &profile.packages {
+        counts
+            .entry(package.name.as_str())
+            .or_default()
+            .push(package.pinned_version());
+    }
+    for (name, versions) in counts.iter().filter(|(_, v)| v.len() > 1) {
+        let identical = versions.iter().all(|v| *v == versions[0]);
+        findings.push(if identical {
+            Finding {
+                severity: Severity::Warning,
+                code: "duplicate-package",
+                file: file.to_string(),
+                message: format!("Package '{}' is listed {} times", name, versions.len()),
+                fixable: true,
+            }
+        } else {
+            Finding {
+                severity: Severity::Error,
+                code: "conflicting-package",
+                file: file.to_string(),
+                message: format!(
+                    "Package '{}' is listed with different versions; keep only one",
+                    name
+                ),
+                fixable: false,
+            }
+        });
+    }
+
+    for env_var in &profile.env_vars {
+        if !is_valid_env_var_name(&env_var.name) {
+            findings.push(Finding {
+                severity: Severity::Error,
+                code: "invalid-env-name",
+                file: file.to_string(),
+                message: format!(
+                    "'{}' is not a valid environment variable name",
+                    env_var.name
+                ),
+                fixable: false,
+            });
+        }
+    }
+
+    for command in &profile.shell_hook.entries {
+        if SHELL_BUILTINS.contains(&command.name.as_str()) {
+            findings.push(Finding {
+                severity: Severity::Warning,
+                code: "shadowed-builtin",
+                file: file.to_string(),
+                message: format!("Command '{}' shadows a shell builtin", command.name),
+                fixable: false,
+            });
+        }
+    }
+}
+
+fn lint_inheritance(project: &Project, findings: &mut Vec<Finding>) {
+    let parents: HashMap<&str, &[String]> = project
+        .profiles
+        .iter()
+        .filter_map(|p| Some((p.name.as_str(), p.parsed.as_ref().ok()?.extends.as_slice())))
+        .collect();
+    let exists = |name: &str| project.profiles.iter().any(|p| p.name == name);
+
+    for profile in &project.profiles {
+        let Some(own_parents) = parents.get(profile.name.as_str()) else {
+            continue;
+        };
+        let file = profile.path.display().to_string();
+
+        for parent in own_parents.iter().filter(|p| !exists(p)) {
+            findings.push(Finding {
+                severity: Severity::Error,
+                code: "missing-parent",
+                file: file.clone(),
+                message: format!(
+                    "Profile '{}' extends '{}', which does not exist",
+                    profile.name, parent
+                ),
+                fixable: false,
+            });
+        }
+
+        if let Some(cycle) = find_cycle(&profile.name, &parents) {
+            findings.push(Finding {
+                severity: Severity::Error,
+                code: "extends-cycle",
+                file,
+                message: format!(
+                    "Profile '{}' extends itself: {}",
+                    profile.name,
+                    cycle.join(" -> ")
+                ),
+                fixable: false,
+            });
+        }
+    }
+}
+
+/// Report command `deps` that name missing commands or form a cycle.
+///
+/// Dependencies may be inherited, so each profile's commands are checked
+/// against its effective command set. Profiles whose inheritance is broken
+/// are skipped; [`lint_inheritance`] already reports them.
+fn lint_command_deps(project: &Project, findings: &mut Vec<Finding>) {
+    let parsed: Vec<Profile> = project
+        .profiles
+        .iter()
+        .filter_map(|p| p.parsed.as_ref().ok().cloned())
+        .collect();
+
+    for profile in &project.profiles {
+        let Ok(own) = &profile.parsed else {
+            continue;
+        };
+        let Ok(effective) = effective_profile(&profile.name, &parsed) else {
+            continue;
+        };
+        let available = ShellHookSection {
+            entries: effective.commands.into_iter().map(|c| c.value).collect(),
+            ..Default::default()
+        };
+
+        for command in own.shell_hook.entries.iter().filter(|c| !c.deps.is_empty()) {
+            if let Err(err) = available.run_order(&command.name) {
+                findings.push(Finding {
+                    severity: Severity::Error,
+                    code: "command-deps",
+                    file: profile.path.display().to_string(),
+                    message: err.to_string(),
+                    fixable: false,
+                });
+            }
+        }
+    }
+}
+
+/// The `extends` path leading from `start` back to itself, if there is one.
+fn find_cycle(start: &str, parents: &HashMap<&str, &[String]>) -> Option<Vec<String>> {
+    fn walk(
+        current: &str,
+        start: &str,
+        parents: &HashMap<&str, &[String]>,
+        path: &mut Vec<String>,
+        visited: &mut HashSet<String>,
+    ) -> bool {
+        for parent in parents.get(current).copied().unwrap_or_default() {
+            path.push(parent.clone());
+            if parent == start
+                || (visited.insert(parent.clone()) && walk(parent, start, parents, path, visited))
+            {
+                return true;
+            }
+            path.pop();
+        }
+        false
+    }
+
+    let mut path = vec![start.to_string()];
+    walk(start, start, parents, &mut path, &mut HashSet::new()).then_some(path)
+}
+
+fn lint_pins(project: &Project, findings: &mut Vec<Finding>) {
+    let (sources, overlays) = match &project.pins {
+        Ok(pins) => pins,
+        Err(err) => {
+            findings.push(Finding {
+                severity: Severity::Error,
+                code: "parse-error",
+                file: PINS_PATH.to_string(),
+                message: format!("pins.nix cannot be parsed: {:#}", err),
+                fixable: false,
+            });
+            return;
+        }
+    };
+
+    let referenced = project.referenced_pins();
+    let pinned: HashSet<&str> = overlays
+        .entries
+        .iter()
+        .flat_map(|e| e.packages.iter().map(|p| p.pin_name.as_str()))
+        .collect();
+
+    for profile in &project.profiles {
+        let Ok(parsed) = &profile.parsed else {
+            continue;
+        };
+        for package in &parsed.packages {
+            let Some(version) = package.pinned_version() else {
+                continue;
+            };
+            let alias = format!("{}@{}", package.name, version);
+            if !pinned.contains(alias.as_str()) {
+                findings.push(Finding {
+                    severity: Severity::Error,
+                    code: "missing-pin",
+                    file: profile.path.display().to_string(),
+                    message: format!(
+                        "'{}' has no entry in pinnedPackages; run 'flk add {} --version {} -p {}'",
+                        alias, package.name, version, profile.name
+                    ),
+                    fixable: false,
+                });
+            }
+        }
+    }
+
+    for entry in &overlays.entries {
+        if !sources.source_exists(&entry.name) {
+            findings.push(Finding {
+                severity: Severity::Error,
+                code: "missing-source",
+                file: PINS_PATH.to_string(),
+                message: format!("Pin '{}' has no matching entry in sources", entry.name),
+                fixable: false,
+            });
+        }
+    }
+
+    // Unreferenced pins can only be judged when every profile was read
+    if !project.all_profiles_parsed() {
+        return;
+    }
+
+    for entry in &overlays.entries {
+        for package in &entry.packages {
+            if !referenced.contains(&package.pin_name) {
+                findings.push(Finding {
+                    severity: Severity::Warning,
+                    code: "unused-pin",
+                    file: PINS_PATH.to_string(),
+                    message: format!(
+                        "'{}' in '{}' is not used by any profile",
+                        package.pin_name, entry.name
+                    ),
+                    fixable: true,
+                });
+            }
+        }
+    }
+
+    for source in &sources.entries {
+        let is_pin_source = source.name.starts_with("pkgs-");
+        if is_pin_source && !overlays.pin_entry_exists(&source.name) {
+            findings.push(Finding {
+                severity: Severity::Warning,
+                code: "unused-source",
+                file: PINS_PATH.to_string(),
+                message: format!("Source '{}' is not used by any pin", source.name),
+                fixable: true,
+            });
+        }
+    }
+}
+
+/// Apply all safe fixes and describe what was changed.
+fn apply_fixes() -> Result<Vec<String>> {
+    let mut fixed = Vec::new();
+    let project = Project::load()?;
+
+    for profile in &project.profiles {
+        if profile.parsed.is_ok() {
+            fix_duplicate_packages(&profile.name, &profile.path, &mut fixed)?;
+        }
+    }
+
+    if project.pins.is_ok() && project.all_profiles_parsed() {
+        fix_unused_pins(&project.referenced_pins(), &mut fixed)?;
+    }
+
+    if let Some(default_shell) = &project.default_shell {
+        if !project.profiles.iter().any(|p| &p.name == default_shell) {
+            let content = fs::read_to_string(IMPORTER_PATH)
+                .with_context(|| format!("Failed to read '{}'", IMPORTER_PATH))?;
+            let updated = default_shell_re().replace(&content, r#"defaultShell = "";"#);
+            fs::write(IMPORTER_PATH, updated.as_ref())
+                .with_context(|| format!("Failed to write '{}'", IMPORTER_PATH))?;
+            fixed.push(format!(
+                "reset defaultShell (profile '{}' does not exist)",
+                default_shell
+            ));
+        }
+    }
+
+    Ok(fixed)
+}
+
+fn fix_duplicate_packages(profile: &str, path: &Path, fixed: &mut Vec<String>) -> Result<()> {
+    let mut content =
+        fs::read_to_string(path).with_context(|| format!("Failed to read '{}'", path.display()))?;
+    let original = content.clone();
+
+    loop {
+        let section = parse_packages_section(&content)?;
+        let duplicate = section.entries.iter().enumerate().find(|(i, entry)| {
+            section.entries[..*i]
+                .iter()
+                .any(|e| e.name == entry.name && e.version == entry.version)
+        });
+        let Some((_, entry)) = duplicate else {
+            break;
+        };
+
+        fixed.push(format!(
+            "removed duplicate package '{}' from profile '{}'",
+            entry.name, profile
+        ));
+        content = PackagesSection::remove_entry(&content, entry);
+    }
+
+    if content != original {
+        fs::write(path, content)
+            .with_context(|| format!("Failed to write '{}'", path.display()))?;
+    }
+    Ok(())
+}
+
+fn fix_unused_pins(referenced: &HashSet<String>, fixed: &mut Vec<String>) -> Result<()> {
+    let content =
+        fs::read_to_string(PINS_PATH).with_context(|| format!("Failed to read '{}'", PINS_PATH))?;
+    let mut sources = parse_sources_section(&content)?;
+    let mut overlays = parse_overlay_section(&content)?;
+    let mut changed = false;
+
+    for entry in &mut overlays.entries {
+        entry.packages.retain(|package| {
+            let keep = referenced.contains(&package.pin_name);
+            if !keep {
+                fixed.push(format!("removed unused pin '{}'", package.pin_name));
+                changed = true;
+            }
+            keep
+        });
+    }
+    overlays.entries.retain(|entry| !entry.packages.is_empty());
+
+    sources.entries.retain(|source| {
+        let keep = !source.name.starts_with("pkgs-") || overlays.pin_entry_exists(&source.name);
+        if !keep {
+            fixed.push(format!("removed unused source '{}'", source.name));
+            changed = true;
+        }
+        keep
+    });
+
+    if changed {
+        fs::write(PINS_PATH, render_file(&sources, &overlays))
+            .with_context(|| format!("Failed to write '{}'", PINS_PATH))?;
+    }
+    Ok(())
+}
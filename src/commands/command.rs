@@ -1,19 +1,124 @@
 //! # Custom Command Handler
 //!
-//! Add, remove, and list custom shell commands in the development environment.
+//! Add, remove, list, show, edit and rename custom shell commands in the
+//! development environment, or import them from existing task files.
 //!
 //! Custom commands become available as shell functions when the dev shell
 //! is activated.
 
 use anyhow::{bail, Context, Result};
+use clap::Args;
 use colored::Colorize;
+use std::env;
 use std::fs;
-use std::path::Path;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::Command;
 
+use flk::flake::inheritance::effective_profile;
+use flk::flake::interfaces::profiles::Profile;
+use flk::flake::interfaces::shellhooks::{ShellHookEntry, ShellHookSection};
 use flk::flake::parsers::{
-    commands::{add_shell_hook_command, parse_shell_hook_section, remove_shell_hook_command},
-    utils::resolve_profile,
+    commands::{parse_shell_hook_section, remove_shell_hook_command, render_shell_hook_section},
+    profile::parse_profile,
+    utils::{is_valid_command_name, list_profiles, resolve_profile, split_profile_ref},
 };
+use flk::utils::tasks::{command_name, fits_command_script, project_root, TaskSource};
+
+/// Optional attributes of a command given to `flk command add`.
+#[derive(Debug, Clone, Default, Args)]
+pub struct CommandDetails {
+    /// One-line summary shown by flk-help and command list
+    #[arg(short, long)]
+    pub description: Option<String>,
+    /// Arguments the command accepts, e.g. '<file> [--watch]'
+    #[arg(short, long)]
+    pub usage: Option<String>,
+    /// Command to run before this one (comma-separated or repeated)
+    #[arg(long = "dep", value_name = "NAME", value_delimiter = ',')]
+    pub deps: Vec<String>,
+}
+
+/// Read a profile and parse its commands section.
+fn load_commands(target_profile: Option<String>) -> Result<(PathBuf, String, ShellHookSection)> {
+    let profile = resolve_profile(target_profile)?;
+    let flake_path = Path::new(".flk/profiles/").join(format!("{}.nix", profile));
+    let flake_content = fs::read_to_string(&flake_path).with_context(|| {
+        format!(
+            "Failed to read profile file at '{}'. Have you run 'flk init'?",
+            flake_path.display()
+        )
+    })?;
+    let section = parse_shell_hook_section(&flake_content).with_context(|| {
+        format!(
+            "Failed to parse shellHook section in profile file '{}'",
+            flake_path.display()
+        )
+    })?;
+    Ok((flake_path, flake_content, section))
+}
+
+/// Render the commands section back into the profile and write it.
+fn write_commands(path: &Path, content: &str, section: &ShellHookSection) -> Result<()> {
+    let rendered = render_shell_hook_section(section);
+    fs::write(path, section.apply_to_content(content, &rendered))
+        .with_context(|| format!("Failed to write '{}'", path.display()))
+}
+
+/// Read and parse every profile of the project.
+pub(crate) fn load_profiles() -> Result<Vec<Profile>> {
+    let mut profiles: Vec<Profile> = Vec::new();
+    for path in list_profiles()? {
+        let name = path
+            .file_stem()
+            .context("Failed to get profile name")?
+            .to_string_lossy()
+            .to_string();
+        let content = fs::read_to_string(&path)
+            .with_context(|| format!("Failed to read profile file: {}", path.display()))?;
+        profiles.push(
+            parse_profile(&name, &content)
+                .with_context(|| format!("Failed to parse profile: {}", path.display()))?,
+        );
+    }
+    Ok(profiles)
+}
+
+/// The commands available in a profile or profile combination.
+///
+/// Inherited commands are included. In a combination, a command defined by a
+/// later profile wins over one of the same name in an earlier profile.
+pub(crate) fn effective_commands(profile_ref: &str) -> Result<ShellHookSection> {
+    let profiles = load_profiles()?;
+
+    let mut section = ShellHookSection::default();
+    for part in split_profile_ref(profile_ref) {
+        for command in effective_profile(part, &profiles)?.commands {
+            section.entries.retain(|e| e.name != command.value.name);
+            section.entries.push(command.value);
+        }
+    }
+    Ok(section)
+}
+
+fn validate_command_name(name: &str) -> Result<()> {
+    if !is_valid_command_name(name) {
+        bail!(
+            "Invalid command name '{}'. Use only letters, numbers, hyphens, and underscores.",
+            name
+        );
+    }
+    Ok(())
+}
+
+/// Trim an optional single-line attribute, treating blank text as absent.
+fn single_line(label: &str, value: Option<String>) -> Result<Option<String>> {
+    match value.map(|v| v.trim().to_string()) {
+        Some(v) if v.contains('\n') => bail!("The command {} must fit on a single line", label),
+        Some(v) if v.is_empty() => Ok(None),
+        other => Ok(other),
+    }
+}
 
 /// Add a custom shell command to the development environment.
 ///
@@ -25,22 +130,26 @@ use flk::flake::parsers::{
 /// * `name` - Command name (alphanumeric, hyphens, underscores)
 /// * `command` - Command body (bash code)
 /// * `file` - Optional path to read command body from a file instead
+/// * `details` - Optional description, usage and dependencies
+/// * `force` - Replace an existing command in place; its description, usage
+///   and dependencies are kept unless new ones are given
 /// * `target_profile` - Optional profile override
 pub fn run_add(
     name: &str,
     command: &str,
     file: Option<String>,
+    details: CommandDetails,
+    force: bool,
     target_profile: Option<String>,
 ) -> Result<()> {
-    let profile = resolve_profile(target_profile)?;
-    let flake_path = Path::new(".flk/profiles/").join(format!("{}.nix", profile));
-
-    // Validate command name
-    if !is_valid_command_name(name) {
-        bail!(
-            "Invalid command name '{}'. Use only letters, numbers, hyphens, and underscores.",
-            name
-        );
+    let CommandDetails {
+        description,
+        usage,
+        deps,
+    } = details;
+    validate_command_name(name)?;
+    for dep in &deps {
+        validate_command_name(dep)?;
     }
 
     println!("{} Adding command: {}", "→".blue().bold(), name.green());
@@ -57,32 +166,51 @@ pub fn run_add(
         bail!("Command cannot be empty");
     }
 
-    let flake_content = fs::read_to_string(&flake_path).with_context(|| {
-        format!(
-            "Failed to read profile file at '{}'. Have you run 'flk init'?",
-            flake_path.display()
-        )
-    })?;
-    let section = parse_shell_hook_section(&flake_content).with_context(|| {
-        format!(
-            "Failed to parse shellHook section in profile file '{}'",
-            flake_path.display()
-        )
-    })?;
-    if section.command_exists(name) {
+    let description = single_line("description", description)?;
+    let usage = single_line("usage", usage)?;
+
+    let profile = resolve_profile(target_profile)?;
+    let (flake_path, flake_content, mut section) = load_commands(Some(profile.clone()))?;
+    let existing = section.entries.iter().find(|e| e.name == name);
+    if existing.is_some() && !force {
         bail!(
-            "Command '{}' already exists. Remove it with: {}",
+            "Command '{}' already exists. Replace it with {} or change it with {}",
             name.cyan(),
-            format!("flk remove-command {}", name).yellow()
+            "--force".yellow(),
+            format!("flk command edit {}", name).yellow()
         );
     }
 
-    // Add the command to shellHook
-    let updated_content = add_shell_hook_command(&flake_content, name, &command_content)
-        .context("Failed to add command to shellHook")?;
+    let entry = ShellHookEntry {
+        description: description.or_else(|| existing.and_then(|e| e.description.clone())),
+        usage: usage.or_else(|| existing.and_then(|e| e.usage.clone())),
+        deps: match (deps.is_empty(), existing) {
+            (true, Some(existing)) => existing.deps.clone(),
+            _ => deps,
+        },
+        extra_attrs: existing.map(|e| e.extra_attrs.clone()).unwrap_or_default(),
+        ..ShellHookEntry::new(name, &command_content)
+    };
 
-    // Write back to file
-    fs::write(flake_path, updated_content).context("Failed to write flake.nix")?;
+    // Dependencies may be inherited commands, so check against the full set
+    if !entry.deps.is_empty() {
+        let mut available = effective_commands(&profile)?;
+        available.set_entry(entry.clone());
+        available.run_order(name)?;
+    }
+
+    let replaced = section.set_entry(entry);
+
+    write_commands(&flake_path, &flake_content, &section)?;
+
+    if replaced {
+        println!(
+            "{} Command '{}' replaced successfully!",
+            "✓".green().bold(),
+            name
+        );
+        return Ok(());
+    }
 
     println!(
         "{} Command '{}' added successfully!",
@@ -92,6 +220,7 @@ pub fn run_add(
     println!("\n{}", "Next steps:".bold());
     println!("  1. Run {} to enter the dev shell", "nix develop".cyan());
     println!("  2. Use your command: {}", name.cyan());
+    println!("  3. Run {} to list every command", "flk-help".cyan());
 
     Ok(())
 }
@@ -128,6 +257,15 @@ pub fn run_remove(name: &str, target_profile: Option<String>) -> Result<()> {
     if !section.command_exists(name) {
         bail!("Command '{}' not found in profile", name.cyan());
     }
+    let dependents = section.dependents(name);
+    if !dependents.is_empty() {
+        println!(
+            "{} {} still list '{}' in their deps and will fail until it is added back",
+            "⚠".yellow(),
+            dependents.join(", "),
+            name
+        );
+    }
 
     // Remove the command from shellHook
     let updated_content = remove_shell_hook_command(&flake_content, name)
@@ -145,22 +283,18 @@ pub fn run_remove(name: &str, target_profile: Option<String>) -> Result<()> {
     Ok(())
 }
 
-/// List all environment variables in the dev shell
-pub fn list(target_profile: Option<String>) -> Result<()> {
+/// List the custom commands of a profile with their usage and description,
+/// including the commands it inherits.
+///
+/// Each command is followed by the `nix run` invocation of its flake app, and
+/// inherited ones name the profile they come from. With `tree`, show how
+/// commands depend on each other instead.
+pub fn list(tree: bool, target_profile: Option<String>) -> Result<()> {
     let profile = resolve_profile(target_profile)?;
-    let flake_path = Path::new(".flk/profiles/").join(format!("{}.nix", profile));
-    let flake_content = fs::read_to_string(&flake_path).with_context(|| {
-        format!(
-            "Failed to read profile file at '{}'. Have you run 'flk init'?",
-            flake_path.display()
-        )
-    })?;
-    let section = parse_shell_hook_section(&flake_content).with_context(|| {
-        format!(
-            "Failed to parse shellHook section in profile file '{}'",
-            flake_path.display()
-        )
-    })?;
+    // Read the profile's own commands first so a missing profile file is
+    // reported as such rather than as an unknown profile.
+    load_commands(Some(profile.clone()))?;
+    let section = effective_commands(&profile)?;
 
     if section.entries.is_empty() {
         println!(
@@ -170,17 +304,329 @@ pub fn list(target_profile: Option<String>) -> Result<()> {
         return Ok(());
     }
 
-    for entry in section.entries {
-        println!("{} {}", "•".green(), entry.name.bold());
+    if !tree {
+        let effective = effective_profile(&profile, &load_profiles()?)?;
+        for command in &effective.commands {
+            let origin = if effective.is_inherited(command) {
+                format!(" {}", format!("(from {})", command.from).dimmed())
+            } else {
+                String::new()
+            };
+            println!("{} {}{}", "•".green(), command.value, origin);
+            println!(
+                "    {}",
+                nix_run_invocation(&profile, &command.value).dimmed()
+            );
+        }
+        return Ok(());
+    }
+
+    // Start from the commands nothing depends on; if every command is part
+    // of a cycle there are none, so show them all
+    let mut roots: Vec<&ShellHookEntry> = section
+        .entries
+        .iter()
+        .filter(|e| section.dependents(&e.name).is_empty())
+        .collect();
+    if roots.is_empty() {
+        roots = section.entries.iter().collect();
+    }
+    for root in roots {
+        println!("{}", root);
+        print_deps(&section, root, "", &mut vec![root.name.clone()]);
+    }
+
+    Ok(())
+}
+
+/// The `nix run` command that runs a command's flake app, e.g.
+/// `nix run .#backend-serve -- [port]`.
+fn nix_run_invocation(profile: &str, entry: &ShellHookEntry) -> String {
+    match &entry.usage {
+        Some(usage) => format!("nix run .#{}-{} -- {}", profile, entry.name, usage),
+        None => format!("nix run .#{}-{}", profile, entry.name),
+    }
+}
+
+/// Print the dependencies of a command as the branches of a tree.
+fn print_deps(
+    section: &ShellHookSection,
+    entry: &ShellHookEntry,
+    prefix: &str,
+    stack: &mut Vec<String>,
+) {
+    for (i, dep) in entry.deps.iter().enumerate() {
+        let last = i + 1 == entry.deps.len();
+        let branch = format!("{}{}", prefix, if last { "└── " } else { "├── " });
+        let Some(dep_entry) = section.entries.iter().find(|e| &e.name == dep) else {
+            println!("{}{} {}", branch, dep, "(missing)".red());
+            continue;
+        };
+        if stack.contains(dep) {
+            println!("{}{} {}", branch, dep, "(cycle)".yellow());
+            continue;
+        }
+
+        println!("{}{}", branch, dep_entry);
+        stack.push(dep.clone());
+        let child_prefix = format!("{}{}", prefix, if last { "    " } else { "│   " });
+        print_deps(section, dep_entry, &child_prefix, stack);
+        stack.pop();
+    }
+}
+
+/// Print a command's usage, description and script.
+///
+/// # Arguments
+///
+/// * `name` - Command name
+/// * `target_profile` - Optional profile override
+pub fn show(name: &str, target_profile: Option<String>) -> Result<()> {
+    let (_, _, section) = load_commands(target_profile)?;
+    let entry = section
+        .entries
+        .iter()
+        .find(|e| e.name == name)
+        .with_context(|| format!("Command '{}' not found in profile", name))?;
+
+    println!("{}", entry.signature().bold());
+    if let Some(description) = &entry.description {
+        println!("{}", description.dimmed());
+    }
+    if !entry.deps.is_empty() {
+        println!("{} {}", "Runs first:".dimmed(), entry.deps.join(", "));
+    }
+    println!();
+    println!("{}", entry.script.trim_end());
+
+    Ok(())
+}
+
+/// Open a command's script in `$VISUAL` or `$EDITOR` and save the result.
+///
+/// The script is written to a private temporary file; once the editor exits,
+/// the edited text replaces the script in place.
+///
+/// # Arguments
+///
+/// * `name` - Command name
+/// * `target_profile` - Optional profile override
+pub fn edit(name: &str, target_profile: Option<String>) -> Result<()> {
+    let (flake_path, flake_content, mut section) = load_commands(target_profile)?;
+    let mut entry = section
+        .entries
+        .iter()
+        .find(|e| e.name == name)
+        .cloned()
+        .with_context(|| format!("Command '{}' not found in profile", name))?;
+
+    // Created exclusively and readable only by the user; removed when dropped
+    let mut temp_file = tempfile::Builder::new()
+        .prefix(&format!("flk-command-{}-", name))
+        .suffix(".sh")
+        .tempfile()
+        .context("Failed to create a temporary file")?;
+    temp_file
+        .write_all(entry.script.as_bytes())
+        .and_then(|()| temp_file.flush())
+        .with_context(|| format!("Failed to write '{}'", temp_file.path().display()))?;
+
+    run_editor(temp_file.path())?;
+    let edited = fs::read_to_string(temp_file.path())
+        .with_context(|| format!("Failed to read '{}'", temp_file.path().display()))?;
+
+    if edited.trim().is_empty() {
+        bail!("Command cannot be empty; '{}' was left unchanged", name);
+    }
+    if edited.trim_end() == entry.script.trim_end() {
+        println!("{} Command '{}' was not changed", "ℹ".blue(), name);
+        return Ok(());
+    }
+
+    entry.script = edited;
+    section.set_entry(entry);
+    write_commands(&flake_path, &flake_content, &section)?;
+
+    println!(
+        "{} Command '{}' updated successfully!",
+        "✓".green().bold(),
+        name
+    );
+
+    Ok(())
+}
+
+/// Run the user's editor on a file and wait for it to exit.
+///
+/// The editor command goes through `sh` so values such as `code --wait` work.
+fn run_editor(path: &Path) -> Result<()> {
+    let editor = env::var("VISUAL")
+        .ok()
+        .or_else(|| env::var("EDITOR").ok())
+        .filter(|e| !e.trim().is_empty())
+        .unwrap_or_else(|| "vi".to_string());
+
+    let status = Command::new("sh")
+        .arg("-c")
+        .arg(format!("{} \"$1\"", editor))
+        .arg("sh")
+        .arg(path)
+        .status()
+        .with_context(|| format!("Failed to start editor '{}'", editor))?;
+
+    if !status.success() {
+        bail!(
+            "Editor '{}' exited with {}; nothing was saved",
+            editor,
+            status
+        );
+    }
+    Ok(())
+}
+
+/// Rename a command, keeping its script, description and usage.
+///
+/// # Arguments
+///
+/// * `old_name` - Current command name
+/// * `new_name` - New command name
+/// * `target_profile` - Optional profile override
+pub fn rename(old_name: &str, new_name: &str, target_profile: Option<String>) -> Result<()> {
+    validate_command_name(new_name)?;
+    let (flake_path, flake_content, mut section) = load_commands(target_profile)?;
+
+    if !section.command_exists(old_name) {
+        bail!("Command '{}' not found in profile", old_name.cyan());
+    }
+    if section.command_exists(new_name) {
+        bail!("Command '{}' already exists", new_name.cyan());
+    }
+    let dependents: Vec<String> = section
+        .dependents(old_name)
+        .into_iter()
+        .map(str::to_string)
+        .collect();
+    section.rename_command(old_name, new_name)?;
+    write_commands(&flake_path, &flake_content, &section)?;
+
+    println!(
+        "{} Renamed command {} to {}",
+        "✓".green().bold(),
+        old_name.cyan(),
+        new_name.cyan()
+    );
+    if !dependents.is_empty() {
+        println!("  Updated the deps of: {}", dependents.join(", "));
     }
 
     Ok(())
 }
 
-fn is_valid_command_name(name: &str) -> bool {
-    !name.is_empty()
-        && name
-            .chars()
-            .all(|c| c.is_alphanumeric() || c == '-' || c == '_')
-        && !name.starts_with('-')
+/// Import tasks from a `package.json`, Makefile or justfile as commands.
+///
+/// Each imported command calls through to the original tool, e.g.
+/// `npm run <script>`, and passes its arguments on. Commands that already
+/// exist are skipped.
+///
+/// # Arguments
+///
+/// * `from` - Path of the task file
+/// * `only` - Task or command names to import; `*` matches any text. Empty
+///   imports every task
+/// * `target_profile` - Optional profile override
+pub fn import(from: &str, only: &[String], target_profile: Option<String>) -> Result<()> {
+    let path = Path::new(from);
+    let source = TaskSource::from_path(path)?;
+    if !fits_command_script(from) {
+        bail!(
+            "Cannot import tasks from '{}': paths containing ' or ${{ cannot be written into a command script",
+            from
+        );
+    }
+    let content = fs::read_to_string(path).with_context(|| format!("Failed to read '{}'", from))?;
+    let tasks = source.parse(&content)?;
+
+    let (flake_path, flake_content, mut section) = load_commands(target_profile)?;
+    let root = project_root()?;
+    println!(
+        "{} Importing commands from {}",
+        "→".blue().bold(),
+        from.cyan()
+    );
+
+    let mut added = 0;
+    let mut skipped = 0;
+    let mut matched = vec![false; only.len()];
+    for task in tasks {
+        let name = command_name(&task.name);
+        if !only.is_empty() {
+            let mut selected = false;
+            for (i, pattern) in only.iter().enumerate() {
+                let hit = wildcard_match(pattern, &task.name)
+                    || name.as_deref().is_some_and(|n| wildcard_match(pattern, n));
+                matched[i] |= hit;
+                selected |= hit;
+            }
+            if !selected {
+                continue;
+            }
+        }
+
+        let Some(name) = name.filter(|_| fits_command_script(&task.name)) else {
+            println!(
+                "{} Skipping '{}': not a usable command name",
+                "⚠".yellow(),
+                task.name
+            );
+            skipped += 1;
+            continue;
+        };
+        if section.command_exists(&name) {
+            println!("{} Skipping '{}': command already exists", "ℹ".blue(), name);
+            skipped += 1;
+            continue;
+        }
+
+        section.add_entry(ShellHookEntry {
+            description: task.description,
+            usage: task.usage,
+            ..ShellHookEntry::new(&name, &source.call_script(path, &task.name, &root))
+        })?;
+        println!("  {} {}", "+".green(), name);
+        added += 1;
+    }
+
+    for (pattern, _) in only.iter().zip(&matched).filter(|(_, hit)| !**hit) {
+        println!("{} No task matches '{}'", "⚠".yellow(), pattern);
+    }
+
+    if added == 0 {
+        println!("{} Nothing to import ({} skipped)", "ℹ".blue(), skipped);
+        return Ok(());
+    }
+
+    write_commands(&flake_path, &flake_content, &section)?;
+    println!(
+        "{} Imported {} command(s), {} skipped",
+        "✓".green().bold(),
+        added,
+        skipped
+    );
+
+    Ok(())
+}
+
+/// Match `text` against a pattern in which `*` stands for any text.
+fn wildcard_match(pattern: &str, text: &str) -> bool {
+    match pattern.split_once('*') {
+        None => pattern == text,
+        Some((prefix, rest)) => {
+            let Some(text) = text.strip_prefix(prefix) else {
+                return false;
+            };
+            (0..=text.len())
+                .filter(|&i| text.is_char_boundary(i))
+                .any(|i| wildcard_match(rest, &text[i..]))
+        }
+    }
 }
@@ -0,0 +1,111 @@
+//! # Config Command Handler
+//!
+//! Read and change the settings in the user and project config files.
+
+use anyhow::{bail, Context, Result};
+use colored::Colorize;
+use std::path::{Path, PathBuf};
+
+use flk::config::{
+    set_value, setting, user_config_path, Config, ConfigSource, PROJECT_CONFIG_FILE,
+};
+
+/// Print the resolved value of a setting.
+///
+/// The value goes to stdout and nothing else, so it can be used in scripts;
+/// the layer it comes from is printed on stderr.
+///
+/// # Arguments
+///
+/// * `key` - Dotted setting key, e.g. `ui.color`
+pub fn run_get(key: String) -> Result<()> {
+    setting(&key)?;
+    let config = Config::load()?;
+    let value = config.get(&key).expect("known keys are always resolved");
+
+    match &value.value {
+        Some(v) => {
+            println!("{}", v);
+            eprintln!(
+                "{} {}",
+                "ℹ".blue(),
+                format!("from {}", value.source).dimmed()
+            );
+        }
+        None => eprintln!("{} {} is not set", "ℹ".blue(), key.cyan()),
+    }
+
+    Ok(())
+}
+
+/// Write a setting to the user or project config file.
+///
+/// # Arguments
+///
+/// * `key` - Dotted setting key
+/// * `value` - New value; validated against the setting's type
+/// * `project` - Write `.flk/config.toml` instead of the user config
+pub fn run_set(key: String, value: String, project: bool) -> Result<()> {
+    let path = if project {
+        if !Path::new(".flk").is_dir() {
+            bail!("No .flk directory found. Run 'flk init' first, or drop --project to change the user config.");
+        }
+        PathBuf::from(PROJECT_CONFIG_FILE)
+    } else {
+        user_config_path().context("Could not detect the user configuration directory")?
+    };
+
+    set_value(&path, &key, &value)?;
+
+    println!(
+        "{} Set {} = {} in {}",
+        "✓".green().bold(),
+        key.cyan(),
+        value.trim().green(),
+        path.display()
+    );
+
+    // Warn when a higher-priority layer hides the value just written
+    if let Some(resolved) = Config::load().ok().and_then(|c| c.get(&key).cloned()) {
+        let written_here = match &resolved.source {
+            ConfigSource::Project(p) | ConfigSource::User(p) => p == &path,
+            _ => false,
+        };
+        if !written_here {
+            println!(
+                "{} {} is overridden by {}",
+                "⚠".yellow(),
+                key.cyan(),
+                resolved.source
+            );
+        }
+    }
+
+    Ok(())
+}
+
+/// List every setting with its value and where the value comes from.
+pub fn run_list() -> Result<()> {
+    let config = Config::load()?;
+
+    println!("{} Configuration:", "ℹ".blue());
+    for value in config.values() {
+        let shown = match &value.value {
+            Some(v) => v.green().to_string(),
+            None => "(not set)".dimmed().to_string(),
+        };
+        println!(
+            "- {} = {} {}",
+            value.setting.key.cyan(),
+            shown,
+            format!("[{}]", value.source).dimmed()
+        );
+        println!(
+            "    {} {}",
+            value.setting.description.dimmed(),
+            format!("(env: {})", value.setting.env_var()).dimmed()
+        );
+    }
+
+    Ok(())
+}
@@ -0,0 +1,150 @@
+//! # Container Command Handler
+//!
+//! Run a profile's container image with the project directory mounted.
+
+use std::env;
+use std::fs;
+use std::io::IsTerminal;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+
+use anyhow::{Context, Result};
+use colored::Colorize;
+
+use crate::commands::export::{build_image, load_image, ContainerRuntime};
+use crate::commands::profile_cache::inputs_unchanged_since;
+use flk::flake::parsers::{profile::parse_profile, utils::resolve_profile};
+
+/// Mount point of the project directory inside the container.
+const WORKSPACE_DIR: &str = "/workspace";
+
+/// Run the profile's image, building and loading it first when it is stale.
+///
+/// The image is rebuilt only when one of the profile cache inputs changed since
+/// the last build, when the runtime no longer has the image, or when `rebuild`
+/// is set. The project directory is mounted at `/workspace`, the profile's
+/// environment variables are passed to the container, and files created inside
+/// it keep the host user's ownership.
+///
+/// # Arguments
+///
+/// * `target_profile` - Optional profile override
+/// * `runtime` - Container runtime to use
+/// * `rebuild` - Always rebuild the image
+/// * `command` - Command to run instead of the image's default
+pub fn run(
+    target_profile: Option<String>,
+    runtime: ContainerRuntime,
+    rebuild: bool,
+    command: &[String],
+) -> Result<()> {
+    let profile = resolve_profile(target_profile)?;
+    let profile_path = Path::new(".flk/profiles/").join(format!("{}.nix", profile));
+    let content = fs::read_to_string(&profile_path)
+        .with_context(|| format!("Failed to read profile file '{}'", profile_path.display()))?;
+    let parsed = parse_profile(&profile, &content)?;
+
+    let image = ensure_image(runtime, &profile, rebuild)?;
+    let project_dir = env::current_dir().context("Failed to determine current directory")?;
+
+    let mut cmd = Command::new(runtime.binary());
+    cmd.args(["run", "--rm"]);
+    cmd.arg(if std::io::stdin().is_terminal() {
+        "-it"
+    } else {
+        "-i"
+    });
+    cmd.arg("-v")
+        .arg(format!("{}:{}", project_dir.display(), WORKSPACE_DIR));
+    cmd.args(["-w", WORKSPACE_DIR]);
+    cmd.args(user_mapping(runtime, &project_dir)?);
+    for env_var in &parsed.env_vars {
+        cmd.arg("-e")
+            .arg(format!("{}={}", env_var.name, env_var.value));
+    }
+    cmd.arg(&image);
+    cmd.args(command);
+
+    println!(
+        "{} Running {} with {}",
+        "→".blue(),
+        image.cyan(),
+        runtime.binary()
+    );
+    let status = cmd
+        .status()
+        .with_context(|| format!("Failed to run '{} run'", runtime.binary()))?;
+
+    if !status.success() {
+        std::process::exit(status.code().unwrap_or(1));
+    }
+    Ok(())
+}
+
+/// Path of the stamp recording the image last loaded for a profile.
+fn image_stamp_path(runtime: ContainerRuntime, profile: &str) -> PathBuf {
+    Path::new(".flk").join(format!(".image-{}-{}.stamp", runtime.binary(), profile))
+}
+
+/// Return a loaded image for the profile, rebuilding it when it is stale.
+fn ensure_image(runtime: ContainerRuntime, profile: &str, rebuild: bool) -> Result<String> {
+    let stamp_path = image_stamp_path(runtime, profile);
+
+    if !rebuild && inputs_unchanged_since(profile, &stamp_path)? {
+        let cached = fs::read_to_string(&stamp_path).unwrap_or_default();
+        let cached = cached.trim();
+        if !cached.is_empty() && image_exists(runtime, cached) {
+            println!("{} Using cached image {}", "ℹ".blue(), cached.cyan());
+            return Ok(cached.to_string());
+        }
+    }
+
+    println!(
+        "{} Building image for profile {}...",
+        "→".blue(),
+        profile.cyan()
+    );
+    let archive = build_image(runtime, profile)?;
+    let image = load_image(runtime, &archive)?;
+
+    fs::write(&stamp_path, format!("{}\n", image))
+        .with_context(|| format!("Failed to write '{}'", stamp_path.display()))?;
+    println!("{} Loaded image {}", "✓".green().bold(), image.cyan());
+
+    Ok(image)
+}
+
+fn image_exists(runtime: ContainerRuntime, image: &str) -> bool {
+    Command::new(runtime.binary())
+        .args(["image", "inspect", image])
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status()
+        .map(|status| status.success())
+        .unwrap_or(false)
+}
+
+/// Runtime arguments that make files written to the mount owned by the host user.
+#[cfg(unix)]
+fn user_mapping(runtime: ContainerRuntime, project_dir: &Path) -> Result<Vec<String>> {
+    use std::os::unix::fs::MetadataExt;
+
+    Ok(match runtime {
+        // Rootless podman maps the host user into the container itself
+        ContainerRuntime::Podman => vec!["--userns=keep-id".to_string()],
+        ContainerRuntime::Docker => {
+            let metadata = fs::metadata(project_dir).with_context(|| {
+                format!("Failed to read metadata for '{}'", project_dir.display())
+            })?;
+            vec![
+                "--user".to_string(),
+                format!("{}:{}", metadata.uid(), metadata.gid()),
+            ]
+        }
+    })
+}
+
+#[cfg(not(unix))]
+fn user_mapping(_runtime: ContainerRuntime, _project_dir: &Path) -> Result<Vec<String>> {
+    Ok(Vec::new())
+}
@@ -0,0 +1,401 @@
+//! # Doctor Command Handler
+//!
+//! Diagnose common setup problems with Nix, shell integration and the project.
+
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use anyhow::{bail, Result};
+use colored::Colorize;
+
+use crate::commands::profile_cache::{
+    profile_cache_is_fresh, profile_cache_path, profile_cache_stamp_path,
+};
+use crate::nix::run_nix_command;
+use flk::flake::parsers::{profile::parse_profile, utils::list_profiles};
+
+/// Outcome of a single diagnostic check.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum CheckStatus {
+    /// Everything is in order
+    Ok,
+    /// Not broken, but worth fixing
+    Warn,
+    /// Something flk relies on is broken
+    Fail,
+}
+
+/// A diagnostic result with an optional suggested fix.
+#[derive(Debug)]
+struct Check {
+    status: CheckStatus,
+    message: String,
+    fix: Option<String>,
+}
+
+impl Check {
+    fn ok(message: impl Into<String>) -> Self {
+        Self {
+            status: CheckStatus::Ok,
+            message: message.into(),
+            fix: None,
+        }
+    }
+
+    fn warn(message: impl Into<String>, fix: impl Into<String>) -> Self {
+        Self {
+            status: CheckStatus::Warn,
+            message: message.into(),
+            fix: Some(fix.into()),
+        }
+    }
+
+    fn fail(message: impl Into<String>, fix: impl Into<String>) -> Self {
+        Self {
+            status: CheckStatus::Fail,
+            message: message.into(),
+            fix: Some(fix.into()),
+        }
+    }
+
+    fn print(&self) {
+        let icon = match self.status {
+            CheckStatus::Ok => "✓".green().bold(),
+            CheckStatus::Warn => "⚠".yellow().bold(),
+            CheckStatus::Fail => "✗".red().bold(),
+        };
+        println!("  {} {}", icon, self.message);
+        if let Some(fix) = &self.fix {
+            for line in fix.lines() {
+                println!("      {}", line.dimmed());
+            }
+        }
+    }
+}
+
+/// Run all diagnostics and print a report.
+///
+/// Checks the Nix installation, shell integration (direnv and `flk hook`)
+/// and, inside an flk project, git tracking of the flake files, the profile
+/// caches and whether every profile parses.
+///
+/// # Errors
+///
+/// Returns an error when at least one check failed, so the process exits
+/// non-zero.
+pub fn run_doctor() -> Result<()> {
+    println!("{}", "System".bold());
+    let system = system_checks();
+    system.iter().for_each(Check::print);
+
+    let mut checks = system;
+    if Path::new(".flk").exists() {
+        println!("\n{}", "Project".bold());
+        let project = project_checks();
+        project.iter().for_each(Check::print);
+        checks.extend(project);
+    } else {
+        println!(
+            "\n{} No .flk directory found; skipping project checks. Run {} to create one.",
+            "ℹ".blue(),
+            "flk init".cyan()
+        );
+    }
+
+    let failures = count(&checks, CheckStatus::Fail);
+    let warnings = count(&checks, CheckStatus::Warn);
+    println!();
+    if failures > 0 {
+        bail!(
+            "{} problem(s) found, {} warning(s). See the suggested fixes above.",
+            failures,
+            warnings
+        );
+    }
+    if warnings > 0 {
+        println!(
+            "{} No problems found, {} warning(s)",
+            "⚠".yellow(),
+            warnings
+        );
+    } else {
+        println!("{} Everything looks good", "✓".green().bold());
+    }
+    Ok(())
+}
+
+fn count(checks: &[Check], status: CheckStatus) -> usize {
+    checks.iter().filter(|c| c.status == status).count()
+}
+
+fn system_checks() -> Vec<Check> {
+    let mut checks = vec![];
+
+    match run_nix_command(&["--version"]) {
+        Ok((stdout, _, true)) => {
+            checks.push(Check::ok(format!("Nix is installed ({})", stdout.trim())));
+            checks.push(check_flakes_enabled());
+        }
+        _ => {
+            checks.push(Check::fail(
+                "Nix is not installed or not in PATH",
+                "Install Nix: https://nixos.org/download",
+            ));
+        }
+    }
+
+    let rc = ShellRc::detect();
+    let rc_content = rc
+        .as_ref()
+        .and_then(|rc| fs::read_to_string(&rc.path).ok())
+        .unwrap_or_default();
+    checks.push(check_direnv(rc.as_ref(), &rc_content));
+    checks.push(check_flk_hook(rc.as_ref(), &rc_content));
+
+    checks
+}
+
+fn check_flakes_enabled() -> Check {
+    let features = run_nix_command(&["config", "show", "experimental-features"])
+        .ok()
+        .filter(|(_, _, success)| *success)
+        .or_else(|| {
+            run_nix_command(&["show-config"])
+                .ok()
+                .filter(|(_, _, success)| *success)
+        })
+        .map(|(stdout, _, _)| {
+            stdout
+                .lines()
+                .find_map(|line| line.strip_prefix("experimental-features ="))
+                .unwrap_or(&stdout)
+                .to_string()
+        })
+        .unwrap_or_default();
+
+    let enabled: Vec<&str> = features.split_whitespace().collect();
+    if enabled.contains(&"flakes") && enabled.contains(&"nix-command") {
+        Check::ok("Flakes and nix-command are enabled")
+    } else {
+        Check::fail(
+            "Flakes are not enabled",
+            "Add this line to ~/.config/nix/nix.conf:\nexperimental-features = nix-command flakes",
+        )
+    }
+}
+
+/// The interactive shell's rc file.
+struct ShellRc {
+    shell: String,
+    path: PathBuf,
+}
+
+impl ShellRc {
+    fn detect() -> Option<Self> {
+        let shell = env::var("SHELL").ok()?;
+        let shell = Path::new(&shell).file_name()?.to_str()?.to_string();
+        let home = dirs::home_dir()?;
+        let path = match shell.as_str() {
+            "bash" => home.join(".bashrc"),
+            "zsh" => env::var_os("ZDOTDIR")
+                .map(PathBuf::from)
+                .unwrap_or(home)
+                .join(".zshrc"),
+            "fish" => home.join(".config/fish/config.fish"),
+            _ => return None,
+        };
+        Some(Self { shell, path })
+    }
+
+    fn hook_line(&self, command: &str) -> String {
+        match self.shell.as_str() {
+            "fish" => format!("{} hook fish | source", command),
+            shell => format!("eval \"$({} hook {})\"", command, shell),
+        }
+    }
+}
+
+fn command_exists(program: &str) -> bool {
+    Command::new(program)
+        .arg("version")
+        .output()
+        .map(|output| output.status.success())
+        .unwrap_or(false)
+}
+
+fn check_direnv(rc: Option<&ShellRc>, rc_content: &str) -> Check {
+    let envrc_uses_flake = fs::read_to_string(".envrc")
+        .map(|content| content.contains("use flake"))
+        .unwrap_or(false);
+
+    if !command_exists("direnv") {
+        return if envrc_uses_flake {
+            Check::fail(
+                "This project has an .envrc but direnv is not installed",
+                "Install direnv and nix-direnv: https://direnv.net",
+            )
+        } else {
+            Check::ok("direnv is not installed (optional)")
+        };
+    }
+
+    let Some(rc) = rc else {
+        return Check::warn(
+            "direnv is installed but the shell could not be detected",
+            "Make sure 'direnv hook <shell>' runs in your shell's startup file",
+        );
+    };
+
+    if rc_content.contains("direnv hook") {
+        Check::ok(format!("direnv is hooked in {}", rc.path.display()))
+    } else {
+        let fix = format!(
+            "Add this line to {}:\n{}",
+            rc.path.display(),
+            rc.hook_line("direnv")
+        );
+        let message = format!(
+            "direnv is installed but not hooked in {}",
+            rc.path.display()
+        );
+        if envrc_uses_flake {
+            Check::fail(message, fix)
+        } else {
+            Check::warn(message, fix)
+        }
+    }
+}
+
+fn check_flk_hook(rc: Option<&ShellRc>, rc_content: &str) -> Check {
+    let Some(rc) = rc else {
+        return Check::warn(
+            "Could not detect a bash, zsh or fish shell to check for 'flk hook'",
+            "Add the output of 'flk hook <shell>' to your shell's startup file",
+        );
+    };
+
+    if rc_content.contains("flk hook") {
+        Check::ok(format!("flk hook is installed in {}", rc.path.display()))
+    } else {
+        Check::warn(
+            format!("flk hook is not installed in {}", rc.path.display()),
+            format!(
+                "Add this line to {} to enable 'refresh' and 'switch':\n{}",
+                rc.path.display(),
+                rc.hook_line("flk")
+            ),
+        )
+    }
+}
+
+fn project_checks() -> Vec<Check> {
+    let mut profiles: Vec<String> = list_profiles()
+        .unwrap_or_default()
+        .iter()
+        .filter_map(|path| path.file_stem())
+        .map(|stem| stem.to_string_lossy().to_string())
+        .collect();
+    profiles.sort();
+
+    let mut checks = vec![check_git_tracking(&profiles)];
+    if profiles.is_empty() {
+        checks.push(Check::fail(
+            "No profiles found in .flk/profiles",
+            "Create one with 'flk profile add <name>'",
+        ));
+    }
+    checks.extend(profiles.iter().map(|profile| check_profile(profile)));
+    checks
+}
+
+/// Files a flake needs to see; flakes in a git repository only see tracked files.
+fn flake_files(profiles: &[String]) -> Vec<PathBuf> {
+    let mut files: Vec<PathBuf> = [
+        "flake.nix",
+        "flake.lock",
+        ".flk/default.nix",
+        ".flk/pins.nix",
+        ".flk/overlays.nix",
+        ".flk/profiles/default.nix",
+    ]
+    .iter()
+    .map(PathBuf::from)
+    .collect();
+    files.extend(
+        profiles
+            .iter()
+            .map(|p| Path::new(".flk/profiles").join(format!("{}.nix", p))),
+    );
+    files.into_iter().filter(|path| path.exists()).collect()
+}
+
+fn check_git_tracking(profiles: &[String]) -> Check {
+    let inside_repo = Command::new("git")
+        .args(["rev-parse", "--is-inside-work-tree"])
+        .output()
+        .map(|output| output.status.success())
+        .unwrap_or(false);
+    if !inside_repo {
+        return Check::ok("Not a git repository; flakes can read all files");
+    }
+
+    let files = flake_files(profiles);
+    let tracked = Command::new("git")
+        .arg("ls-files")
+        .arg("--")
+        .args(&files)
+        .output()
+        .map(|output| String::from_utf8_lossy(&output.stdout).into_owned())
+        .unwrap_or_default();
+    let tracked: Vec<&str> = tracked.lines().collect();
+
+    let untracked: Vec<String> = files
+        .iter()
+        .map(|path| path.display().to_string())
+        .filter(|path| !tracked.contains(&path.as_str()))
+        .collect();
+
+    if untracked.is_empty() {
+        Check::ok("Flake files are tracked by git")
+    } else {
+        Check::fail(
+            format!(
+                "Files not tracked by git are invisible to the flake: {}",
+                untracked.join(", ")
+            ),
+            format!("Run: git add --intent-to-add {}", untracked.join(" ")),
+        )
+    }
+}
+
+fn check_profile(profile: &str) -> Check {
+    let path = Path::new(".flk/profiles").join(format!("{}.nix", profile));
+    let parsed = fs::read_to_string(&path)
+        .map_err(anyhow::Error::from)
+        .and_then(|content| parse_profile(profile, &content));
+    if let Err(err) = parsed {
+        return Check::fail(
+            format!("Profile {} cannot be parsed: {:#}", profile.cyan(), err),
+            format!("Fix the syntax in {}", path.display()),
+        );
+    }
+
+    let cache_path = profile_cache_path(profile);
+    if !cache_path.exists() {
+        return Check::ok(format!("Profile {} parses (no cache yet)", profile.cyan()));
+    }
+
+    match profile_cache_is_fresh(profile, &cache_path, &profile_cache_stamp_path(profile)) {
+        Ok(true) => Check::ok(format!("Profile {} parses, cache is fresh", profile.cyan())),
+        _ => Check::warn(
+            format!("Profile {} has a stale cache", profile.cyan()),
+            format!(
+                "Run 'flk activate -p {}' to rebuild it, or delete {}",
+                profile,
+                cache_path.display()
+            ),
+        ),
+    }
+}
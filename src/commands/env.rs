@@ -1,20 +1,45 @@
 //! # Environment Variables Command Handler
 //!
-//! Add, remove, and list environment variables in the development environment.
+//! Add, remove, and list environment variables in the development environment,
+//! and move them in and out of `.env` files.
+//!
+//! `set`, `unset` and `rename` can apply to every profile at once. All
+//! profiles are updated in memory first, so a failure in one leaves every
+//! file untouched.
+//!
+//! Secrets are environment variables whose value is read from a file or a
+//! command when the shell starts. Only that reference is stored in the
+//! profile, and the value is never printed.
 
 use anyhow::{bail, Context, Result};
+use clap::ValueEnum;
 use colored::Colorize;
 use std::fs;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 
-use flk::flake::parsers::{env::parse_env_vars_section, utils::resolve_profile};
+use flk::flake::interfaces::profiles::{EnvVar, Secret, SecretSource};
+use flk::flake::parsers::{
+    env::parse_env_vars_section,
+    profile::{parse_profile, write_profile, ProfileWriteMode},
+    secrets::{add_secret as add_secret_line, parse_secrets, remove_secret},
+    utils::{is_valid_env_var_name, list_profiles, resolve_profile},
+};
+use flk::utils::dotenv::{parse_dotenv, render_dotenv, render_json, render_shell};
 
-/// Add an environment variable to the dev shell
-pub fn add(name: &str, value: &str, target_profile: Option<String>) -> Result<()> {
-    let profile_to_parse = resolve_profile(target_profile)?;
-    let flake_path = Path::new(".flk/profiles/").join(format!("{}.nix", profile_to_parse));
+/// Output formats for `flk env export`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+#[value(rename_all = "lowercase")]
+pub enum EnvFormat {
+    /// `NAME=value` lines, as in a `.env` file
+    #[default]
+    Dotenv,
+    /// A JSON object of names to values
+    Json,
+    /// `export NAME='value'` lines for POSIX shells
+    Shell,
+}
 
-    // Validate variable name
+fn validate_name(name: &str) -> Result<()> {
     if name.trim().is_empty() {
         bail!("Environment variable name cannot be empty");
     }
@@ -26,6 +51,16 @@ pub fn add(name: &str, value: &str, target_profile: Option<String>) -> Result<()
         );
     }
 
+    Ok(())
+}
+
+/// Add an environment variable to the dev shell
+pub fn add(name: &str, value: &str, target_profile: Option<String>) -> Result<()> {
+    let profile_to_parse = resolve_profile(target_profile)?;
+    let flake_path = Path::new(".flk/profiles/").join(format!("{}.nix", profile_to_parse));
+
+    validate_name(name)?;
+
     println!(
         "{} Adding environment variable: {} = {}",
         "→".blue().bold(),
@@ -41,9 +76,10 @@ pub fn add(name: &str, value: &str, target_profile: Option<String>) -> Result<()
     })?;
     let section = parse_env_vars_section(&flake_content)?;
 
-    if section.env_var_exists(name)? {
+    if section.env_var_exists(name)? || parse_secrets(&flake_content).iter().any(|s| s.name == name)
+    {
         bail!(
-            "Environment variable '{}' already exists in profile '{}'",
+            "Environment variable '{}' already exists in profile '{}'. Use 'flk env set' to change it.",
             name.cyan(),
             profile_to_parse.yellow()
         );
@@ -62,6 +98,72 @@ pub fn add(name: &str, value: &str, target_profile: Option<String>) -> Result<()
     Ok(())
 }
 
+/// Add a secret environment variable to the dev shell.
+///
+/// Only the source is written to the profile; the shell reads the value from
+/// it each time it starts.
+///
+/// # Arguments
+///
+/// * `name` - Variable name
+/// * `source` - File or command the value is read from
+/// * `target_profile` - Optional profile override
+pub fn add_secret(name: &str, source: SecretSource, target_profile: Option<String>) -> Result<()> {
+    let profile_to_parse = resolve_profile(target_profile)?;
+    let flake_path = Path::new(".flk/profiles/").join(format!("{}.nix", profile_to_parse));
+
+    validate_name(name)?;
+
+    println!(
+        "{} Adding secret: {} (from {})",
+        "→".blue().bold(),
+        name.cyan(),
+        source
+    );
+
+    let flake_content = fs::read_to_string(&flake_path).with_context(|| {
+        format!(
+            "Failed to read profile file at '{}'. Have you run 'flk init'?",
+            flake_path.display()
+        )
+    })?;
+
+    if parse_env_vars_section(&flake_content)?.env_var_exists(name)?
+        || parse_secrets(&flake_content).iter().any(|s| s.name == name)
+    {
+        bail!(
+            "Environment variable '{}' already exists in profile '{}'",
+            name.cyan(),
+            profile_to_parse.yellow()
+        );
+    }
+
+    if let SecretSource::File(path) = &source {
+        if !Path::new(path).exists() {
+            println!(
+                "{} {} does not exist yet; the shell will warn until it does",
+                "⚠".yellow(),
+                path
+            );
+        }
+    }
+
+    let secret = Secret {
+        name: name.to_string(),
+        source,
+    };
+    let updated_content = add_secret_line(&flake_content, &secret)?;
+    fs::write(flake_path, updated_content).context("Failed to write profile file")?;
+
+    println!(
+        "{} Secret {} will be resolved when the shell starts",
+        "✓".green().bold(),
+        name.cyan()
+    );
+
+    Ok(())
+}
+
 /// Remove an environment variable from the dev shell
 pub fn remove(name: &str, target_profile: Option<String>) -> Result<()> {
     let profile_to_parse = resolve_profile(target_profile)?;
@@ -81,6 +183,12 @@ pub fn remove(name: &str, target_profile: Option<String>) -> Result<()> {
     })?;
     let section = parse_env_vars_section(&flake_content)?;
 
+    if parse_secrets(&flake_content).iter().any(|s| s.name == name) {
+        let updated_content = remove_secret(&flake_content, name)?;
+        fs::write(flake_path, updated_content).context("Failed to write profile file")?;
+        return Ok(());
+    }
+
     if !section.env_var_exists(name)? {
         bail!(
             "Environment variable '{}' does not exist in profile '{}'",
@@ -109,8 +217,9 @@ pub fn list(target_profile: Option<String>) -> Result<()> {
     })?;
     let section = parse_env_vars_section(&flake_content)?;
     let env_vars = section.to_env_vars();
+    let secrets = parse_secrets(&flake_content);
 
-    if env_vars.is_empty() {
+    if env_vars.is_empty() && secrets.is_empty() {
         println!(
             "{} No environment variables found in the current profile.",
             "✗".red().bold()
@@ -121,20 +230,428 @@ pub fn list(target_profile: Option<String>) -> Result<()> {
     for env_var in env_vars {
         println!("{} {}", "•".green(), env_var);
     }
+    for secret in secrets {
+        println!("{} {}", "•".green(), secret);
+    }
 
     Ok(())
 }
 
-/// Validate environment variable name
-fn is_valid_env_var_name(name: &str) -> bool {
-    if name.is_empty() {
-        return false;
+/// Import environment variables from a `.env` file.
+///
+/// Variables that already exist with a different value are skipped unless
+/// `overwrite` is set. Names used by secrets are always skipped.
+///
+/// # Arguments
+///
+/// * `file` - Path to the `.env` file
+/// * `overwrite` - Replace the values of existing variables
+/// * `target_profile` - Optional profile override
+pub fn import(file: &str, overwrite: bool, target_profile: Option<String>) -> Result<()> {
+    let profile_name = resolve_profile(target_profile)?;
+    let flake_path = Path::new(".flk/profiles/").join(format!("{}.nix", profile_name));
+
+    let dotenv = fs::read_to_string(file).with_context(|| format!("Failed to read {}", file))?;
+    let vars = parse_dotenv(&dotenv).with_context(|| format!("Failed to parse {}", file))?;
+
+    println!(
+        "{} Importing {} variable(s) from {} into profile {}",
+        "→".blue().bold(),
+        vars.len(),
+        file.cyan(),
+        profile_name.yellow()
+    );
+
+    let flake_content = fs::read_to_string(&flake_path).with_context(|| {
+        format!(
+            "Failed to read profile file at '{}'. Have you run 'flk init'?",
+            flake_path.display()
+        )
+    })?;
+    let mut profile = parse_profile(&profile_name, &flake_content)?;
+
+    let mut skipped = Vec::new();
+    for var in vars {
+        if profile.secrets.iter().any(|s| s.name == var.name) {
+            println!(
+                "{} {} is a secret in this profile; skipped",
+                "⚠".yellow(),
+                var.name.cyan()
+            );
+            continue;
+        }
+
+        match profile.env_vars.iter_mut().find(|e| e.name == var.name) {
+            Some(existing) if existing.value == var.value => {}
+            Some(existing) if overwrite => *existing = var,
+            Some(_) => skipped.push(var.name),
+            None => profile.env_vars.push(var),
+        }
     }
 
-    let first_char = name.chars().next().unwrap();
-    if !first_char.is_alphabetic() && first_char != '_' {
-        return false;
+    let (updated_content, report) =
+        write_profile(&flake_content, &profile, ProfileWriteMode::Merge)?;
+
+    if !skipped.is_empty() {
+        println!(
+            "{} Kept the existing value of {}; use {} to replace them",
+            "⚠".yellow(),
+            skipped.join(", ").cyan(),
+            "--overwrite".cyan()
+        );
     }
 
-    name.chars().all(|c| c.is_alphanumeric() || c == '_')
+    if report.is_empty() {
+        println!("{} Nothing to import", "ℹ".blue());
+        return Ok(());
+    }
+
+    fs::write(&flake_path, updated_content).context("Failed to write profile file")?;
+    println!(
+        "{} {} added, {} updated",
+        "✓".green().bold(),
+        report.added.len(),
+        report.updated.len()
+    );
+
+    Ok(())
+}
+
+/// Print the profile's environment variables in another format.
+///
+/// Secrets are left out, since their values only exist inside the shell,
+/// and so are values using Nix interpolation, which only Nix can evaluate.
+///
+/// # Arguments
+///
+/// * `format` - Output format
+/// * `target_profile` - Optional profile override
+pub fn export(format: EnvFormat, target_profile: Option<String>) -> Result<()> {
+    let profile_name = resolve_profile(target_profile)?;
+    let flake_path = Path::new(".flk/profiles/").join(format!("{}.nix", profile_name));
+    let flake_content = fs::read_to_string(&flake_path).with_context(|| {
+        format!(
+            "Failed to read profile file at '{}'. Have you run 'flk init'?",
+            flake_path.display()
+        )
+    })?;
+    let (nix_vars, env_vars): (Vec<EnvVar>, Vec<EnvVar>) = parse_env_vars_section(&flake_content)?
+        .to_env_vars()
+        .into_iter()
+        .partition(|v| v.nix.is_some());
+
+    let output = match format {
+        EnvFormat::Dotenv => render_dotenv(&env_vars),
+        EnvFormat::Json => format!("{}\n", render_json(&env_vars)?),
+        EnvFormat::Shell => render_shell(&env_vars),
+    };
+    print!("{}", output);
+
+    // Keep stdout clean for redirection
+    let secrets: Vec<String> = parse_secrets(&flake_content)
+        .into_iter()
+        .map(|s| s.name)
+        .collect();
+    if !secrets.is_empty() {
+        eprintln!(
+            "{} Secrets are not exported: {}",
+            "ℹ".blue(),
+            secrets.join(", ")
+        );
+    }
+    if !nix_vars.is_empty() {
+        let names: Vec<&str> = nix_vars.iter().map(|v| v.name.as_str()).collect();
+        eprintln!(
+            "{} Variables using Nix interpolation are not exported: {}",
+            "ℹ".blue(),
+            names.join(", ")
+        );
+    }
+
+    Ok(())
+}
+
+/// A profile file loaded for a change that may span several profiles.
+struct TargetProfile {
+    name: String,
+    path: PathBuf,
+    content: String,
+}
+
+/// Load the profile selected with `--profile`, or every profile.
+fn target_profiles(
+    target_profile: Option<String>,
+    all_profiles: bool,
+) -> Result<Vec<TargetProfile>> {
+    let paths = if all_profiles {
+        let mut paths = list_profiles().context("No profiles found. Have you run 'flk init'?")?;
+        paths.sort();
+        paths
+    } else {
+        let name = resolve_profile(target_profile)?;
+        vec![Path::new(".flk/profiles/").join(format!("{}.nix", name))]
+    };
+
+    if paths.is_empty() {
+        bail!("No profiles found. Have you run 'flk init'?");
+    }
+
+    paths
+        .into_iter()
+        .map(|path| {
+            let content = fs::read_to_string(&path).with_context(|| {
+                format!(
+                    "Failed to read profile file at '{}'. Have you run 'flk init'?",
+                    path.display()
+                )
+            })?;
+            let name = path
+                .file_stem()
+                .context("Failed to get profile name")?
+                .to_string_lossy()
+                .to_string();
+            Ok(TargetProfile {
+                name,
+                path,
+                content,
+            })
+        })
+        .collect()
+}
+
+/// Describe where a name was looked up, for error messages.
+fn scope(profiles: &[TargetProfile], all_profiles: bool) -> String {
+    if all_profiles {
+        "any profile".to_string()
+    } else {
+        format!("profile '{}'", profiles[0].name)
+    }
+}
+
+/// Write the updated profiles once all of them have been computed.
+fn write_profiles<'a>(
+    pending: impl IntoIterator<Item = (&'a TargetProfile, &'a String)>,
+) -> Result<()> {
+    for (profile, updated) in pending {
+        if *updated != profile.content {
+            fs::write(&profile.path, updated)
+                .with_context(|| format!("Failed to write '{}'", profile.path.display()))?;
+        }
+    }
+    Ok(())
+}
+
+/// Add an environment variable, or replace the value of an existing one.
+///
+/// # Arguments
+///
+/// * `name` - Variable name
+/// * `value` - New value
+/// * `target_profile` - Optional profile override
+/// * `all_profiles` - Set the variable in every profile
+pub fn set(
+    name: &str,
+    value: &str,
+    target_profile: Option<String>,
+    all_profiles: bool,
+) -> Result<()> {
+    validate_name(name)?;
+    let profiles = target_profiles(target_profile, all_profiles)?;
+
+    let mut pending = Vec::new();
+    for profile in &profiles {
+        if parse_secrets(&profile.content)
+            .iter()
+            .any(|s| s.name == name)
+        {
+            bail!(
+                "'{}' is a secret in profile '{}'. Remove it first to replace it with a plain value.",
+                name.cyan(),
+                profile.name.yellow()
+            );
+        }
+
+        let section = parse_env_vars_section(&profile.content)
+            .with_context(|| format!("Failed to parse profile '{}'", profile.name))?;
+        let action = match section.entries.iter().find(|e| e.name == name) {
+            Some(existing) if existing.value == value => None,
+            Some(_) => Some("Updated"),
+            None => Some("Added"),
+        };
+        let updated = section.set_env_var(&profile.content, name, value);
+        pending.push((profile, updated, action));
+    }
+
+    write_profiles(
+        pending
+            .iter()
+            .map(|(profile, updated, _)| (*profile, updated)),
+    )?;
+
+    for (profile, _, action) in &pending {
+        match action {
+            Some(action) => println!(
+                "{} {} {} = {} in profile {}",
+                "✓".green().bold(),
+                action,
+                name.cyan(),
+                value.green(),
+                profile.name.yellow()
+            ),
+            None => println!(
+                "{} {} already has this value in profile {}",
+                "ℹ".blue(),
+                name.cyan(),
+                profile.name.yellow()
+            ),
+        }
+    }
+
+    Ok(())
+}
+
+/// Remove several environment variables or secrets at once.
+///
+/// Fails without changing anything if a name exists in none of the target
+/// profiles.
+///
+/// # Arguments
+///
+/// * `names` - Variable names to remove
+/// * `target_profile` - Optional profile override
+/// * `all_profiles` - Remove the variables from every profile
+pub fn unset(names: &[String], target_profile: Option<String>, all_profiles: bool) -> Result<()> {
+    let profiles = target_profiles(target_profile, all_profiles)?;
+
+    let mut found: Vec<&str> = Vec::new();
+    let mut pending = Vec::new();
+    for profile in &profiles {
+        let mut content = profile.content.clone();
+        let mut removed = Vec::new();
+
+        for name in names {
+            if removed.contains(&name.as_str()) {
+                continue;
+            }
+            if parse_secrets(&content).iter().any(|s| &s.name == name) {
+                content = remove_secret(&content, name)?;
+            } else {
+                let section = parse_env_vars_section(&content)
+                    .with_context(|| format!("Failed to parse profile '{}'", profile.name))?;
+                if !section.env_var_exists(name)? {
+                    continue;
+                }
+                content = section.remove_env_var(&content, name)?;
+            }
+            removed.push(name.as_str());
+        }
+
+        found.extend(removed.iter().copied());
+        pending.push((profile, content, removed));
+    }
+
+    let missing: Vec<&str> = names
+        .iter()
+        .map(String::as_str)
+        .filter(|name| !found.contains(name))
+        .collect();
+    if !missing.is_empty() {
+        bail!(
+            "Environment variable(s) {} not found in {}; nothing was removed",
+            missing.join(", ").cyan(),
+            scope(&profiles, all_profiles).yellow()
+        );
+    }
+
+    write_profiles(
+        pending
+            .iter()
+            .map(|(profile, updated, _)| (*profile, updated)),
+    )?;
+
+    for (profile, _, removed) in pending.iter().filter(|(_, _, r)| !r.is_empty()) {
+        println!(
+            "{} Removed {} from profile {}",
+            "✓".green().bold(),
+            removed.join(", ").cyan(),
+            profile.name.yellow()
+        );
+    }
+
+    Ok(())
+}
+
+/// Rename an environment variable or secret, keeping its value or source.
+///
+/// # Arguments
+///
+/// * `old_name` - Current variable name
+/// * `new_name` - New variable name
+/// * `target_profile` - Optional profile override
+/// * `all_profiles` - Rename the variable in every profile that has it
+pub fn rename(
+    old_name: &str,
+    new_name: &str,
+    target_profile: Option<String>,
+    all_profiles: bool,
+) -> Result<()> {
+    validate_name(new_name)?;
+    if old_name == new_name {
+        bail!("The old and new names are the same");
+    }
+    let profiles = target_profiles(target_profile, all_profiles)?;
+
+    let mut pending = Vec::new();
+    for profile in &profiles {
+        let secrets = parse_secrets(&profile.content);
+        let section = parse_env_vars_section(&profile.content)
+            .with_context(|| format!("Failed to parse profile '{}'", profile.name))?;
+
+        let secret = secrets.iter().find(|s| s.name == old_name);
+        if secret.is_none() && !section.env_var_exists(old_name)? {
+            continue;
+        }
+        if section.env_var_exists(new_name)? || secrets.iter().any(|s| s.name == new_name) {
+            bail!(
+                "Environment variable '{}' already exists in profile '{}'",
+                new_name.cyan(),
+                profile.name.yellow()
+            );
+        }
+
+        let updated = match secret {
+            Some(secret) => {
+                let content = remove_secret(&profile.content, old_name)?;
+                let renamed = Secret {
+                    name: new_name.to_string(),
+                    source: secret.source.clone(),
+                };
+                add_secret_line(&content, &renamed)?
+            }
+            None => section.rename_env_var(&profile.content, old_name, new_name)?,
+        };
+        pending.push((profile, updated));
+    }
+
+    if pending.is_empty() {
+        bail!(
+            "Environment variable '{}' not found in {}",
+            old_name.cyan(),
+            scope(&profiles, all_profiles).yellow()
+        );
+    }
+
+    write_profiles(pending.iter().map(|(profile, updated)| (*profile, updated)))?;
+
+    for (profile, _) in &pending {
+        println!(
+            "{} Renamed {} to {} in profile {}",
+            "✓".green().bold(),
+            old_name.cyan(),
+            new_name.cyan(),
+            profile.name.yellow()
+        );
+    }
+
+    Ok(())
 }
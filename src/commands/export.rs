@@ -2,14 +2,23 @@
 //!
 //! Export flake configurations to Docker, Podman, or JSON formats.
 
-use std::{path::Path, process::Command};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
 
-use anyhow::{Context, Ok, Result};
-use clap::ValueEnum;
-use std::fs::File;
+use anyhow::{bail, Context, Result};
+use clap::{Args, ValueEnum};
+use colored::Colorize;
 
 use crate::nix::run_nix_command;
-use flk::flake::parsers::{flake::parse_flake, utils::resolve_profile};
+use flk::config::Config;
+use flk::flake::nix_render::nix_string;
+use flk::flake::parsers::{
+    container::{parse_container_config_section, render_exposed_ports, render_string_list},
+    flake::parse_flake,
+    secrets::parse_secrets,
+    utils::resolve_profile,
+};
 use flk::utils::visual::with_spinner;
 
 /// Export format options.
@@ -24,93 +33,451 @@ pub enum ExportType {
     Json,
 }
 
+/// Options for Docker and Podman image exports.
+#[derive(Debug, Clone, Default, Args)]
+pub struct ImageOptions {
+    /// Image name (defaults to the export.image_name setting, or the profile name when --tag is given)
+    #[arg(long)]
+    pub image_name: Option<String>,
+    /// Image tag (defaults to "latest" when --image-name is given)
+    #[arg(long)]
+    pub tag: Option<String>,
+    /// Save the image archive to this path instead of loading it
+    #[arg(short, long, value_name = "TAR")]
+    pub output: Option<PathBuf>,
+    /// Load the image into the container runtime (the default unless --output is given)
+    #[arg(long)]
+    pub load: bool,
+    /// Port to expose in the image, e.g. 8080 or 53/udp (repeatable)
+    #[arg(long = "port", value_name = "PORT")]
+    pub ports: Vec<String>,
+    /// Working directory inside the container
+    #[arg(long)]
+    pub workdir: Option<String>,
+    /// Container entrypoint, e.g. "npm start"
+    #[arg(long)]
+    pub entrypoint: Option<String>,
+}
+
+impl ImageOptions {
+    fn is_empty(&self) -> bool {
+        self.image_name.is_none()
+            && self.tag.is_none()
+            && self.output.is_none()
+            && !self.load
+            && self.ports.is_empty()
+            && self.workdir.is_none()
+            && self.entrypoint.is_none()
+    }
+
+    /// The `name:tag` reference requested on the command line or by the
+    /// `export.image_name` setting, if any.
+    fn image_reference(&self, profile: &str, configured_name: Option<&str>) -> Option<String> {
+        let image_name = self.image_name.as_deref().or(configured_name);
+        if image_name.is_none() && self.tag.is_none() {
+            return None;
+        }
+        Some(format!(
+            "{}:{}",
+            image_name.unwrap_or(profile),
+            self.tag.as_deref().unwrap_or("latest")
+        ))
+    }
+}
+
+/// Container runtime used to load and run images.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+#[value(rename_all = "lowercase")]
+pub enum ContainerRuntime {
+    /// Use Docker
+    Docker,
+    /// Use Podman
+    Podman,
+}
+
+impl ContainerRuntime {
+    /// Executable name of the runtime.
+    pub(crate) fn binary(&self) -> &'static str {
+        match self {
+            ContainerRuntime::Docker => "docker",
+            ContainerRuntime::Podman => "podman",
+        }
+    }
+
+    fn display_name(&self) -> &'static str {
+        match self {
+            ContainerRuntime::Docker => "Docker",
+            ContainerRuntime::Podman => "Podman",
+        }
+    }
+}
+
 /// Export the flake configuration to the specified format.
 ///
 /// Supports Docker image, Podman image, and JSON export. Docker and Podman
-/// exports build a Nix-based container image and load it into the respective
-/// runtime. JSON export serializes the parsed flake configuration to `flake.json`.
+/// exports build a Nix-based container image, then save the archive to
+/// `--output` and/or load it into the respective runtime. JSON export
+/// serializes the parsed flake configuration to `flake.json`.
 ///
 /// # Arguments
 ///
 /// * `export_type` - Target format (Docker, Podman, or JSON)
 /// * `target_profile` - Optional profile override
-pub fn run_export(export_type: &ExportType, target_profile: Option<String>) -> Result<()> {
-    let profile = resolve_profile(target_profile)?;
+/// * `options` - Image naming, output and `containerConfig` options
+/// * `config` - Resolved settings
+pub fn run_export(
+    export_type: &ExportType,
+    target_profile: Option<String>,
+    options: &ImageOptions,
+    config: &Config,
+) -> Result<()> {
     match export_type {
         ExportType::Docker => {
-            println!("Exporting flake.nix to Docker image...");
-            let (_, _, success) = with_spinner("<export-docker>", || {
-                run_nix_command(&[
-                    "build",
-                    &format!(".#docker-{}", profile.as_str()),
-                    "--out-link",
-                    ".flk/result",
-                    "--impure",
-                ])
-                .context("Failed to build Docker image from flake.nix")
-            })?;
-            println!("Docker image created successfully ✅");
-            let file = File::open(".flk/result").context("Failed to open .flk/result")?;
-
-            let output = with_spinner("<load-image>", || {
-                Command::new("docker")
-                    .args(["load"])
-                    .stdin(file)
-                    .output()
-                    .context("Failed to load Docker image")
-            })?;
-            println!(
-                "Docker image export {}",
-                if success {
-                    "succeeded ✅"
-                } else {
-                    "failed ❌"
-                }
-            );
-            println!("{}", String::from_utf8_lossy(&output.stdout));
+            export_image(ContainerRuntime::Docker, target_profile, options, config)
         }
         ExportType::Podman => {
-            println!("Exporting flake.nix to Podman image...");
-            let (_, _, success) = with_spinner("<export-podman>", || {
-                run_nix_command(&[
-                    "build",
-                    &format!(".#podman-{}", profile.as_str()),
-                    "--out-link",
-                    ".flk/result",
-                    "--impure",
-                ])
-                .context("Failed to build Podman image from flake.nix")
-            })?;
-            println!("Podman image created successfully ✅");
-            let file = File::open(".flk/result").context("Failed to open .flk/result")?;
-
-            let output = with_spinner("<load-image>", || {
-                Command::new("podman")
-                    .args(["load"])
-                    .stdin(file)
-                    .output()
-                    .context("Failed to load Podman image")
-            })?;
-            println!(
-                "Podman image export {}",
-                if success {
-                    "succeeded ✅"
-                } else {
-                    "failed ❌"
-                }
-            );
-            println!("{}", String::from_utf8_lossy(&output.stdout));
+            export_image(ContainerRuntime::Podman, target_profile, options, config)
         }
         ExportType::Json => {
+            if !options.is_empty() {
+                bail!("Image options can only be used with the docker and podman formats");
+            }
             let flake_path = Path::new("flake.nix");
             let flake_content = parse_flake(flake_path.to_str().unwrap())?;
 
             // Serialize the flake content to JSON file
             let json_output = serde_json::to_string_pretty(&flake_content)
                 .context("Failed to serialize flake content to JSON")?;
-            std::fs::write("flake.json", json_output).context("Failed to write flake.json file")?;
+            fs::write("flake.json", json_output).context("Failed to write flake.json file")?;
             println!("Flake export to JSON succeeded ✅");
+            Ok(())
+        }
+    }
+}
+
+fn export_image(
+    runtime: ContainerRuntime,
+    target_profile: Option<String>,
+    options: &ImageOptions,
+    config: &Config,
+) -> Result<()> {
+    let profile = resolve_profile(target_profile)?;
+    validate_image_options(options)?;
+
+    let staged = apply_container_config(&profile, options)?;
+    note_skipped_secrets(&profile);
+
+    println!(
+        "{} Building {} image for profile {}...",
+        "→".blue(),
+        runtime.display_name(),
+        profile.cyan()
+    );
+    let archive = match build_image(runtime, &profile) {
+        Ok(archive) => archive,
+        Err(err) => {
+            if let Some(staged) = staged {
+                staged.restore()?;
+            }
+            return Err(err);
+        }
+    };
+    if staged.is_some() {
+        println!(
+            "{} Updated containerConfig for profile {}",
+            "✓".green().bold(),
+            profile.cyan()
+        );
+    }
+    let reference = options.image_reference(&profile, config.export_image_name());
+
+    if let Some(output) = &options.output {
+        save_archive(&archive, output, reference.as_deref())?;
+        println!(
+            "{} Saved image archive to {}",
+            "✓".green().bold(),
+            output.display().to_string().cyan()
+        );
+    }
+
+    if options.load || options.output.is_none() {
+        let loaded = load_image(runtime, &archive)?;
+        let image = match reference {
+            Some(reference) => {
+                tag_image(runtime, &loaded, &reference)?;
+                reference
+            }
+            None => loaded,
+        };
+        println!(
+            "{} Loaded image {} into {}",
+            "✓".green().bold(),
+            image.cyan(),
+            runtime.binary()
+        );
+    }
+
+    Ok(())
+}
+
+/// Build the container image for a profile and return the archive path.
+///
+/// # Errors
+///
+/// Returns an error with Nix's output if the build fails.
+pub(crate) fn build_image(runtime: ContainerRuntime, profile: &str) -> Result<PathBuf> {
+    let archive = PathBuf::from(".flk/result");
+    let (_, stderr, success) = with_spinner("Building image...", || {
+        run_nix_command(&[
+            "build",
+            &format!(".#{}-{}", runtime.binary(), profile),
+            "--out-link",
+            ".flk/result",
+            "--impure",
+        ])
+        .context("Failed to run nix build")
+    })?;
+
+    if !success {
+        bail!(
+            "Failed to build {} image for profile '{}':\n{}",
+            runtime.display_name(),
+            profile,
+            stderr.trim()
+        );
+    }
+
+    Ok(archive)
+}
+
+/// Load an image archive into the runtime and return the loaded image reference.
+///
+/// # Errors
+///
+/// Returns an error if the runtime cannot be started or rejects the archive.
+pub(crate) fn load_image(runtime: ContainerRuntime, archive: &Path) -> Result<String> {
+    let output = with_spinner("Loading image...", || {
+        Command::new(runtime.binary())
+            .arg("load")
+            .arg("-i")
+            .arg(archive)
+            .output()
+            .with_context(|| format!("Failed to run '{} load'", runtime.binary()))
+    })?;
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        bail!("'{} load' failed:\n{}", runtime.binary(), stderr.trim());
+    }
+
+    parse_loaded_image(&stdout).with_context(|| {
+        format!(
+            "Could not determine the loaded image from '{} load' output:\n{}",
+            runtime.binary(),
+            stdout.trim()
+        )
+    })
+}
+
+/// Extract the image reference from `docker load` / `podman load` output.
+fn parse_loaded_image(stdout: &str) -> Option<String> {
+    stdout.lines().find_map(|line| {
+        let (label, image) = line.split_once(": ")?;
+        match label.trim() {
+            "Loaded image" | "Loaded image(s)" | "Loaded image ID" => {
+                image.split(',').next().map(|s| s.trim().to_string())
+            }
+            _ => None,
         }
+    })
+}
+
+fn tag_image(runtime: ContainerRuntime, source: &str, reference: &str) -> Result<()> {
+    let output = Command::new(runtime.binary())
+        .args(["tag", source, reference])
+        .output()
+        .with_context(|| format!("Failed to run '{} tag'", runtime.binary()))?;
+
+    if !output.status.success() {
+        bail!(
+            "Failed to tag image '{}' as '{}':\n{}",
+            source,
+            reference,
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    Ok(())
+}
+
+/// Write the image archive to `output`, retagging it when a reference is given.
+///
+/// Retagging uses skopeo through `nix run`, so no container daemon is needed.
+fn save_archive(archive: &Path, output: &Path, reference: Option<&str>) -> Result<()> {
+    if output.exists() {
+        fs::remove_file(output)
+            .with_context(|| format!("Failed to replace '{}'", output.display()))?;
+    }
+
+    let Some(reference) = reference else {
+        fs::copy(archive, output)
+            .with_context(|| format!("Failed to write '{}'", output.display()))?;
+        return Ok(());
+    };
+
+    let (_, stderr, success) = with_spinner("Writing image archive...", || {
+        run_nix_command(&[
+            "run",
+            "nixpkgs#skopeo",
+            "--",
+            "--insecure-policy",
+            "copy",
+            &format!("docker-archive:{}", archive.display()),
+            &format!("docker-archive:{}:{}", output.display(), reference),
+        ])
+        .context("Failed to run skopeo")
+    })?;
+
+    if !success {
+        bail!(
+            "Failed to write image archive '{}':\n{}",
+            output.display(),
+            stderr.trim()
+        );
     }
     Ok(())
 }
+
+/// A profile file changed for a build, with its content before the change.
+struct StagedProfile {
+    path: PathBuf,
+    original: String,
+}
+
+impl StagedProfile {
+    /// Put the profile file back as it was before the change.
+    fn restore(self) -> Result<()> {
+        fs::write(&self.path, self.original)
+            .with_context(|| format!("Failed to restore '{}'", self.path.display()))
+    }
+}
+
+/// Write `--port`, `--workdir` and `--entrypoint` into the profile's `containerConfig`.
+///
+/// The image is built from the profile file, so the change is written before
+/// the build; the returned [`StagedProfile`] restores it if the build fails.
+fn apply_container_config(profile: &str, options: &ImageOptions) -> Result<Option<StagedProfile>> {
+    if options.ports.is_empty() && options.workdir.is_none() && options.entrypoint.is_none() {
+        return Ok(None);
+    }
+
+    let profile_path = Path::new(".flk/profiles/").join(format!("{}.nix", profile));
+    let mut content = fs::read_to_string(&profile_path)
+        .with_context(|| format!("Failed to read profile file '{}'", profile_path.display()))?;
+    let original = content.clone();
+
+    let mut updates = Vec::new();
+    if !options.ports.is_empty() {
+        let ports = options
+            .ports
+            .iter()
+            .map(|p| normalize_port(p))
+            .collect::<Result<Vec<_>>>()?;
+        updates.push(("ExposedPorts", render_exposed_ports(&ports)));
+    }
+    if let Some(workdir) = &options.workdir {
+        updates.push(("WorkingDir", nix_string(workdir)));
+    }
+    if let Some(entrypoint) = &options.entrypoint {
+        let Some(args) = shlex::split(entrypoint) else {
+            bail!("Invalid entrypoint '{}': unbalanced quotes", entrypoint);
+        };
+        if args.is_empty() {
+            bail!("Entrypoint cannot be empty");
+        }
+        updates.push(("Entrypoint", render_string_list(&args)));
+    }
+
+    for (key, value) in updates {
+        let section = parse_container_config_section(&content).with_context(|| {
+            format!(
+                "Failed to parse containerConfig in '{}'",
+                profile_path.display()
+            )
+        })?;
+        content = section.set_attr(&content, key, &value);
+    }
+
+    if content == original {
+        return Ok(None);
+    }
+    fs::write(&profile_path, content)
+        .with_context(|| format!("Failed to write '{}'", profile_path.display()))?;
+    Ok(Some(StagedProfile {
+        path: profile_path,
+        original,
+    }))
+}
+
+/// Tell the user that secrets stay out of the image; they only exist in the dev shell.
+fn note_skipped_secrets(profile: &str) {
+    let profile_path = Path::new(".flk/profiles/").join(format!("{}.nix", profile));
+    let secrets = fs::read_to_string(profile_path)
+        .map(|content| parse_secrets(&content))
+        .unwrap_or_default();
+    if !secrets.is_empty() {
+        let names: Vec<&str> = secrets.iter().map(|s| s.name.as_str()).collect();
+        println!(
+            "{} Secrets are not included in the image: {}",
+            "ℹ".blue(),
+            names.join(", ")
+        );
+    }
+}
+
+/// Normalize a port to OCI `port/protocol` form, defaulting to TCP.
+fn normalize_port(port: &str) -> Result<String> {
+    let (number, protocol) = port.split_once('/').unwrap_or((port, "tcp"));
+    let valid_number = number.parse::<u16>().map(|n| n > 0).unwrap_or(false);
+    if !valid_number || !matches!(protocol, "tcp" | "udp" | "sctp") {
+        bail!(
+            "Invalid port '{}'. Use a number between 1 and 65535, optionally followed by /tcp, /udp or /sctp.",
+            port
+        );
+    }
+    Ok(format!("{}/{}", number, protocol))
+}
+
+fn validate_image_options(options: &ImageOptions) -> Result<()> {
+    if let Some(name) = &options.image_name {
+        let valid = !name.is_empty()
+            && name
+                .chars()
+                .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || "._-/:".contains(c))
+            && name.starts_with(|c: char| c.is_ascii_alphanumeric())
+            && name.ends_with(|c: char| c.is_ascii_alphanumeric());
+        if !valid {
+            bail!(
+                "Invalid image name '{}'. Use lowercase letters, digits and '.', '_', '-', '/' separators.",
+                name
+            );
+        }
+    }
+
+    if let Some(tag) = &options.tag {
+        let valid = !tag.is_empty()
+            && tag.len() <= 128
+            && tag
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '.' | '-'))
+            && !tag.starts_with(['.', '-']);
+        if !valid {
+            bail!(
+                "Invalid image tag '{}'. Tags may contain letters, digits, '_', '.' and '-' (max 128 characters).",
+                tag
+            );
+        }
+    }
+
+    Ok(())
+}
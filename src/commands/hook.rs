@@ -2,6 +2,8 @@
 //!
 //! Generate shell hooks for bash, zsh, and fish that enable the
 //! `refresh` and `switch` commands for hot-reloading environments.
+//!
+//! Both accept profile combinations such as `switch rust+python`.
 
 use crate::commands::profile_cache::profile_cache_hook_inputs;
 use anyhow::Result;
@@ -28,11 +30,20 @@ pub fn run_hook(shell: HookShell) -> Result<()> {
 }
 
 fn print_bash_like() {
-    let cache_inputs = profile_cache_hook_inputs("$profile");
+    let cache_inputs = profile_cache_hook_inputs("$(_flk_profile_files \"$profile\")");
     println!(
         r#"# flk hook: refresh/switch for direnv + nix develop
 _flk_use_direnv() {{ command -v direnv >/dev/null 2>&1 && [ -f .envrc ]; }}
-_flk_valid_profile() {{ [[ "$1" =~ ^[a-zA-Z0-9_-]+$ ]]; }}
+_flk_valid_profile() {{
+  local re='^[a-zA-Z0-9_-]+(\+[a-zA-Z0-9_-]+)*$'
+  [[ "$1" =~ $re ]]
+}}
+_flk_profile_files() {{
+  local part
+  for part in $(printf '%s' "$1" | tr '+' ' '); do
+    printf '.flk/profiles/%s.nix\n' "$part"
+  done
+}}
 _flk_profile_path() {{ printf '.flk/.nix-profile-%s' "$1"; }}
 _flk_profile_stamp() {{ printf '.flk/.nix-profile-%s.stamp' "$1"; }}
 _flk_profile_is_fresh() {{
@@ -87,7 +98,7 @@ refresh() {{
 switch() {{
   local profile="$1"
   if [ -z "$profile" ]; then
-    printf 'usage: switch <profile>\n' >&2
+    printf 'usage: switch <profile>[+<profile>...]\n' >&2
     return 1
   fi
   if ! _flk_valid_profile "$profile"; then
@@ -110,7 +121,7 @@ switch() {{
 }
 
 fn print_fish() {
-    let cache_inputs = profile_cache_hook_inputs("$profile");
+    let cache_inputs = profile_cache_hook_inputs("(_flk_profile_files $profile)");
     println!(
         r#"# flk hook: refresh/switch for direnv + nix develop (fish)
 function _flk_use_direnv
@@ -118,7 +129,13 @@ function _flk_use_direnv
 end
 
 function _flk_valid_profile
-  string match -qr '^[a-zA-Z0-9_-]+$' -- $argv[1]
+  string match -qr '^[a-zA-Z0-9_-]+(\+[a-zA-Z0-9_-]+)*$' -- $argv[1]
+end
+
+function _flk_profile_files
+  for part in (string split + -- $argv[1])
+    printf '.flk/profiles/%s.nix\n' $part
+  end
 end
 
 function _flk_profile_path
@@ -181,7 +198,7 @@ end
 
 function switch --description "Switch profile and reload"
   if test (count $argv) -lt 1
-    echo "usage: switch <profile>" 1>&2
+    echo "usage: switch <profile>[+<profile>...]" 1>&2
     return 1
   end
   set profile $argv[1]
@@ -0,0 +1,257 @@
+//! # Import Command Handler
+//!
+//! Recreate or update profiles from a document produced by `flk export`.
+
+use anyhow::{bail, Context, Result};
+use clap::ValueEnum;
+use colored::Colorize;
+use std::fs;
+use std::path::Path;
+
+use flk::flake::generator;
+use flk::flake::interfaces::profiles::{FlakeConfig, Profile};
+use flk::flake::parsers::{
+    env::parse_env_value,
+    overlays::parse_overlay_section,
+    profile::{write_profile, ProfileWriteMode},
+    secrets::validate_secret_source,
+    utils::{is_valid_command_name, is_valid_env_var_name, is_valid_profile_name},
+};
+
+/// Import format options.
+#[derive(Debug, Clone, ValueEnum)]
+#[value(rename_all = "lowercase")]
+pub enum ImportType {
+    /// Import a `flk export --format json` document
+    Json,
+}
+
+/// Import profiles from a previously exported document.
+///
+/// New profiles are created from the base template. Existing profiles are
+/// only touched when `merge` or `replace` is set: merging adds and updates
+/// entries while keeping local ones, replacing makes the profile match the
+/// document exactly. All files are validated and rendered before any is written.
+///
+/// # Arguments
+///
+/// * `import_type` - Source format
+/// * `file` - Path to the document to import
+/// * `target_profile` - Only import the profile with this name
+/// * `merge` - Merge into existing profiles
+/// * `replace` - Replace existing profiles
+pub fn run_import(
+    import_type: &ImportType,
+    file: &str,
+    target_profile: Option<String>,
+    merge: bool,
+    replace: bool,
+) -> Result<()> {
+    let config = match import_type {
+        ImportType::Json => read_json(file)?,
+    };
+
+    let profiles_path = Path::new(".flk/profiles");
+    if !profiles_path.exists() {
+        bail!("No .flk/profiles directory found. Have you run 'flk init'?");
+    }
+
+    let profiles: Vec<&Profile> = match &target_profile {
+        Some(name) => {
+            let profile = config
+                .profiles
+                .iter()
+                .find(|p| &p.name == name)
+                .with_context(|| format!("Profile '{}' not found in {}", name, file))?;
+            vec![profile]
+        }
+        None => config.profiles.iter().collect(),
+    };
+
+    if profiles.is_empty() {
+        bail!("No profiles found in {}", file);
+    }
+
+    let mode = if replace {
+        ProfileWriteMode::Replace
+    } else {
+        ProfileWriteMode::Merge
+    };
+
+    let mut pending = Vec::new();
+    for profile in profiles {
+        validate_profile(profile)?;
+
+        let profile_path = profiles_path.join(format!("{}.nix", profile.name));
+        let (content, created) = if profile_path.exists() {
+            if !merge && !replace {
+                bail!(
+                    "Profile {} already exists! Use {} or {} to update it.",
+                    profile.name.cyan(),
+                    "--merge".yellow(),
+                    "--replace".yellow()
+                );
+            }
+            let content = fs::read_to_string(&profile_path).with_context(|| {
+                format!("Failed to read profile file '{}'", profile_path.display())
+            })?;
+            (content, false)
+        } else {
+            (generator::generate_flake("base")?, true)
+        };
+
+        // New profiles always mirror the document, never the template defaults
+        let write_mode = if created {
+            ProfileWriteMode::Replace
+        } else {
+            mode
+        };
+        let (updated, report) = write_profile(&content, profile, write_mode)
+            .with_context(|| format!("Failed to write profile '{}'", profile.name))?;
+
+        pending.push((profile, profile_path, updated, report, created));
+    }
+
+    for (profile, profile_path, updated, report, created) in &pending {
+        fs::write(profile_path, updated)
+            .with_context(|| format!("Failed to write '{}'", profile_path.display()))?;
+
+        if *created {
+            println!(
+                "{} Created profile {}",
+                "✓".green().bold(),
+                profile.name.cyan()
+            );
+        } else if report.is_empty() {
+            println!(
+                "{} Profile {} is already up to date",
+                "✓".green().bold(),
+                profile.name.cyan()
+            );
+        } else {
+            println!(
+                "{} Updated profile {} ({} added, {} updated, {} removed)",
+                "✓".green().bold(),
+                profile.name.cyan(),
+                report.added.len(),
+                report.updated.len(),
+                report.removed.len()
+            );
+        }
+    }
+
+    warn_missing_pins(pending.iter().map(|(profile, ..)| *profile));
+
+    Ok(())
+}
+
+fn read_json(file: &str) -> Result<FlakeConfig> {
+    let content = fs::read_to_string(file).with_context(|| format!("Failed to read '{}'", file))?;
+    serde_json::from_str(&content).with_context(|| format!("Failed to parse '{}' as JSON", file))
+}
+
+fn validate_profile(profile: &Profile) -> Result<()> {
+    if !is_valid_profile_name(&profile.name) {
+        bail!(
+            "Invalid profile name '{}'. Profile names must be alphanumeric (with - or _) and cannot contain path separators.",
+            profile.name
+        );
+    }
+
+    for package in &profile.packages {
+        let valid = !package.name.is_empty()
+            && package
+                .name
+                .chars()
+                .all(|c| c.is_alphanumeric() || matches!(c, '_' | '-' | '.'));
+        if !valid {
+            bail!(
+                "Invalid package name '{}' in profile '{}'",
+                package.name,
+                profile.name
+            );
+        }
+    }
+
+    for env_var in &profile.env_vars {
+        if !is_valid_env_var_name(&env_var.name) {
+            bail!(
+                "Invalid environment variable name '{}' in profile '{}'",
+                env_var.name,
+                profile.name
+            );
+        }
+        if let Some(nix) = &env_var.nix {
+            if parse_env_value(nix).as_deref() != Some(env_var.value.as_str()) {
+                bail!(
+                    "Invalid Nix value {} for environment variable '{}' in profile '{}'",
+                    nix,
+                    env_var.name,
+                    profile.name
+                );
+            }
+        }
+    }
+
+    for secret in &profile.secrets {
+        if !is_valid_env_var_name(&secret.name) {
+            bail!(
+                "Invalid secret name '{}' in profile '{}'",
+                secret.name,
+                profile.name
+            );
+        }
+        validate_secret_source(&secret.source).with_context(|| {
+            format!(
+                "Invalid secret '{}' in profile '{}'",
+                secret.name, profile.name
+            )
+        })?;
+    }
+
+    for entry in &profile.shell_hook.entries {
+        if !is_valid_command_name(&entry.name) {
+            bail!(
+                "Invalid command name '{}' in profile '{}'",
+                entry.name,
+                profile.name
+            );
+        }
+    }
+
+    Ok(())
+}
+
+/// Warn about pinned packages whose pin is not recorded in `.flk/pins.nix`.
+fn warn_missing_pins<'a>(profiles: impl Iterator<Item = &'a Profile>) {
+    let pins_content = fs::read_to_string(".flk/pins.nix").unwrap_or_default();
+    let pinned: Vec<String> = parse_overlay_section(&pins_content)
+        .map(|section| {
+            section
+                .entries
+                .into_iter()
+                .flat_map(|e| e.packages.into_iter().map(|p| p.pin_name))
+                .collect()
+        })
+        .unwrap_or_default();
+
+    for profile in profiles {
+        for package in &profile.packages {
+            if let Some(version) = package.pinned_version() {
+                let pin_name = format!("{}@{}", package.name, version);
+                if !pinned.contains(&pin_name) {
+                    println!(
+                        "{} {} is pinned but has no entry in .flk/pins.nix. Run {} to fetch the pin.",
+                        "⚠".yellow(),
+                        pin_name.cyan(),
+                        format!(
+                            "flk remove {0} -p {1} && flk add {0} --version {2} -p {1}",
+                            package.name, profile.name, version
+                        )
+                        .yellow()
+                    );
+                }
+            }
+        }
+    }
+}
@@ -1,21 +1,61 @@
 //! # Initialize Command Handler
 //!
-//! Initialize a new flk-managed development environment with project type detection.
+//! Initialize a new flk-managed development environment with project type detection,
+//! creating one profile per language found in the project.
 
 use anyhow::{bail, Context, Result};
 use colored::Colorize;
+use regex::Regex;
 use std::fs;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 
+use flk::config::Config;
 use flk::flake::generator;
+use flk::flake::parsers::extends::set_extends;
+
+/// Name of the profile created by `--combined`.
+const COMBINED_PROFILE: &str = "all";
+
+/// Subdirectories skipped while looking for projects.
+const IGNORED_DIRS: &[&str] = &[
+    "node_modules",
+    "target",
+    "vendor",
+    "dist",
+    "build",
+    "venv",
+    "_build",
+    "deps",
+    "bin",
+    "obj",
+];
+
+/// Directories whose children are scanned as well, as monorepos usually keep
+/// their projects one level below them.
+const WORKSPACE_DIRS: &[&str] = &["apps", "packages", "services", "libs", "crates", "projects"];
 
 /// Initialize a new flake environment in the current directory.
 ///
+/// Without a template every language found in the project and its common
+/// subdirectories gets its own profile, and `defaultShell` points at the main
+/// one: the first language found at the project root.
+///
 /// # Arguments
 ///
-/// * `template` - Optional project type (rust, python, node, go, generic)
+/// * `template` - Optional template name; detected from the project files when omitted
+/// * `only` - Only create profiles for these detected languages
+/// * `combined` - Also create an `all` profile extending every detected profile and make it the default
+/// * `vars` - `key=value` assignments for template variables
 /// * `force` - If true, overwrite existing flake.nix
-pub fn run(template: Option<String>, force: bool) -> Result<()> {
+/// * `config` - Resolved settings
+pub fn run(
+    template: Option<String>,
+    only: Vec<String>,
+    combined: bool,
+    vars: Vec<String>,
+    force: bool,
+    config: &Config,
+) -> Result<()> {
     let flake_path = Path::new("flake.nix");
 
     // Check if flake.nix already exists
@@ -26,20 +66,64 @@ pub fn run(template: Option<String>, force: bool) -> Result<()> {
         );
     }
 
-    // Detect project type if not specified
-    let project_type = template.unwrap_or_else(detect_project_type);
+    // Detect project types if not specified
+    let profiles = match template {
+        Some(template) => vec![template],
+        None => detect_profiles(&only, config.default_template())?,
+    };
+    let values = generator::template_values(&profiles, &vars)?;
 
     println!(
-        "{} Initializing flake for {} project...",
+        "{} Initializing flake for {} {}...",
         "→".blue().bold(),
-        project_type.green()
+        profiles.join(", ").green(),
+        if profiles.len() == 1 {
+            "project"
+        } else {
+            "projects"
+        }
     );
 
+    let combined = if combined && profiles.len() < 2 {
+        println!(
+            "{} Only one profile was created; skipping the combined profile",
+            "⚠".yellow()
+        );
+        false
+    } else {
+        combined
+    };
+    let default_shell = if combined {
+        Some(COMBINED_PROFILE)
+    } else if profiles.len() > 1 {
+        Some(profiles[0].as_str())
+    } else {
+        None
+    };
+
     // Generate flake.nix content
-    let root_flake_content = generator::generate_root_flake()?;
-    let helper_content = generator::generate_helper_module()?;
+    let root_flake_content = generator::generate_root_flake_with_channel(config.channel())?;
+    let mut helper_content = generator::generate_helper_module()?;
+    if let Some(default_shell) = default_shell {
+        helper_content = helper_content.replacen(
+            r#"defaultShell = "";"#,
+            &format!(r#"defaultShell = "{}";"#, default_shell),
+            1,
+        );
+    }
     let importer_content = generator::generate_importer_module()?;
-    let profile_content = generator::generate_flake(&project_type)?;
+    let mut profile_contents = profiles
+        .iter()
+        .map(|name| {
+            Ok((
+                name.clone(),
+                generator::generate_flake_with_vars(name, &values)?,
+            ))
+        })
+        .collect::<Result<Vec<_>>>()?;
+    if combined {
+        profile_contents.push((COMBINED_PROFILE.to_string(), combined_profile(&profiles)?));
+    }
     let overlays_content = generator::generate_overlays()?;
     let pins_content = generator::generate_pins()?;
 
@@ -48,11 +132,10 @@ pub fn run(template: Option<String>, force: bool) -> Result<()> {
 
     // Write to file
     fs::write(flake_path, root_flake_content).context("Failed to write flake.nix")?;
-    fs::write(
-        format!(".flk/profiles/{}.nix", project_type),
-        profile_content,
-    )
-    .context("Failed to write profile file")?;
+    for (name, content) in &profile_contents {
+        fs::write(format!(".flk/profiles/{}.nix", name), content)
+            .with_context(|| format!("Failed to write profile file for '{}'", name))?;
+    }
     fs::write(".flk/default.nix", helper_content).context("Failed to write helper nix file")?;
     fs::write(".flk/overlays.nix", overlays_content).context("Failed to write overlays.nix")?;
     fs::write(".flk/pins.nix", pins_content).context("Failed to write pins.nix")?;
@@ -63,6 +146,21 @@ pub fn run(template: Option<String>, force: bool) -> Result<()> {
         "{} Created flk environment successfully!",
         "✓".green().bold()
     );
+    if profile_contents.len() > 1 {
+        println!(
+            "{} Created profiles: {}",
+            "ℹ".blue(),
+            profile_contents
+                .iter()
+                .map(|(name, _)| name.as_str())
+                .collect::<Vec<_>>()
+                .join(", ")
+                .cyan()
+        );
+    }
+    if let Some(default_shell) = default_shell {
+        println!("{} Default profile: {}", "ℹ".blue(), default_shell.cyan());
+    }
     // Add message for adding `flk hook <shell>` to shell config
     println!(
         "\n{} To enable shell integration, add the hook to your shell configuration:",
@@ -77,28 +175,265 @@ pub fn run(template: Option<String>, force: bool) -> Result<()> {
     Ok(())
 }
 
-fn detect_project_type() -> String {
-    // Check for common project files
-    if Path::new("Cargo.toml").exists() {
-        println!("{} Detected Rust project", "ℹ".blue());
-        return "rust".to_string();
+/// Build the profile created by `--combined`: an empty profile extending all others.
+fn combined_profile(profiles: &[String]) -> Result<String> {
+    let content = generator::generate_flake("base")?;
+    let content = Regex::new(r#"description = "[^"]*";"#)
+        .unwrap()
+        .replace(
+            &content,
+            format!(
+                r#"description = "Combined development environment ({})";"#,
+                profiles.join(", ")
+            ),
+        )
+        .to_string();
+    set_extends(&content, profiles)
+}
+
+/// Project files that identify a project type.
+struct ProjectMarker {
+    /// Template used for the project
+    template: &'static str,
+    /// Human-readable project type
+    label: &'static str,
+    /// Files whose presence identifies the project
+    files: &'static [&'static str],
+    /// File extensions whose presence identifies the project
+    extensions: &'static [&'static str],
+}
+
+/// Project markers, checked in order; the first match wins.
+const PROJECT_MARKERS: &[ProjectMarker] = &[
+    ProjectMarker {
+        template: "rust",
+        label: "Rust",
+        files: &["Cargo.toml"],
+        extensions: &[],
+    },
+    ProjectMarker {
+        template: "node",
+        label: "Node.js",
+        files: &["package.json"],
+        extensions: &[],
+    },
+    ProjectMarker {
+        template: "python",
+        label: "Python",
+        files: &["pyproject.toml", "requirements.txt"],
+        extensions: &[],
+    },
+    ProjectMarker {
+        template: "go",
+        label: "Go",
+        files: &["go.mod"],
+        extensions: &[],
+    },
+    ProjectMarker {
+        template: "java",
+        label: "Java/Kotlin",
+        files: &[
+            "pom.xml",
+            "build.gradle",
+            "build.gradle.kts",
+            "settings.gradle",
+            "settings.gradle.kts",
+        ],
+        extensions: &[],
+    },
+    ProjectMarker {
+        template: "cpp",
+        label: "C/C++",
+        files: &["CMakeLists.txt", "meson.build"],
+        extensions: &[],
+    },
+    ProjectMarker {
+        template: "zig",
+        label: "Zig",
+        files: &["build.zig", "build.zig.zon"],
+        extensions: &[],
+    },
+    ProjectMarker {
+        template: "haskell",
+        label: "Haskell",
+        files: &["stack.yaml", "cabal.project"],
+        extensions: &["cabal"],
+    },
+    ProjectMarker {
+        template: "elixir",
+        label: "Elixir",
+        files: &["mix.exs"],
+        extensions: &[],
+    },
+    ProjectMarker {
+        template: "ruby",
+        label: "Ruby",
+        files: &["Gemfile"],
+        extensions: &["gemspec"],
+    },
+    ProjectMarker {
+        template: "php",
+        label: "PHP",
+        files: &["composer.json"],
+        extensions: &[],
+    },
+    ProjectMarker {
+        template: "dotnet",
+        label: ".NET",
+        files: &[],
+        extensions: &["csproj", "fsproj", "sln"],
+    },
+];
+
+impl ProjectMarker {
+    fn matches(&self, dir: &Path) -> bool {
+        if self.files.iter().any(|f| dir.join(f).exists()) {
+            return true;
+        }
+        if self.extensions.is_empty() {
+            return false;
+        }
+        let Ok(entries) = fs::read_dir(dir) else {
+            return false;
+        };
+        entries.flatten().any(|entry| {
+            entry
+                .path()
+                .extension()
+                .and_then(|e| e.to_str())
+                .is_some_and(|ext| self.extensions.contains(&ext))
+        })
+    }
+}
+
+/// A language found in the project.
+struct DetectedProject {
+    marker: &'static ProjectMarker,
+    /// Directories containing the project files, relative to the project root
+    dirs: Vec<PathBuf>,
+}
+
+/// Directories searched for project files: the root, its subdirectories and
+/// the children of common workspace directories.
+fn candidate_dirs() -> Vec<PathBuf> {
+    let subdirs = |dir: &Path| -> Vec<PathBuf> {
+        let Ok(entries) = fs::read_dir(dir) else {
+            return Vec::new();
+        };
+        let mut dirs: Vec<PathBuf> = entries
+            .flatten()
+            .filter(|entry| entry.file_type().is_ok_and(|t| t.is_dir()))
+            .filter(|entry| {
+                let name = entry.file_name();
+                let name = name.to_string_lossy();
+                !name.starts_with('.') && !IGNORED_DIRS.contains(&name.as_ref())
+            })
+            .map(|entry| entry.path())
+            .collect();
+        dirs.sort();
+        dirs
+    };
+
+    let root = Path::new(".");
+    let mut dirs = vec![root.to_path_buf()];
+    for dir in subdirs(root) {
+        let is_workspace = dir
+            .file_name()
+            .and_then(|n| n.to_str())
+            .is_some_and(|n| WORKSPACE_DIRS.contains(&n));
+        dirs.push(dir.clone());
+        if is_workspace {
+            dirs.extend(subdirs(&dir));
+        }
+    }
+    dirs
+}
+
+/// Find every language used in the project, the main one first.
+fn detect_project_types() -> Vec<DetectedProject> {
+    let dirs = candidate_dirs();
+    let mut detected: Vec<DetectedProject> = PROJECT_MARKERS
+        .iter()
+        .filter_map(|marker| {
+            let found: Vec<PathBuf> = dirs.iter().filter(|d| marker.matches(d)).cloned().collect();
+            (!found.is_empty()).then_some(DetectedProject {
+                marker,
+                dirs: found,
+            })
+        })
+        .collect();
+
+    // The main language is the first one found at the project root
+    if let Some(main) = detected
+        .iter()
+        .position(|p| p.dirs.iter().any(|d| d == Path::new(".")))
+    {
+        let main = detected.remove(main);
+        detected.insert(0, main);
+    }
+    detected
+}
+
+/// Pick the profiles to create from the detected languages.
+///
+/// `fallback` is used when nothing is detected, instead of `generic`.
+fn detect_profiles(only: &[String], fallback: Option<&str>) -> Result<Vec<String>> {
+    for name in only {
+        if !PROJECT_MARKERS.iter().any(|m| m.template == name) {
+            bail!(
+                "Unknown language '{}' for --only. Detectable templates: {}",
+                name,
+                PROJECT_MARKERS
+                    .iter()
+                    .map(|m| m.template)
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            );
+        }
     }
-    if Path::new("package.json").exists() {
-        println!("{} Detected Node.js project", "ℹ".blue());
-        return "node".to_string();
+
+    let mut detected = detect_project_types();
+    for project in &detected {
+        let dirs: Vec<String> = project
+            .dirs
+            .iter()
+            .map(|d| match d.strip_prefix(".") {
+                Ok(rel) if !rel.as_os_str().is_empty() => rel.display().to_string(),
+                _ => ".".to_string(),
+            })
+            .collect();
+        println!(
+            "{} Detected {} project {}",
+            "ℹ".blue(),
+            project.marker.label,
+            format!("({})", dirs.join(", ")).dimmed()
+        );
     }
-    if Path::new("pyproject.toml").exists() || Path::new("requirements.txt").exists() {
-        println!("{} Detected Python project", "ℹ".blue());
-        return "python".to_string();
+
+    if !only.is_empty() {
+        for name in only {
+            if !detected.iter().any(|p| p.marker.template == name) {
+                println!("{} No {} project detected; skipping", "⚠".yellow(), name);
+            }
+        }
+        detected.retain(|p| only.iter().any(|name| name == p.marker.template));
+        if detected.is_empty() {
+            bail!("None of the languages passed to --only were detected");
+        }
     }
-    if Path::new("go.mod").exists() {
-        println!("{} Detected Go project", "ℹ".blue());
-        return "go".to_string();
+
+    if detected.is_empty() {
+        let fallback = fallback.unwrap_or("generic");
+        println!(
+            "{} No specific project type detected, using {} template",
+            "ℹ".blue(),
+            fallback
+        );
+        return Ok(vec![fallback.to_string()]);
     }
 
-    println!(
-        "{} No specific project type detected, using generic template",
-        "ℹ".blue()
-    );
-    "generic".to_string()
+    Ok(detected
+        .iter()
+        .map(|p| p.marker.template.to_string())
+        .collect())
 }
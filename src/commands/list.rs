@@ -1,13 +1,18 @@
 //! # List Command Handler
 //!
-//! List packages in the active development profile.
+//! List packages in the active development profile, including the ones it
+//! inherits through `extends`.
 
 use colored::Colorize;
 use std::path::Path;
 
 use anyhow::{Context, Result};
 
-use flk::flake::parsers::{packages::parse_packages_section, utils::resolve_profile};
+use flk::flake::inheritance::effective_profile;
+use flk::flake::parsers::{
+    profile::parse_profile,
+    utils::{list_profiles, resolve_profile},
+};
 
 /// List all packages in the active development profile.
 ///
@@ -24,13 +29,40 @@ pub fn run_list(target_profile: Option<String>) -> Result<()> {
             flake_path.display()
         )
     })?;
-    let section = parse_packages_section(&flake_content).with_context(|| {
-        format!(
-            "Failed to parse packages section in profile file '{}'",
-            flake_path.display()
-        )
-    })?;
-    let packages_info = section.to_packages();
+    let current = parse_profile(&profile, &flake_content)
+        .with_context(|| format!("Failed to parse profile file '{}'", flake_path.display()))?;
+
+    let packages_info: Vec<(String, Option<String>)> = if current.extends.is_empty() {
+        current
+            .packages
+            .into_iter()
+            .map(|pkg| (pkg.name, None))
+            .collect()
+    } else {
+        let mut profiles = Vec::new();
+        for path in list_profiles()? {
+            let name = path
+                .file_stem()
+                .context("Failed to get profile name")?
+                .to_string_lossy()
+                .to_string();
+            let content = std::fs::read_to_string(&path)
+                .with_context(|| format!("Failed to read profile file: {}", path.display()))?;
+            profiles.push(
+                parse_profile(&name, &content)
+                    .with_context(|| format!("Failed to parse profile: {}", path.display()))?,
+            );
+        }
+        let effective = effective_profile(&profile, &profiles)?;
+        effective
+            .packages
+            .iter()
+            .map(|pkg| {
+                let from = effective.is_inherited(pkg).then(|| pkg.from.clone());
+                (pkg.value.name.clone(), from)
+            })
+            .collect()
+    };
 
     if packages_info.is_empty() {
         println!(
@@ -40,8 +72,16 @@ pub fn run_list(target_profile: Option<String>) -> Result<()> {
         return Ok(());
     }
 
-    for pkg in packages_info {
-        println!("{} {}", "•".green(), pkg.name);
+    for (name, from) in packages_info {
+        match from {
+            Some(from) => println!(
+                "{} {} {}",
+                "•".green(),
+                name,
+                format!("(from {})", from).dimmed()
+            ),
+            None => println!("{} {}", "•".green(), name),
+        }
     }
 
     Ok(())
@@ -8,7 +8,8 @@ use serde_json::Value;
 use std::fs;
 use std::path::{Path, PathBuf};
 
-use flk::utils::backup::{create_backup, get_backup_dir};
+use flk::config::Config;
+use flk::utils::backup::{create_backup, get_backup_dir, prune_backups};
 
 /// Display detailed information about the current flake.lock file.
 pub fn show() -> Result<()> {
@@ -191,7 +192,8 @@ pub fn history() -> Result<()> {
 /// # Arguments
 ///
 /// * `backup_id` - Timestamp identifier or "latest" for the most recent backup
-pub fn restore(backup_id: &str) -> Result<()> {
+/// * `config` - Resolved settings
+pub fn restore(backup_id: &str, config: &Config) -> Result<()> {
     let backup_dir = get_backup_dir()?;
 
     if !backup_dir.exists() {
@@ -228,6 +230,7 @@ pub fn restore(backup_id: &str) -> Result<()> {
 
     // Restore the backup
     fs::copy(&backup_path, current_lock).context("Failed to restore backup")?;
+    prune_backups(current_lock, config.backup_retention())?;
 
     println!("{}", "✓ Lock file restored successfully!".green().bold());
     println!(
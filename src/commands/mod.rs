@@ -9,7 +9,12 @@
 //! ### Project Setup
 //! - [`init`] - Initialize a new flake environment with language detection
 //! - [`activate`] - Enter the Nix development shell
-//! - [`profiles`] - Create, remove, list, and set default profiles
+//! - [`run`] - Run a program or custom command in a profile's shell
+//! - [`profiles`] - Create, remove, rename, copy, merge, diff, list, and set default profiles
+//! - [`template`] - List, show and install profile templates
+//! - [`config`] - Read and change user and project settings
+//! - [`doctor`] - Diagnose Nix, shell integration and project setup problems
+//! - [`check`] - Lint profiles and pins, optionally fixing safe problems
 //!
 //! ### Package Management
 //! - [`add`] - Add packages to the environment (with optional version pinning)
@@ -20,13 +25,17 @@
 //! ### Environment Customization
 //! - [`command`] - Add/remove custom shell commands
 //! - [`env`] - Add/remove environment variables
+//! - [`service`] - Declare and supervise background services
 //!
 //! ### State Management
 //! - [`update`] - Update flake inputs
 //! - [`lock`] - Manage flake.lock backups and restoration
+//! - [`channel`] - Show or switch the nixpkgs branch
 //!
 //! ### Integration & Export
 //! - [`export`] - Export to Docker, Podman, or JSON
+//! - [`import`] - Import profiles from an exported JSON document
+//! - [`container`] - Run the exported container image of a profile
 //! - [`direnv`] - Manage direnv integration
 //! - [`hook`] - Generate shell hooks for bash/zsh/fish
 //! - [`completions`] - Generate shell completions
@@ -36,18 +45,27 @@
 
 pub mod activate;
 pub mod add;
+pub mod channel;
+pub mod check;
 pub mod command;
 pub mod completions;
+pub mod config;
+pub mod container;
 pub mod direnv;
+pub mod doctor;
 pub mod env;
 pub mod export;
 pub mod hook;
+pub mod import;
 pub mod init;
 pub mod list;
 pub mod lock;
 pub mod profile_cache;
 pub mod profiles;
 pub mod remove;
+pub mod run;
 pub mod search;
+pub mod service;
 pub mod show;
+pub mod template;
 pub mod update;
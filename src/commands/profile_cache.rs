@@ -1,5 +1,9 @@
+use anyhow::{Context, Result};
+use std::fs;
 use std::path::{Path, PathBuf};
 
+use flk::flake::parsers::utils::split_profile_ref;
+
 pub(crate) const PROFILE_CACHE_INPUTS: [&str; 5] = [
     "flake.nix",
     "flake.lock",
@@ -8,26 +12,142 @@ pub(crate) const PROFILE_CACHE_INPUTS: [&str; 5] = [
     ".flk/overlays.nix",
 ];
 
+/// Files whose changes invalidate the cache of a profile or combination (`rust+python`).
 pub(crate) fn profile_cache_inputs(profile: &str) -> Vec<PathBuf> {
     let mut paths = PROFILE_CACHE_INPUTS
         .iter()
         .map(PathBuf::from)
         .collect::<Vec<_>>();
-    paths.push(Path::new(".flk/profiles").join(format!("{profile}.nix")));
+    paths.extend(
+        split_profile_ref(profile)
+            .into_iter()
+            .map(|part| Path::new(".flk/profiles").join(format!("{part}.nix"))),
+    );
     paths
 }
 
-/// Generate a space-separated list of quoted cache-input paths for shell `for` loops.
+/// Generate a space-separated list of cache-input paths for shell `for` loops.
 ///
-/// `profile_expr` should be a shell variable reference (e.g. `"$profile"`) so it is
-/// expanded at runtime by the target shell.
-pub(crate) fn profile_cache_hook_inputs(profile_expr: &str) -> String {
+/// `profile_files_expr` is appended unquoted and should be a shell expression
+/// expanding to the profile files of the current profile reference (one per
+/// combined profile), so it is evaluated at runtime by the target shell.
+pub(crate) fn profile_cache_hook_inputs(profile_files_expr: &str) -> String {
     PROFILE_CACHE_INPUTS
         .iter()
         .map(|path| format!("\"{path}\""))
-        .chain(std::iter::once(format!(
-            "\".flk/profiles/{profile_expr}.nix\""
-        )))
+        .chain(std::iter::once(profile_files_expr.to_string()))
         .collect::<Vec<_>>()
         .join(" ")
 }
+
+/// Path of the cached `nix develop --profile` environment for a profile.
+///
+/// Combinations get their own cache, e.g. `.flk/.nix-profile-rust+python`.
+pub(crate) fn profile_cache_path(profile: &str) -> PathBuf {
+    Path::new(".flk").join(format!(".nix-profile-{profile}"))
+}
+
+/// Path of the stamp file marking when the profile cache was last built.
+pub(crate) fn profile_cache_stamp_path(profile: &str) -> PathBuf {
+    Path::new(".flk").join(format!(".nix-profile-{profile}.stamp"))
+}
+
+/// Delete the cached environments of every profile reference that includes `profile`.
+///
+/// Covers the cache itself, its stamp and the `-<n>-link` generations that
+/// `nix develop --profile` leaves next to it, for the profile alone and for any
+/// combination containing it. Returns how many files were removed.
+pub(crate) fn remove_profile_caches(profile: &str) -> Result<usize> {
+    let flk_dir = Path::new(".flk");
+    if !flk_dir.exists() {
+        return Ok(0);
+    }
+
+    let mut removed = 0;
+    for entry in fs::read_dir(flk_dir).context("Failed to read .flk directory")? {
+        let path = entry?.path();
+        let Some(file_name) = path.file_name().and_then(|n| n.to_str()) else {
+            continue;
+        };
+        let Some(profile_ref) = file_name.strip_prefix(".nix-profile-") else {
+            continue;
+        };
+        let profile_ref = profile_ref.strip_suffix(".stamp").unwrap_or(profile_ref);
+        let profile_ref = strip_generation_suffix(profile_ref);
+
+        if split_profile_ref(profile_ref).contains(&profile) {
+            fs::remove_file(&path)
+                .with_context(|| format!("Failed to remove '{}'", path.display()))?;
+            removed += 1;
+        }
+    }
+    Ok(removed)
+}
+
+/// Strip the `-<n>-link` suffix of a `nix develop --profile` generation link.
+fn strip_generation_suffix(name: &str) -> &str {
+    name.strip_suffix("-link")
+        .and_then(|rest| rest.rsplit_once('-'))
+        .filter(|(_, generation)| {
+            !generation.is_empty() && generation.chars().all(|c| c.is_ascii_digit())
+        })
+        .map(|(base, _)| base)
+        .unwrap_or(name)
+}
+
+/// Whether the cached environment exists and no cache input changed since its stamp.
+pub(crate) fn profile_cache_is_fresh(
+    profile: &str,
+    profile_path: &Path,
+    stamp_path: &Path,
+) -> Result<bool> {
+    if !profile_path.exists() {
+        return Ok(false);
+    }
+    inputs_unchanged_since(profile, stamp_path)
+}
+
+/// Whether no cache input of `profile` was modified after `stamp_path`.
+///
+/// Returns `false` when the stamp does not exist.
+pub(crate) fn inputs_unchanged_since(profile: &str, stamp_path: &Path) -> Result<bool> {
+    if !stamp_path.exists() {
+        return Ok(false);
+    }
+
+    let stamp_modified = fs::metadata(stamp_path)
+        .with_context(|| {
+            format!(
+                "Failed to read metadata for profile cache stamp '{}'",
+                stamp_path.display()
+            )
+        })?
+        .modified()
+        .with_context(|| {
+            format!(
+                "Failed to read modification time for profile cache stamp '{}'",
+                stamp_path.display()
+            )
+        })?;
+
+    for path in profile_cache_inputs(profile) {
+        if !path.exists() {
+            continue;
+        }
+
+        let modified = fs::metadata(&path)
+            .with_context(|| format!("Failed to read metadata for '{}'", path.display()))?
+            .modified()
+            .with_context(|| {
+                format!("Failed to read modification time for '{}'", path.display())
+            })?;
+
+        // Treat equal mtimes as fresh — the stamp is written after a successful
+        // build, so inputs touched in the same clock tick were already incorporated.
+        if modified > stamp_modified {
+            return Ok(false);
+        }
+    }
+
+    Ok(true)
+}
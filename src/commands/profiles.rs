@@ -1,15 +1,24 @@
 //! # Profile Management Handler
 //!
-//! Create, remove, list, and set default profiles for managing multiple
-//! development environment configurations within a single project.
+//! Create, remove, rename, copy, merge, diff, list, and set default profiles
+//! for managing multiple development environment configurations within a
+//! single project.
 
 use anyhow::{bail, Context, Result};
 use colored::Colorize;
 use regex::Regex;
 use std::fs;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 
+use crate::commands::check::OutputFormat;
+use crate::commands::profile_cache::remove_profile_caches;
+use flk::config::Config;
+use flk::flake::diff::{diff_profiles, EntryDiff, ProfileDiff};
 use flk::flake::generator;
+use flk::flake::parsers::extends::{parse_extends_section, set_extends};
+use flk::flake::parsers::profile::{
+    merge_profiles, parse_profile, write_profile, ProfileWriteMode,
+};
 use flk::flake::parsers::utils::{get_default_shell_profile, is_valid_profile_name};
 
 /// Create a new profile from a template.
@@ -17,9 +26,18 @@ use flk::flake::parsers::utils::{get_default_shell_profile, is_valid_profile_nam
 /// # Arguments
 ///
 /// * `profile` - Name for the new profile
-/// * `template` - Template type (base, rust, python, node, go, generic); defaults to base
+/// * `template` - Template name (see `flk template list`); defaults to the
+///   `template.default` setting, or base
+/// * `vars` - `key=value` assignments for template variables
 /// * `force` - If true, overwrite an existing profile
-pub fn run_add(profile: String, template: Option<String>, force: bool) -> Result<()> {
+/// * `config` - Resolved settings
+pub fn run_add(
+    profile: String,
+    template: Option<String>,
+    vars: Vec<String>,
+    force: bool,
+    config: &Config,
+) -> Result<()> {
     let profiles_path = Path::new(".flk/profiles");
     let profile_path = profiles_path.join(format!("{}.nix", profile));
 
@@ -31,7 +49,10 @@ pub fn run_add(profile: String, template: Option<String>, force: bool) -> Result
         );
     }
 
-    let template = template.unwrap_or_else(|| "base".to_string());
+    let template = match template {
+        Some(template) => template,
+        None => config.default_template().unwrap_or("base").to_string(),
+    };
 
     // Check if profiles directory already exists
     if profile_path.exists() && !force {
@@ -42,7 +63,8 @@ pub fn run_add(profile: String, template: Option<String>, force: bool) -> Result
         );
     }
 
-    let profile_content = generator::generate_flake(&template)?;
+    let values = generator::template_values(std::slice::from_ref(&template), &vars)?;
+    let profile_content = generator::generate_flake_with_vars(&template, &values)?;
 
     fs::create_dir_all(profiles_path).context("Failed to create .flk and profiles directories")?;
 
@@ -57,8 +79,8 @@ pub fn run_add(profile: String, template: Option<String>, force: bool) -> Result
 
 /// Remove an existing profile.
 ///
-/// Validates the profile name, checks it exists, and ensures it is not
-/// the current default profile before deleting.
+/// Validates the profile name, checks it exists, and ensures it is neither
+/// the current default profile nor extended by another profile before deleting.
 ///
 /// # Arguments
 ///
@@ -91,6 +113,17 @@ pub fn run_remove(profile: String) -> Result<()> {
         }
     }
 
+    // Check if other profiles inherit from this one
+    let children = profiles_extending(profiles_path, &profile)?;
+    if !children.is_empty() {
+        bail!(
+            "Cannot remove profile {} because it is extended by: {}.\nRemove it from their {} list first.",
+            profile.cyan(),
+            children.join(", ").cyan(),
+            "extends".yellow()
+        );
+    }
+
     fs::remove_file(profile_path).context("Failed to remove profile file")?;
 
     println!("{} Removed profile successfully!", "✓".green().bold());
@@ -98,6 +131,347 @@ pub fn run_remove(profile: String) -> Result<()> {
     Ok(())
 }
 
+/// Rename a profile.
+///
+/// Also points `defaultShell` and the `extends` lists of other profiles at the
+/// new name, and deletes the cached environments built for the old name.
+///
+/// # Arguments
+///
+/// * `old` - Current profile name
+/// * `new` - New profile name
+pub fn run_rename(old: String, new: String) -> Result<()> {
+    let old_path = existing_profile_path(&old)?;
+    let new_path = new_profile_path(&new, None)?;
+
+    fs::rename(&old_path, &new_path).context("Failed to rename profile file")?;
+
+    let importer_path = Path::new(".flk/default.nix");
+    if importer_path.exists() {
+        let importer_content =
+            fs::read_to_string(importer_path).context("Failed to read default.nix file")?;
+        let default_re = Regex::new(&format!(
+            r#"defaultShell\s*=\s*"{}"\s*;"#,
+            regex::escape(&old)
+        ))
+        .unwrap();
+        if default_re.is_match(&importer_content) {
+            let updated =
+                default_re.replace(&importer_content, format!(r#"defaultShell = "{}";"#, new));
+            fs::write(importer_path, updated.as_ref())
+                .context("Failed to write default.nix file")?;
+            println!(
+                "{} Updated the default profile to {}",
+                "✓".green().bold(),
+                new.cyan()
+            );
+        }
+    }
+
+    let profiles_path = Path::new(".flk/profiles");
+    for child in profiles_extending(profiles_path, &old)? {
+        let child_path = profiles_path.join(format!("{}.nix", child));
+        let content = fs::read_to_string(&child_path)
+            .with_context(|| format!("Failed to read profile file: {}", child_path.display()))?;
+        let parents: Vec<String> = parse_extends_section(&content)?
+            .map(|section| section.parents)
+            .unwrap_or_default()
+            .into_iter()
+            .map(|parent| if parent == old { new.clone() } else { parent })
+            .collect();
+        fs::write(&child_path, set_extends(&content, &parents)?)
+            .with_context(|| format!("Failed to write profile file: {}", child_path.display()))?;
+        println!(
+            "{} Updated {} to extend {}",
+            "✓".green().bold(),
+            child.cyan(),
+            new.cyan()
+        );
+    }
+
+    let removed = remove_profile_caches(&old)?;
+    if removed > 0 {
+        println!(
+            "{} Removed {} cached environment file(s) for {}",
+            "ℹ".blue(),
+            removed,
+            old.cyan()
+        );
+    }
+
+    println!(
+        "{} Renamed profile {} to {}",
+        "✓".green().bold(),
+        old.cyan(),
+        new.cyan()
+    );
+
+    Ok(())
+}
+
+/// Copy a profile to a new name.
+///
+/// # Arguments
+///
+/// * `source` - Profile to copy
+/// * `destination` - Name of the new profile
+/// * `force` - If true, overwrite an existing destination profile
+pub fn run_copy(source: String, destination: String, force: bool) -> Result<()> {
+    let source_path = existing_profile_path(&source)?;
+    let destination_path = new_profile_path(&destination, Some(force))?;
+
+    fs::copy(&source_path, &destination_path).context("Failed to copy profile file")?;
+
+    println!(
+        "{} Copied profile {} to {}",
+        "✓".green().bold(),
+        source.cyan(),
+        destination.cyan()
+    );
+
+    Ok(())
+}
+
+/// Merge two profiles into a third one.
+///
+/// Packages, environment variables, commands and `extends` are combined.
+/// When both profiles define the same entry differently, the value from
+/// `first` is kept and the conflict is reported. The target may be a new
+/// profile or one of the two sources.
+///
+/// # Arguments
+///
+/// * `first` - Profile whose values win on conflicts
+/// * `second` - Profile merged on top of `first`
+/// * `into` - Name of the resulting profile
+pub fn run_merge(first: String, second: String, into: String) -> Result<()> {
+    if first == second {
+        bail!("Cannot merge profile {} with itself", first.cyan());
+    }
+
+    let first_path = existing_profile_path(&first)?;
+    let second_path = existing_profile_path(&second)?;
+    let into_path = if into == first || into == second {
+        existing_profile_path(&into)?
+    } else {
+        // Merging never overwrites a third profile
+        new_profile_path(&into, None)?
+    };
+
+    let first_content = fs::read_to_string(&first_path)
+        .with_context(|| format!("Failed to read profile file: {}", first_path.display()))?;
+    let second_content = fs::read_to_string(&second_path)
+        .with_context(|| format!("Failed to read profile file: {}", second_path.display()))?;
+    let first_profile = parse_profile(&first, &first_content)
+        .with_context(|| format!("Failed to parse profile: {}", first_path.display()))?;
+    let second_profile = parse_profile(&second, &second_content)
+        .with_context(|| format!("Failed to parse profile: {}", second_path.display()))?;
+
+    let (merged, conflicts) = merge_profiles(&into, &first_profile, &second_profile);
+
+    // Keep the target's own description, shellHook and containerConfig
+    let base = if into == second {
+        &second_content
+    } else {
+        &first_content
+    };
+    let (content, report) = write_profile(base, &merged, ProfileWriteMode::Merge)?;
+    fs::write(&into_path, content).context("Failed to write merged profile file")?;
+
+    println!(
+        "{} Merged {} and {} into {} ({} entr{} added)",
+        "✓".green().bold(),
+        first.cyan(),
+        second.cyan(),
+        into.cyan(),
+        report.added.len(),
+        if report.added.len() == 1 { "y" } else { "ies" }
+    );
+
+    if !conflicts.is_empty() {
+        println!(
+            "{} {} conflict(s), kept the values from {}:",
+            "⚠".yellow(),
+            conflicts.len(),
+            first.cyan()
+        );
+        for conflict in &conflicts {
+            println!(
+                "  {} {}: kept {}, dropped {}",
+                "•".yellow(),
+                conflict.entry.bold(),
+                format!("{:?}", conflict.kept).green(),
+                format!("{:?}", conflict.dropped).red()
+            );
+        }
+    }
+
+    Ok(())
+}
+
+/// Compare the packages, environment variables and commands of two profiles.
+///
+/// # Arguments
+///
+/// * `first` - Profile to compare from
+/// * `second` - Profile to compare to
+/// * `format` - Human-readable text or JSON
+pub fn run_diff(first: String, second: String, format: OutputFormat) -> Result<()> {
+    let mut profiles = Vec::new();
+    for name in [&first, &second] {
+        let path = existing_profile_path(name)?;
+        let content = fs::read_to_string(&path)
+            .with_context(|| format!("Failed to read profile file: {}", path.display()))?;
+        profiles.push(
+            parse_profile(name, &content)
+                .with_context(|| format!("Failed to parse profile: {}", path.display()))?,
+        );
+    }
+
+    let diff = diff_profiles(&profiles[0], &profiles[1]);
+
+    match format {
+        OutputFormat::Json => println!(
+            "{}",
+            serde_json::to_string_pretty(&diff).context("Failed to serialize profile diff")?
+        ),
+        OutputFormat::Text => print_diff(&diff),
+    }
+
+    Ok(())
+}
+
+fn print_diff(diff: &ProfileDiff) {
+    println!(
+        "{} Comparing {} {} {}",
+        "ℹ".blue(),
+        diff.from.cyan(),
+        "→".dimmed(),
+        diff.to.cyan()
+    );
+
+    if diff.is_empty() {
+        println!(
+            "{} Profiles have the same packages, environment variables and commands",
+            "✓".green().bold()
+        );
+        return;
+    }
+
+    print_section("Packages:", &diff.packages, |p| {
+        format!(
+            "{} {}",
+            p.name,
+            format!("({})", p.pinned_version().unwrap_or("latest")).dimmed()
+        )
+    });
+    print_section("Environment Variables:", &diff.env_vars, |e| {
+        format!("{} = {:?}", e.name, e.value)
+    });
+    print_section("Commands:", &diff.commands, |c| c.name.clone());
+}
+
+fn print_section<T>(title: &str, section: &EntryDiff<T>, describe: impl Fn(&T) -> String) {
+    if section.is_empty() {
+        return;
+    }
+
+    println!("\n{}", title.bold());
+    for entry in &section.removed {
+        println!("  {} {}", "-".red().bold(), describe(entry));
+    }
+    for entry in &section.added {
+        println!("  {} {}", "+".green().bold(), describe(entry));
+    }
+    for change in &section.changed {
+        if change.from.contains('\n') || change.to.contains('\n') {
+            println!("  {} {}", "~".yellow().bold(), change.name);
+            for line in change.from.lines() {
+                println!("      {}", format!("- {}", line).red());
+            }
+            for line in change.to.lines() {
+                println!("      {}", format!("+ {}", line).green());
+            }
+        } else {
+            println!(
+                "  {} {}: {} {} {}",
+                "~".yellow().bold(),
+                change.name,
+                format!("{:?}", change.from).red(),
+                "→".dimmed(),
+                format!("{:?}", change.to).green()
+            );
+        }
+    }
+}
+
+/// Validate `profile` and return the path of its existing file.
+fn existing_profile_path(profile: &str) -> Result<PathBuf> {
+    if !is_valid_profile_name(profile) {
+        bail!(
+            "Invalid profile name '{}'. Profile names must be alphanumeric (with - or _) and cannot contain path separators.",
+            profile.cyan()
+        );
+    }
+    let path = Path::new(".flk/profiles").join(format!("{}.nix", profile));
+    if !path.exists() {
+        bail!("Profile {} does not exist!", profile.cyan());
+    }
+    Ok(path)
+}
+
+/// Validate `profile` and return the path for a profile file about to be created.
+///
+/// `force` is `None` for commands without a `--force` flag; an existing
+/// profile is then never overwritten and the error does not suggest it.
+fn new_profile_path(profile: &str, force: Option<bool>) -> Result<PathBuf> {
+    if !is_valid_profile_name(profile) {
+        bail!(
+            "Invalid profile name '{}'. Profile names must be alphanumeric (with - or _) and cannot contain path separators.",
+            profile.cyan()
+        );
+    }
+    let path = Path::new(".flk/profiles").join(format!("{}.nix", profile));
+    if path.exists() {
+        match force {
+            Some(true) => {}
+            Some(false) => bail!(
+                "Profile {} already exists! Use {} to overwrite.",
+                profile.cyan(),
+                "--force".yellow()
+            ),
+            None => bail!(
+                "Profile {} already exists! Choose another name or remove it first.",
+                profile.cyan()
+            ),
+        }
+    }
+    Ok(path)
+}
+
+/// Names of the profiles in `profiles_path` whose `extends` list contains `profile`.
+fn profiles_extending(profiles_path: &Path, profile: &str) -> Result<Vec<String>> {
+    let mut children = Vec::new();
+    for entry in fs::read_dir(profiles_path).context("Failed to read profiles directory")? {
+        let path = entry?.path();
+        let Some(name) = path.file_stem().and_then(|s| s.to_str()) else {
+            continue;
+        };
+        if name == "default" || name == profile || path.extension().is_none_or(|e| e != "nix") {
+            continue;
+        }
+        let content = fs::read_to_string(&path)
+            .with_context(|| format!("Failed to read profile file: {}", path.display()))?;
+        if let Ok(Some(section)) = parse_extends_section(&content) {
+            if section.parents.iter().any(|p| p == profile) {
+                children.push(name.to_string());
+            }
+        }
+    }
+    children.sort();
+    Ok(children)
+}
+
 /// List all available profiles in `.flk/profiles/`.
 pub fn run_list() -> Result<()> {
     let profiles_path = Path::new(".flk/profiles");
@@ -200,3 +574,58 @@ pub fn run_set_default(profile: String) -> Result<()> {
 
     Ok(())
 }
+
+/// Set how many profiles can be combined into one shell (e.g. `rust+python`).
+///
+/// Updates the `maxCombinations` attribute passed to `profile-lib` in
+/// `.flk/default.nix`. Higher values generate more shells and slow down
+/// evaluation.
+///
+/// # Arguments
+///
+/// * `value` - Maximum number of profiles per combination
+pub fn run_set_max_combinations(value: usize) -> Result<()> {
+    if value == 0 {
+        bail!("maxCombinations must be at least 1");
+    }
+
+    let importer_path = Path::new(".flk/default.nix");
+    let importer_content =
+        fs::read_to_string(importer_path).context("Failed to read default.nix file")?;
+
+    let max_re = Regex::new(r"maxCombinations\s*=\s*\d+\s*;").unwrap();
+    let new_importer_content = if max_re.is_match(&importer_content) {
+        max_re
+            .replace(&importer_content, format!("maxCombinations = {};", value))
+            .to_string()
+    } else {
+        let marker = "inherit profileDefinitions;";
+        let Some(start) = importer_content.find(marker) else {
+            bail!("Could not find the mkProfileOutputs block in default.nix");
+        };
+        let line_start = importer_content[..start]
+            .rfind('\n')
+            .map(|i| i + 1)
+            .unwrap_or(0);
+        let indent = &importer_content[line_start..start];
+        let insert_pos = start + marker.len();
+        format!(
+            "{}\n{}maxCombinations = {};{}",
+            &importer_content[..insert_pos],
+            indent,
+            value,
+            &importer_content[insert_pos..]
+        )
+    };
+
+    fs::write(importer_path, &new_importer_content).context("Failed to write default.nix file")?;
+
+    println!(
+        "{} Profiles can now be combined up to {} at a time (e.g. {}).",
+        "✓".green().bold(),
+        value.to_string().cyan(),
+        "flk activate -p rust+python".cyan()
+    );
+
+    Ok(())
+}
@@ -0,0 +1,155 @@
+//! # Run Command Handler
+//!
+//! Run a program or custom command inside a profile's development shell
+//! without entering it interactively.
+
+use crate::commands::command::effective_commands;
+use crate::commands::profile_cache::{
+    profile_cache_is_fresh, profile_cache_path, profile_cache_stamp_path,
+};
+use anyhow::{Context, Result};
+use colored::Colorize;
+use flk::flake::interfaces::shellhooks::ShellHookEntry;
+use flk::flake::parsers::utils::resolve_profile_ref;
+use std::process::Command;
+
+/// Run a command in the development shell of the resolved profile.
+///
+/// If `command` names a custom command of the profile (including inherited
+/// ones), its dependencies run first and then its script runs with `bash`,
+/// receiving `args` as `$1`, `$2`, ...; otherwise `command` is run as a
+/// program. Scripts are passed to `bash` as written in the profile, so Nix
+/// interpolation and `''` escapes are not evaluated; such scripts get a
+/// warning. The cached `nix develop --profile`
+/// environment is reused when it is fresh and refreshed otherwise, as with
+/// `flk activate`. The command's exit code becomes flk's exit code.
+///
+/// # Arguments
+///
+/// * `command` - Custom command name or program to run
+/// * `args` - Arguments passed to the command
+/// * `current_profile` - Optional profile override or combination
+pub fn run(command: &str, args: &[String], current_profile: Option<String>) -> Result<()> {
+    let profile = resolve_profile_ref(current_profile)?;
+
+    let commands = effective_commands(&profile)?;
+    let mut argv: Vec<String> = if commands.command_exists(command) {
+        let order = commands.run_order(command)?;
+        for entry in order.iter().filter(|e| uses_nix_syntax(&e.script)) {
+            eprintln!(
+                "{} Command '{}' uses Nix interpolation or '' escapes, which flk run passes to bash unevaluated",
+                "⚠".yellow(),
+                entry.name
+            );
+        }
+        vec![
+            "bash".to_string(),
+            "-c".to_string(),
+            command_script(&order),
+            command.to_string(),
+        ]
+    } else {
+        vec![command.to_string()]
+    };
+    argv.extend(args.iter().cloned());
+
+    let mut cmd = develop_command(&profile, &argv, true)?;
+
+    // Keep stdout for the command itself so CI can capture its output
+    eprintln!(
+        "{} Running {} in profile {}",
+        "→".blue(),
+        command.cyan(),
+        profile.cyan()
+    );
+    let status = cmd.status().with_context(|| {
+        format!(
+            "Failed to start nix develop shell for profile '{}'",
+            profile
+        )
+    })?;
+
+    if !status.success() {
+        std::process::exit(status.code().unwrap_or(1));
+    }
+    Ok(())
+}
+
+/// Build a `nix develop` invocation running `argv` in a profile's shell.
+///
+/// The cached `nix develop --profile` environment is used when it is fresh.
+/// Otherwise the shell is evaluated from the flake, and with `update_cache`
+/// the cache is rebuilt and stamped on the way, as `flk activate` does.
+pub(crate) fn develop_command(
+    profile: &str,
+    argv: &[String],
+    update_cache: bool,
+) -> Result<Command> {
+    let profile_path = profile_cache_path(profile);
+    let stamp_path = profile_cache_stamp_path(profile);
+    let use_cached_profile = profile_cache_is_fresh(profile, &profile_path, &stamp_path)?;
+    let update_cache = update_cache && !use_cached_profile;
+    let mut cmd = Command::new("nix");
+    cmd.arg("develop");
+    if use_cached_profile {
+        cmd.arg(&profile_path);
+    } else {
+        cmd.arg(format!(".#{}", profile));
+    }
+    cmd.arg("--impure");
+    if update_cache {
+        cmd.arg("--profile");
+        cmd.arg(&profile_path);
+        cmd.env("FLK_PROFILE_PATH", &profile_path);
+        cmd.env("FLK_PROFILE_STAMP", &stamp_path);
+    }
+    cmd.arg("-c");
+    if update_cache {
+        cmd.arg("/bin/sh");
+        cmd.arg("-c");
+        cmd.arg(
+            "if [ -e \"$FLK_PROFILE_PATH\" ]; then \
+             mkdir -p \"$(dirname \"$FLK_PROFILE_STAMP\")\"; \
+             touch \"$FLK_PROFILE_STAMP\" || exit 1; \
+             fi; \
+             exec \"$@\"",
+        );
+        cmd.arg("sh");
+    }
+    cmd.args(argv);
+    Ok(cmd)
+}
+
+/// Whether a script relies on Nix evaluation: `${...}` interpolation or a
+/// `''` escape, both only meaningful inside the profile's `''` string.
+fn uses_nix_syntax(script: &str) -> bool {
+    script.contains("${") || script.contains("''")
+}
+
+/// Build the `bash` script that runs a command after its dependencies.
+///
+/// `order` comes from [`ShellHookSection::run_order`], so the command itself
+/// is last. Every command is defined as a function, as in the dev shell, so
+/// `local` and `return` work. Dependencies run without arguments; the first
+/// failure stops the script with its exit code.
+///
+/// [`ShellHookSection::run_order`]: flk::flake::interfaces::shellhooks::ShellHookSection::run_order
+fn command_script(order: &[&ShellHookEntry]) -> String {
+    let Some((command, deps)) = order.split_last() else {
+        return String::new();
+    };
+
+    let mut script = String::new();
+    for entry in order {
+        script.push_str(&format!(
+            "{}() {{\n{}\n}}\n",
+            entry.name,
+            entry.script.trim_end()
+        ));
+    }
+    for dep in deps {
+        script.push_str(&format!("{} || exit $?\n", dep.name));
+    }
+    script.push_str(&format!("{} \"$@\"\n", command.name));
+    script
+}
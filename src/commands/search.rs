@@ -1,13 +1,17 @@
 //! # Search Command Handler
 //!
-//! Search nixpkgs for packages using the nix-versions tool.
+//! Search nixpkgs for packages using the nix-versions tool, or `nix search`
+//! when `search.backend` is set to `nix`.
 
 use anyhow::{bail, Context, Result};
 use colored::Colorize;
 
 use crate::nix::{check_nix_available, run_nix_command};
+use flk::config::Config;
 use flk::flake::interfaces::profiles::Package;
-use flk::flake::parsers::packages::extract_packages_from_output;
+use flk::flake::parsers::packages::{
+    extract_packages_from_output, extract_packages_from_search_json,
+};
 use flk::utils::visual::{display_list, display_table, with_spinner};
 
 /// Search nixpkgs for packages matching the query.
@@ -16,36 +20,50 @@ use flk::utils::visual::{display_list, display_table, with_spinner};
 ///
 /// * `query` - Search term (supports wildcards)
 /// * `limit` - Maximum number of results to display
+/// * `config` - Resolved settings
 ///
 /// # Returns
 ///
 /// `true` if packages were found, `false` otherwise.
-pub fn run_search(query: &str, limit: usize) -> Result<bool> {
+pub fn run_search(query: &str, limit: usize, config: &Config) -> Result<bool> {
     println!(
         "{} Searching nixpkgs for: {}",
         "→".blue().bold(),
         query.green()
     );
-    let search_query = format!("*{}*", query);
 
     if !check_nix_available() {
         bail!("Nix command is not available, is it installed on the system?");
     }
 
-    // Use nix search command with JSON output
-    let (stdout, _, _) = with_spinner("Searching packages...", || {
-        run_nix_command(&[
-            "run",
-            "github:vic/nix-versions",
-            &search_query,
-            "--",
-            "--one",
-        ])
-        .context("Failed to execute nix search. Is nix installed?")
-    })?;
-
-    let packages: Vec<Package> =
-        extract_packages_from_output(&stdout).context("Failed to parse nix search output")?;
+    let packages: Vec<Package> = match config.search_backend() {
+        "nix" => {
+            let (stdout, stderr, success) = with_spinner("Searching packages...", || {
+                run_nix_command(&["search", "nixpkgs", query, "--json"])
+                    .context("Failed to execute nix search. Is nix installed?")
+            })?;
+            if !success {
+                bail!("nix search failed:\n{}", stderr.trim());
+            }
+            extract_packages_from_search_json(&stdout)
+                .context("Failed to parse nix search output")?
+        }
+        _ => {
+            let search_query = format!("*{}*", query);
+            // Use nix-versions for name and latest version
+            let (stdout, _, _) = with_spinner("Searching packages...", || {
+                run_nix_command(&[
+                    "run",
+                    "github:vic/nix-versions",
+                    &search_query,
+                    "--",
+                    "--one",
+                ])
+                .context("Failed to execute nix search. Is nix installed?")
+            })?;
+            extract_packages_from_output(&stdout).context("Failed to parse nix search output")?
+        }
+    };
 
     if packages.is_empty() {
         println!(
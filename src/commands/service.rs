@@ -0,0 +1,642 @@
+//! # Service Handler
+//!
+//! Declare background services such as databases in a profile and supervise
+//! them: start them detached in the profile's development shell, stop them,
+//! and show their status and logs.
+//!
+//! Each service keeps its state in `.flk/state/<profile>/<name>/`, where
+//! `<profile>` is the profile defining the service: the generated `run.sh`,
+//! the `pid` of its process group, `service.log`, and whatever the service
+//! stores itself, such as PostgreSQL's `data/` directory.
+
+use anyhow::{bail, Context, Result};
+use clap::Args;
+use colored::Colorize;
+use std::env;
+use std::fs::{self, File, OpenOptions};
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::net::{SocketAddr, TcpStream};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::commands::command::load_profiles;
+use crate::commands::run::develop_command;
+use flk::flake::inheritance::{effective_profile, Inherited};
+use flk::flake::interfaces::services::{Service, ServiceKind};
+use flk::flake::parsers::services::{parse_services_section, set_services};
+use flk::flake::parsers::utils::{
+    is_valid_attribute_path, is_valid_command_name, resolve_profile, resolve_profile_ref,
+    split_profile_ref,
+};
+
+/// Directory holding the state of every service.
+const STATE_DIR: &str = ".flk/state";
+/// How long `up` watches a new service for an immediate crash.
+const STARTUP_GRACE: Duration = Duration::from_secs(1);
+/// How long `down` waits for a service to exit before killing it.
+const STOP_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// How `flk service add` runs a service.
+#[derive(Debug, Clone, Default, Args)]
+pub struct ServiceDefinition {
+    /// TCP port the service listens on (postgres and redis have defaults)
+    #[arg(long)]
+    pub port: Option<u16>,
+    /// Major version of postgres, e.g. 16 for postgresql_16
+    #[arg(long, conflicts_with = "package")]
+    pub version: Option<String>,
+    /// nixpkgs package providing the service
+    #[arg(long)]
+    pub package: Option<String>,
+    /// Command running the service in the foreground
+    #[arg(long)]
+    pub command: Option<String>,
+}
+
+/// Read a profile file for editing its services.
+fn load_profile_file(target_profile: Option<String>) -> Result<(String, PathBuf, String)> {
+    let profile = resolve_profile(target_profile)?;
+    let path = Path::new(".flk/profiles/").join(format!("{}.nix", profile));
+    let content = fs::read_to_string(&path).with_context(|| {
+        format!(
+            "Failed to read profile file at '{}'. Have you run 'flk init'?",
+            path.display()
+        )
+    })?;
+    Ok((profile, path, content))
+}
+
+/// The services of a profile or profile combination, including inherited
+/// ones, each with the profile that defines it.
+fn effective_services(profile_ref: &str) -> Result<Vec<Inherited<Service>>> {
+    let profiles = load_profiles()?;
+    let mut services: Vec<Inherited<Service>> = Vec::new();
+    for part in split_profile_ref(profile_ref) {
+        for service in effective_profile(part, &profiles)?.services {
+            services.retain(|s| s.value.name != service.value.name);
+            services.push(service);
+        }
+    }
+    Ok(services)
+}
+
+/// The services named in `names`, or all of them if `names` is empty.
+fn select_services(profile: &str, names: &[String]) -> Result<Vec<Inherited<Service>>> {
+    let services = effective_services(profile)?;
+    if names.is_empty() {
+        return Ok(services);
+    }
+    names
+        .iter()
+        .map(|name| {
+            services
+                .iter()
+                .find(|s| &s.value.name == name)
+                .cloned()
+                .with_context(|| {
+                    format!("Service '{}' is not defined in profile '{}'", name, profile)
+                })
+        })
+        .collect()
+}
+
+/// State directory of a service, keyed by the profile defining it so that
+/// profiles with different versions of a service never share its data.
+fn state_dir(service: &Inherited<Service>) -> PathBuf {
+    Path::new(STATE_DIR)
+        .join(&service.from)
+        .join(&service.value.name)
+}
+
+fn log_path(service: &Inherited<Service>) -> PathBuf {
+    state_dir(service).join("service.log")
+}
+
+fn pid_path(service: &Inherited<Service>) -> PathBuf {
+    state_dir(service).join("pid")
+}
+
+fn read_pid(service: &Inherited<Service>) -> Option<u32> {
+    fs::read_to_string(pid_path(service))
+        .ok()
+        .and_then(|pid| pid.trim().parse().ok())
+}
+
+/// Send `signal` to the process group led by `pid`.
+///
+/// With signal `0` this only checks whether any process of the group is left.
+fn signal_group(pid: u32, signal: &str) -> bool {
+    Command::new("kill")
+        .args(["-s", signal, "--", &format!("-{}", pid)])
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status()
+        .map(|status| status.success())
+        .unwrap_or(false)
+}
+
+/// The pid of the running service, if it is running.
+fn running_pid(service: &Inherited<Service>) -> Option<u32> {
+    read_pid(service).filter(|pid| signal_group(*pid, "0"))
+}
+
+fn port_in_use(port: u16) -> bool {
+    let addr = SocketAddr::from(([127, 0, 0, 1], port));
+    TcpStream::connect_timeout(&addr, Duration::from_millis(200)).is_ok()
+}
+
+/// Add `.flk/state/` to the project's `.gitignore` so service data and logs
+/// are never committed.
+fn ignore_state_dir() -> Result<()> {
+    let path = Path::new(".gitignore");
+    let content = fs::read_to_string(path).unwrap_or_default();
+    let ignored = content.lines().any(|line| {
+        matches!(
+            line.trim(),
+            ".flk/state" | ".flk/state/" | "/.flk/state" | "/.flk/state/" | ".flk" | ".flk/"
+        )
+    });
+    if ignored {
+        return Ok(());
+    }
+
+    let mut updated = content.clone();
+    if !updated.is_empty() && !updated.ends_with('\n') {
+        updated.push('\n');
+    }
+    updated.push_str(".flk/state/\n");
+    fs::write(path, updated).context("Failed to update .gitignore")?;
+    println!(
+        "{} Added {} to .gitignore",
+        "ℹ".blue(),
+        ".flk/state/".cyan()
+    );
+    Ok(())
+}
+
+/// Start the service in its own process group so `down` can stop everything
+/// it spawned, and so it survives the terminal flk was started from.
+#[cfg(unix)]
+fn detach(cmd: &mut Command) {
+    use std::os::unix::process::CommandExt;
+
+    cmd.process_group(0);
+}
+
+#[cfg(not(unix))]
+fn detach(_cmd: &mut Command) {}
+
+/// Add a service to a profile.
+///
+/// `postgres` and `redis` are built in: their package, port and start
+/// command are filled in. Any other service needs a `--command` that runs
+/// it in the foreground, and usually a `--package` providing it.
+///
+/// # Arguments
+///
+/// * `name` - Service name, also used for its state directory
+/// * `definition` - Port, version, package and command options
+/// * `force` - Replace a service of the same name
+/// * `target_profile` - Optional profile override
+pub fn add(
+    name: &str,
+    definition: ServiceDefinition,
+    force: bool,
+    target_profile: Option<String>,
+) -> Result<()> {
+    if !is_valid_command_name(name) {
+        bail!(
+            "Invalid service name '{}'. Use only letters, numbers, hyphens, and underscores.",
+            name
+        );
+    }
+
+    if let Some(package) = definition.package.as_deref() {
+        if !is_valid_attribute_path(package) {
+            bail!(
+                "Invalid package '{}'. Use a Nix attribute path such as 'nodejs_20' or 'python3Packages.gunicorn'.",
+                package
+            );
+        }
+    }
+
+    let builtin = ServiceKind::from_name(name);
+    let package = match (definition.package, builtin, definition.version.as_deref()) {
+        (Some(package), _, _) => Some(package),
+        (None, Some(kind), version) => Some(kind.package(version)?),
+        (None, None, Some(_)) => bail!(
+            "--version only applies to the built-in services (postgres, redis); use --package instead"
+        ),
+        (None, None, None) => None,
+    };
+    let mut service = Service {
+        name: name.to_string(),
+        package,
+        port: definition.port,
+        command: definition.command.filter(|c| !c.trim().is_empty()),
+    };
+    service.port = service.effective_port();
+    if service.start_script().is_none() {
+        bail!(
+            "flk does not know how to run '{}'. Pass --command with the command that runs it in the foreground.",
+            name
+        );
+    }
+
+    let (profile, path, content) = load_profile_file(target_profile)?;
+    let mut services = parse_services_section(&content)
+        .with_context(|| format!("Failed to parse services in '{}'", path.display()))?
+        .map(|s| s.services)
+        .unwrap_or_default();
+
+    match services.iter().position(|s| s.name == name) {
+        Some(_) if !force => bail!(
+            "Service '{}' already exists in profile '{}'. Use --force to replace it.",
+            name,
+            profile
+        ),
+        Some(index) => services[index] = service.clone(),
+        None => services.push(service.clone()),
+    }
+    if let Some(port) = service.port {
+        if let Some(other) = services
+            .iter()
+            .find(|s| s.name != name && s.port == Some(port))
+        {
+            println!(
+                "{} {} also uses port {}; only one of them can run at a time",
+                "⚠".yellow(),
+                other.name.cyan(),
+                port
+            );
+        }
+    }
+
+    fs::write(&path, set_services(&content, &services)?)
+        .with_context(|| format!("Failed to write '{}'", path.display()))?;
+
+    println!(
+        "{} Service {} added to profile {}",
+        "✓".green().bold(),
+        service,
+        profile.cyan()
+    );
+    println!(
+        "{} Start it with {}",
+        "ℹ".blue(),
+        format!("flk service up {}", name).cyan()
+    );
+    Ok(())
+}
+
+/// Remove a service from a profile.
+///
+/// Its state directory is kept, so adding the service back finds its data.
+pub fn remove(name: &str, target_profile: Option<String>) -> Result<()> {
+    let (profile, path, content) = load_profile_file(target_profile)?;
+    let mut services = parse_services_section(&content)
+        .with_context(|| format!("Failed to parse services in '{}'", path.display()))?
+        .map(|s| s.services)
+        .unwrap_or_default();
+
+    let Some(removed) = services.iter().find(|s| s.name == name) else {
+        bail!("Service '{}' not found in profile '{}'", name, profile);
+    };
+    let removed = Inherited {
+        value: removed.clone(),
+        from: profile.clone(),
+    };
+    if let Some(pid) = running_pid(&removed) {
+        println!(
+            "{} {} is still running (pid {}); stop it with {} first",
+            "⚠".yellow(),
+            name.cyan(),
+            pid,
+            format!("flk service down {}", name).cyan()
+        );
+    }
+    services.retain(|s| s.name != name);
+
+    fs::write(&path, set_services(&content, &services)?)
+        .with_context(|| format!("Failed to write '{}'", path.display()))?;
+
+    println!(
+        "{} Service '{}' removed from profile {}",
+        "✓".green().bold(),
+        name,
+        profile.cyan()
+    );
+    if state_dir(&removed).exists() {
+        println!(
+            "{} Its state in {} was kept",
+            "ℹ".blue(),
+            state_dir(&removed).display()
+        );
+    }
+    Ok(())
+}
+
+/// Start services in the background.
+///
+/// Each service runs its `run.sh` inside the profile's development shell,
+/// detached from the terminal, with its output appended to `service.log`.
+/// Services that are already running are left alone.
+///
+/// # Arguments
+///
+/// * `names` - Services to start; all services of the profile if empty
+/// * `current_profile` - Optional profile override or combination
+pub fn up(names: &[String], current_profile: Option<String>) -> Result<()> {
+    let profile = resolve_profile_ref(current_profile)?;
+    let services = select_services(&profile, names)?;
+    if services.is_empty() {
+        println!(
+            "{} No services defined in profile {}",
+            "✗".red().bold(),
+            profile.cyan()
+        );
+        return Ok(());
+    }
+
+    ignore_state_dir()?;
+
+    let mut failed = Vec::new();
+    for service in &services {
+        let name = &service.value.name;
+        if let Some(pid) = running_pid(service) {
+            println!(
+                "{} {} is already running (pid {})",
+                "ℹ".blue(),
+                name.cyan(),
+                pid
+            );
+            continue;
+        }
+        if let Err(err) = start_service(&profile, service) {
+            println!("{} {}: {:#}", "✗".red().bold(), name.cyan(), err);
+            failed.push(name.clone());
+        }
+    }
+
+    if !failed.is_empty() {
+        bail!("Failed to start {}", failed.join(", "));
+    }
+    Ok(())
+}
+
+fn start_service(profile: &str, entry: &Inherited<Service>) -> Result<()> {
+    let service = &entry.value;
+    let script = service.start_script().with_context(|| {
+        format!(
+            "No command to run; set one with flk service add {} --command '...' --force",
+            service.name
+        )
+    })?;
+    let port = service.effective_port();
+    if let Some(port) = port {
+        if port_in_use(port) {
+            println!(
+                "{} Port {} is already in use; {} may fail to start",
+                "⚠".yellow(),
+                port,
+                service.name.cyan()
+            );
+        }
+    }
+
+    let dir = env::current_dir()
+        .context("Failed to get current directory")?
+        .join(state_dir(entry));
+    fs::create_dir_all(&dir).with_context(|| format!("Failed to create '{}'", dir.display()))?;
+    let run_script = dir.join("run.sh");
+    fs::write(
+        &run_script,
+        format!(
+            "# Generated by flk service up; change the profile's services instead\n{}",
+            script
+        ),
+    )
+    .with_context(|| format!("Failed to write '{}'", run_script.display()))?;
+
+    let mut log = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(log_path(entry))
+        .with_context(|| format!("Failed to open '{}'", log_path(entry).display()))?;
+    writeln!(
+        log,
+        "==> {} starting {} in profile {}",
+        chrono::Local::now().format("%Y-%m-%d %H:%M:%S"),
+        service.name,
+        profile
+    )?;
+
+    let argv = vec!["bash".to_string(), run_script.display().to_string()];
+    let mut cmd = develop_command(profile, &argv, false)?;
+    cmd.env("FLK_SERVICE_DIR", &dir)
+        .stdin(Stdio::null())
+        .stdout(log.try_clone()?)
+        .stderr(log);
+    if let Some(port) = port {
+        cmd.env("PORT", port.to_string());
+    }
+    detach(&mut cmd);
+
+    let mut child = cmd
+        .spawn()
+        .context("Failed to start nix develop for the service")?;
+    fs::write(pid_path(entry), format!("{}\n", child.id()))
+        .context("Failed to write the pid file")?;
+
+    // Catch services that fail right away, e.g. because of a typo in the command
+    let started = Instant::now();
+    while started.elapsed() < STARTUP_GRACE {
+        if let Some(status) = child.try_wait()? {
+            let _ = fs::remove_file(pid_path(entry));
+            bail!(
+                "exited right away ({}); see {}",
+                status,
+                format!("flk service logs {}", service.name).cyan()
+            );
+        }
+        thread::sleep(Duration::from_millis(100));
+    }
+
+    let port_note = port.map(|p| format!(" on port {}", p)).unwrap_or_default();
+    println!(
+        "{} Started {} (pid {}){}",
+        "✓".green().bold(),
+        service.name.cyan(),
+        child.id(),
+        port_note
+    );
+    Ok(())
+}
+
+/// Stop running services.
+///
+/// Each service's process group is sent the service's stop signal and given
+/// a few seconds to exit before it is killed.
+///
+/// # Arguments
+///
+/// * `names` - Services to stop; all services of the profile if empty
+/// * `current_profile` - Optional profile override or combination
+pub fn down(names: &[String], current_profile: Option<String>) -> Result<()> {
+    let profile = resolve_profile_ref(current_profile)?;
+    for service in select_services(&profile, names)? {
+        let name = &service.value.name;
+        let Some(pid) = running_pid(&service) else {
+            let _ = fs::remove_file(pid_path(&service));
+            println!("{} {} is not running", "ℹ".blue(), name.cyan());
+            continue;
+        };
+
+        signal_group(pid, service.value.stop_signal());
+        let stopping = Instant::now();
+        while signal_group(pid, "0") && stopping.elapsed() < STOP_TIMEOUT {
+            thread::sleep(Duration::from_millis(100));
+        }
+        if signal_group(pid, "0") {
+            println!(
+                "{} {} did not stop within {}s; killing it",
+                "⚠".yellow(),
+                name.cyan(),
+                STOP_TIMEOUT.as_secs()
+            );
+            signal_group(pid, "KILL");
+        }
+
+        let _ = fs::remove_file(pid_path(&service));
+        println!("{} Stopped {}", "✓".green().bold(), name.cyan());
+    }
+    Ok(())
+}
+
+/// Show whether each service of the profile is running and listening.
+pub fn status(current_profile: Option<String>) -> Result<()> {
+    let profile = resolve_profile_ref(current_profile)?;
+    let services = effective_services(&profile)?;
+    if services.is_empty() {
+        println!(
+            "{} No services defined in profile {}",
+            "✗".red().bold(),
+            profile.cyan()
+        );
+        return Ok(());
+    }
+
+    let width = services
+        .iter()
+        .map(|s| s.value.name.len())
+        .max()
+        .unwrap_or(0);
+    for entry in &services {
+        let service = &entry.value;
+        let name = format!("{:<width$}", service.name, width = width);
+        let port = service
+            .effective_port()
+            .map(|p| format!("  port {}", p))
+            .unwrap_or_default();
+        let state = match (running_pid(entry), read_pid(entry)) {
+            (Some(pid), _) => {
+                let listening = match service.effective_port() {
+                    Some(p) if port_in_use(p) => " listening".green().to_string(),
+                    Some(_) => " starting".yellow().to_string(),
+                    None => String::new(),
+                };
+                format!("{} (pid {}){}{}", "running".green(), pid, port, listening)
+            }
+            // A pid file without a process means the service died on its own
+            (None, Some(_)) => format!(
+                "{}{} {}",
+                "exited".red(),
+                port,
+                format!("(see flk service logs {})", service.name).dimmed()
+            ),
+            (None, None) => format!("{}{}", "stopped".dimmed(), port),
+        };
+        println!("{} {}  {}", "•".green(), name.cyan(), state);
+    }
+    Ok(())
+}
+
+/// Print the end of the services' logs, optionally following new output.
+///
+/// # Arguments
+///
+/// * `name` - Service to show; all services of the profile if `None`
+/// * `lines` - Number of lines to print from the end of each log
+/// * `follow` - Keep printing new output until interrupted
+/// * `current_profile` - Optional profile override or combination
+pub fn logs(
+    name: Option<String>,
+    lines: usize,
+    follow: bool,
+    current_profile: Option<String>,
+) -> Result<()> {
+    let profile = resolve_profile_ref(current_profile)?;
+    let names: Vec<String> = name.into_iter().collect();
+    let services = select_services(&profile, &names)?;
+    if services.is_empty() {
+        println!(
+            "{} No services defined in profile {}",
+            "✗".red().bold(),
+            profile.cyan()
+        );
+        return Ok(());
+    }
+
+    // Prefix lines with the service name when showing several logs
+    let prefixed = services.len() > 1;
+    let width = services
+        .iter()
+        .map(|s| s.value.name.len())
+        .max()
+        .unwrap_or(0);
+    let mut tails = Vec::new();
+    for service in &services {
+        let prefix = if prefixed {
+            format!(
+                "{} | ",
+                format!("{:<width$}", service.value.name, width = width).cyan()
+            )
+        } else {
+            String::new()
+        };
+        let bytes = fs::read(log_path(service)).unwrap_or_default();
+        let content = String::from_utf8_lossy(&bytes);
+        let all: Vec<&str> = content.lines().collect();
+        for line in &all[all.len().saturating_sub(lines)..] {
+            println!("{}{}", prefix, line);
+        }
+        tails.push((log_path(service), bytes.len() as u64, prefix));
+    }
+
+    if !follow {
+        return Ok(());
+    }
+    loop {
+        thread::sleep(Duration::from_millis(500));
+        for (path, offset, prefix) in &mut tails {
+            let Ok(mut file) = File::open(&*path) else {
+                continue;
+            };
+            // The log was truncated or replaced; start over
+            if file.metadata()?.len() < *offset {
+                *offset = 0;
+            }
+            file.seek(SeekFrom::Start(*offset))?;
+            let mut new = Vec::new();
+            file.read_to_end(&mut new)?;
+            // Leave an unfinished last line for the next round
+            let complete = new.iter().rposition(|b| *b == b'\n').map_or(0, |i| i + 1);
+            for line in String::from_utf8_lossy(&new[..complete]).lines() {
+                println!("{}{}", prefix, line);
+            }
+            *offset += complete as u64;
+        }
+    }
+}
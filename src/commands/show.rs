@@ -6,12 +6,17 @@ use std::path::Path;
 
 use anyhow::{Context, Result};
 
+use flk::flake::inheritance::check_inheritance;
 use flk::flake::parsers::flake::parse_flake;
 
 /// Display the current flake configuration in a human-readable format.
+///
+/// Profiles that extend others are shown with their effective contents, with
+/// inherited entries marked by the profile they come from.
 pub fn run_show() -> Result<()> {
     let flake_path = Path::new("flake.nix");
     let flake_info = parse_flake(flake_path.to_str().context("Invalid path encoding")?)?;
+    check_inheritance(&flake_info.profiles)?;
 
     println!("{}", flake_info);
 
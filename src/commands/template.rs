@@ -0,0 +1,168 @@
+//! # Template Command Handler
+//!
+//! List, show and install the profile templates used by `flk init` and
+//! `flk profile add`.
+
+use anyhow::{bail, Context, Result};
+use colored::Colorize;
+use std::fs;
+use std::path::PathBuf;
+
+use flk::flake::generator::{
+    find_template, list_templates, user_templates_dir, TemplateSource, PROJECT_TEMPLATES_DIR,
+};
+use flk::flake::parsers::{profile::parse_profile, utils::is_valid_profile_name};
+use flk::flake::template_vars::{parse_variables, render_template, undeclared_placeholders};
+
+/// List every available template with where it comes from.
+pub fn run_list() -> Result<()> {
+    let templates = list_templates()?;
+
+    println!("{} Available templates:", "ℹ".blue());
+    for template in templates {
+        let shadowed = if template.shadows.is_empty() {
+            String::new()
+        } else {
+            format!(
+                " {}",
+                format!(
+                    "(overrides {})",
+                    template
+                        .shadows
+                        .iter()
+                        .map(|s| s.to_string())
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                )
+                .dimmed()
+            )
+        };
+        println!(
+            "- {} {}{}",
+            template.name.cyan(),
+            format!("[{}]", template.source).dimmed(),
+            shadowed
+        );
+    }
+
+    Ok(())
+}
+
+/// Print the content of a template.
+///
+/// # Arguments
+///
+/// * `name` - Template name
+pub fn run_show(name: String) -> Result<()> {
+    let Some((source, content)) = find_template(&name)? else {
+        bail!(
+            "Unknown template '{}'. Run 'flk template list' to see the available templates.",
+            name
+        );
+    };
+
+    eprintln!("{} {} [{}]", "ℹ".blue(), name.cyan(), source);
+    let variables = parse_variables(&content)
+        .with_context(|| format!("Invalid variable declarations in template '{}'", name))?;
+    if !variables.is_empty() {
+        eprintln!("{} Variables:", "ℹ".blue());
+        for variable in &variables {
+            eprintln!(
+                "  {} {} {}{}",
+                "•".green(),
+                variable.name.bold(),
+                format!("(default: {})", variable.default).dimmed(),
+                variable
+                    .description
+                    .as_deref()
+                    .map(|d| format!(" - {}", d))
+                    .unwrap_or_default()
+            );
+        }
+        eprintln!(
+            "  Set them with {}",
+            format!("--set {}=<value>", variables[0].name).cyan()
+        );
+    }
+    print!("{}", content);
+
+    Ok(())
+}
+
+/// Install a profile file as a template.
+///
+/// # Arguments
+///
+/// * `path` - Profile file to install
+/// * `name` - Template name; defaults to the file name without `.nix`
+/// * `project` - Install into the project's `.flk-templates/` instead of the user directory
+/// * `force` - If true, overwrite an existing template with the same name
+pub fn run_add(path: PathBuf, name: Option<String>, project: bool, force: bool) -> Result<()> {
+    let name = match name {
+        Some(name) => name,
+        None => path
+            .file_stem()
+            .and_then(|s| s.to_str())
+            .context("Could not derive a template name from the path; pass --name")?
+            .to_string(),
+    };
+    if !is_valid_profile_name(&name) {
+        bail!(
+            "Invalid template name '{}'. Template names must be alphanumeric (with - or _).",
+            name
+        );
+    }
+
+    let content = fs::read_to_string(&path)
+        .with_context(|| format!("Failed to read template file '{}'", path.display()))?;
+    render_template(&content, &[])
+        .and_then(|rendered| parse_profile(&name, &rendered))
+        .with_context(|| format!("'{}' is not a valid profile template", path.display()))?;
+    for placeholder in undeclared_placeholders(&content)? {
+        println!(
+            "{} '{{{{{}}}}}' is not a declared variable and is kept as written; declare it with '# flk:var {} = <default>' to make it one",
+            "⚠".yellow(),
+            placeholder,
+            placeholder
+        );
+    }
+
+    let dir = if project {
+        PathBuf::from(PROJECT_TEMPLATES_DIR)
+    } else {
+        user_templates_dir().context("Could not detect the user configuration directory")?
+    };
+    let target = dir.join(format!("{}.nix", name));
+    if target.exists() && !force {
+        bail!(
+            "Template {} already exists at {}! Use {} to overwrite.",
+            name.cyan(),
+            target.display(),
+            "--force".yellow()
+        );
+    }
+
+    fs::create_dir_all(&dir)
+        .with_context(|| format!("Failed to create template directory '{}'", dir.display()))?;
+    fs::write(&target, content)
+        .with_context(|| format!("Failed to write template '{}'", target.display()))?;
+
+    println!(
+        "{} Added template {} to {}",
+        "✓".green().bold(),
+        name.cyan(),
+        dir.display()
+    );
+    if matches!(find_template(&name)?, Some((TemplateSource::Project(_), _))) && !project {
+        println!(
+            "{} A project template with the same name takes precedence in this directory.",
+            "⚠".yellow()
+        );
+    }
+    println!(
+        "   Use it with {}",
+        format!("flk profile add <name> --template {}", name).cyan()
+    );
+
+    Ok(())
+}
@@ -11,6 +11,7 @@ use serde_json::Value;
 use std::fs;
 
 use crate::nix::run_nix_command;
+use flk::config::Config;
 use flk::utils::{backup, visual::with_spinner};
 
 /// Run the update command.
@@ -19,7 +20,8 @@ use flk::utils::{backup, visual::with_spinner};
 ///
 /// * `packages` - Specific packages to update (not yet supported)
 /// * `show` - If true, preview updates without applying them
-pub fn run_update(packages: Vec<String>, show: bool) -> Result<()> {
+/// * `config` - Resolved settings
+pub fn run_update(packages: Vec<String>, show: bool, config: &Config) -> Result<()> {
     if !packages.is_empty() {
         anyhow::bail!(
             "Updating specific packages requires version pinning (see issue #7). Use 'flk update' to update all packages."
@@ -29,7 +31,7 @@ pub fn run_update(packages: Vec<String>, show: bool) -> Result<()> {
     if show {
         show_update_preview()?;
     } else {
-        perform_update()?;
+        perform_update(config)?;
     }
 
     Ok(())
@@ -80,7 +82,7 @@ fn show_update_preview() -> Result<()> {
 }
 
 /// Perform the actual update
-fn perform_update() -> Result<()> {
+fn perform_update(config: &Config) -> Result<()> {
     println!("{}", "Updating flake inputs...".bold().cyan());
 
     // Ensure .flk directory exists
@@ -94,6 +96,17 @@ fn perform_update() -> Result<()> {
             "→".blue().bold(),
             backup_path.file_name().unwrap().to_string_lossy().dimmed()
         );
+        let pruned = backup::prune_backups(
+            std::path::Path::new("flake.lock"),
+            config.backup_retention(),
+        )?;
+        if !pruned.is_empty() {
+            println!(
+                "{} Removed {} old backup(s) (backup.retention)",
+                "→".blue().bold(),
+                pruned.len()
+            );
+        }
     }
 
     // Run the update
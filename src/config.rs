@@ -0,0 +1,516 @@
+//! # Configuration
+//!
+//! Layered settings read from TOML files and environment variables.
+//!
+//! A setting is looked up in this order; the first layer that sets it wins:
+//!
+//! 1. Command-line flags of the command being run
+//! 2. Environment variables: `FLK_` followed by the key in upper case with
+//!    dots replaced by underscores (`ui.color` → `FLK_UI_COLOR`)
+//! 3. The project config, `.flk/config.toml`
+//! 4. The user config, `$XDG_CONFIG_HOME/flk/config.toml` (default
+//!    `~/.config/flk/config.toml`)
+//! 5. The built-in default
+//!
+//! Keys are grouped in TOML tables, so `ui.color` is written as:
+//!
+//! ```toml
+//! [ui]
+//! color = "never"
+//! ```
+
+use anyhow::{bail, Context, Result};
+use regex::Regex;
+use std::env;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::flake::generator::DEFAULT_NIXPKGS_CHANNEL;
+
+/// Location of the project config, relative to the project root.
+pub const PROJECT_CONFIG_FILE: &str = ".flk/config.toml";
+
+/// The type of value a setting accepts.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SettingKind {
+    /// A name such as a template, channel or image name
+    Name,
+    /// `true` or `false`
+    Bool,
+    /// A non-negative integer
+    Integer,
+    /// One of a fixed list of values
+    Choice(&'static [&'static str]),
+}
+
+/// A known configuration key.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Setting {
+    /// Dotted key, e.g. `ui.color`
+    pub key: &'static str,
+    /// Accepted values
+    pub kind: SettingKind,
+    /// Value used when no layer sets the key
+    pub default: Option<&'static str>,
+    /// One-line explanation shown by `flk config list`
+    pub description: &'static str,
+}
+
+/// Every setting flk understands.
+pub const SETTINGS: &[Setting] = &[
+    Setting {
+        key: "template.default",
+        kind: SettingKind::Name,
+        default: None,
+        description:
+            "Template for `flk profile add`, and for `flk init` when no language is detected",
+    },
+    Setting {
+        key: "nix.channel",
+        kind: SettingKind::Name,
+        default: Some(DEFAULT_NIXPKGS_CHANNEL),
+        description: "nixpkgs branch used by `flk init` for new flakes",
+    },
+    Setting {
+        key: "search.backend",
+        kind: SettingKind::Choice(&["nix-versions", "nix"]),
+        default: Some("nix-versions"),
+        description: "Tool used by `flk search`",
+    },
+    Setting {
+        key: "backup.retention",
+        kind: SettingKind::Integer,
+        default: Some("0"),
+        description: "Number of flake.lock backups to keep; 0 keeps all of them",
+    },
+    Setting {
+        key: "ui.color",
+        kind: SettingKind::Choice(&["auto", "always", "never"]),
+        default: Some("auto"),
+        description: "When to use colored output",
+    },
+    Setting {
+        key: "ui.spinner",
+        kind: SettingKind::Bool,
+        default: Some("true"),
+        description: "Show spinners while waiting for Nix",
+    },
+    Setting {
+        key: "export.image_name",
+        kind: SettingKind::Name,
+        default: None,
+        description: "Image name for Docker and Podman exports; defaults to the profile name",
+    },
+    Setting {
+        key: "packages.sort",
+        kind: SettingKind::Bool,
+        default: Some("false"),
+        description: "Keep the packages list of a profile sorted when adding packages",
+    },
+];
+
+impl Setting {
+    /// Environment variable overriding this setting.
+    pub fn env_var(&self) -> String {
+        format!("FLK_{}", self.key.to_uppercase().replace('.', "_"))
+    }
+
+    /// Validate a value given as text and convert it to TOML.
+    fn parse(&self, raw: &str) -> Result<toml::Value> {
+        let raw = raw.trim();
+        match self.kind {
+            SettingKind::Name => {
+                if !Regex::new(r"^[A-Za-z0-9._/@:+-]+$").unwrap().is_match(raw) {
+                    bail!(
+                        "Invalid value '{}' for {}: expected a name without spaces or quotes",
+                        raw,
+                        self.key
+                    );
+                }
+                Ok(toml::Value::String(raw.to_string()))
+            }
+            SettingKind::Bool => match raw {
+                "true" | "yes" | "on" | "1" => Ok(toml::Value::Boolean(true)),
+                "false" | "no" | "off" | "0" => Ok(toml::Value::Boolean(false)),
+                _ => bail!(
+                    "Invalid value '{}' for {}: expected true or false",
+                    raw,
+                    self.key
+                ),
+            },
+            SettingKind::Integer => raw
+                .parse::<u32>()
+                .map(|n| toml::Value::Integer(n.into()))
+                .map_err(|_| {
+                    anyhow::anyhow!(
+                        "Invalid value '{}' for {}: expected a non-negative integer",
+                        raw,
+                        self.key
+                    )
+                }),
+            SettingKind::Choice(choices) => {
+                if !choices.contains(&raw) {
+                    bail!(
+                        "Invalid value '{}' for {}: expected one of {}",
+                        raw,
+                        self.key,
+                        choices.join(", ")
+                    );
+                }
+                Ok(toml::Value::String(raw.to_string()))
+            }
+        }
+    }
+
+    /// Validate a value read from a config file and convert it to text.
+    fn read_toml(&self, value: &toml::Value) -> Result<String> {
+        let raw = match value {
+            toml::Value::String(s) => s.clone(),
+            toml::Value::Boolean(b) if self.kind == SettingKind::Bool => b.to_string(),
+            toml::Value::Integer(n) if self.kind == SettingKind::Integer => n.to_string(),
+            other => bail!(
+                "Invalid value {} for {}",
+                other.to_string().trim(),
+                self.key
+            ),
+        };
+        Ok(display(&self.parse(&raw)?))
+    }
+}
+
+/// Text form of a validated value.
+fn display(value: &toml::Value) -> String {
+    match value {
+        toml::Value::String(s) => s.clone(),
+        other => other.to_string(),
+    }
+}
+
+/// Find a setting by key.
+///
+/// # Errors
+///
+/// Returns an error listing the known keys if the key does not exist.
+pub fn setting(key: &str) -> Result<&'static Setting> {
+    SETTINGS.iter().find(|s| s.key == key).with_context(|| {
+        format!(
+            "Unknown config key '{}'. Known keys: {}",
+            key,
+            SETTINGS
+                .iter()
+                .map(|s| s.key)
+                .collect::<Vec<_>>()
+                .join(", ")
+        )
+    })
+}
+
+/// The user's flk configuration directory.
+///
+/// `$XDG_CONFIG_HOME/flk`, or `~/.config/flk` when `XDG_CONFIG_HOME` is not set.
+pub fn user_config_dir() -> Option<PathBuf> {
+    let config_home = env::var_os("XDG_CONFIG_HOME")
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| dirs::home_dir().map(|home| home.join(".config")))?;
+    Some(config_home.join("flk"))
+}
+
+/// Path of the user config file.
+pub fn user_config_path() -> Option<PathBuf> {
+    user_config_dir().map(|dir| dir.join("config.toml"))
+}
+
+/// Where the value of a setting comes from.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ConfigSource {
+    /// Built-in default
+    Default,
+    /// The user config file
+    User(PathBuf),
+    /// The project config file
+    Project(PathBuf),
+    /// An environment variable
+    Env(String),
+}
+
+impl fmt::Display for ConfigSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConfigSource::Default => write!(f, "default"),
+            ConfigSource::User(path) => write!(f, "user ({})", path.display()),
+            ConfigSource::Project(path) => write!(f, "project ({})", path.display()),
+            ConfigSource::Env(var) => write!(f, "env ({})", var),
+        }
+    }
+}
+
+/// The resolved value of a setting.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConfigValue {
+    /// The setting
+    pub setting: &'static Setting,
+    /// Its value, if any layer or the default sets it
+    pub value: Option<String>,
+    /// The layer the value comes from
+    pub source: ConfigSource,
+}
+
+/// A config file together with its parsed contents.
+pub type ConfigLayer = (PathBuf, toml::Table);
+
+/// All settings, resolved across the configuration layers.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Config {
+    values: Vec<ConfigValue>,
+}
+
+fn lookup<'a>(table: &'a toml::Table, key: &str) -> Option<&'a toml::Value> {
+    let (section, name) = key.split_once('.')?;
+    table.get(section)?.as_table()?.get(name)
+}
+
+/// Read a config file; a missing file is an empty layer.
+fn read_layer(path: &Path) -> Result<Option<ConfigLayer>> {
+    if !path.exists() {
+        return Ok(None);
+    }
+    let content = fs::read_to_string(path)
+        .with_context(|| format!("Failed to read config file '{}'", path.display()))?;
+    let table: toml::Table = toml::from_str(&content)
+        .with_context(|| format!("Failed to parse config file '{}'", path.display()))?;
+    Ok(Some((path.to_path_buf(), table)))
+}
+
+impl Config {
+    /// Load the configuration from the config files and the environment.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a config file cannot be parsed or holds an invalid value.
+    pub fn load() -> Result<Self> {
+        let user = match user_config_path() {
+            Some(path) => read_layer(&path)?,
+            None => None,
+        };
+        let project = read_layer(Path::new(PROJECT_CONFIG_FILE))?;
+        Self::from_layers(user, project, |var| env::var(var).ok())
+    }
+
+    /// Resolve the settings from explicit layers.
+    ///
+    /// # Arguments
+    ///
+    /// * `user` - The user config file, if any
+    /// * `project` - The project config file, if any
+    /// * `env` - Looks up an environment variable
+    pub fn from_layers(
+        user: Option<ConfigLayer>,
+        project: Option<ConfigLayer>,
+        env: impl Fn(&str) -> Option<String>,
+    ) -> Result<Self> {
+        let mut values = Vec::new();
+
+        for setting in SETTINGS {
+            let var = setting.env_var();
+            let resolved = if let Some(raw) = env(&var).filter(|v| !v.is_empty()) {
+                let value = setting
+                    .parse(&raw)
+                    .with_context(|| format!("Invalid environment variable {}", var))?;
+                Some((display(&value), ConfigSource::Env(var)))
+            } else {
+                let mut found = None;
+                for (layer, source) in [
+                    (
+                        &project,
+                        ConfigSource::Project as fn(PathBuf) -> ConfigSource,
+                    ),
+                    (&user, ConfigSource::User),
+                ] {
+                    let Some((path, table)) = layer else {
+                        continue;
+                    };
+                    if let Some(value) = lookup(table, setting.key) {
+                        let raw = setting
+                            .read_toml(value)
+                            .with_context(|| format!("In config file '{}'", path.display()))?;
+                        found = Some((raw, source(path.clone())));
+                        break;
+                    }
+                }
+                found
+            };
+
+            values.push(match resolved {
+                Some((value, source)) => ConfigValue {
+                    setting,
+                    value: Some(value),
+                    source,
+                },
+                None => ConfigValue {
+                    setting,
+                    value: setting.default.map(str::to_string),
+                    source: ConfigSource::Default,
+                },
+            });
+        }
+
+        Ok(Self { values })
+    }
+
+    /// Every setting with its resolved value.
+    pub fn values(&self) -> &[ConfigValue] {
+        &self.values
+    }
+
+    /// The resolved value of a setting.
+    pub fn get(&self, key: &str) -> Option<&ConfigValue> {
+        self.values.iter().find(|v| v.setting.key == key)
+    }
+
+    fn value(&self, key: &str) -> Option<&str> {
+        self.get(key).and_then(|v| v.value.as_deref())
+    }
+
+    fn flag(&self, key: &str) -> bool {
+        self.value(key) == Some("true")
+    }
+
+    /// `template.default`
+    pub fn default_template(&self) -> Option<&str> {
+        self.value("template.default")
+    }
+
+    /// `nix.channel`
+    pub fn channel(&self) -> &str {
+        self.value("nix.channel").unwrap_or(DEFAULT_NIXPKGS_CHANNEL)
+    }
+
+    /// `search.backend`
+    pub fn search_backend(&self) -> &str {
+        self.value("search.backend").unwrap_or("nix-versions")
+    }
+
+    /// `backup.retention`; `0` keeps every backup.
+    pub fn backup_retention(&self) -> usize {
+        self.value("backup.retention")
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(0)
+    }
+
+    /// `ui.color`
+    pub fn color(&self) -> &str {
+        self.value("ui.color").unwrap_or("auto")
+    }
+
+    /// `ui.spinner`
+    pub fn spinner(&self) -> bool {
+        self.flag("ui.spinner")
+    }
+
+    /// `export.image_name`
+    pub fn export_image_name(&self) -> Option<&str> {
+        self.value("export.image_name")
+    }
+
+    /// `packages.sort`
+    pub fn sort_packages(&self) -> bool {
+        self.flag("packages.sort")
+    }
+}
+
+/// Set a key in a config file, creating the file if needed.
+///
+/// Other keys in the file are kept; comments and formatting are not.
+///
+/// # Errors
+///
+/// Returns an error for unknown keys, invalid values, or if the file cannot
+/// be read, parsed or written.
+pub fn set_value(path: &Path, key: &str, raw: &str) -> Result<()> {
+    let setting = setting(key)?;
+    let value = setting.parse(raw)?;
+
+    let mut table = read_layer(path)?.map(|(_, t)| t).unwrap_or_default();
+    let (section, name) = key.split_once('.').expect("setting keys are dotted");
+    let section = table
+        .entry(section)
+        .or_insert_with(|| toml::Value::Table(toml::Table::new()));
+    let Some(section) = section.as_table_mut() else {
+        bail!(
+            "'{}' in '{}' is not a table",
+            key.split('.').next().unwrap_or(key),
+            path.display()
+        );
+    };
+    section.insert(name.to_string(), value);
+
+    if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+        fs::create_dir_all(parent)
+            .with_context(|| format!("Failed to create directory '{}'", parent.display()))?;
+    }
+    let content = toml::to_string(&table).context("Failed to serialize config")?;
+    fs::write(path, content)
+        .with_context(|| format!("Failed to write config file '{}'", path.display()))?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn layer(path: &str, content: &str) -> Option<ConfigLayer> {
+        Some((PathBuf::from(path), toml::from_str(content).unwrap()))
+    }
+
+    #[test]
+    fn test_config_layers_precedence() {
+        let user = layer(
+            "user.toml",
+            "[ui]\ncolor = \"never\"\nspinner = false\n[search]\nbackend = \"nix\"\n",
+        );
+        let project = layer("project.toml", "[ui]\ncolor = \"always\"\n");
+        let config = Config::from_layers(user, project, |var| {
+            (var == "FLK_SEARCH_BACKEND").then(|| "nix-versions".to_string())
+        })
+        .unwrap();
+
+        assert_eq!(config.color(), "always");
+        assert_eq!(
+            config.get("ui.color").unwrap().source,
+            ConfigSource::Project(PathBuf::from("project.toml"))
+        );
+        assert!(!config.spinner());
+        assert_eq!(config.search_backend(), "nix-versions");
+        assert_eq!(
+            config.get("search.backend").unwrap().source,
+            ConfigSource::Env("FLK_SEARCH_BACKEND".to_string())
+        );
+        assert_eq!(config.channel(), "nixos-25.11");
+        assert_eq!(
+            config.get("nix.channel").unwrap().source,
+            ConfigSource::Default
+        );
+        assert_eq!(config.default_template(), None);
+    }
+
+    #[test]
+    fn test_config_rejects_invalid_values() {
+        let bad = layer("user.toml", "[ui]\ncolor = \"sometimes\"\n");
+        let err = Config::from_layers(bad, None, |_| None).unwrap_err();
+        assert!(format!("{:#}", err).contains("expected one of auto, always, never"));
+
+        let bad = layer("user.toml", "[backup]\nretention = \"many\"\n");
+        assert!(Config::from_layers(bad, None, |_| None).is_err());
+
+        let err = Config::from_layers(None, None, |var| {
+            (var == "FLK_UI_SPINNER").then(|| "maybe".to_string())
+        })
+        .unwrap_err();
+        assert!(format!("{:#}", err).contains("FLK_UI_SPINNER"));
+
+        assert!(setting("ui.colour").is_err());
+        assert_eq!(setting("ui.color").unwrap().env_var(), "FLK_UI_COLOR");
+    }
+}
@@ -0,0 +1,264 @@
+//! # nixpkgs Channel
+//!
+//! Helpers for the branch of nixpkgs a project follows: validating channel
+//! names, reading the locked revision from `flake.lock`, and comparing the
+//! package versions two revisions provide.
+
+use regex::Regex;
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::BTreeMap;
+
+use crate::flake::nix_render::nix_string;
+
+/// Name of the nixpkgs input in the root `flake.nix`.
+pub const NIXPKGS_INPUT: &str = "nixpkgs";
+
+/// Name of the `pins.nix` source that follows the latest release channel.
+pub const STABLE_SOURCE: &str = "stable";
+
+/// Whether a name is a nixpkgs branch flk can follow.
+///
+/// Accepts `nixos-unstable`, release branches such as `nixos-25.11` or
+/// `nixpkgs-25.11-darwin`, their `-small` variants, and `master`.
+pub fn is_valid_channel(channel: &str) -> bool {
+    channel == "master"
+        || Regex::new(r"^(nixos|nixpkgs)-(unstable|\d{2}\.\d{2})(-small|-darwin)?$")
+            .unwrap()
+            .is_match(channel)
+}
+
+/// Whether a channel is a release branch, e.g. `nixos-25.11`.
+pub fn is_release_channel(channel: &str) -> bool {
+    Regex::new(r"^(nixos|nixpkgs)-\d{2}\.\d{2}")
+        .unwrap()
+        .is_match(channel)
+}
+
+/// Flake URL of a nixpkgs branch.
+pub fn channel_url(channel: &str) -> String {
+    format!("github:NixOS/nixpkgs/{}", channel)
+}
+
+/// The branch a nixpkgs flake URL follows, if it is a `github:NixOS/nixpkgs/<branch>` URL.
+pub fn channel_from_url(url: &str) -> Option<&str> {
+    let prefix = "github:nixos/nixpkgs/";
+    if url.len() <= prefix.len() || !url[..prefix.len()].eq_ignore_ascii_case(prefix) {
+        return None;
+    }
+    let branch = &url[prefix.len()..];
+    let branch = branch.split('?').next().unwrap_or(branch);
+    (!branch.is_empty()).then_some(branch)
+}
+
+/// The revision an input is locked to in `flake.lock`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LockedInput {
+    /// Flake reference of the exact revision, e.g. `github:NixOS/nixpkgs/<rev>`
+    pub reference: String,
+    /// Commit hash
+    pub rev: String,
+    /// Commit time as a Unix timestamp
+    pub last_modified: Option<i64>,
+}
+
+/// Find the revision a root input is locked to.
+///
+/// # Arguments
+///
+/// * `lock` - Parsed `flake.lock`
+/// * `input` - Name of the input in the root flake
+pub fn locked_input(lock: &Value, input: &str) -> Option<LockedInput> {
+    let nodes = lock.get("nodes")?;
+    let root = lock.get("root").and_then(|r| r.as_str()).unwrap_or("root");
+    let node_name = nodes
+        .get(root)?
+        .get("inputs")?
+        .get(input)
+        .and_then(|n| n.as_str())
+        .unwrap_or(input);
+    let locked = nodes.get(node_name)?.get("locked")?;
+
+    let rev = locked.get("rev")?.as_str()?.to_string();
+    let reference = match locked.get("type").and_then(|t| t.as_str()) {
+        Some("github") => format!(
+            "github:{}/{}/{}",
+            locked.get("owner")?.as_str()?,
+            locked.get("repo")?.as_str()?,
+            rev
+        ),
+        _ => format!("{}?rev={}", locked.get("url")?.as_str()?, rev),
+    };
+
+    Some(LockedInput {
+        reference,
+        rev,
+        last_modified: locked.get("lastModified").and_then(|t| t.as_i64()),
+    })
+}
+
+/// Nix expression evaluating to the version of each package in a nixpkgs revision.
+///
+/// Evaluate it with `nix eval --impure --json --expr`. The result maps each
+/// attribute path to its version, `""` when the package has none, or `null`
+/// when the revision does not provide it.
+///
+/// # Arguments
+///
+/// * `nixpkgs` - Flake reference of the nixpkgs revision
+/// * `packages` - Attribute paths, e.g. `ripgrep` or `python313Packages.pip`
+pub fn version_probe_expr(nixpkgs: &str, packages: &[String]) -> String {
+    let names: Vec<String> = packages.iter().map(|p| nix_string(p)).collect();
+    format!(
+        r#"let
+  pkgs = (builtins.getFlake {nixpkgs}).legacyPackages.${{builtins.currentSystem}};
+  lib = pkgs.lib;
+  probe = name: let
+    found = builtins.tryEval (lib.attrByPath (lib.splitString "." name) null pkgs);
+    version = builtins.tryEval (found.value.version or "");
+  in
+    if found.success && found.value != null
+    then (if version.success then version.value else "")
+    else null;
+in
+  builtins.listToAttrs (map (name: {{ inherit name; value = probe name; }}) [ {names} ])"#,
+        nixpkgs = nix_string(nixpkgs),
+        names = names.join(" ")
+    )
+}
+
+/// A package whose version differs between two nixpkgs revisions.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct PackageChange {
+    /// Attribute path of the package
+    pub package: String,
+    /// Version before the change
+    pub from: String,
+    /// Version after the change; `None` if the package disappeared
+    pub to: Option<String>,
+}
+
+/// Compare the versions reported by [`version_probe_expr`] for two revisions.
+///
+/// Packages missing from the first revision are ignored, since they come
+/// from somewhere else, such as an overlay.
+pub fn compare_versions(
+    before: &BTreeMap<String, Option<String>>,
+    after: &BTreeMap<String, Option<String>>,
+) -> Vec<PackageChange> {
+    before
+        .iter()
+        .filter_map(|(package, from)| {
+            let from = from.as_ref()?;
+            let to = after.get(package).cloned().flatten();
+            (to.as_ref() != Some(from)).then(|| PackageChange {
+                package: package.clone(),
+                from: from.clone(),
+                to,
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_channel_names_and_urls() {
+        for channel in [
+            "nixos-unstable",
+            "nixos-25.11",
+            "nixos-25.11-small",
+            "nixpkgs-25.05-darwin",
+            "master",
+        ] {
+            assert!(is_valid_channel(channel), "{}", channel);
+        }
+        for channel in ["unstable", "nixos-25", "nixos-25.11; rm", ""] {
+            assert!(!is_valid_channel(channel), "{}", channel);
+        }
+        assert!(is_release_channel("nixos-25.11"));
+        assert!(!is_release_channel("nixos-unstable"));
+
+        assert_eq!(
+            channel_from_url("github:NixOS/nixpkgs/nixos-25.11"),
+            Some("nixos-25.11")
+        );
+        assert_eq!(
+            channel_from_url("github:nixos/nixpkgs/nixos-unstable?dir=lib"),
+            Some("nixos-unstable")
+        );
+        assert_eq!(channel_from_url("github:numtide/flake-utils"), None);
+        assert_eq!(
+            channel_url("nixos-unstable"),
+            "github:NixOS/nixpkgs/nixos-unstable"
+        );
+    }
+
+    #[test]
+    fn test_locked_input() {
+        let lock: Value = serde_json::from_str(
+            r#"{
+  "nodes": {
+    "nixpkgs_2": {
+      "locked": {"lastModified": 1700000000, "owner": "NixOS", "repo": "nixpkgs", "rev": "abc123", "type": "github"}
+    },
+    "root": {"inputs": {"nixpkgs": "nixpkgs_2"}}
+  },
+  "root": "root",
+  "version": 7
+}"#,
+        )
+        .unwrap();
+
+        let locked = locked_input(&lock, NIXPKGS_INPUT).unwrap();
+        assert_eq!(locked.reference, "github:NixOS/nixpkgs/abc123");
+        assert_eq!(locked.rev, "abc123");
+        assert_eq!(locked.last_modified, Some(1700000000));
+        assert!(locked_input(&lock, "flake-utils").is_none());
+    }
+
+    #[test]
+    fn test_compare_versions() {
+        let map = |entries: &[(&str, Option<&str>)]| -> BTreeMap<String, Option<String>> {
+            entries
+                .iter()
+                .map(|(k, v)| (k.to_string(), v.map(str::to_string)))
+                .collect()
+        };
+        let before = map(&[
+            ("git", Some("2.44.0")),
+            ("ripgrep", Some("14.1.0")),
+            ("old-tool", Some("1.0")),
+            ("rust-bin.stable.latest.default", None),
+        ]);
+        let after = map(&[
+            ("git", Some("2.44.0")),
+            ("ripgrep", Some("14.1.1")),
+            ("old-tool", None),
+            ("rust-bin.stable.latest.default", None),
+        ]);
+
+        let changes = compare_versions(&before, &after);
+        assert_eq!(
+            changes,
+            vec![
+                PackageChange {
+                    package: "old-tool".to_string(),
+                    from: "1.0".to_string(),
+                    to: None,
+                },
+                PackageChange {
+                    package: "ripgrep".to_string(),
+                    from: "14.1.0".to_string(),
+                    to: Some("14.1.1".to_string()),
+                },
+            ]
+        );
+
+        let expr = version_probe_expr("github:NixOS/nixpkgs/abc123", &["ripgrep".to_string()]);
+        assert!(expr.contains(r#"builtins.getFlake "github:NixOS/nixpkgs/abc123""#));
+        assert!(expr.contains(r#"[ "ripgrep" ]"#));
+    }
+}
@@ -0,0 +1,190 @@
+//! # Profile Diff
+//!
+//! Compare the parsed models of two profiles.
+//!
+//! Entries are matched by name, so reordering packages, environment variables
+//! or commands does not count as a difference. Command scripts are compared
+//! after dedenting, the way the parser reads them back.
+
+use serde::Serialize;
+
+use crate::flake::interfaces::profiles::{EnvVar, Package, Profile};
+use crate::flake::interfaces::shellhooks::ShellHookEntry;
+use crate::flake::parsers::utils::dedent_nix_string;
+
+/// An entry present in both profiles with different values.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ValueChange {
+    /// Entry name
+    pub name: String,
+    /// Value in the first profile
+    pub from: String,
+    /// Value in the second profile
+    pub to: String,
+}
+
+/// Differences for one kind of entry, seen from the first profile to the second.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct EntryDiff<T> {
+    /// Entries only in the second profile
+    pub added: Vec<T>,
+    /// Entries only in the first profile
+    pub removed: Vec<T>,
+    /// Entries in both profiles with different values
+    pub changed: Vec<ValueChange>,
+}
+
+impl<T> EntryDiff<T> {
+    /// Whether both profiles agree on every entry of this kind.
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.changed.is_empty()
+    }
+}
+
+/// All differences between two profiles.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ProfileDiff {
+    /// Name of the first profile
+    pub from: String,
+    /// Name of the second profile
+    pub to: String,
+    /// Packages; a change is a different pinned version
+    pub packages: EntryDiff<Package>,
+    /// Environment variables; a change is a different value
+    pub env_vars: EntryDiff<EnvVar>,
+    /// Custom commands; a change is a different script
+    pub commands: EntryDiff<ShellHookEntry>,
+}
+
+impl ProfileDiff {
+    /// Whether the two profiles have the same packages, env vars and commands.
+    pub fn is_empty(&self) -> bool {
+        self.packages.is_empty() && self.env_vars.is_empty() && self.commands.is_empty()
+    }
+}
+
+fn diff_entries<T: Clone>(
+    first: &[T],
+    second: &[T],
+    name: impl Fn(&T) -> &str,
+    value: impl Fn(&T) -> String,
+) -> EntryDiff<T> {
+    let find = |entries: &[T], wanted: &str| entries.iter().find(|e| name(e) == wanted).cloned();
+
+    let removed = first
+        .iter()
+        .filter(|e| find(second, name(e)).is_none())
+        .cloned()
+        .collect();
+    let added = second
+        .iter()
+        .filter(|e| find(first, name(e)).is_none())
+        .cloned()
+        .collect();
+    let changed = first
+        .iter()
+        .filter_map(|a| {
+            let b = find(second, name(a))?;
+            let (from, to) = (value(a), value(&b));
+            (from != to).then(|| ValueChange {
+                name: name(a).to_string(),
+                from,
+                to,
+            })
+        })
+        .collect();
+
+    EntryDiff {
+        added,
+        removed,
+        changed,
+    }
+}
+
+/// Compare two profiles.
+///
+/// # Arguments
+///
+/// * `first` - Profile to compare from
+/// * `second` - Profile to compare to
+pub fn diff_profiles(first: &Profile, second: &Profile) -> ProfileDiff {
+    ProfileDiff {
+        from: first.name.clone(),
+        to: second.name.clone(),
+        packages: diff_entries(
+            &first.packages,
+            &second.packages,
+            |p| &p.name,
+            |p| p.pinned_version().unwrap_or("latest").to_string(),
+        ),
+        env_vars: diff_entries(
+            &first.env_vars,
+            &second.env_vars,
+            |e| &e.name,
+            |e| e.value.clone(),
+        ),
+        commands: diff_entries(
+            &first.shell_hook.entries,
+            &second.shell_hook.entries,
+            |c| &c.name,
+            |c| dedent_nix_string(&c.script).trim().to_string(),
+        ),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_diff_profiles() {
+        let mut first = Profile::new("backend".to_string());
+        first.packages = vec![
+            Package::new("git".to_string()),
+            Package::new("cargo".to_string()),
+            Package {
+                name: "ripgrep".to_string(),
+                version: Some("14.1.0".to_string()),
+            },
+        ];
+        first.env_vars = vec![
+            EnvVar::new("PORT".to_string(), "8080".to_string()),
+            EnvVar::new("LANG".to_string(), "C".to_string()),
+        ];
+        first.shell_hook.add_command("test", "cargo test").unwrap();
+        first.shell_hook.add_command("fmt", "cargo fmt").unwrap();
+
+        let mut second = Profile::new("frontend".to_string());
+        second.packages = vec![
+            Package::new("ripgrep".to_string()),
+            Package::new("nodejs".to_string()),
+            Package::new("git".to_string()),
+        ];
+        second.env_vars = vec![
+            EnvVar::new("LANG".to_string(), "C".to_string()),
+            EnvVar::new("PORT".to_string(), "3000".to_string()),
+        ];
+        second.shell_hook.add_command("test", "npm test").unwrap();
+        second.shell_hook.add_command("dev", "npm run dev").unwrap();
+
+        let diff = diff_profiles(&first, &second);
+        assert!(!diff.is_empty());
+        assert_eq!(diff.packages.added[0].name, "nodejs");
+        assert_eq!(diff.packages.removed[0].name, "cargo");
+        assert_eq!(
+            diff.packages.changed,
+            vec![ValueChange {
+                name: "ripgrep".to_string(),
+                from: "14.1.0".to_string(),
+                to: "latest".to_string(),
+            }]
+        );
+        assert!(diff.env_vars.added.is_empty());
+        assert_eq!(diff.env_vars.changed[0].to, "3000");
+        assert_eq!(diff.commands.added[0].name, "dev");
+        assert_eq!(diff.commands.removed[0].name, "fmt");
+        assert_eq!(diff.commands.changed[0].from, "cargo test");
+
+        assert!(diff_profiles(&first, &first).is_empty());
+    }
+}
@@ -13,8 +13,28 @@
 //! - `.flk/profiles/<type>.nix` - Language-specific profile (rust, python, etc.)
 //! - `.flk/overlays.nix` - Overlay configuration for version pinning
 //! - `.flk/pins.nix` - Version pinning sources
+//!
+//! ## Profile Templates
+//!
+//! Profile templates are looked up by name in this order:
+//!
+//! 1. `<project>/.flk-templates/<name>.nix`
+//! 2. `$XDG_CONFIG_HOME/flk/templates/<name>.nix` (default `~/.config/flk/templates`)
+//! 3. The built-in templates embedded in the binary
+//!
+//! so a local template with the same name as a built-in one replaces it.
+//! Templates may declare variables, see [`crate::flake::template_vars`].
 
-use anyhow::{Ok, Result};
+use anyhow::{bail, Context, Ok, Result};
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::config::user_config_dir;
+use crate::flake::parsers::utils::is_valid_profile_name;
+use crate::flake::template_vars::{
+    parse_assignment, parse_variables, render_template, TemplateVariable,
+};
 
 const ROOT_FLAKE_TEMPLATE: &str = include_str!("../../templates/flake.nix");
 const HELPER_TEMPLATE: &str = include_str!("../../templates/default.nix");
@@ -27,6 +47,248 @@ const RUST_TEMPLATE: &str = include_str!("../../templates/profiles/rust.nix");
 const PYTHON_TEMPLATE: &str = include_str!("../../templates/profiles/python.nix");
 const NODE_TEMPLATE: &str = include_str!("../../templates/profiles/node.nix");
 const GO_TEMPLATE: &str = include_str!("../../templates/profiles/go.nix");
+const JAVA_TEMPLATE: &str = include_str!("../../templates/profiles/java.nix");
+const CPP_TEMPLATE: &str = include_str!("../../templates/profiles/cpp.nix");
+const ZIG_TEMPLATE: &str = include_str!("../../templates/profiles/zig.nix");
+const HASKELL_TEMPLATE: &str = include_str!("../../templates/profiles/haskell.nix");
+const ELIXIR_TEMPLATE: &str = include_str!("../../templates/profiles/elixir.nix");
+const RUBY_TEMPLATE: &str = include_str!("../../templates/profiles/ruby.nix");
+const PHP_TEMPLATE: &str = include_str!("../../templates/profiles/php.nix");
+const DOTNET_TEMPLATE: &str = include_str!("../../templates/profiles/dotnet.nix");
+
+/// nixpkgs branch followed by the root flake template.
+pub const DEFAULT_NIXPKGS_CHANNEL: &str = "nixos-25.11";
+
+/// Built-in profile templates, by name.
+const BUILTIN_TEMPLATES: &[(&str, &str)] = &[
+    ("base", GENERIC_TEMPLATE),
+    ("cpp", CPP_TEMPLATE),
+    ("dotnet", DOTNET_TEMPLATE),
+    ("elixir", ELIXIR_TEMPLATE),
+    ("generic", GENERIC_TEMPLATE),
+    ("go", GO_TEMPLATE),
+    ("haskell", HASKELL_TEMPLATE),
+    ("java", JAVA_TEMPLATE),
+    ("node", NODE_TEMPLATE),
+    ("php", PHP_TEMPLATE),
+    ("python", PYTHON_TEMPLATE),
+    ("ruby", RUBY_TEMPLATE),
+    ("rust", RUST_TEMPLATE),
+    ("zig", ZIG_TEMPLATE),
+];
+
+/// Directory holding project-local profile templates.
+pub const PROJECT_TEMPLATES_DIR: &str = ".flk-templates";
+
+/// Where a profile template comes from.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TemplateSource {
+    /// A `.nix` file in the project's `.flk-templates/` directory
+    Project(PathBuf),
+    /// A `.nix` file in the user's template directory
+    User(PathBuf),
+    /// Embedded in the flk binary
+    Builtin,
+}
+
+impl fmt::Display for TemplateSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TemplateSource::Project(path) => write!(f, "project ({})", path.display()),
+            TemplateSource::User(path) => write!(f, "user ({})", path.display()),
+            TemplateSource::Builtin => write!(f, "built-in"),
+        }
+    }
+}
+
+/// A profile template available to `flk init` and `flk profile add`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TemplateInfo {
+    /// Template name, used with `--template`
+    pub name: String,
+    /// Where the template is loaded from
+    pub source: TemplateSource,
+    /// Names of lower-priority templates this one replaces
+    pub shadows: Vec<TemplateSource>,
+}
+
+/// Directory holding the user's profile templates.
+///
+/// `$XDG_CONFIG_HOME/flk/templates`, or `~/.config/flk/templates` when
+/// `XDG_CONFIG_HOME` is not set.
+pub fn user_templates_dir() -> Option<PathBuf> {
+    user_config_dir().map(|dir| dir.join("templates"))
+}
+
+/// Template names and paths of the `.nix` files in `dir`, sorted by name.
+///
+/// Files whose name is not a valid template name are ignored.
+fn templates_in(dir: &Path) -> Result<Vec<(String, PathBuf)>> {
+    if !dir.is_dir() {
+        return Ok(Vec::new());
+    }
+
+    let mut templates = Vec::new();
+    for entry in fs::read_dir(dir)
+        .with_context(|| format!("Failed to read template directory '{}'", dir.display()))?
+    {
+        let path = entry?.path();
+        if path.extension().is_some_and(|ext| ext == "nix") {
+            if let Some(name) = path
+                .file_stem()
+                .and_then(|s| s.to_str())
+                .filter(|name| is_valid_profile_name(name))
+            {
+                templates.push((name.to_string(), path.clone()));
+            }
+        }
+    }
+    templates.sort();
+    Ok(templates)
+}
+
+/// Every template source that provides `name`, highest priority first.
+///
+/// # Errors
+///
+/// Returns an error if `name` is not a valid template name, since it is
+/// joined onto the template directories.
+fn template_sources(name: &str) -> Result<Vec<TemplateSource>> {
+    if !is_valid_profile_name(name) {
+        bail!(
+            "Invalid template name '{}'. Template names must be alphanumeric (with - or _).",
+            name
+        );
+    }
+
+    let mut sources = Vec::new();
+    let project = Path::new(PROJECT_TEMPLATES_DIR).join(format!("{}.nix", name));
+    if project.is_file() {
+        sources.push(TemplateSource::Project(project));
+    }
+    if let Some(dir) = user_templates_dir() {
+        let user = dir.join(format!("{}.nix", name));
+        if user.is_file() {
+            sources.push(TemplateSource::User(user));
+        }
+    }
+    if BUILTIN_TEMPLATES.iter().any(|(n, _)| *n == name) {
+        sources.push(TemplateSource::Builtin);
+    }
+    Ok(sources)
+}
+
+/// List every available profile template, sorted by name.
+///
+/// Each name appears once, with the source that wins the lookup.
+pub fn list_templates() -> Result<Vec<TemplateInfo>> {
+    let mut names: Vec<String> = BUILTIN_TEMPLATES
+        .iter()
+        .map(|(name, _)| name.to_string())
+        .collect();
+    names.extend(
+        templates_in(Path::new(PROJECT_TEMPLATES_DIR))?
+            .into_iter()
+            .map(|(name, _)| name),
+    );
+    if let Some(dir) = user_templates_dir() {
+        names.extend(templates_in(&dir)?.into_iter().map(|(name, _)| name));
+    }
+    names.sort();
+    names.dedup();
+
+    let mut templates = Vec::new();
+    for name in names {
+        let mut sources = template_sources(&name)?.into_iter();
+        if let Some(source) = sources.next() {
+            templates.push(TemplateInfo {
+                name,
+                source,
+                shadows: sources.collect(),
+            });
+        }
+    }
+    Ok(templates)
+}
+
+/// Find a profile template by name.
+///
+/// # Returns
+///
+/// The template's source and content, or `None` if no template has that name.
+pub fn find_template(name: &str) -> Result<Option<(TemplateSource, String)>> {
+    let Some(source) = template_sources(name)?.into_iter().next() else {
+        return Ok(None);
+    };
+
+    let content = match &source {
+        TemplateSource::Project(path) | TemplateSource::User(path) => fs::read_to_string(path)
+            .with_context(|| format!("Failed to read template '{}'", path.display()))?,
+        TemplateSource::Builtin => BUILTIN_TEMPLATES
+            .iter()
+            .find(|(n, _)| *n == name)
+            .map(|(_, content)| content.to_string())
+            .unwrap_or_default(),
+    };
+    Ok(Some((source, content)))
+}
+
+/// Variables declared by a template.
+///
+/// # Returns
+///
+/// The variables, or an empty list if no template has that name.
+pub fn template_variables(name: &str) -> Result<Vec<TemplateVariable>> {
+    match find_template(name)? {
+        Some((_, content)) => parse_variables(&content)
+            .with_context(|| format!("Invalid variable declarations in template '{}'", name)),
+        None => Ok(Vec::new()),
+    }
+}
+
+/// Parse `key=value` assignments and check that the given templates declare them.
+///
+/// # Errors
+///
+/// Returns an error for malformed assignments and for variables none of the
+/// templates declare.
+pub fn template_values(
+    templates: &[String],
+    assignments: &[String],
+) -> Result<Vec<(String, String)>> {
+    let values = assignments
+        .iter()
+        .map(|a| parse_assignment(a))
+        .collect::<Result<Vec<_>>>()?;
+    if values.is_empty() {
+        return Ok(values);
+    }
+
+    let mut declared: Vec<String> = Vec::new();
+    for template in templates {
+        declared.extend(template_variables(template)?.into_iter().map(|v| v.name));
+    }
+    declared.sort();
+    declared.dedup();
+
+    for (key, _) in &values {
+        if !declared.contains(key) {
+            if declared.is_empty() {
+                bail!(
+                    "Unknown template variable '{}': template {} declares no variables",
+                    key,
+                    templates.join(", ")
+                );
+            }
+            bail!(
+                "Unknown template variable '{}'. Available variables: {}. Run 'flk template show <name>' for details.",
+                key,
+                declared.join(", ")
+            );
+        }
+    }
+    Ok(values)
+}
 
 /// Generate the root `flake.nix` content.
 ///
@@ -35,6 +297,18 @@ pub fn generate_root_flake() -> Result<String> {
     Ok(ROOT_FLAKE_TEMPLATE.to_string())
 }
 
+/// Generate the root `flake.nix` content following a nixpkgs branch.
+///
+/// # Arguments
+///
+/// * `channel` - nixpkgs branch, e.g. "nixos-unstable"
+pub fn generate_root_flake_with_channel(channel: &str) -> Result<String> {
+    Ok(ROOT_FLAKE_TEMPLATE.replace(
+        &format!("github:NixOS/nixpkgs/{}", DEFAULT_NIXPKGS_CHANNEL),
+        &format!("github:NixOS/nixpkgs/{}", channel),
+    ))
+}
+
 /// Generate the `.flk/default.nix` helper module.
 ///
 /// This orchestrator file loads profiles, overlays, and generates the
@@ -51,25 +325,48 @@ pub fn generate_importer_module() -> Result<String> {
     Ok(IMPORTER_TEMPLATE.to_string())
 }
 
-/// Generate a language-specific profile template.
+/// Generate a profile from a template, using the defaults of its variables.
 ///
 /// # Arguments
 ///
-/// * `project_type` - One of: "rust", "python", "node", "go", or any other value for generic
+/// * `project_type` - Template name: a built-in one ("base", "generic", "rust",
+///   "python", "node", "go", ...) or a user or project template
 ///
 /// # Returns
 ///
 /// The Nix content for the profile template.
+///
+/// # Errors
+///
+/// Returns an error if no template has that name or it cannot be rendered.
 pub fn generate_flake(project_type: &str) -> Result<String> {
-    let template = match project_type {
-        "rust" => RUST_TEMPLATE,
-        "python" => PYTHON_TEMPLATE,
-        "node" => NODE_TEMPLATE,
-        "go" => GO_TEMPLATE,
-        _ => GENERIC_TEMPLATE,
-    };
+    generate_flake_with_vars(project_type, &[])
+}
 
-    Ok(template.to_string())
+/// Generate a profile from a template, filling in its variables.
+///
+/// # Arguments
+///
+/// * `project_type` - Template name
+/// * `values` - `(name, value)` pairs; variables the template does not declare are ignored
+///
+/// # Errors
+///
+/// Returns an error if no template has that name, a value is invalid, or the
+/// template's variable declarations are invalid.
+pub fn generate_flake_with_vars(project_type: &str, values: &[(String, String)]) -> Result<String> {
+    match find_template(project_type)? {
+        Some((_, content)) => render_template(&content, values)
+            .with_context(|| format!("Failed to render template '{}'", project_type)),
+        None => {
+            let available: Vec<String> = list_templates()?.into_iter().map(|t| t.name).collect();
+            bail!(
+                "Unknown template '{}'. Available templates: {}. Run 'flk template list' for details.",
+                project_type,
+                available.join(", ")
+            )
+        }
+    }
 }
 
 /// Generate the `.flk/overlays.nix` overlay configuration.
@@ -0,0 +1,397 @@
+//! # Profile Inheritance
+//!
+//! Resolve `extends` chains into the effective contents of a profile.
+//!
+//! The merge mirrors the one performed by `.flk/default.nix` when Nix evaluates
+//! the profiles:
+//!
+//! 1. Parents are merged in declaration order; a later parent overrides an
+//!    earlier one for environment variables, secrets, commands and services
+//!    with the same name.
+//! 2. The profile's own entries are applied last and override everything it
+//!    inherited. Packages are combined without duplicates.
+//!
+//! Cycles and references to missing profiles are reported as errors.
+
+use anyhow::{bail, Result};
+use colored::Colorize;
+use std::fmt;
+
+use crate::flake::interfaces::profiles::{EnvVar, Package, Profile, Secret};
+use crate::flake::interfaces::services::Service;
+use crate::flake::interfaces::shellhooks::ShellHookEntry;
+
+/// An entry of an effective profile together with the profile defining it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Inherited<T> {
+    /// The entry itself
+    pub value: T,
+    /// Name of the profile that defines the entry
+    pub from: String,
+}
+
+/// The contents of a profile after applying everything it extends.
+#[derive(Debug, Clone, PartialEq)]
+pub struct EffectiveProfile {
+    /// Profile name
+    pub name: String,
+    /// Direct parents, as declared in the profile
+    pub extends: Vec<String>,
+    /// Effective packages
+    pub packages: Vec<Inherited<Package>>,
+    /// Effective environment variables
+    pub env_vars: Vec<Inherited<EnvVar>>,
+    /// Effective secrets
+    pub secrets: Vec<Inherited<Secret>>,
+    /// Effective custom commands
+    pub commands: Vec<Inherited<ShellHookEntry>>,
+    /// Effective background services
+    pub services: Vec<Inherited<Service>>,
+}
+
+impl EffectiveProfile {
+    fn empty(name: &str, extends: Vec<String>) -> Self {
+        Self {
+            name: name.to_string(),
+            extends,
+            packages: Vec::new(),
+            env_vars: Vec::new(),
+            secrets: Vec::new(),
+            commands: Vec::new(),
+            services: Vec::new(),
+        }
+    }
+
+    /// Whether an entry defined by `from` is inherited rather than declared here.
+    pub fn is_inherited<T>(&self, entry: &Inherited<T>) -> bool {
+        entry.from != self.name
+    }
+
+    /// Add entries from a parent or from the profile itself; later entries win.
+    fn apply(
+        &mut self,
+        packages: Vec<Inherited<Package>>,
+        env_vars: Vec<Inherited<EnvVar>>,
+        secrets: Vec<Inherited<Secret>>,
+        commands: Vec<Inherited<ShellHookEntry>>,
+        services: Vec<Inherited<Service>>,
+    ) {
+        for package in packages {
+            self.packages.retain(|p| {
+                p.value.name != package.value.name || p.value.version != package.value.version
+            });
+            self.packages.push(package);
+        }
+        for env_var in env_vars {
+            match self
+                .env_vars
+                .iter_mut()
+                .find(|e| e.value.name == env_var.value.name)
+            {
+                Some(existing) => *existing = env_var,
+                None => self.env_vars.push(env_var),
+            }
+        }
+        for secret in secrets {
+            self.secrets.retain(|s| s.value.name != secret.value.name);
+            self.secrets.push(secret);
+        }
+        for command in commands {
+            self.commands.retain(|c| c.value.name != command.value.name);
+            self.commands.push(command);
+        }
+        for service in services {
+            self.services.retain(|s| s.value.name != service.value.name);
+            self.services.push(service);
+        }
+    }
+
+    /// Convert to a plain [`Profile`] with the effective contents.
+    pub fn to_profile(&self) -> Profile {
+        let mut profile = Profile::new(self.name.clone());
+        profile.extends = self.extends.clone();
+        profile.packages = self.packages.iter().map(|p| p.value.clone()).collect();
+        profile.env_vars = self.env_vars.iter().map(|e| e.value.clone()).collect();
+        profile.secrets = self.secrets.iter().map(|s| s.value.clone()).collect();
+        profile.shell_hook.entries = self.commands.iter().map(|c| c.value.clone()).collect();
+        profile.services = self.services.iter().map(|s| s.value.clone()).collect();
+        profile
+    }
+}
+
+/// Resolve the effective contents of a profile.
+///
+/// # Arguments
+///
+/// * `name` - Profile to resolve
+/// * `profiles` - All profiles of the project
+///
+/// # Errors
+///
+/// Returns an error if the profile or one of its ancestors does not exist, or
+/// if the `extends` chain contains a cycle.
+pub fn effective_profile(name: &str, profiles: &[Profile]) -> Result<EffectiveProfile> {
+    if !profiles.iter().any(|p| p.name == name) {
+        bail!("Profile '{}' does not exist", name);
+    }
+    resolve(name, profiles, &mut Vec::new())
+}
+
+fn resolve(name: &str, profiles: &[Profile], stack: &mut Vec<String>) -> Result<EffectiveProfile> {
+    if stack.iter().any(|p| p == name) {
+        let mut chain = stack.clone();
+        chain.push(name.to_string());
+        bail!("Profile inheritance cycle: {}", chain.join(" -> "));
+    }
+
+    let profile = profiles
+        .iter()
+        .find(|p| p.name == name)
+        .expect("caller checks that the profile exists");
+
+    stack.push(name.to_string());
+    let mut effective = EffectiveProfile::empty(name, profile.extends.clone());
+
+    for parent in &profile.extends {
+        if !profiles.iter().any(|p| &p.name == parent) {
+            bail!(
+                "Profile '{}' extends '{}', which does not exist",
+                name,
+                parent
+            );
+        }
+        let resolved = resolve(parent, profiles, stack)?;
+        effective.apply(
+            resolved.packages,
+            resolved.env_vars,
+            resolved.secrets,
+            resolved.commands,
+            resolved.services,
+        );
+    }
+
+    effective.apply(
+        profile
+            .packages
+            .iter()
+            .map(|p| Inherited {
+                value: p.clone(),
+                from: name.to_string(),
+            })
+            .collect(),
+        profile
+            .env_vars
+            .iter()
+            .map(|e| Inherited {
+                value: e.clone(),
+                from: name.to_string(),
+            })
+            .collect(),
+        profile
+            .secrets
+            .iter()
+            .map(|s| Inherited {
+                value: s.clone(),
+                from: name.to_string(),
+            })
+            .collect(),
+        profile
+            .shell_hook
+            .entries
+            .iter()
+            .map(|c| Inherited {
+                value: c.clone(),
+                from: name.to_string(),
+            })
+            .collect(),
+        profile
+            .services
+            .iter()
+            .map(|s| Inherited {
+                value: s.clone(),
+                from: name.to_string(),
+            })
+            .collect(),
+    );
+
+    stack.pop();
+    Ok(effective)
+}
+
+/// Check every profile's `extends` chain for cycles and missing parents.
+///
+/// # Errors
+///
+/// Returns the first inheritance problem found.
+pub fn check_inheritance(profiles: &[Profile]) -> Result<()> {
+    for profile in profiles {
+        effective_profile(&profile.name, profiles)?;
+    }
+    Ok(())
+}
+
+fn origin<T>(profile: &EffectiveProfile, entry: &Inherited<T>) -> String {
+    if profile.is_inherited(entry) {
+        format!(" {}", format!("(from {})", entry.from).dimmed())
+    } else {
+        String::new()
+    }
+}
+
+impl fmt::Display for EffectiveProfile {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "{}", self.name.bold().magenta())?;
+
+        if !self.extends.is_empty() {
+            writeln!(
+                f,
+                "  {} {}",
+                "Extends:".dimmed(),
+                self.extends.join(", ").cyan()
+            )?;
+        }
+
+        if !self.packages.is_empty() {
+            writeln!(
+                f,
+                "  {} {}",
+                "Packages:".dimmed(),
+                format!("({})", self.packages.len()).dimmed()
+            )?;
+            for pkg in &self.packages {
+                writeln!(f, "    {} {}{}", "•".green(), pkg.value, origin(self, pkg))?;
+            }
+        }
+
+        if !self.env_vars.is_empty() {
+            writeln!(
+                f,
+                "  {} {}",
+                "Environment Variables:".dimmed(),
+                format!("({})", self.env_vars.len()).dimmed()
+            )?;
+            for env in &self.env_vars {
+                writeln!(f, "    {} {}{}", "•".green(), env.value, origin(self, env))?;
+            }
+        }
+
+        if !self.secrets.is_empty() {
+            writeln!(
+                f,
+                "  {} {}",
+                "Secrets:".dimmed(),
+                format!("({})", self.secrets.len()).dimmed()
+            )?;
+            for secret in &self.secrets {
+                writeln!(
+                    f,
+                    "    {} {}{}",
+                    "•".green(),
+                    secret.value,
+                    origin(self, secret)
+                )?;
+            }
+        }
+
+        if !self.commands.is_empty() {
+            writeln!(f, "  {}", "Commands:".dimmed())?;
+            for entry in &self.commands {
+                writeln!(
+                    f,
+                    "    {} {}{}",
+                    "•".green(),
+                    entry.value,
+                    origin(self, entry)
+                )?;
+            }
+        }
+
+        if !self.services.is_empty() {
+            writeln!(f, "  {}", "Services:".dimmed())?;
+            for service in &self.services {
+                writeln!(
+                    f,
+                    "    {} {}{}",
+                    "•".green(),
+                    service.value,
+                    origin(self, service)
+                )?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn profile(name: &str, extends: &[&str], packages: &[&str], env: &[(&str, &str)]) -> Profile {
+        let mut profile = Profile::new(name.to_string());
+        profile.extends = extends.iter().map(|s| s.to_string()).collect();
+        profile.packages = packages
+            .iter()
+            .map(|p| Package::new(p.to_string()))
+            .collect();
+        profile.env_vars = env
+            .iter()
+            .map(|(k, v)| EnvVar::new(k.to_string(), v.to_string()))
+            .collect();
+        profile
+    }
+
+    #[test]
+    fn test_effective_profile_merges_parents_in_order() {
+        let mut base = profile("base", &[], &["git"], &[("EDITOR", "vi"), ("LANG", "C")]);
+        base.shell_hook.add_command("fmt", "echo base").unwrap();
+        let mut tools = profile("tools", &["base"], &["jq"], &[("EDITOR", "nano")]);
+        tools.shell_hook.add_command("fmt", "echo tools").unwrap();
+        let app = profile(
+            "app",
+            &["base", "tools"],
+            &["git", "node"],
+            &[("LANG", "en")],
+        );
+        let profiles = vec![base, tools, app];
+
+        let effective = effective_profile("app", &profiles).unwrap();
+        let packages: Vec<(&str, &str)> = effective
+            .packages
+            .iter()
+            .map(|p| (p.value.name.as_str(), p.from.as_str()))
+            .collect();
+        assert_eq!(
+            packages,
+            vec![("jq", "tools"), ("git", "app"), ("node", "app")]
+        );
+
+        let env: Vec<(&str, &str)> = effective
+            .env_vars
+            .iter()
+            .map(|e| (e.value.name.as_str(), e.value.value.as_str()))
+            .collect();
+        assert_eq!(env, vec![("EDITOR", "nano"), ("LANG", "en")]);
+
+        assert_eq!(effective.commands.len(), 1);
+        assert_eq!(effective.commands[0].from, "tools");
+        assert_eq!(effective.to_profile().packages.len(), 3);
+    }
+
+    #[test]
+    fn test_effective_profile_detects_cycles_and_missing_parents() {
+        let profiles = vec![
+            profile("a", &["b"], &[], &[]),
+            profile("b", &["a"], &[], &[]),
+            profile("c", &["nope"], &[], &[]),
+        ];
+
+        let err = effective_profile("a", &profiles).unwrap_err().to_string();
+        assert_eq!(err, "Profile inheritance cycle: a -> b -> a");
+
+        let err = effective_profile("c", &profiles).unwrap_err().to_string();
+        assert!(err.contains("extends 'nope', which does not exist"));
+
+        assert!(check_inheritance(&profiles).is_err());
+    }
+}
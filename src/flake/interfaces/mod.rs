@@ -8,6 +8,7 @@
 //! - [`profiles`] - Profile, Package, EnvVar, and FlakeConfig types
 //! - [`overlays`] - Pinned package and overlay configuration types
 //! - [`shellhooks`] - Custom command/shell hook types
+//! - [`services`] - Background service types and built-in services
 //! - [`utils`] - Shared constants (indentation levels)
 //!
 //! ## Type Hierarchy
@@ -19,11 +20,13 @@
 //!     ├── name: String
 //!     ├── packages: Vec<Package>
 //!     ├── env_vars: Vec<EnvVar>
+//!     ├── services: Vec<Service>
 //!     └── shell_hook: ShellHookSection
 //!         └── entries: Vec<ShellHookEntry>
 //! ```
 
 pub mod overlays;
 pub mod profiles;
+pub mod services;
 pub mod shellhooks;
 pub mod utils;
@@ -92,6 +92,24 @@ impl SourcesSection {
         Ok(())
     }
 
+    /// Point an existing source at a new reference.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the source doesn't exist.
+    pub fn set_source(&mut self, source_name: &str, source_ref: &str) -> Result<()> {
+        let entry = self
+            .entries
+            .iter_mut()
+            .find(|e| e.name == source_name)
+            .ok_or_else(|| {
+                anyhow::anyhow!("Source '{}' not found in sources section", source_name)
+            })?;
+        entry.reference = source_ref.to_string();
+
+        Ok(())
+    }
+
     /// Remove a source entry.
     ///
     /// # Errors
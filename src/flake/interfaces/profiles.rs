@@ -8,7 +8,10 @@ use colored::Colorize;
 use serde::{Deserialize, Serialize};
 use std::fmt;
 
+use crate::flake::inheritance::effective_profile;
+use crate::flake::interfaces::services::Service;
 use crate::flake::interfaces::shellhooks::ShellHookSection;
+use crate::flake::nix_render::nix_string;
 
 /// Complete configuration parsed from a flake project.
 ///
@@ -18,6 +21,7 @@ use crate::flake::interfaces::shellhooks::ShellHookSection;
 #[derive(Debug, Default, Serialize, Deserialize)]
 pub struct FlakeConfig {
     /// Names of flake inputs (e.g., "nixpkgs", "flake-utils")
+    #[serde(default)]
     pub inputs: Vec<String>,
     /// Development environment profiles
     pub profiles: Vec<Profile>,
@@ -44,11 +48,23 @@ pub struct FlakeConfig {
 pub struct Profile {
     /// Profile name (derived from filename, e.g., "rust" from "rust.nix")
     pub name: String,
+    /// Profiles this one inherits from (`extends = [ ... ];`)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub extends: Vec<String>,
     /// Packages included in this profile
+    #[serde(default)]
     pub packages: Vec<Package>,
     /// Environment variables set when this profile is active
+    #[serde(default)]
     pub env_vars: Vec<EnvVar>,
+    /// Environment variables resolved from a file or command when the shell starts
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub secrets: Vec<Secret>,
+    /// Background services started by `flk service up`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub services: Vec<Service>,
     /// Custom shell commands available in this profile
+    #[serde(default)]
     pub shell_hook: ShellHookSection,
 }
 
@@ -61,8 +77,11 @@ impl Profile {
     pub fn new(name: String) -> Self {
         Self {
             name,
+            extends: Vec::new(),
             packages: Vec::new(),
             env_vars: Vec::new(),
+            secrets: Vec::new(),
+            services: Vec::new(),
             shell_hook: ShellHookSection {
                 entries: Vec::new(),
                 indentation: "  ".to_string(),
@@ -100,12 +119,26 @@ pub struct EnvVar {
     pub name: String,
     /// Variable value
     pub value: String,
+    /// Nix expression the value was read from when it is not a plain string,
+    /// such as `"${pkgs.openssl.dev}/lib"`; `None` for literal values
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub nix: Option<String>,
 }
 
 impl EnvVar {
-    /// Create a new environment variable.
+    /// Create a new environment variable with a literal value.
     pub fn new(name: String, value: String) -> Self {
-        Self { name, value }
+        Self {
+            name,
+            value,
+            nix: None,
+        }
+    }
+
+    /// The Nix expression for the value: the one it was read from, or the
+    /// literal value as an escaped string.
+    pub fn nix_value(&self) -> String {
+        self.nix.clone().unwrap_or_else(|| nix_string(&self.value))
     }
 }
 
@@ -115,6 +148,52 @@ impl fmt::Display for EnvVar {
     }
 }
 
+/// Placeholder shown instead of the value of a secret.
+pub const SECRET_MASK: &str = "********";
+
+/// Where a secret environment variable takes its value from.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SecretSource {
+    /// Contents of a file, e.g. `.secrets/api-token`
+    File(String),
+    /// Output of a shell command, e.g. `pass show api/token`
+    Command(String),
+}
+
+impl fmt::Display for SecretSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SecretSource::File(path) => write!(f, "file {}", path),
+            SecretSource::Command(command) => write!(f, "command `{}`", command),
+        }
+    }
+}
+
+/// An environment variable resolved when the development shell starts.
+///
+/// Only the [`SecretSource`] is stored in the profile, so the value itself
+/// never ends up in the profile file or the Nix store.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Secret {
+    /// Variable name (e.g., "API_TOKEN")
+    pub name: String,
+    /// Where the value comes from
+    pub source: SecretSource,
+}
+
+impl fmt::Display for Secret {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} = {} {}",
+            self.name.cyan().bold(),
+            SECRET_MASK.yellow(),
+            format!("(secret from {})", self.source).dimmed()
+        )
+    }
+}
+
 impl Package {
     /// Create a new package with "latest" as the default version.
     ///
@@ -127,6 +206,24 @@ impl Package {
             version: Some("latest".to_string()),
         }
     }
+
+    /// Return the pinned version, if any.
+    ///
+    /// Both `None` and `"latest"` mean the package follows nixpkgs.
+    pub fn pinned_version(&self) -> Option<&str> {
+        self.version.as_deref().filter(|v| *v != "latest")
+    }
+
+    /// Render the package as it appears in a profile's `packages` list.
+    ///
+    /// Unpinned packages render as `pkgs.<name>`, pinned ones as
+    /// `pkgs."<name>@<version>"`.
+    pub fn to_nix_reference(&self) -> String {
+        match self.pinned_version() {
+            Some(version) => format!("pkgs.\"{}@{}\"", self.name, version),
+            None => format!("pkgs.{}", self.name),
+        }
+    }
 }
 
 impl fmt::Display for Package {
@@ -172,10 +269,29 @@ impl fmt::Display for Profile {
             }
         }
 
+        if !self.secrets.is_empty() {
+            writeln!(
+                f,
+                "  {} {}",
+                "Secrets:".dimmed(),
+                format!("({})", self.secrets.len()).dimmed()
+            )?;
+            for secret in &self.secrets {
+                writeln!(f, "    {} {}", "•".green(), secret)?;
+            }
+        }
+
         if !self.shell_hook.entries.is_empty() {
             writeln!(f, "  {}", "Commands:".dimmed())?;
             for entry in &self.shell_hook.entries {
-                writeln!(f, "    {} {}", "•".green(), entry.name.bold())?;
+                writeln!(f, "    {} {}", "•".green(), entry)?;
+            }
+        }
+
+        if !self.services.is_empty() {
+            writeln!(f, "  {}", "Services:".dimmed())?;
+            for service in &self.services {
+                writeln!(f, "    {} {}", "•".green(), service)?;
             }
         }
 
@@ -231,15 +347,18 @@ impl FlakeConfig {
         println!();
 
         for profile in &self.profiles {
-            if !profile.env_vars.is_empty() {
+            if !profile.env_vars.is_empty() || !profile.secrets.is_empty() {
                 println!(
                     "{} {}",
                     profile.name.bold().magenta(),
-                    format!("({})", profile.env_vars.len()).dimmed()
+                    format!("({})", profile.env_vars.len() + profile.secrets.len()).dimmed()
                 );
                 for env_var in &profile.env_vars {
                     println!("  {} {}", "•".green(), env_var);
                 }
+                for secret in &profile.secrets {
+                    println!("  {} {}", "•".green(), secret);
+                }
                 println!();
             }
         }
@@ -262,7 +381,7 @@ impl FlakeConfig {
             if !profile.shell_hook.entries.is_empty() {
                 println!("{}", profile.name.bold().magenta());
                 for entry in &profile.shell_hook.entries {
-                    println!("  {} {}", "•".green(), entry.name.bold());
+                    println!("  {} {}", "•".green(), entry);
                 }
             }
         }
@@ -291,7 +410,11 @@ impl fmt::Display for FlakeConfig {
                 format!("({})", self.profiles.len()).dimmed()
             )?;
             for profile in &self.profiles {
-                writeln!(f, "{}", profile)?;
+                // Show inherited entries too when the extends chain resolves
+                match effective_profile(&profile.name, &self.profiles) {
+                    Ok(effective) if !profile.extends.is_empty() => writeln!(f, "{}", effective)?,
+                    _ => writeln!(f, "{}", profile)?,
+                }
             }
         }
 
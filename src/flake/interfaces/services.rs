@@ -0,0 +1,233 @@
+//! # Service Types
+//!
+//! Background services (databases, caches, workers) declared in a profile's
+//! `services` section and supervised by `flk service`.
+//!
+//! A service either names a package flk knows how to run, such as
+//! `postgresql_16` or `redis`, or brings its own foreground `command`.
+
+use anyhow::{bail, Result};
+use colored::Colorize;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// A background process belonging to a profile.
+///
+/// # Representation in Nix
+///
+/// ```nix
+/// services = {
+///   postgres = { package = pkgs.postgresql_16; port = 5432; };
+///   worker = { command = "python worker.py"; };
+/// };
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Service {
+    /// Service name, also the name of its state directory
+    pub name: String,
+    /// nixpkgs attribute providing the service (e.g. "postgresql_16")
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub package: Option<String>,
+    /// TCP port the service listens on
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub port: Option<u16>,
+    /// Shell command running the service in the foreground
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub command: Option<String>,
+}
+
+/// Services flk can initialize and start without a `command`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ServiceKind {
+    /// PostgreSQL, from the `postgresql` or `postgresql_<major>` packages
+    Postgres,
+    /// Redis, from the `redis` package
+    Redis,
+}
+
+impl ServiceKind {
+    /// The built-in service called `name`, as typed in `flk service add`.
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "postgres" | "postgresql" => Some(ServiceKind::Postgres),
+            "redis" => Some(ServiceKind::Redis),
+            _ => None,
+        }
+    }
+
+    /// The built-in service a nixpkgs package provides, if any.
+    pub fn detect(package: &str) -> Option<Self> {
+        let versioned_postgres = package
+            .strip_prefix("postgresql_")
+            .is_some_and(|major| !major.is_empty() && major.chars().all(|c| c.is_ascii_digit()));
+        if package == "postgresql" || versioned_postgres {
+            Some(ServiceKind::Postgres)
+        } else if package == "redis" {
+            Some(ServiceKind::Redis)
+        } else {
+            None
+        }
+    }
+
+    /// The nixpkgs attribute for a major `version`, or the default one.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the version is not a major version number, or if
+    /// nixpkgs does not package the service per version.
+    pub fn package(self, version: Option<&str>) -> Result<String> {
+        match (self, version) {
+            (ServiceKind::Postgres, None) => Ok("postgresql".to_string()),
+            (ServiceKind::Postgres, Some(v))
+                if !v.is_empty() && v.chars().all(|c| c.is_ascii_digit()) =>
+            {
+                Ok(format!("postgresql_{}", v))
+            }
+            (ServiceKind::Postgres, Some(v)) => bail!(
+                "Invalid postgres version '{}'. Use a major version such as 16.",
+                v
+            ),
+            (ServiceKind::Redis, None) => Ok("redis".to_string()),
+            (ServiceKind::Redis, Some(_)) => {
+                bail!("nixpkgs has a single redis package; use --package to pick another one")
+            }
+        }
+    }
+
+    /// The port the service listens on unless told otherwise.
+    pub fn default_port(self) -> u16 {
+        match self {
+            ServiceKind::Postgres => 5432,
+            ServiceKind::Redis => 6379,
+        }
+    }
+
+    /// Shell script starting the service in the foreground.
+    ///
+    /// The script expects `FLK_SERVICE_DIR` (the state directory) and `PORT`.
+    /// PostgreSQL's socket goes to `$XDG_RUNTIME_DIR` or `/tmp` instead, as
+    /// socket paths are limited to about 107 bytes.
+    fn start_script(self) -> &'static str {
+        match self {
+            ServiceKind::Postgres => {
+                r#"if [ ! -f "$FLK_SERVICE_DIR/data/PG_VERSION" ]; then
+  initdb -D "$FLK_SERVICE_DIR/data" -U postgres --auth=trust --no-locale --encoding=UTF8 || exit 1
+fi
+exec postgres -D "$FLK_SERVICE_DIR/data" -p "$PORT" -k "${XDG_RUNTIME_DIR:-/tmp}" -c listen_addresses=localhost
+"#
+            }
+            ServiceKind::Redis => {
+                r#"exec redis-server --port "$PORT" --bind 127.0.0.1 --dir "$FLK_SERVICE_DIR"
+"#
+            }
+        }
+    }
+}
+
+impl Service {
+    /// The built-in service this one runs, if it has no `command` of its own.
+    pub fn kind(&self) -> Option<ServiceKind> {
+        if self.command.is_some() {
+            return None;
+        }
+        self.package.as_deref().and_then(ServiceKind::detect)
+    }
+
+    /// The declared port, or the built-in default.
+    pub fn effective_port(&self) -> Option<u16> {
+        self.port
+            .or_else(|| self.kind().map(ServiceKind::default_port))
+    }
+
+    /// Shell script that runs the service in the foreground.
+    ///
+    /// Returns `None` if the service has neither a `command` nor a package
+    /// flk knows how to run.
+    pub fn start_script(&self) -> Option<String> {
+        match (&self.command, self.kind()) {
+            (Some(command), _) => Some(format!("{}\n", command.trim_end())),
+            (None, Some(kind)) => Some(kind.start_script().to_string()),
+            (None, None) => None,
+        }
+    }
+
+    /// Signal asking the service to shut down cleanly.
+    ///
+    /// PostgreSQL waits for clients to disconnect on `TERM`, so it is sent
+    /// `INT` (fast shutdown) instead.
+    pub fn stop_signal(&self) -> &'static str {
+        match self.kind() {
+            Some(ServiceKind::Postgres) => "INT",
+            _ => "TERM",
+        }
+    }
+}
+
+impl fmt::Display for Service {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.name.cyan().bold())?;
+        if let Some(package) = &self.package {
+            write!(f, " {}", package.green())?;
+        }
+        if let Some(port) = self.port {
+            write!(f, " {}", format!(":{}", port).yellow())?;
+        }
+        if let Some(command) = &self.command {
+            write!(f, " {}", format!("`{}`", command).dimmed())?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn service(package: Option<&str>, command: Option<&str>) -> Service {
+        Service {
+            name: "db".to_string(),
+            package: package.map(str::to_string),
+            port: None,
+            command: command.map(str::to_string),
+        }
+    }
+
+    #[test]
+    fn test_service_kind_packages() {
+        assert_eq!(
+            ServiceKind::from_name("postgres"),
+            Some(ServiceKind::Postgres)
+        );
+        assert_eq!(ServiceKind::Postgres.package(None).unwrap(), "postgresql");
+        assert_eq!(
+            ServiceKind::Postgres.package(Some("16")).unwrap(),
+            "postgresql_16"
+        );
+        assert!(ServiceKind::Postgres.package(Some("16.2")).is_err());
+        assert!(ServiceKind::Redis.package(Some("7")).is_err());
+
+        assert_eq!(
+            ServiceKind::detect("postgresql_15"),
+            Some(ServiceKind::Postgres)
+        );
+        assert_eq!(ServiceKind::detect("postgresql_jit"), None);
+        assert_eq!(ServiceKind::detect("redis"), Some(ServiceKind::Redis));
+    }
+
+    #[test]
+    fn test_service_start_script_and_port() {
+        let postgres = service(Some("postgresql_16"), None);
+        assert_eq!(postgres.effective_port(), Some(5432));
+        assert!(postgres.start_script().unwrap().contains("initdb"));
+        assert_eq!(postgres.stop_signal(), "INT");
+
+        // A command replaces the built-in recipe
+        let custom = service(Some("postgresql_16"), Some("postgres -D data"));
+        assert_eq!(custom.kind(), None);
+        assert_eq!(custom.effective_port(), None);
+        assert_eq!(custom.start_script().unwrap(), "postgres -D data\n");
+        assert_eq!(custom.stop_signal(), "TERM");
+
+        assert_eq!(service(Some("nginx"), None).start_script(), None);
+    }
+}
@@ -10,27 +10,41 @@
 //! ```nix
 //! {
 //!   commands = [
-//!     { name = "dev"; script = ''npm run dev''; }
+//!     { name = "dev"; description = "Start the dev server"; usage = "[port]"; script = ''npm run dev''; }
 //!     { name = "test"; script = ''cargo test --all''; }
+//!     { name = "ci"; deps = [ "test" ]; script = ''cargo build --release''; }
 //!   ];
 //! }
 //! ```
+//!
+//! `description` and `usage` are optional; the shell lists them with the
+//! generated `flk-help` function. `deps` names commands that run first.
 
 use crate::flake::parsers::utils::{multiline_string, ws};
-use anyhow::Result;
+use anyhow::{bail, Result};
+use colored::Colorize;
 use nom::{character::complete::char, IResult};
 use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::fmt;
 
 /// A collection of shell hook entries with position tracking for editing.
-#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+///
+/// Only `entries` is required when deserializing; the formatting and position
+/// fields describe a parsed source file and default to empty values.
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
 pub struct ShellHookSection {
     /// Custom command entries
+    #[serde(default)]
     pub entries: Vec<ShellHookEntry>,
     /// Detected indentation for consistent formatting
+    #[serde(default)]
     pub indentation: String,
     /// Byte position where the section starts in the source file
+    #[serde(default)]
     pub section_start: usize,
     /// Byte position where the section ends in the source file
+    #[serde(default)]
     pub section_end: usize,
 }
 
@@ -39,8 +53,53 @@ pub struct ShellHookSection {
 pub struct ShellHookEntry {
     /// Command name (becomes the shell function name)
     pub name: String,
+    /// What the command does, shown by `flk-help` and `flk command list`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    /// Arguments the command accepts, e.g. `<file> [--watch]`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub usage: Option<String>,
+    /// Commands that run, in order, before this one
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub deps: Vec<String>,
     /// Bash script to execute when the command is invoked
     pub script: String,
+    /// Attributes flk does not use, as `(name, Nix value)` pairs in the order
+    /// they were written; kept so rewriting the profile does not lose them
+    #[serde(skip)]
+    pub extra_attrs: Vec<(String, String)>,
+}
+
+impl ShellHookEntry {
+    /// Create a command without a description or usage.
+    pub fn new(name: &str, script: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            description: None,
+            usage: None,
+            deps: Vec::new(),
+            script: script.to_string(),
+            extra_attrs: Vec::new(),
+        }
+    }
+
+    /// The command name followed by its usage, e.g. `serve [port]`.
+    pub fn signature(&self) -> String {
+        match &self.usage {
+            Some(usage) => format!("{} {}", self.name, usage),
+            None => self.name.clone(),
+        }
+    }
+}
+
+impl fmt::Display for ShellHookEntry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.signature().bold())?;
+        if let Some(description) = &self.description {
+            write!(f, " {}", format!("- {}", description).dimmed())?;
+        }
+        Ok(())
+    }
 }
 
 /// Parse a shellHook value from Nix syntax.
@@ -69,20 +128,76 @@ impl ShellHookSection {
     ///
     /// Returns an error if a command with the same name already exists.
     pub fn add_command(&mut self, name: &str, script: &str) -> Result<()> {
-        if self.command_exists(name) {
+        self.add_entry(ShellHookEntry::new(name, script))
+    }
+
+    /// Add a command with all of its attributes.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a command with the same name already exists.
+    pub fn add_entry(&mut self, entry: ShellHookEntry) -> Result<()> {
+        if self.command_exists(&entry.name) {
             Err(anyhow::anyhow!(
                 "Command '{}' already exists in shellHook",
-                name
+                entry.name
             ))
         } else {
-            self.entries.push(ShellHookEntry {
-                name: name.to_string(),
-                script: script.to_string(),
-            });
+            self.entries.push(entry);
             Ok(())
         }
     }
 
+    /// Replace a command in place, or add it if it does not exist yet.
+    ///
+    /// Returns `true` if an existing command was replaced.
+    pub fn set_entry(&mut self, entry: ShellHookEntry) -> bool {
+        match self.entries.iter_mut().find(|e| e.name == entry.name) {
+            Some(existing) => {
+                *existing = entry;
+                true
+            }
+            None => {
+                self.entries.push(entry);
+                false
+            }
+        }
+    }
+
+    /// Rename a command, keeping its position, script, description and usage.
+    ///
+    /// Commands that depend on it are updated to the new name.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if `old_name` doesn't exist or `new_name` already does.
+    pub fn rename_command(&mut self, old_name: &str, new_name: &str) -> Result<()> {
+        if self.command_exists(new_name) {
+            anyhow::bail!("Command '{}' already exists in shellHook", new_name);
+        }
+        let entry = self
+            .entries
+            .iter_mut()
+            .find(|e| e.name == old_name)
+            .ok_or_else(|| anyhow::anyhow!("Command '{}' does not exist in shellHook", old_name))?;
+        entry.name = new_name.to_string();
+        for dep in self.entries.iter_mut().flat_map(|e| e.deps.iter_mut()) {
+            if dep == old_name {
+                *dep = new_name.to_string();
+            }
+        }
+        Ok(())
+    }
+
+    /// Names of the commands that list `name` in their `deps`.
+    pub fn dependents(&self, name: &str) -> Vec<&str> {
+        self.entries
+            .iter()
+            .filter(|e| e.deps.iter().any(|d| d == name))
+            .map(|e| e.name.as_str())
+            .collect()
+    }
+
     /// Remove a command from the shell hook section.
     ///
     /// # Errors
@@ -99,6 +214,59 @@ impl ShellHookSection {
             Ok(())
         }
     }
+    /// The commands that run when `name` is invoked, in order.
+    ///
+    /// Dependencies come before the commands that need them, each command
+    /// appears once even if several others depend on it, and `name` is last.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if `name` or one of its dependencies doesn't exist, or
+    /// if the dependencies form a cycle.
+    pub fn run_order(&self, name: &str) -> Result<Vec<&ShellHookEntry>> {
+        fn visit<'a>(
+            section: &'a ShellHookSection,
+            name: &str,
+            stack: &mut Vec<String>,
+            done: &mut HashSet<String>,
+            order: &mut Vec<&'a ShellHookEntry>,
+        ) -> Result<()> {
+            if let Some(start) = stack.iter().position(|n| n == name) {
+                let mut cycle = stack[start..].to_vec();
+                cycle.push(name.to_string());
+                bail!("Command dependency cycle: {}", cycle.join(" -> "));
+            }
+            if done.contains(name) {
+                return Ok(());
+            }
+            let entry = match (
+                section.entries.iter().find(|e| e.name == name),
+                stack.last(),
+            ) {
+                (Some(entry), _) => entry,
+                (None, Some(parent)) => bail!(
+                    "Command '{}' depends on '{}', which does not exist",
+                    parent,
+                    name
+                ),
+                (None, None) => bail!("Command '{}' does not exist", name),
+            };
+
+            stack.push(name.to_string());
+            for dep in &entry.deps {
+                visit(section, dep, stack, done, order)?;
+            }
+            stack.pop();
+            done.insert(name.to_string());
+            order.push(entry);
+            Ok(())
+        }
+
+        let mut order = Vec::new();
+        visit(self, name, &mut Vec::new(), &mut HashSet::new(), &mut order)?;
+        Ok(order)
+    }
+
     /// Apply modifications back to the original file content.
     ///
     /// Replaces the section between `section_start` and `section_end`
@@ -26,9 +26,17 @@
 //! - [`generator`] - Template-based generation of flake files from embedded templates
 //! - [`parsers`] - Nom-based parsers for reading and modifying Nix file sections
 //! - [`interfaces`] - Data structures representing flake configuration elements
+//! - [`inheritance`] - Resolution of profile `extends` chains
+//! - [`diff`] - Comparison of two profiles
 //! - [`nix_render`] - Safe rendering of Nix strings and attributes
+//! - [`template_vars`] - Variables declared by profile templates
+//! - [`channel`] - The nixpkgs branch a project follows
 
+pub mod channel;
+pub mod diff;
 pub mod generator;
+pub mod inheritance;
 pub mod interfaces;
 pub mod nix_render;
 pub mod parsers;
+pub mod template_vars;
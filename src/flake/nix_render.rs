@@ -38,11 +38,14 @@ pub fn nix_attr_key(key: &str) -> Cow<'_, str> {
 
 /// Escape content for a Nix double-quoted string.
 ///
-/// Handles backslash, quotes, newlines, carriage returns, and tabs.
+/// Handles backslash, quotes, newlines, carriage returns, tabs, and `${`,
+/// which would otherwise start an interpolation.
 fn nix_escape_string(s: &str) -> String {
     let mut out = String::with_capacity(s.len() + 8);
-    for ch in s.chars() {
+    let mut chars = s.chars().peekable();
+    while let Some(ch) = chars.next() {
         match ch {
+            '$' if chars.peek() == Some(&'{') => out.push_str("\\$"),
             '\\' => out.push_str("\\\\"),
             '"' => out.push_str("\\\""),
             '\n' => out.push_str("\\n"),
@@ -136,6 +139,7 @@ mod tests {
             nix_string("say \"hi\"\n\tpath\\file"),
             "\"say \\\"hi\\\"\\n\\tpath\\\\file\""
         );
+        assert_eq!(nix_string("${HOME} costs $5"), "\"\\${HOME} costs $5\"");
     }
 
     #[test]
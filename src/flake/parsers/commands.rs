@@ -9,63 +9,123 @@
 //!
 //! ```nix
 //! commands = [
-//!   { name = "dev"; script = ''npm run dev''; }
+//!   {
+//!     name = "dev";
+//!     description = "Start the dev server";
+//!     usage = "[port]";
+//!     script = ''npm run dev'';
+//!   }
 //!   { name = "test"; script = ''cargo test --all''; }
+//!   { name = "ci"; deps = [ "lint" "test" ]; script = ''cargo build''; }
 //! ];
 //! ```
 
 use crate::flake::interfaces::shellhooks::{ShellHookEntry, ShellHookSection};
 use crate::flake::nix_render::{indent_line, nix_multiline_string, nix_string};
-use crate::flake::parsers::utils::{detect_indentation, multiline_string, multiws, string_literal};
+use crate::flake::parsers::utils::{
+    dedent_nix_string, detect_indentation, escaped_string_literal, identifier, multiline_string,
+    multiws, string_literal,
+};
 use anyhow::{Context, Result};
 use nom::Parser;
 use nom::{
-    bytes::complete::tag,
+    branch::alt,
     character::complete::char,
-    combinator::map,
+    combinator::{consumed, map, map_opt},
     multi::many0,
     sequence::{delimited, preceded},
     IResult,
 };
+use std::collections::HashMap;
 
 // ============================================================================
 // SHELL HOOK ENTRY PARSERS
 // ============================================================================
 
-/// Parse a single command entry:  { name = "... "; script = ''.. .''; }
-fn shell_hook_entry(input: &str) -> IResult<&str, ShellHookEntry> {
-    map(
-        delimited(
-            multiws,
-            delimited(
-                char('{'),
-                delimited(
-                    multiws,
-                    (
-                        preceded((tag("name"), multiws, char('='), multiws), string_literal),
-                        preceded(
-                            (
-                                multiws,
-                                char(';'),
-                                multiws,
-                                tag("script"),
-                                multiws,
-                                char('='),
-                                multiws,
-                            ),
-                            multiline_string,
-                        ),
-                        preceded((multiws, char(';'), multiws), nom::combinator::success(())),
+/// Attributes of a command entry that flk reads.
+const KNOWN_ATTRS: [&str; 5] = ["name", "description", "usage", "deps", "script"];
+
+/// Value of a command entry attribute.
+enum AttrValue {
+    Text(String),
+    List(Vec<String>),
+}
+
+/// Parse one `key = value;` attribute of a command entry, together with the
+/// value's source text.
+///
+/// Values are quoted strings (escapes decoded), `'' ... ''` strings
+/// (dedented) or lists of quoted strings.
+fn command_attr(input: &str) -> IResult<&str, (&str, AttrValue, &str)> {
+    (
+        preceded(multiws, identifier),
+        preceded(
+            (multiws, char('='), multiws),
+            consumed(alt((
+                map(escaped_string_literal, AttrValue::Text),
+                map(map(multiline_string, dedent_nix_string), AttrValue::Text),
+                map(
+                    delimited(
+                        char('['),
+                        many0(preceded(multiws, map(string_literal, str::to_string))),
+                        (multiws, char(']')),
                     ),
-                    multiws,
+                    AttrValue::List,
                 ),
-                char('}'),
-            ),
-            multiws,
+            ))),
         ),
-        |(name, script, _)| ShellHookEntry {
-            name: name.to_string(),
-            script: script.to_string(),
+        preceded(multiws, char(';')),
+    )
+        .map(|(key, (source, value), _)| (key, value, source))
+        .parse(input)
+}
+
+/// Parse a single command entry:  { name = "..."; description = "..."; script = ''...''; }
+///
+/// Attributes may appear in any order; `name` and `script` are required,
+/// `description`, `usage` and `deps` are optional and unknown attributes are
+/// kept as written in [`ShellHookEntry::extra_attrs`].
+fn shell_hook_entry(input: &str) -> IResult<&str, ShellHookEntry> {
+    map_opt(
+        delimited(
+            (multiws, char('{')),
+            many0(command_attr),
+            (multiws, char('}'), multiws),
+        ),
+        |attrs| {
+            let mut extra_attrs = Vec::new();
+            let mut known: HashMap<&str, AttrValue> = HashMap::new();
+            for (key, value, source) in attrs {
+                if KNOWN_ATTRS.contains(&key) {
+                    known.insert(key, value);
+                } else {
+                    extra_attrs.push((key.to_string(), source.to_string()));
+                }
+            }
+            let mut attrs = known;
+            let mut text = |key| match attrs.remove(key) {
+                Some(AttrValue::Text(value)) => Some(value),
+                _ => None,
+            };
+            let (name, description, usage, script) = (
+                text("name")?,
+                text("description"),
+                text("usage"),
+                text("script")?,
+            );
+            let deps = match attrs.remove("deps") {
+                Some(AttrValue::List(deps)) => deps,
+                Some(AttrValue::Text(_)) => return None,
+                None => Vec::new(),
+            };
+            Some(ShellHookEntry {
+                name,
+                description,
+                usage,
+                deps,
+                script,
+                extra_attrs,
+            })
         },
     )
     .parse(input)
@@ -195,6 +255,24 @@ pub fn render_commands_section(
         out.push_str(&nix_string(&entry.name));
         out.push_str(";\n");
 
+        for (key, value) in [("description", &entry.description), ("usage", &entry.usage)] {
+            if let Some(value) = value {
+                indent_line(out, indent, level + 2);
+                out.push_str(&format!("{} = {};\n", key, nix_string(value)));
+            }
+        }
+
+        if !entry.deps.is_empty() {
+            let deps: Vec<String> = entry.deps.iter().map(|d| nix_string(d)).collect();
+            indent_line(out, indent, level + 2);
+            out.push_str(&format!("deps = [ {} ];\n", deps.join(" ")));
+        }
+
+        for (key, value) in &entry.extra_attrs {
+            indent_line(out, indent, level + 2);
+            out.push_str(&format!("{} = {};\n", key, value));
+        }
+
         indent_line(out, indent, level + 2);
         out.push_str("script = ");
         out.push_str(&nix_multiline_string(&entry.script, indent, level + 2));
@@ -239,9 +317,18 @@ pub fn render_shell_hook_section(section: &ShellHookSection) -> String {
 ///
 /// Returns an error if parsing fails or the command already exists.
 pub fn add_shell_hook_command(content: &str, name: &str, script: &str) -> Result<String> {
+    add_shell_hook_entry(content, ShellHookEntry::new(name, script))
+}
+
+/// Add a command with its description and usage, returning the updated file content.
+///
+/// # Errors
+///
+/// Returns an error if parsing fails or the command already exists.
+pub fn add_shell_hook_entry(content: &str, entry: ShellHookEntry) -> Result<String> {
     let mut section = parse_shell_hook_section(content)?;
 
-    section.add_command(name, script)?;
+    section.add_entry(entry)?;
 
     let rendered = render_shell_hook_section(&section);
     Ok(section.apply_to_content(content, &rendered))
@@ -387,4 +474,115 @@ mod tests {
         assert!(updated.contains("env = { FOO = \"bar\"; }"));
         assert!(updated.ends_with("# Footer comment"));
     }
+
+    #[test]
+    fn test_command_description_and_usage_round_trip() {
+        let content = r#"{
+  commands = [
+    {
+      script = ''
+        npm run dev -- --port "$1"
+      '';
+      usage = "[port]";
+      name = "dev";
+      description = "Start the \"dev\" server";
+    }
+    { name = "test"; script = ''npm test''; }
+  ];
+}"#;
+
+        let section = parse_shell_hook_section(content).unwrap();
+        assert_eq!(section.entries.len(), 2);
+        let dev = &section.entries[0];
+        assert_eq!(dev.description.as_deref(), Some("Start the \"dev\" server"));
+        assert_eq!(dev.usage.as_deref(), Some("[port]"));
+        assert_eq!(dev.signature(), "dev [port]");
+        assert_eq!(section.entries[1].description, None);
+
+        let rendered = section.apply_to_content(content, &render_shell_hook_section(&section));
+        assert!(rendered.contains(
+            "      name = \"dev\";\n      description = \"Start the \\\"dev\\\" server\";\n      usage = \"[port]\";\n      script = ''"
+        ));
+        assert_eq!(
+            parse_shell_hook_section(&rendered).unwrap().entries,
+            section.entries
+        );
+    }
+
+    #[test]
+    fn test_command_deps_round_trip_and_run_order() {
+        let content = r#"{
+  commands = [
+    { name = "setup"; script = ''npm ci''; }
+    { name = "lint"; deps = [ "setup" ]; script = ''npm run lint''; }
+    { name = "test"; deps = [ "setup" ]; script = ''npm test''; }
+    {
+      name = "ci";
+      deps = [
+        "lint"
+        "test"
+      ];
+      script = ''npm run build'';
+    }
+  ];
+}"#;
+
+        let section = parse_shell_hook_section(content).unwrap();
+        assert_eq!(section.entries[3].deps, vec!["lint", "test"]);
+        let order: Vec<&str> = section
+            .run_order("ci")
+            .unwrap()
+            .iter()
+            .map(|e| e.name.as_str())
+            .collect();
+        assert_eq!(order, vec!["setup", "lint", "test", "ci"]);
+
+        let rendered = section.apply_to_content(content, &render_shell_hook_section(&section));
+        assert!(rendered.contains("      deps = [ \"lint\" \"test\" ];\n"));
+        assert_eq!(
+            parse_shell_hook_section(&rendered).unwrap().entries,
+            section.entries
+        );
+
+        let mut cyclic = section.clone();
+        cyclic.entries[0].deps = vec!["ci".to_string()];
+        let err = cyclic.run_order("ci").unwrap_err().to_string();
+        assert_eq!(err, "Command dependency cycle: ci -> lint -> setup -> ci");
+
+        let mut missing = section;
+        missing.entries[1].deps.push("format".to_string());
+        let err = missing.run_order("ci").unwrap_err().to_string();
+        assert!(err.contains("'lint' depends on 'format'"));
+    }
+
+    #[test]
+    fn test_unknown_command_attributes_are_kept() {
+        let content = r#"{
+  commands = [
+    {
+      name = "dev";
+      category = "web";
+      script = ''npm run dev'';
+      tags = [ "server" "watch" ];
+    }
+  ];
+}"#;
+
+        let section = parse_shell_hook_section(content).unwrap();
+        assert_eq!(
+            section.entries[0].extra_attrs,
+            vec![
+                ("category".to_string(), "\"web\"".to_string()),
+                ("tags".to_string(), "[ \"server\" \"watch\" ]".to_string()),
+            ]
+        );
+
+        let updated = add_shell_hook_command(content, "test", "npm test").unwrap();
+        assert!(updated.contains(
+            "      name = \"dev\";\n      category = \"web\";\n      tags = [ \"server\" \"watch\" ];\n      script = ''"
+        ));
+        let reparsed = parse_shell_hook_section(&updated).unwrap();
+        assert_eq!(reparsed.entries[0], section.entries[0]);
+        assert_eq!(reparsed.entries[1].name, "test");
+    }
 }
@@ -0,0 +1,265 @@
+//! # Container Config Section Parser
+//!
+//! Parser for the `containerConfig = { ... };` section in profile files.
+//!
+//! The attribute set is passed to the OCI image builder as the image config, so
+//! its keys follow the OCI/Docker naming (`Cmd`, `Entrypoint`, `WorkingDir`,
+//! `ExposedPorts`, ...). Values are kept as raw Nix expressions; only the
+//! attributes flk manages are rendered by this module.
+//!
+//! ## Supported Syntax
+//!
+//! ```nix
+//! containerConfig = {
+//!   Cmd = ["${pkgs.bashInteractive}/bin/bash"];
+//!   WorkingDir = "/workspace";
+//!   ExposedPorts = { "8080/tcp" = {}; };
+//! };
+//! ```
+
+use anyhow::{Context, Result};
+
+use crate::flake::nix_render::{nix_attr_key, nix_string};
+use crate::flake::parsers::utils::detect_indentation;
+
+/// A top-level attribute of the `containerConfig` set.
+#[derive(Debug, Clone)]
+pub struct ContainerConfigEntry {
+    /// Attribute name (e.g. "WorkingDir")
+    pub key: String,
+    /// Raw Nix expression of the value
+    pub value: String,
+    /// Byte position where the entry's line starts
+    pub start_pos: usize,
+    /// Byte position just after the entry's terminating `;`
+    pub end_pos: usize,
+}
+
+/// Parsed `containerConfig` section with editing support.
+#[derive(Debug)]
+pub struct ContainerConfigSection {
+    /// Top-level attributes in source order
+    pub entries: Vec<ContainerConfigEntry>,
+    /// Detected indentation of the attributes
+    pub indentation: String,
+    /// Byte position just after the opening `{`
+    pub content_start: usize,
+    /// Byte position of the closing `}`
+    pub content_end: usize,
+}
+
+/// Parse the `containerConfig` section from profile file content.
+///
+/// # Errors
+///
+/// Returns an error if the section cannot be found or its braces are unbalanced.
+pub fn parse_container_config_section(content: &str) -> Result<ContainerConfigSection> {
+    let section_start = content
+        .find("containerConfig")
+        .context("Could not find 'containerConfig'")?;
+    let brace_offset = content[section_start..]
+        .find('{')
+        .context("Could not find '{' after 'containerConfig'")?;
+    let content_start = section_start + brace_offset + 1;
+
+    let mut entries = Vec::new();
+    let mut pos = content_start;
+    let content_end = loop {
+        pos = skip_trivia(content, pos);
+        if pos >= content.len() {
+            anyhow::bail!("Unmatched braces in containerConfig section");
+        }
+        if content[pos..].starts_with('}') {
+            break pos;
+        }
+
+        let key_len = content[pos..]
+            .find(|c: char| c == '=' || c.is_whitespace())
+            .context("Malformed attribute in containerConfig section")?;
+        let key = content[pos..pos + key_len].trim_matches('"').to_string();
+        let eq = pos
+            + content[pos..]
+                .find('=')
+                .context("Missing '=' in containerConfig section")?;
+        let value_end = find_value_end(content, eq + 1)
+            .context("Unterminated attribute in containerConfig section")?;
+
+        let start_pos = content[..pos].rfind('\n').map(|i| i + 1).unwrap_or(pos);
+        entries.push(ContainerConfigEntry {
+            key,
+            value: content[eq + 1..value_end].trim().to_string(),
+            start_pos,
+            end_pos: value_end + 1,
+        });
+        pos = value_end + 1;
+    };
+
+    let indentation = detect_indentation(&content[content_start..content_end]);
+
+    Ok(ContainerConfigSection {
+        entries,
+        indentation,
+        content_start,
+        content_end,
+    })
+}
+
+/// Skip whitespace and `#` comments starting at `pos`.
+fn skip_trivia(content: &str, mut pos: usize) -> usize {
+    loop {
+        let rest = &content[pos..];
+        let trimmed = rest.trim_start();
+        pos += rest.len() - trimmed.len();
+        if trimmed.starts_with('#') {
+            pos += trimmed.find('\n').unwrap_or(trimmed.len());
+        } else {
+            return pos;
+        }
+    }
+}
+
+/// Find the `;` terminating a value, skipping nested brackets and strings.
+fn find_value_end(content: &str, from: usize) -> Option<usize> {
+    let mut depth = 0usize;
+    let mut in_string = false;
+    let mut escaped = false;
+
+    for (i, ch) in content[from..].char_indices() {
+        if in_string {
+            match ch {
+                _ if escaped => escaped = false,
+                '\\' => escaped = true,
+                '"' => in_string = false,
+                _ => {}
+            }
+            continue;
+        }
+        match ch {
+            '"' => in_string = true,
+            '{' | '[' | '(' => depth += 1,
+            '}' | ']' | ')' => depth = depth.checked_sub(1)?,
+            ';' if depth == 0 => return Some(from + i),
+            _ => {}
+        }
+    }
+    None
+}
+
+impl ContainerConfigSection {
+    /// Look up the raw value of an attribute.
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.entries
+            .iter()
+            .find(|e| e.key == key)
+            .map(|e| e.value.as_str())
+    }
+
+    /// Set an attribute to a raw Nix expression, returning the modified content.
+    ///
+    /// Existing attributes are replaced in place; new ones are appended before
+    /// the closing brace.
+    pub fn set_attr(&self, original_content: &str, key: &str, value: &str) -> String {
+        let assignment = format!("{} = {};", nix_attr_key(key), value);
+        let line = format!("{}{}", self.indentation, assignment);
+
+        if let Some(entry) = self.entries.iter().find(|e| e.key == key) {
+            let mut result = String::new();
+            result.push_str(&original_content[..entry.start_pos]);
+            result.push_str(&line);
+            result.push_str(&original_content[entry.end_pos..]);
+            return result;
+        }
+
+        let closing_line_start = original_content[..self.content_end]
+            .rfind('\n')
+            .filter(|i| *i >= self.content_start);
+
+        let mut result = String::new();
+        match closing_line_start {
+            Some(i) => {
+                result.push_str(&original_content[..i]);
+                result.push('\n');
+                result.push_str(&line);
+                result.push_str(&original_content[i..]);
+            }
+            None => {
+                // Single-line set such as `containerConfig = {};`
+                result.push_str(&original_content[..self.content_end]);
+                result.push(' ');
+                result.push_str(&assignment);
+                result.push(' ');
+                result.push_str(&original_content[self.content_end..]);
+            }
+        }
+        result
+    }
+}
+
+/// Render a list of strings as a Nix list: `["a" "b"]`.
+pub fn render_string_list(items: &[String]) -> String {
+    let rendered: Vec<String> = items.iter().map(|i| nix_string(i)).collect();
+    format!("[{}]", rendered.join(" "))
+}
+
+/// Render OCI `ExposedPorts`: `{ "8080/tcp" = {}; }`.
+pub fn render_exposed_ports(ports: &[String]) -> String {
+    let rendered: Vec<String> = ports
+        .iter()
+        .map(|p| format!("{} = {{}};", nix_string(p)))
+        .collect();
+    format!("{{ {} }}", rendered.join(" "))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CONTENT: &str = r#"{pkgs, ...}: {
+  packages = [];
+
+  containerConfig = {
+    Cmd = ["${pkgs.bashInteractive}/bin/bash"]; # default shell
+    Env = [ "A=1;2" ];
+  };
+}
+"#;
+
+    #[test]
+    fn test_parse_container_config_section() {
+        let section = parse_container_config_section(CONTENT).unwrap();
+        assert_eq!(section.entries.len(), 2);
+        assert_eq!(section.entries[0].key, "Cmd");
+        assert_eq!(
+            section.get("Cmd"),
+            Some(r#"["${pkgs.bashInteractive}/bin/bash"]"#)
+        );
+        assert_eq!(section.get("Env"), Some(r#"[ "A=1;2" ]"#));
+    }
+
+    #[test]
+    fn test_set_attr_adds_and_replaces() {
+        let section = parse_container_config_section(CONTENT).unwrap();
+        let updated = section.set_attr(CONTENT, "WorkingDir", &nix_string("/app"));
+        assert!(updated.contains("    WorkingDir = \"/app\";\n  };"));
+
+        let section = parse_container_config_section(&updated).unwrap();
+        let updated = section.set_attr(&updated, "WorkingDir", &nix_string("/srv"));
+        assert!(updated.contains("WorkingDir = \"/srv\";"));
+        assert!(!updated.contains("/app"));
+        assert!(updated.contains("# default shell"));
+    }
+
+    #[test]
+    fn test_set_attr_on_inline_empty_set() {
+        let content = "{\n  containerConfig = {};\n}\n";
+        let section = parse_container_config_section(content).unwrap();
+        let updated = section.set_attr(
+            content,
+            "ExposedPorts",
+            &render_exposed_ports(&["8080/tcp".to_string()]),
+        );
+        assert!(updated.contains("containerConfig = { ExposedPorts = { \"8080/tcp\" = {}; }; };"));
+        let section = parse_container_config_section(&updated).unwrap();
+        assert_eq!(section.entries.len(), 1);
+    }
+}
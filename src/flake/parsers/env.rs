@@ -15,15 +15,16 @@
 //! ```
 
 use crate::flake::interfaces::profiles::EnvVar;
+use crate::flake::nix_render::nix_string;
 use crate::flake::parsers::utils::{
-    byte_offset, detect_indentation, identifier, multiws, string_literal, ws,
+    byte_offset, detect_indentation, escaped_string_literal, identifier, multiws, ws,
 };
 use anyhow::{Context, Result};
 use nom::Parser;
 use nom::{
     branch::alt,
     character::complete::{char, line_ending},
-    combinator::opt,
+    combinator::{consumed, map, opt},
     sequence::separated_pair,
     IResult,
 };
@@ -33,8 +34,10 @@ use nom::{
 pub struct EnvVarEntry {
     /// Variable name
     pub name: String,
-    /// Variable value
+    /// Variable value, with string escapes decoded
     pub value: String,
+    /// Value as written in the profile, e.g. `"${pkgs.openssl.dev}/lib"`
+    pub source: String,
     /// Byte position where this entry starts
     pub start_pos: usize,
     /// Byte position where this entry ends
@@ -58,9 +61,24 @@ pub struct EnvVarsSection {
     pub _section_end: usize,
 }
 
-/// Parse a value (quoted string or unquoted identifier)
-fn env_value(input: &str) -> IResult<&str, &str> {
-    alt((string_literal, identifier)).parse(input)
+/// Parse a value (quoted string with its escapes decoded, or unquoted
+/// identifier), together with its source text
+fn env_value(input: &str) -> IResult<&str, (&str, String)> {
+    consumed(alt((
+        escaped_string_literal,
+        map(identifier, str::to_string),
+    )))
+    .parse(input)
+}
+
+/// Decode a value written as in an `envVars` entry, e.g. `"${HOME}/bin"`.
+///
+/// Returns `None` unless `source` is exactly one quoted string or identifier.
+pub fn parse_env_value(source: &str) -> Option<String> {
+    match env_value(source.trim()) {
+        Ok(("", (_, value))) => Some(value),
+        _ => None,
+    }
 }
 
 /// Parse a single env var entry:   NAME = "value";
@@ -72,7 +90,7 @@ fn env_var_entry<'a>(
     let start_pos = base_offset + byte_offset(original_input, input);
 
     let (remaining, _) = multiws(input)?;
-    let (remaining, (name, value)) =
+    let (remaining, (name, (source, value))) =
         separated_pair(identifier, (ws, char('='), ws), env_value).parse(remaining)?;
     let (remaining, _) = ws(remaining)?;
     let (remaining, _) = char(';')(remaining)?;
@@ -84,7 +102,8 @@ fn env_var_entry<'a>(
         remaining,
         EnvVarEntry {
             name: name.to_string(),
-            value: value.to_string(),
+            value,
+            source: source.to_string(),
             start_pos,
             end_pos,
         },
@@ -187,17 +206,34 @@ pub fn parse_env_vars_section(content: &str) -> Result<EnvVarsSection> {
 
 impl EnvVarsSection {
     /// Convert parsed entries to a list of [`EnvVar`] structs.
+    ///
+    /// Values using Nix syntax, such as `"${pkgs.openssl.dev}/lib"`, keep it in
+    /// [`EnvVar::nix`] so they can be written back unchanged.
     pub fn to_env_vars(&self) -> Vec<EnvVar> {
         self.entries
             .iter()
-            .map(|e| EnvVar::new(e.name.clone(), e.value.clone()))
+            .map(|e| EnvVar {
+                name: e.name.clone(),
+                value: e.value.clone(),
+                // Only kept when escaping the value would not give it back
+                nix: (e.source != nix_string(&e.value)).then(|| e.source.clone()),
+            })
             .collect()
     }
 
     /// Add an environment variable, returning the modified file content.
     ///
+    /// The value is escaped, so quotes, newlines and `${` are kept literally.
     /// If the variable already exists, returns the original content unchanged.
     pub fn add_env_var(&self, original_content: &str, name: &str, value: &str) -> String {
+        self.add_env_expr(original_content, name, &nix_string(value))
+    }
+
+    /// Add an environment variable whose value is already a Nix expression,
+    /// such as one read from another profile, returning the modified content.
+    ///
+    /// If the variable already exists, returns the original content unchanged.
+    pub fn add_env_expr(&self, original_content: &str, name: &str, expr: &str) -> String {
         if self.entries.iter().any(|e| e.name == name) {
             return original_content.to_string();
         }
@@ -206,7 +242,7 @@ impl EnvVarsSection {
             .rfind('\n')
             .unwrap_or(self._content_end);
 
-        let new_entry = format!("\n{}{} = \"{}\";", self.indentation, name, value);
+        let new_entry = format!("\n{}{} = {};", self.indentation, name, expr);
 
         let mut result = String::new();
         result.push_str(&original_content[..inserstion_point]);
@@ -216,6 +252,49 @@ impl EnvVarsSection {
         result
     }
 
+    /// Set an environment variable, returning the modified file content.
+    ///
+    /// An existing entry keeps its position and only has its value replaced;
+    /// otherwise the variable is added like [`Self::add_env_var`].
+    pub fn set_env_var(&self, original_content: &str, name: &str, value: &str) -> String {
+        match self.entries.iter().find(|e| e.name == name) {
+            Some(entry) => replace_entry(original_content, entry, name, &nix_string(value)),
+            None => self.add_env_var(original_content, name, value),
+        }
+    }
+
+    /// Rename an environment variable in place.
+    ///
+    /// Only the name is replaced; the value is kept exactly as written.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if `old_name` is not found or `new_name` already exists.
+    pub fn rename_env_var(
+        &self,
+        original_content: &str,
+        old_name: &str,
+        new_name: &str,
+    ) -> Result<String> {
+        if self.entries.iter().any(|e| e.name == new_name) {
+            anyhow::bail!("Environment variable '{}' already exists", new_name);
+        }
+        let entry = self
+            .entries
+            .iter()
+            .find(|e| e.name == old_name)
+            .context(format!("Environment variable '{}' not found", old_name))?;
+
+        let entry_text = &original_content[entry.start_pos..entry.end_pos];
+        let name_start = entry.start_pos + entry_text.len() - entry_text.trim_start().len();
+        Ok(format!(
+            "{}{}{}",
+            &original_content[..name_start],
+            new_name,
+            &original_content[name_start + entry.name.len()..]
+        ))
+    }
+
     /// Remove an environment variable, returning the modified file content.
     ///
     /// # Errors
@@ -247,6 +326,20 @@ impl EnvVarsSection {
     }
 }
 
+/// Rewrite a single entry, keeping the line ending it was parsed with.
+fn replace_entry(content: &str, entry: &EnvVarEntry, name: &str, expr: &str) -> String {
+    let old = &content[entry.start_pos..entry.end_pos];
+    let line_ending = &old[old.trim_end_matches(['\r', '\n']).len()..];
+    format!(
+        "{}{} = {};{}{}",
+        &content[..entry.start_pos],
+        name,
+        expr,
+        line_ending,
+        &content[entry.end_pos..]
+    )
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -279,4 +372,90 @@ mod tests {
 
         assert!(new_content.contains("NEW_VAR = \"new_value\""));
     }
+
+    #[test]
+    fn test_env_var_value_round_trip() {
+        let content = r#"{
+  envVars = {
+    RUST_BACKTRACE = "1";
+  };
+}"#;
+
+        let value = "line one\nsay \"hi\" to ${USER} at C:\\tmp";
+        let section = parse_env_vars_section(content).unwrap();
+        let new_content = section.add_env_var(content, "GREETING", value);
+        assert!(
+            new_content.contains(r#"GREETING = "line one\nsay \"hi\" to \${USER} at C:\\tmp";"#)
+        );
+
+        let section = parse_env_vars_section(&new_content).unwrap();
+        assert_eq!(section.entries.len(), 2);
+        assert_eq!(section.entries[1].value, value);
+    }
+
+    #[test]
+    fn test_env_var_interpolation_round_trip() {
+        let content = r#"{
+  envVars = {
+    OPENSSL_LIB = "${pkgs.openssl.dev}/lib";
+    LITERAL = "\${HOME}";
+  };
+}"#;
+
+        let section = parse_env_vars_section(content).unwrap();
+        let vars = section.to_env_vars();
+        assert_eq!(vars[0].value, "${pkgs.openssl.dev}/lib");
+        assert_eq!(vars[0].nix_value(), r#""${pkgs.openssl.dev}/lib""#);
+        assert_eq!(vars[1].value, "${HOME}");
+        assert_eq!(vars[1].nix_value(), r#""\${HOME}""#);
+
+        // Writing parsed values back keeps the interpolation
+        let target = "{\n  envVars = {\n    RUST_BACKTRACE = \"1\";\n  };\n}";
+        let mut written = target.to_string();
+        for var in &vars {
+            let section = parse_env_vars_section(&written).unwrap();
+            written = section.add_env_expr(&written, &var.name, &var.nix_value());
+        }
+        assert!(written.contains(r#"    OPENSSL_LIB = "${pkgs.openssl.dev}/lib";"#));
+        assert!(written.contains(r#"    LITERAL = "\${HOME}";"#));
+
+        // A value typed by the user is escaped
+        let typed = section.set_env_var(content, "OPENSSL_LIB", "${pkgs.openssl.dev}/lib");
+        assert!(typed.contains(r#"OPENSSL_LIB = "\${pkgs.openssl.dev}/lib";"#));
+
+        assert_eq!(
+            parse_env_value(r#""${pkgs.openssl.dev}/lib""#).as_deref(),
+            Some("${pkgs.openssl.dev}/lib")
+        );
+        assert_eq!(parse_env_value(r#""a"; B = "b""#), None);
+    }
+
+    #[test]
+    fn test_set_and_rename_env_var() {
+        let content = r#"{
+  envVars = {
+    FIRST = "1";
+    SECOND = "2";
+  };
+}"#;
+
+        let section = parse_env_vars_section(content).unwrap();
+        let updated = section.set_env_var(content, "FIRST", "one");
+        assert!(updated.contains("    FIRST = \"one\";\n    SECOND = \"2\";\n"));
+        let updated = section.set_env_var(content, "THIRD", "3");
+        assert!(updated.contains("    SECOND = \"2\";\n    THIRD = \"3\";\n"));
+
+        let renamed = section.rename_env_var(content, "FIRST", "PRIMARY").unwrap();
+        assert!(renamed.contains("    PRIMARY = \"1\";\n    SECOND = \"2\";\n"));
+        assert!(section.rename_env_var(content, "FIRST", "SECOND").is_err());
+        assert!(section.rename_env_var(content, "MISSING", "OTHER").is_err());
+
+        let content = "{\n  envVars = {\n    LIB  =  \"${pkgs.zlib}/lib\" ;\n  };\n}";
+        let section = parse_env_vars_section(content).unwrap();
+        let renamed = section.rename_env_var(content, "LIB", "ZLIB_LIB").unwrap();
+        assert_eq!(
+            renamed,
+            "{\n  envVars = {\n    ZLIB_LIB  =  \"${pkgs.zlib}/lib\" ;\n  };\n}"
+        );
+    }
 }
@@ -0,0 +1,196 @@
+//! # Extends Section Parser
+//!
+//! Parser for the optional `extends = [ ... ];` section in profile files.
+//!
+//! A profile inherits packages, environment variables, commands and the shell
+//! hook of every profile it extends. The section is optional; a profile without
+//! it stands alone.
+//!
+//! ## Supported Syntax
+//!
+//! ```nix
+//! {pkgs, ...}: {
+//!   description = "Backend";
+//!   extends = [ "base" "tools" ];
+//!   packages = [ ... ];
+//! }
+//! ```
+
+use anyhow::{bail, Result};
+use nom::{character::complete::char, multi::many0, sequence::delimited, IResult, Parser};
+
+use crate::flake::nix_render::nix_string;
+use crate::flake::parsers::utils::{byte_offset, find_top_level_attr, multiws, string_literal};
+
+/// Parsed `extends` section with its location.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ExtendsSection {
+    /// Parent profile names, in declaration order
+    pub parents: Vec<String>,
+    /// Byte position where the section's line starts
+    pub start_pos: usize,
+    /// Byte position just after the terminating `;`
+    pub end_pos: usize,
+}
+
+fn parent_list(input: &str) -> IResult<&str, Vec<&str>> {
+    delimited(
+        (char('['), multiws),
+        many0(delimited(multiws, string_literal, multiws)),
+        (multiws, char(']'), multiws, char(';')),
+    )
+    .parse(input)
+}
+
+/// Parse the `extends` section from profile file content.
+///
+/// Only an `extends` attribute of the profile's top-level attribute set is
+/// recognized, so the word appearing in comments or scripts is ignored.
+///
+/// # Returns
+///
+/// `None` if the profile does not extend any other profile.
+///
+/// # Errors
+///
+/// Returns an error if an `extends` attribute exists but is not a list of strings.
+pub fn parse_extends_section(content: &str) -> Result<Option<ExtendsSection>> {
+    let Some(pos) = find_top_level_attr(content, "extends") else {
+        return Ok(None);
+    };
+    let line_start = content[..pos].rfind('\n').map_or(0, |i| i + 1);
+    let eq = pos + content[pos..].find('=').unwrap_or(0);
+    let (after_eq, _) = multiws(&content[eq + 1..]).unwrap_or(("", ""));
+    let Ok((remaining, parents)) = parent_list(after_eq) else {
+        bail!("'extends' must be a list of profile names, e.g. extends = [ \"base\" ];");
+    };
+
+    Ok(Some(ExtendsSection {
+        parents: parents.into_iter().map(String::from).collect(),
+        start_pos: line_start,
+        end_pos: byte_offset(content, remaining),
+    }))
+}
+
+/// Render an `extends` attribute: `extends = [ "a" "b" ];`.
+pub fn render_extends(parents: &[String]) -> String {
+    let rendered: Vec<String> = parents.iter().map(|p| nix_string(p)).collect();
+    format!("extends = [ {} ];", rendered.join(" "))
+}
+
+/// Set the parents of a profile, returning the modified content.
+///
+/// An existing section is replaced in place, an empty list removes it, and a
+/// new section is inserted before `packages`.
+///
+/// # Errors
+///
+/// Returns an error if the existing section is malformed or no `packages`
+/// section exists to insert before.
+pub fn set_extends(content: &str, parents: &[String]) -> Result<String> {
+    let existing = parse_extends_section(content)?;
+
+    match existing {
+        Some(section) => {
+            let indent: String = content[section.start_pos..]
+                .chars()
+                .take_while(|c| *c == ' ' || *c == '\t')
+                .collect();
+            let mut result = String::new();
+            result.push_str(&content[..section.start_pos]);
+            if parents.is_empty() {
+                let mut after = &content[section.end_pos..];
+                after = after.strip_prefix('\n').unwrap_or(after);
+                // Drop the blank line that separated the section from the next one
+                if result.ends_with("\n\n") {
+                    after = after.strip_prefix('\n').unwrap_or(after);
+                }
+                result.push_str(after);
+            } else {
+                result.push_str(&indent);
+                result.push_str(&render_extends(parents));
+                result.push_str(&content[section.end_pos..]);
+            }
+            Ok(result)
+        }
+        None if parents.is_empty() => Ok(content.to_string()),
+        None => {
+            let Some(packages_pos) = content.find("packages =") else {
+                bail!("Could not find 'packages =' to insert 'extends' before");
+            };
+            let line_start = content[..packages_pos]
+                .rfind('\n')
+                .map(|i| i + 1)
+                .unwrap_or(0);
+            let indent = &content[line_start..packages_pos];
+
+            let mut result = String::new();
+            result.push_str(&content[..line_start]);
+            result.push_str(indent);
+            result.push_str(&render_extends(parents));
+            result.push_str("\n\n");
+            result.push_str(&content[line_start..]);
+            Ok(result)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CONTENT: &str = r#"{pkgs, ...}: {
+  description = "Backend";
+
+  packages = [
+    pkgs.git
+  ];
+
+  shellHook = ''
+    # extends = [ "ignored" ];
+  '';
+}
+"#;
+
+    #[test]
+    fn test_parse_extends_section() {
+        assert_eq!(parse_extends_section(CONTENT).unwrap(), None);
+
+        let content = CONTENT.replace(
+            "  packages",
+            "  extends = [\n    \"base\"\n    \"tools\"\n  ];\n\n  packages",
+        );
+        let section = parse_extends_section(&content).unwrap().unwrap();
+        assert_eq!(section.parents, vec!["base", "tools"]);
+        assert!(content[section.start_pos..section.end_pos].ends_with("];"));
+
+        let invalid = CONTENT.replace("  packages", "  extends = base;\n  packages");
+        assert!(parse_extends_section(&invalid).is_err());
+    }
+
+    #[test]
+    fn test_parse_extends_ignores_script_assignments() {
+        let content = CONTENT.replace(
+            "    # extends = [ \"ignored\" ];",
+            "    extends=\"x\"\n    echo \"''${extends}\"",
+        );
+        assert_eq!(parse_extends_section(&content).unwrap(), None);
+        let added = set_extends(&content, &["base".to_string()]).unwrap();
+        let section = parse_extends_section(&added).unwrap().unwrap();
+        assert_eq!(section.parents, vec!["base"]);
+        assert!(added.contains("    extends=\"x\"\n"));
+    }
+
+    #[test]
+    fn test_set_extends_inserts_replaces_and_removes() {
+        let added = set_extends(CONTENT, &["base".to_string()]).unwrap();
+        assert!(added.contains("  extends = [ \"base\" ];\n\n  packages = ["));
+
+        let replaced = set_extends(&added, &["base".to_string(), "tools".to_string()]).unwrap();
+        assert!(replaced.contains("  extends = [ \"base\" \"tools\" ];\n"));
+        assert_eq!(replaced.matches("extends =").count(), 2);
+
+        let removed = set_extends(&replaced, &[]).unwrap();
+        assert_eq!(removed, CONTENT);
+    }
+}
@@ -9,7 +9,7 @@
 //!
 //! 1. Parse inputs from `flake.nix`
 //! 2. Discover profiles in `.flk/profiles/`
-//! 3. Parse each profile (packages, env vars, commands)
+//! 3. Parse each profile (extends, packages, env vars, commands)
 //! 4. Combine into a unified [`FlakeConfig`]
 
 use anyhow::{Context, Result};
@@ -23,9 +23,7 @@ use std::fs;
 
 use crate::flake::interfaces::profiles::{FlakeConfig, Profile};
 use crate::flake::parsers::{
-    commands::parse_shell_hook_section,
-    env::parse_env_vars_section,
-    packages::parse_packages_section,
+    profile::parse_profile,
     utils::{
         byte_offset, detect_indentation, identifier, list_profiles, multiws, string_literal, ws,
     },
@@ -67,43 +65,18 @@ pub fn parse_flake(path: &str) -> Result<FlakeConfig> {
             )
         })?;
 
-        // Parse each section using nom parsers
-        let packages_section = parse_packages_section(&profile_data).with_context(|| {
-            format!(
-                "Failed to parse packages in profile: {}",
-                profile_path.to_string_lossy()
-            )
-        })?;
-
-        let env_vars_section = parse_env_vars_section(&profile_data).with_context(|| {
-            format!(
-                "Failed to parse envVars in profile: {}",
-                profile_path.to_string_lossy()
-            )
-        })?;
-
-        let shell_hook_section = parse_shell_hook_section(&profile_data).with_context(|| {
-            format!(
-                "Failed to parse shellHook in profile: {}",
-                profile_path.to_string_lossy()
-            )
-        })?;
-
-        // Convert parsed sections to FlakeConfig types
-        let packages = packages_section.to_packages();
-        let env_vars = env_vars_section.to_env_vars();
-
-        // Create profile
         let profile_name = profile_path
             .file_stem()
             .context("Failed to get profile name")?
             .to_string_lossy()
             .to_string();
 
-        let mut profile = Profile::new(profile_name.clone());
-        profile.packages = packages;
-        profile.env_vars = env_vars;
-        profile.shell_hook = shell_hook_section;
+        let profile = parse_profile(&profile_name, &profile_data).with_context(|| {
+            format!(
+                "Failed to parse profile: {}",
+                profile_path.to_string_lossy()
+            )
+        })?;
 
         profiles.push(profile);
     }
@@ -124,27 +97,13 @@ pub fn parse_flake(path: &str) -> Result<FlakeConfig> {
 pub fn _parse_profile_file(path: &str) -> Result<Profile> {
     let content = fs::read_to_string(path).context("Failed to read profile file")?;
 
-    let packages_section =
-        parse_packages_section(&content).context("Failed to parse packages section")?;
-
-    let env_vars_section =
-        parse_env_vars_section(&content).context("Failed to parse envVars section")?;
-
-    let shell_hook_section =
-        parse_shell_hook_section(&content).context("Failed to parse shellHook section")?;
-
     let profile_name = std::path::Path::new(path)
         .file_stem()
         .context("Failed to get profile name")?
         .to_string_lossy()
         .to_string();
 
-    let mut profile = Profile::new(profile_name);
-    profile.packages = packages_section.to_packages();
-    profile.env_vars = env_vars_section.to_env_vars();
-    profile.shell_hook = shell_hook_section;
-
-    Ok(profile)
+    parse_profile(&profile_name, &content)
 }
 
 /// A parsed input entry with position information.
@@ -153,7 +112,7 @@ pub struct InputEntry {
     /// Input name (e.g., "nixpkgs", "flake-utils")
     pub name: String,
     /// Input URL (e.g., "github:NixOS/nixpkgs/nixos-unstable")
-    pub _url: String,
+    pub url: String,
     /// Byte position where this entry starts
     pub _start_pos: usize,
     /// Byte position where this entry ends
@@ -204,7 +163,7 @@ fn input_entry<'a>(
         remaining,
         InputEntry {
             name: name.to_string(),
-            _url: url.to_string(),
+            url: url.to_string(),
             _start_pos: start_pos,
             _end_pos: end_pos,
         },
@@ -337,8 +296,12 @@ impl InputsSection {
         Ok(format!("{}{}", before, after))
     }
 
-    /// Update an existing input's URL (internal).
-    pub fn _update_input(
+    /// Update an existing input's URL.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if no input with that name exists.
+    pub fn update_input(
         &self,
         original_content: &str,
         name: &str,
@@ -350,7 +313,8 @@ impl InputsSection {
             .find(|e| e.name == name)
             .context(format!("Input '{}' not found", name))?;
 
-        let new_line = format!("{}{}.url = \"{}\";\n", self._indentation, name, new_url);
+        // The entry starts after its indentation, which stays in place
+        let new_line = format!("{}.url = \"{}\";\n", name, new_url);
 
         let mut result = String::new();
         result.push_str(&original_content[..entry._start_pos]);
@@ -384,7 +348,7 @@ mod tests {
         assert_eq!(section.entries.len(), 3);
         assert_eq!(section.entries[0].name, "nixpkgs");
         assert_eq!(
-            section.entries[0]._url,
+            section.entries[0].url,
             "github:NixOS/nixpkgs/nixos-unstable"
         );
         assert_eq!(section.entries[1].name, "flake-utils");
@@ -436,10 +400,12 @@ mod tests {
 
         let section = parse_inputs_section(content).unwrap();
         let new_content = section
-            ._update_input(content, "nixpkgs", "github:NixOS/nixpkgs/nixos-24.05")
+            .update_input(content, "nixpkgs", "github:NixOS/nixpkgs/nixos-24.05")
             .unwrap();
 
-        assert!(new_content.contains("nixos-24.05"));
+        assert!(
+            new_content.contains("\n    nixpkgs.url = \"github:NixOS/nixpkgs/nixos-24.05\";\n  };")
+        );
         assert!(!new_content.contains("nixos-unstable"));
     }
 }
@@ -19,13 +19,23 @@
 //! - [`packages`] - Parse and modify `packages = [ ... ];` sections
 //! - [`mod@env`] - Parse and modify `envVars = { ... };` sections
 //! - [`commands`] - Parse and modify `commands = [ ... ];` sections
+//! - [`container`] - Parse and modify `containerConfig = { ... };` sections
+//! - [`extends`] - Parse and modify the optional `extends = [ ... ];` section
+//! - [`services`] - Parse and modify the optional `services = { ... };` section
+//! - [`secrets`] - Parse and modify secret lines in the `shellHook`
 //! - [`overlays`] - Parse and modify `pins.nix` overlays and sources
 //! - [`flake`] - Parse top-level flake structure and inputs
+//! - [`profile`] - Parse and write complete profile files
 //! - [`utils`] - Shared parsing utilities and combinators
 
 pub mod commands;
+pub mod container;
 pub mod env;
+pub mod extends;
 pub mod flake;
 pub mod overlays;
 pub mod packages;
+pub mod profile;
+pub mod secrets;
+pub mod services;
 pub mod utils;
@@ -357,6 +357,21 @@ pub fn remove_pinned_package_with_cleanup(content: &str, package: &str) -> Resul
     Ok(render_file(&sources_section, &overlays_section))
 }
 
+/// Point an existing source at a new reference, keeping every pin.
+///
+/// # Errors
+///
+/// Returns an error if the file cannot be parsed or the source does not exist.
+pub fn set_source_reference(content: &str, source_name: &str, source_ref: &str) -> Result<String> {
+    let mut sources_section = parse_sources_section(content)?;
+    let mut overlays_section = parse_overlay_section(content)?;
+    normalize_indentation(&mut sources_section, &mut overlays_section);
+
+    sources_section.set_source(source_name, source_ref)?;
+
+    Ok(render_file(&sources_section, &overlays_section))
+}
+
 // ============================================================================
 // RENDER HELPERS
 // ============================================================================
@@ -576,4 +591,34 @@ mod tests {
         assert!(!updated_content.contains("example-package"));
         assert!(!updated_content.contains("example-package@1.0.0"));
     }
+
+    #[test]
+    fn test_set_source_reference() {
+        let original_content = r#"{
+  sources = {
+    stable = "github:NixOS/nixpkgs/nixos-25.05";
+    pkgs-abc123 = "github:user/repo/commit";
+  };
+  pinnedPackages = {
+    pkgs-abc123 = [
+      {
+        pkg = "example-package";
+        name = "example-package@1.0.0";
+      }
+    ];
+  };
+}"#;
+
+        let updated_content = set_source_reference(
+            original_content,
+            "stable",
+            "github:NixOS/nixpkgs/nixos-25.11",
+        )
+        .unwrap();
+
+        assert!(updated_content.contains(r#"stable = "github:NixOS/nixpkgs/nixos-25.11";"#));
+        assert!(updated_content.contains(r#"pkgs-abc123 = "github:user/repo/commit";"#));
+        assert!(updated_content.contains("example-package@1.0.0"));
+        assert!(set_source_reference(original_content, "missing", "x").is_err());
+    }
 }
@@ -12,8 +12,10 @@
 //!   pkgs.ripgrep
 //!   pkgs.rust-bin.stable.latest.default  # From rust-overlay
 //!   pkgs."openssl@3.6.0"  # Version pinned
-//! ];
+//! ] ++ pkgs.lib.optionals pkgs.stdenv.isLinux [ pkgs.inotify-tools ];
 //! ```
+//!
+//! Anything appended to the list with `++` is kept as written but not parsed.
 
 use crate::flake::interfaces::profiles::Package;
 use crate::flake::parsers::utils::{
@@ -23,7 +25,7 @@ use crate::flake::parsers::utils::{
 use anyhow::{Context, Result};
 use nom::Parser;
 use nom::{
-    bytes::complete::tag,
+    bytes::complete::{tag, take_till},
     character::complete::{char, line_ending},
     combinator::opt,
     sequence::delimited,
@@ -78,6 +80,8 @@ fn package_entry<'a>(
     let (remaining, _) = multiws(input)?;
     let (remaining, name) = pkgs_suffix(remaining)?;
     let (remaining, version) = opt_attribute_version(remaining)?;
+    // Closing quote of a quoted attribute such as `pkgs."openssl@3.6.0"`
+    let (remaining, _) = opt(char('"')).parse(remaining)?;
     let (remaining, comment) = opt_inline_comment(remaining)?;
     let (remaining, _) = opt(line_ending).parse(remaining)?;
 
@@ -95,8 +99,10 @@ fn package_entry<'a>(
     ))
 }
 
-/// Parse the full packages section with nom
-fn parse_packages(input: &str, base_offset: usize) -> IResult<&str, Vec<PackageEntry>> {
+/// Parse the full packages section with nom.
+///
+/// Also returns the input at the list's closing `]`.
+fn parse_packages(input: &str, base_offset: usize) -> IResult<&str, (Vec<PackageEntry>, &str)> {
     let original_input = input; // Store original for offset calculations
 
     let (input, _) = ws(input)?;
@@ -136,10 +142,11 @@ fn parse_packages(input: &str, base_offset: usize) -> IResult<&str, Vec<PackageE
     }
 
     let (input, _) = char(']')(remaining)?;
-    let (input, _) = ws(input)?;
+    let (input, _) = multiws(input)?;
+    let (input, _) = opt((tag("++"), take_till(|c| c == ';'))).parse(input)?;
     let (input, _) = char(';')(input)?;
 
-    Ok((input, entries))
+    Ok((input, (entries, remaining)))
 }
 
 /// Parse the packages section from profile file content.
@@ -164,7 +171,7 @@ pub fn parse_packages_section(content: &str) -> Result<PackagesSection> {
     let to_parse = &content[parse_from..];
 
     match parse_packages(to_parse, parse_from) {
-        Ok((remaining, entries)) => {
+        Ok((remaining, (entries, close))) => {
             let list_start = content[parse_from..]
                 .find('[')
                 .context("Could not find '['")?
@@ -173,10 +180,7 @@ pub fn parse_packages_section(content: &str) -> Result<PackagesSection> {
 
             let section_end = parse_from + byte_offset(to_parse, remaining);
 
-            let list_end = content[list_start..section_end]
-                .rfind(']')
-                .context("Could not find ']'")?
-                + list_start;
+            let list_end = parse_from + byte_offset(to_parse, close);
 
             let list_content = &content[list_start..list_end];
             let indentation = detect_indentation(list_content);
@@ -196,10 +200,18 @@ pub fn parse_packages_section(content: &str) -> Result<PackagesSection> {
 
 impl PackagesSection {
     /// Convert parsed entries to a list of [`Package`] structs.
+    ///
+    /// Pinned entries keep their version; unpinned entries report "latest".
     pub fn to_packages(&self) -> Vec<Package> {
         self.entries
             .iter()
-            .map(|e| Package::new(e.name.clone()))
+            .map(|e| match &e.version {
+                Some(version) => Package {
+                    name: e.name.clone(),
+                    version: Some(version.clone()),
+                },
+                None => Package::new(e.name.clone()),
+            })
             .collect()
     }
 
@@ -237,6 +249,44 @@ impl PackagesSection {
         result
     }
 
+    /// Add a package before the first entry that sorts after it.
+    ///
+    /// Entries are compared by name, ignoring the `pkgs.` prefix, quotes and
+    /// pinned versions. In a sorted list the result stays sorted; otherwise
+    /// the package is still placed next to its neighbours by name.
+    pub fn add_package_sorted(
+        &self,
+        original_content: &str,
+        name: &str,
+        comment: Option<&str>,
+    ) -> String {
+        if self.entries.iter().any(|e| e.name == name) {
+            return original_content.to_string();
+        }
+
+        let key = sort_key(name);
+        let Some(next) = self.entries.iter().find(|e| sort_key(&e.name) > key) else {
+            return self.add_package(original_content, name, comment);
+        };
+
+        let new_entry = if let Some(cmt) = comment {
+            format!("{}{} # {}\n", self.indentation, name, cmt)
+        } else {
+            format!("{}{}\n", self.indentation, name)
+        };
+        let insertion_point = original_content[..next.start_pos]
+            .rfind('\n')
+            .map(|pos| pos + 1)
+            .unwrap_or(next.start_pos);
+
+        let mut result = String::new();
+        result.push_str(&original_content[..insertion_point]);
+        result.push_str(&new_entry);
+        result.push_str(&original_content[insertion_point..]);
+
+        result
+    }
+
     /// Remove a package from the section, returning the modified file content.
     ///
     /// # Arguments
@@ -254,17 +304,25 @@ impl PackagesSection {
             .find(|e| e.name == name)
             .context(format!("Package '{}' not found", name))?;
 
+        Ok(Self::remove_entry(original_content, entry))
+    }
+
+    /// Remove a specific parsed entry (and its line) from the content.
+    ///
+    /// Unlike [`remove_package`](Self::remove_package), this targets one entry
+    /// even when the same package is listed several times.
+    pub fn remove_entry(original_content: &str, entry: &PackageEntry) -> String {
         let start_line = original_content[..entry.start_pos]
             .rfind('\n')
             .map(|pos| pos + 1)
             .unwrap_or(0);
 
         let before = &original_content[..start_line];
-        let after = &original_content[entry.end_pos + 1..];
+        let after = &original_content[entry.end_pos..];
 
         let after = after.strip_prefix('\n').unwrap_or(after);
 
-        Ok(format!("{}{}", before, after))
+        format!("{}{}", before, after)
     }
 
     /// Check whether a package with the given name already exists in the section.
@@ -275,6 +333,12 @@ impl PackagesSection {
     }
 }
 
+/// Name used to order packages: no `pkgs.` prefix, quotes or pinned version.
+fn sort_key(name: &str) -> String {
+    let name = name.strip_prefix("pkgs.").unwrap_or(name).replace('"', "");
+    name.split('@').next().unwrap_or(&name).to_lowercase()
+}
+
 /// Extract package information from `nix search` output.
 ///
 /// Used by the search command to parse nix search results into
@@ -297,6 +361,32 @@ pub fn extract_packages_from_output(output: &str) -> Result<Vec<Package>> {
         .collect())
 }
 
+/// Extract package information from `nix search nixpkgs <query> --json` output.
+///
+/// Attribute paths are reported without the `legacyPackages.<system>.`
+/// prefix, sorted by name.
+pub fn extract_packages_from_search_json(output: &str) -> Result<Vec<Package>> {
+    let results: serde_json::Map<String, serde_json::Value> =
+        serde_json::from_str(output).context("Invalid JSON from nix search")?;
+
+    let mut packages: Vec<Package> = results
+        .iter()
+        .map(|(attr, info)| {
+            let name = attr.splitn(3, '.').nth(2).unwrap_or(attr).to_string();
+            Package {
+                name,
+                version: info
+                    .get("version")
+                    .and_then(|v| v.as_str())
+                    .filter(|v| !v.is_empty())
+                    .map(str::to_string),
+            }
+        })
+        .collect();
+    packages.sort_by(|a, b| a.name.cmp(&b.name));
+    Ok(packages)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -322,6 +412,22 @@ mod tests {
         assert_eq!(section.entries[1].name, "rust-analyzer");
     }
 
+    #[test]
+    fn test_packages_with_appended_list() {
+        let content = r#"{
+  packages = [
+    pkgs.elixir
+  ] ++ pkgs.lib.optionals pkgs.stdenv.isLinux [ pkgs.inotify-tools ];
+}"#;
+
+        let section = parse_packages_section(content).unwrap();
+        assert_eq!(section.entries.len(), 1);
+        let updated = section.add_package(content, "pkgs.erlang", None);
+        assert!(updated.contains(
+            "    pkgs.elixir\n    pkgs.erlang\n  ] ++ pkgs.lib.optionals pkgs.stdenv.isLinux [ pkgs.inotify-tools ];"
+        ));
+    }
+
     #[test]
     fn test_add_package() {
         let content = r#"{
@@ -403,4 +509,39 @@ fd 9.0.0 x86_64-linux Fast find
         assert_eq!(packages[1].name, "fd");
         assert_eq!(packages[1].version.as_deref(), Some("9.0.0"));
     }
+
+    #[test]
+    fn test_extract_packages_from_search_json() {
+        let output = r#"{
+  "legacyPackages.x86_64-linux.ripgrep": {"pname": "ripgrep", "version": "14.1.1", "description": "grep"},
+  "legacyPackages.x86_64-linux.python313Packages.ripgrepy": {"pname": "ripgrepy", "version": "", "description": ""}
+}"#;
+        let packages = extract_packages_from_search_json(output).unwrap();
+        assert_eq!(packages.len(), 2);
+        assert_eq!(packages[0].name, "python313Packages.ripgrepy");
+        assert_eq!(packages[0].version, None);
+        assert_eq!(packages[1].name, "ripgrep");
+        assert_eq!(packages[1].version.as_deref(), Some("14.1.1"));
+        assert!(extract_packages_from_search_json("not json").is_err());
+    }
+
+    #[test]
+    fn test_add_package_sorted() {
+        let content = r#"{
+  packages = [
+    pkgs.cargo
+    pkgs."ripgrep@14.1.0"
+  ];
+}"#;
+
+        let section = parse_packages_section(content).unwrap();
+        let new_content = section.add_package_sorted(content, "pkgs.jq", None);
+        assert!(new_content.contains("pkgs.cargo\n    pkgs.jq\n    pkgs.\"ripgrep@14.1.0\""));
+
+        let new_content = section.add_package_sorted(content, "pkgs.zoxide", None);
+        assert!(new_content.contains("\"ripgrep@14.1.0\"\n    pkgs.zoxide\n  ];"));
+
+        let new_content = section.add_package_sorted(content, "pkgs.bat", None);
+        assert!(new_content.contains("[\n    pkgs.bat\n    pkgs.cargo"));
+    }
 }
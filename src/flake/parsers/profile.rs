@@ -0,0 +1,642 @@
+//! # Profile File Parser
+//!
+//! Whole-profile parsing and editing built on top of the section parsers.
+//!
+//! The section parsers each handle a single block (`extends`, `packages`,
+//! `envVars`, `commands`, `services`, secrets in the `shellHook`). This module combines them so that a complete [`Profile`] can be
+//! read from, or written into, a profile file while leaving everything else in
+//! the file (description, `shellHook`, `containerConfig`) untouched, and so that
+//! two profiles can be merged into one.
+
+use anyhow::{Context, Result};
+
+use crate::flake::interfaces::{profiles::Profile, shellhooks::ShellHookEntry};
+use crate::flake::parsers::{
+    commands::{parse_shell_hook_section, render_shell_hook_section},
+    env::parse_env_vars_section,
+    extends::{parse_extends_section, set_extends},
+    packages::parse_packages_section,
+    secrets::{add_secret, parse_secrets, remove_secret},
+    services::{parse_services_section, render_service, set_services},
+    utils::dedent_nix_string,
+};
+
+/// How a [`Profile`] model is written into existing profile content.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ProfileWriteMode {
+    /// Add missing entries and update changed ones, keeping all other entries.
+    Merge,
+    /// Make the file match the model exactly, removing entries it does not contain.
+    Replace,
+}
+
+/// Summary of the changes made by [`write_profile`].
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct ProfileWriteReport {
+    /// Entries that were not present before (e.g. "package ripgrep")
+    pub added: Vec<String>,
+    /// Entries whose value changed
+    pub updated: Vec<String>,
+    /// Entries removed because the model does not contain them
+    pub removed: Vec<String>,
+}
+
+impl ProfileWriteReport {
+    /// Whether the write left the content unchanged.
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.updated.is_empty() && self.removed.is_empty()
+    }
+}
+
+/// Parse profile file content into a [`Profile`].
+///
+/// # Arguments
+///
+/// * `name` - Profile name (usually the file stem)
+/// * `content` - The full profile file content
+///
+/// # Errors
+///
+/// Returns an error if any of the managed sections cannot be parsed.
+pub fn parse_profile(name: &str, content: &str) -> Result<Profile> {
+    let packages_section =
+        parse_packages_section(content).context("Failed to parse packages section")?;
+
+    let env_vars_section =
+        parse_env_vars_section(content).context("Failed to parse envVars section")?;
+
+    let shell_hook_section =
+        parse_shell_hook_section(content).context("Failed to parse shellHook section")?;
+
+    let extends_section =
+        parse_extends_section(content).context("Failed to parse extends section")?;
+
+    let services_section =
+        parse_services_section(content).context("Failed to parse services section")?;
+
+    let mut profile = Profile::new(name.to_string());
+    profile.extends = extends_section.map(|s| s.parents).unwrap_or_default();
+    profile.packages = packages_section.to_packages();
+    profile.env_vars = env_vars_section.to_env_vars();
+    profile.secrets = parse_secrets(content);
+    profile.services = services_section.map(|s| s.services).unwrap_or_default();
+    profile.shell_hook = shell_hook_section;
+
+    Ok(profile)
+}
+
+/// Write a [`Profile`] model into existing profile file content.
+///
+/// Each section is edited in place, so formatting and any content flk does not
+/// manage are preserved. The profile name is not used; callers decide which file
+/// the content belongs to.
+///
+/// # Arguments
+///
+/// * `content` - The full profile file content to update
+/// * `profile` - The desired packages, environment variables and commands
+/// * `mode` - Whether entries missing from `profile` are kept or removed
+///
+/// # Returns
+///
+/// The updated content and a report of what changed.
+///
+/// # Errors
+///
+/// Returns an error if any section cannot be parsed or edited.
+pub fn write_profile(
+    content: &str,
+    profile: &Profile,
+    mode: ProfileWriteMode,
+) -> Result<(String, ProfileWriteReport)> {
+    let mut report = ProfileWriteReport::default();
+    let mut content = write_extends(content, profile, mode, &mut report)?;
+    content = write_packages(&content, profile, mode, &mut report)?;
+    content = write_env_vars(&content, profile, mode, &mut report)?;
+    content = write_commands(&content, profile, mode, &mut report)?;
+    content = write_secrets(&content, profile, mode, &mut report)?;
+    content = write_services(&content, profile, mode, &mut report)?;
+    Ok((content, report))
+}
+
+/// A value two merged profiles disagree on.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ProfileConflict {
+    /// The conflicting entry (e.g. "env EDITOR", "command build")
+    pub entry: String,
+    /// Value kept from the first profile
+    pub kept: String,
+    /// Value dropped from the second profile
+    pub dropped: String,
+}
+
+/// Union two profiles into a new profile called `name`.
+///
+/// Entries present in only one profile are taken as they are. When both
+/// profiles define the same environment variable or command differently, or
+/// pin a package to different versions, the first profile wins and the
+/// difference is returned as a [`ProfileConflict`].
+pub fn merge_profiles(
+    name: &str,
+    first: &Profile,
+    second: &Profile,
+) -> (Profile, Vec<ProfileConflict>) {
+    let mut merged = first.clone();
+    merged.name = name.to_string();
+    let mut conflicts = Vec::new();
+
+    for parent in &second.extends {
+        if !merged.extends.contains(parent) {
+            merged.extends.push(parent.clone());
+        }
+    }
+    merged.extends.retain(|parent| parent != name);
+
+    for package in &second.packages {
+        match merged.packages.iter().find(|p| p.name == package.name) {
+            Some(existing) if existing.pinned_version() != package.pinned_version() => {
+                conflicts.push(ProfileConflict {
+                    entry: format!("package {}", package.name),
+                    kept: existing.pinned_version().unwrap_or("latest").to_string(),
+                    dropped: package.pinned_version().unwrap_or("latest").to_string(),
+                });
+            }
+            Some(_) => {}
+            None => merged.packages.push(package.clone()),
+        }
+    }
+
+    for env_var in &second.env_vars {
+        match merged.env_vars.iter().find(|e| e.name == env_var.name) {
+            Some(existing) if existing.value != env_var.value => {
+                conflicts.push(ProfileConflict {
+                    entry: format!("env {}", env_var.name),
+                    kept: existing.value.clone(),
+                    dropped: env_var.value.clone(),
+                });
+            }
+            Some(_) => {}
+            None => merged.env_vars.push(env_var.clone()),
+        }
+    }
+
+    for secret in &second.secrets {
+        match merged.secrets.iter().find(|s| s.name == secret.name) {
+            Some(existing) if existing.source != secret.source => {
+                conflicts.push(ProfileConflict {
+                    entry: format!("secret {}", secret.name),
+                    kept: existing.source.to_string(),
+                    dropped: secret.source.to_string(),
+                });
+            }
+            Some(_) => {}
+            None => merged.secrets.push(secret.clone()),
+        }
+    }
+
+    for command in &second.shell_hook.entries {
+        match merged
+            .shell_hook
+            .entries
+            .iter()
+            .find(|e| e.name == command.name)
+        {
+            Some(existing)
+                if dedent_nix_string(&existing.script) != dedent_nix_string(&command.script) =>
+            {
+                conflicts.push(ProfileConflict {
+                    entry: format!("command {}", command.name),
+                    kept: dedent_nix_string(&existing.script).trim().to_string(),
+                    dropped: dedent_nix_string(&command.script).trim().to_string(),
+                });
+            }
+            Some(_) => {}
+            None => merged.shell_hook.entries.push(command.clone()),
+        }
+    }
+
+    for service in &second.services {
+        match merged.services.iter().find(|s| s.name == service.name) {
+            Some(existing) if existing != service => {
+                conflicts.push(ProfileConflict {
+                    entry: format!("service {}", service.name),
+                    kept: render_service(existing),
+                    dropped: render_service(service),
+                });
+            }
+            Some(_) => {}
+            None => merged.services.push(service.clone()),
+        }
+    }
+
+    (merged, conflicts)
+}
+
+fn write_extends(
+    content: &str,
+    profile: &Profile,
+    mode: ProfileWriteMode,
+    report: &mut ProfileWriteReport,
+) -> Result<String> {
+    let current = parse_extends_section(content)?
+        .map(|s| s.parents)
+        .unwrap_or_default();
+
+    // Merging never drops inheritance the local profile already declares
+    let wanted = match mode {
+        ProfileWriteMode::Replace => profile.extends.clone(),
+        ProfileWriteMode::Merge => {
+            let mut parents = current.clone();
+            for parent in &profile.extends {
+                if !parents.contains(parent) {
+                    parents.push(parent.clone());
+                }
+            }
+            parents
+        }
+    };
+
+    if wanted == current {
+        return Ok(content.to_string());
+    }
+
+    match (current.is_empty(), wanted.is_empty()) {
+        (true, _) => report.added.push("extends".to_string()),
+        (false, true) => report.removed.push("extends".to_string()),
+        (false, false) => report.updated.push("extends".to_string()),
+    }
+    set_extends(content, &wanted)
+}
+
+fn write_packages(
+    content: &str,
+    profile: &Profile,
+    mode: ProfileWriteMode,
+    report: &mut ProfileWriteReport,
+) -> Result<String> {
+    let mut content = content.to_string();
+
+    // Drop entries that are unwanted or pinned to a different version.
+    loop {
+        let section = parse_packages_section(&content)?;
+        let stale = section.entries.iter().find(|entry| {
+            match profile.packages.iter().find(|p| p.name == entry.name) {
+                Some(wanted) => wanted.pinned_version() != entry.version.as_deref(),
+                None => mode == ProfileWriteMode::Replace,
+            }
+        });
+        let Some(stale) = stale else { break };
+
+        let name = stale.name.clone();
+        content = section.remove_package(&content, &name)?;
+        if profile.packages.iter().any(|p| p.name == name) {
+            report.updated.push(format!("package {}", name));
+        } else {
+            report.removed.push(format!("package {}", name));
+        }
+    }
+
+    for package in &profile.packages {
+        let section = parse_packages_section(&content)?;
+        if section.entries.iter().any(|e| e.name == package.name) {
+            continue;
+        }
+
+        content = section.add_package(&content, &package.to_nix_reference(), None);
+        let label = format!("package {}", package.name);
+        if !report.updated.contains(&label) {
+            report.added.push(label);
+        }
+    }
+
+    Ok(content)
+}
+
+fn write_env_vars(
+    content: &str,
+    profile: &Profile,
+    mode: ProfileWriteMode,
+    report: &mut ProfileWriteReport,
+) -> Result<String> {
+    let mut content = content.to_string();
+
+    if mode == ProfileWriteMode::Replace {
+        loop {
+            let section = parse_env_vars_section(&content)?;
+            let stale = section
+                .entries
+                .iter()
+                .find(|e| !profile.env_vars.iter().any(|v| v.name == e.name));
+            let Some(stale) = stale else { break };
+
+            let name = stale.name.clone();
+            content = section.remove_env_var(&content, &name)?;
+            report.removed.push(format!("env {}", name));
+        }
+    }
+
+    for env_var in &profile.env_vars {
+        let section = parse_env_vars_section(&content)?;
+        let expr = env_var.nix_value();
+        match section.entries.iter().find(|e| e.name == env_var.name) {
+            Some(existing)
+                if existing.value == env_var.value
+                    && env_var
+                        .nix
+                        .as_ref()
+                        .is_none_or(|nix| *nix == existing.source) =>
+            {
+                continue
+            }
+            Some(_) => {
+                content = section.remove_env_var(&content, &env_var.name)?;
+                let section = parse_env_vars_section(&content)?;
+                content = section.add_env_expr(&content, &env_var.name, &expr);
+                report.updated.push(format!("env {}", env_var.name));
+            }
+            None => {
+                content = section.add_env_expr(&content, &env_var.name, &expr);
+                report.added.push(format!("env {}", env_var.name));
+            }
+        }
+    }
+
+    Ok(content)
+}
+
+fn write_commands(
+    content: &str,
+    profile: &Profile,
+    mode: ProfileWriteMode,
+    report: &mut ProfileWriteReport,
+) -> Result<String> {
+    let mut section = parse_shell_hook_section(content)?;
+    let before = section.entries.clone();
+
+    if mode == ProfileWriteMode::Replace {
+        section.entries.retain(|entry| {
+            let keep = profile.shell_hook.command_exists(&entry.name);
+            if !keep {
+                report.removed.push(format!("command {}", entry.name));
+            }
+            keep
+        });
+    }
+
+    for wanted in &profile.shell_hook.entries {
+        // Scripts are compared the way the parser reads them back
+        let mut wanted = ShellHookEntry {
+            script: dedent_nix_string(&wanted.script),
+            ..wanted.clone()
+        };
+        match section.entries.iter_mut().find(|e| e.name == wanted.name) {
+            Some(existing) => {
+                // Imported documents carry no extra attributes; keep the profile's
+                if wanted.extra_attrs.is_empty() {
+                    wanted.extra_attrs = existing.extra_attrs.clone();
+                }
+                if *existing != wanted {
+                    report.updated.push(format!("command {}", wanted.name));
+                    *existing = wanted;
+                }
+            }
+            None => {
+                report.added.push(format!("command {}", wanted.name));
+                section.entries.push(wanted);
+            }
+        }
+    }
+
+    if section.entries == before {
+        return Ok(content.to_string());
+    }
+
+    let rendered = render_shell_hook_section(&section);
+    Ok(section.apply_to_content(content, &rendered))
+}
+
+fn write_secrets(
+    content: &str,
+    profile: &Profile,
+    mode: ProfileWriteMode,
+    report: &mut ProfileWriteReport,
+) -> Result<String> {
+    let mut content = content.to_string();
+
+    for existing in parse_secrets(&content) {
+        match profile.secrets.iter().find(|s| s.name == existing.name) {
+            Some(wanted) if *wanted == existing => continue,
+            Some(_) => report.updated.push(format!("secret {}", existing.name)),
+            None if mode == ProfileWriteMode::Replace => {
+                report.removed.push(format!("secret {}", existing.name))
+            }
+            None => continue,
+        }
+        content = remove_secret(&content, &existing.name)?;
+    }
+
+    let present = parse_secrets(&content);
+    for secret in &profile.secrets {
+        if present.iter().any(|s| s.name == secret.name) {
+            continue;
+        }
+        if !report.updated.contains(&format!("secret {}", secret.name)) {
+            report.added.push(format!("secret {}", secret.name));
+        }
+        content = add_secret(&content, secret)?;
+    }
+
+    Ok(content)
+}
+
+fn write_services(
+    content: &str,
+    profile: &Profile,
+    mode: ProfileWriteMode,
+    report: &mut ProfileWriteReport,
+) -> Result<String> {
+    let current = parse_services_section(content)?
+        .map(|s| s.services)
+        .unwrap_or_default();
+
+    let mut wanted = match mode {
+        ProfileWriteMode::Replace => Vec::new(),
+        ProfileWriteMode::Merge => current.clone(),
+    };
+    for service in &profile.services {
+        match wanted.iter_mut().find(|s| s.name == service.name) {
+            Some(existing) => *existing = service.clone(),
+            None => wanted.push(service.clone()),
+        }
+    }
+
+    for service in &wanted {
+        match current.iter().find(|s| s.name == service.name) {
+            Some(existing) if existing == service => {}
+            Some(_) => report.updated.push(format!("service {}", service.name)),
+            None => report.added.push(format!("service {}", service.name)),
+        }
+    }
+    for service in &current {
+        if !wanted.iter().any(|s| s.name == service.name) {
+            report.removed.push(format!("service {}", service.name));
+        }
+    }
+
+    if wanted == current {
+        return Ok(content.to_string());
+    }
+    set_services(content, &wanted)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::flake::interfaces::profiles::{EnvVar, Package, Secret, SecretSource};
+
+    const CONTENT: &str = r#"{pkgs, ...}: {
+  description = "Test profile";
+
+  packages = [
+    pkgs.git
+    pkgs.curl
+  ];
+
+  envVars = {
+    KEEP = "1";
+    CHANGE = "old";
+  };
+
+  commands = [
+    {
+      name = "greet";
+      script = ''
+        echo hello
+      '';
+    }
+  ];
+
+  shellHook = ''
+    echo ready
+  '';
+}
+"#;
+
+    fn model() -> Profile {
+        let mut profile = Profile::new("test".to_string());
+        profile.packages = vec![
+            Package::new("git".to_string()),
+            Package {
+                name: "ripgrep".to_string(),
+                version: Some("14.1.0".to_string()),
+            },
+        ];
+        profile.env_vars = vec![EnvVar::new("CHANGE".to_string(), "new".to_string())];
+        profile
+            .shell_hook
+            .add_command("build", "cargo build")
+            .unwrap();
+        profile
+    }
+
+    #[test]
+    fn test_parse_profile_reads_all_sections() {
+        let profile = parse_profile("test", CONTENT).unwrap();
+        assert_eq!(profile.name, "test");
+        assert_eq!(profile.packages.len(), 2);
+        assert_eq!(profile.env_vars.len(), 2);
+        assert!(profile.shell_hook.command_exists("greet"));
+    }
+
+    #[test]
+    fn test_write_profile_merge_keeps_existing_entries() {
+        let (content, report) = write_profile(CONTENT, &model(), ProfileWriteMode::Merge).unwrap();
+        let profile = parse_profile("test", &content).unwrap();
+
+        assert!(content.contains("pkgs.curl"));
+        assert!(content.contains("pkgs.\"ripgrep@14.1.0\""));
+        assert!(content.contains("KEEP = \"1\";"));
+        assert!(content.contains("CHANGE = \"new\";"));
+        assert!(profile.shell_hook.command_exists("greet"));
+        assert!(profile.shell_hook.command_exists("build"));
+        assert!(content.contains("echo ready"));
+        assert!(report.removed.is_empty());
+        assert!(report.updated.contains(&"env CHANGE".to_string()));
+    }
+
+    #[test]
+    fn test_write_profile_replace_removes_extra_entries() {
+        let (content, report) =
+            write_profile(CONTENT, &model(), ProfileWriteMode::Replace).unwrap();
+        let profile = parse_profile("test", &content).unwrap();
+
+        assert!(!content.contains("pkgs.curl"));
+        assert!(!content.contains("KEEP"));
+        assert!(!profile.shell_hook.command_exists("greet"));
+        assert_eq!(profile.packages.len(), 2);
+        assert_eq!(profile.packages[1].version.as_deref(), Some("14.1.0"));
+        assert!(content.contains("description = \"Test profile\";"));
+        assert!(report.removed.contains(&"package curl".to_string()));
+        assert!(report.removed.contains(&"command greet".to_string()));
+    }
+
+    #[test]
+    fn test_write_profile_is_noop_for_identical_model() {
+        let profile = parse_profile("test", CONTENT).unwrap();
+        let (content, report) =
+            write_profile(CONTENT, &profile, ProfileWriteMode::Replace).unwrap();
+        assert_eq!(content, CONTENT);
+        assert!(report.is_empty());
+    }
+
+    #[test]
+    fn test_write_profile_adds_and_replaces_secrets() {
+        let mut wanted = model();
+        wanted.secrets = vec![Secret {
+            name: "TOKEN".to_string(),
+            source: SecretSource::File(".secrets/token".to_string()),
+        }];
+
+        let (content, report) = write_profile(CONTENT, &wanted, ProfileWriteMode::Merge).unwrap();
+        assert_eq!(
+            parse_profile("test", &content).unwrap().secrets,
+            wanted.secrets
+        );
+        assert!(report.added.contains(&"secret TOKEN".to_string()));
+        assert!(content.contains("    echo ready\n    export TOKEN="));
+
+        wanted.secrets[0].source = SecretSource::Command("pass show token".to_string());
+        let (content, report) = write_profile(&content, &wanted, ProfileWriteMode::Merge).unwrap();
+        assert_eq!(
+            parse_profile("test", &content).unwrap().secrets,
+            wanted.secrets
+        );
+        assert_eq!(report.updated, vec!["secret TOKEN".to_string()]);
+
+        let (content, report) =
+            write_profile(&content, &model(), ProfileWriteMode::Replace).unwrap();
+        assert!(parse_profile("test", &content).unwrap().secrets.is_empty());
+        assert!(report.removed.contains(&"secret TOKEN".to_string()));
+    }
+
+    #[test]
+    fn test_merge_profiles_unions_and_reports_conflicts() {
+        let first = parse_profile("first", CONTENT).unwrap();
+        let mut second = model();
+        second.extends = vec!["base".to_string(), "merged".to_string()];
+        second.shell_hook.add_command("greet", "echo hi").unwrap();
+
+        let (merged, conflicts) = merge_profiles("merged", &first, &second);
+
+        assert_eq!(merged.name, "merged");
+        assert_eq!(merged.extends, vec!["base"]);
+        let packages: Vec<&str> = merged.packages.iter().map(|p| p.name.as_str()).collect();
+        assert_eq!(packages, vec!["git", "curl", "ripgrep"]);
+        assert!(merged.shell_hook.command_exists("build"));
+
+        let entries: Vec<&str> = conflicts.iter().map(|c| c.entry.as_str()).collect();
+        assert_eq!(entries, vec!["env CHANGE", "command greet"]);
+        assert_eq!(conflicts[0].kept, "old");
+        assert_eq!(conflicts[0].dropped, "new");
+    }
+}
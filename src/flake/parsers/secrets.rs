@@ -0,0 +1,279 @@
+//! # Secrets Parser
+//!
+//! Secret environment variables are resolved when the development shell
+//! starts, so their values never end up in the profile file or the Nix store.
+//! Each secret is a single line of the profile's `shellHook`, tagged with a
+//! marker comment:
+//!
+//! ```nix
+//! shellHook = ''
+//!   echo "Development environment ready!"
+//!   export API_TOKEN="$(cat -- '.secrets/api-token')" # flk:secret
+//!   export DB_PASSWORD="$(pass show db/password)" # flk:secret
+//! '';
+//! ```
+
+use anyhow::{bail, Context, Result};
+use regex::Regex;
+
+use crate::flake::interfaces::profiles::{Secret, SecretSource};
+use crate::flake::parsers::utils::multiline_string;
+
+/// Comment marking a `shellHook` line as a flk-managed secret.
+const SECRET_MARKER: &str = "# flk:secret";
+
+fn secret_line_re() -> Regex {
+    Regex::new(r#"^\s*export ([A-Za-z_][A-Za-z0-9_]*)="\$\((.*)\)" # flk:secret\s*$"#).unwrap()
+}
+
+fn shell_hook_re() -> Regex {
+    Regex::new(r"(?m)^([ \t]*)shellHook\s*=\s*''").unwrap()
+}
+
+/// Check that a secret source can be written into a `shellHook`.
+///
+/// # Errors
+///
+/// Returns an error if the source is empty, spans several lines, or contains
+/// `''` or `${`, which would end or interpolate the Nix string. File paths
+/// additionally cannot contain `'`.
+pub fn validate_secret_source(source: &SecretSource) -> Result<()> {
+    let (kind, value) = match source {
+        SecretSource::File(path) => ("file path", path),
+        SecretSource::Command(command) => ("command", command),
+    };
+
+    if value.trim().is_empty() {
+        bail!("The secret {} cannot be empty", kind);
+    }
+    if value.contains('\n') {
+        bail!("The secret {} must fit on a single line", kind);
+    }
+    if value.contains("''") || value.contains("${") {
+        bail!(
+            "The secret {} cannot contain \"''\" or \"${{\"; use $VAR instead of ${{VAR}}",
+            kind
+        );
+    }
+    if matches!(source, SecretSource::File(path) if path.contains('\'')) {
+        bail!("The secret file path cannot contain a single quote");
+    }
+
+    Ok(())
+}
+
+/// Render the `shellHook` line that resolves a secret.
+pub fn render_secret_line(secret: &Secret) -> String {
+    let resolve = match &secret.source {
+        SecretSource::File(path) => format!("cat -- '{}'", path),
+        SecretSource::Command(command) => command.clone(),
+    };
+    format!(
+        "export {}=\"$({})\" {}",
+        secret.name, resolve, SECRET_MARKER
+    )
+}
+
+/// Parse a `shellHook` line written by [`render_secret_line`].
+pub fn parse_secret_line(line: &str) -> Option<Secret> {
+    let caps = secret_line_re().captures(line)?;
+    let resolve = &caps[2];
+    let source = match resolve
+        .strip_prefix("cat -- '")
+        .and_then(|rest| rest.strip_suffix('\''))
+    {
+        Some(path) if !path.contains('\'') => SecretSource::File(path.to_string()),
+        _ => SecretSource::Command(resolve.to_string()),
+    };
+
+    Some(Secret {
+        name: caps[1].to_string(),
+        source,
+    })
+}
+
+/// Byte range of the `shellHook` string content, and the hook's indentation.
+fn shell_hook_body(content: &str) -> Option<(usize, usize, String)> {
+    let caps = shell_hook_re().captures(content)?;
+    let start = caps.get(0)?.end();
+    let (_, body) = multiline_string(&content[start - 2..]).ok()?;
+    Some((start, start + body.len(), caps[1].to_string()))
+}
+
+/// Parse the secrets declared in a profile's `shellHook`.
+///
+/// Profiles without a `shellHook` have no secrets.
+pub fn parse_secrets(content: &str) -> Vec<Secret> {
+    let Some((start, end, _)) = shell_hook_body(content) else {
+        return Vec::new();
+    };
+    content[start..end]
+        .lines()
+        .filter_map(parse_secret_line)
+        .collect()
+}
+
+/// Add a secret to the end of the profile's `shellHook`.
+///
+/// # Errors
+///
+/// Returns an error if the profile has no `shellHook`, the secret already
+/// exists, or its source is invalid.
+pub fn add_secret(content: &str, secret: &Secret) -> Result<String> {
+    validate_secret_source(&secret.source)?;
+    if parse_secrets(content).iter().any(|s| s.name == secret.name) {
+        bail!("Secret '{}' already exists", secret.name);
+    }
+
+    let (start, end, hook_indent) =
+        shell_hook_body(content).context("Could not find a shellHook = '' ... ''; section")?;
+    let body = &content[start..end];
+    let line = render_secret_line(secret);
+
+    if !body.contains('\n') {
+        // Expand a single-line hook such as `shellHook = '''';`
+        let indent = format!("{}  ", hook_indent);
+        let new_body = if body.trim().is_empty() {
+            format!("\n{}{}\n{}", indent, line, hook_indent)
+        } else {
+            format!(
+                "\n{}{}\n{}{}\n{}",
+                indent,
+                body.trim(),
+                indent,
+                line,
+                hook_indent
+            )
+        };
+        return Ok(format!(
+            "{}{}{}",
+            &content[..start],
+            new_body,
+            &content[end..]
+        ));
+    }
+
+    // Insert before the line holding the closing quotes, matching the last line's indentation
+    let closing_line = content[..end].rfind('\n').map(|i| i + 1).unwrap_or(end);
+    let indent = body
+        .lines()
+        .rev()
+        .find(|l| !l.trim().is_empty())
+        .map(|l| l[..l.len() - l.trim_start().len()].to_string())
+        .unwrap_or_else(|| format!("{}  ", hook_indent));
+
+    Ok(format!(
+        "{}{}{}\n{}",
+        &content[..closing_line],
+        indent,
+        line,
+        &content[closing_line..]
+    ))
+}
+
+/// Remove a secret from the profile's `shellHook`.
+///
+/// # Errors
+///
+/// Returns an error if the secret does not exist.
+pub fn remove_secret(content: &str, name: &str) -> Result<String> {
+    let (start, end, _) =
+        shell_hook_body(content).with_context(|| format!("Secret '{}' not found", name))?;
+
+    let mut body = String::with_capacity(end - start);
+    let mut found = false;
+    for line in content[start..end].split_inclusive('\n') {
+        if parse_secret_line(line).is_some_and(|s| s.name == name) {
+            found = true;
+        } else {
+            body.push_str(line);
+        }
+    }
+
+    if !found {
+        bail!("Secret '{}' not found", name);
+    }
+
+    Ok(format!("{}{}{}", &content[..start], body, &content[end..]))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PROFILE: &str = r#"{pkgs, ...}: {
+  envVars = {
+    LANG = "en_US.UTF-8";
+  };
+
+  shellHook = ''
+    echo "ready"
+  '';
+}"#;
+
+    fn secret(name: &str, source: SecretSource) -> Secret {
+        Secret {
+            name: name.to_string(),
+            source,
+        }
+    }
+
+    #[test]
+    fn test_add_and_parse_secrets() {
+        let token = secret(
+            "API_TOKEN",
+            SecretSource::File(".secrets/token".to_string()),
+        );
+        let password = secret(
+            "DB_PASSWORD",
+            SecretSource::Command("pass show db | head -n1".to_string()),
+        );
+
+        let content = add_secret(PROFILE, &token).unwrap();
+        let content = add_secret(&content, &password).unwrap();
+        assert!(content.contains(
+            "    echo \"ready\"\n    export API_TOKEN=\"$(cat -- '.secrets/token')\" # flk:secret\n    export DB_PASSWORD=\"$(pass show db | head -n1)\" # flk:secret\n  '';"
+        ));
+        assert_eq!(parse_secrets(&content), vec![token.clone(), password]);
+        assert!(add_secret(&content, &token).is_err());
+
+        let content = remove_secret(&content, "API_TOKEN").unwrap();
+        assert_eq!(parse_secrets(&content).len(), 1);
+        assert!(!content.contains("API_TOKEN"));
+        assert!(remove_secret(&content, "API_TOKEN").is_err());
+    }
+
+    #[test]
+    fn test_add_secret_after_escaped_quotes() {
+        let content = PROFILE.replace("    echo \"ready\"\n", "    echo '''ready''' in ''${PWD}\n");
+        let token = secret("TOKEN", SecretSource::Command("gh auth token".to_string()));
+        let updated = add_secret(&content, &token).unwrap();
+        assert!(updated.contains(
+            "    echo '''ready''' in ''${PWD}\n    export TOKEN=\"$(gh auth token)\" # flk:secret\n  '';"
+        ));
+        assert_eq!(parse_secrets(&updated), vec![token]);
+    }
+
+    #[test]
+    fn test_add_secret_to_single_line_hook() {
+        let content = "{\n  shellHook = '''';\n}";
+        let token = secret("TOKEN", SecretSource::Command("gh auth token".to_string()));
+        let updated = add_secret(content, &token).unwrap();
+        assert_eq!(
+            updated,
+            "{\n  shellHook = ''\n    export TOKEN=\"$(gh auth token)\" # flk:secret\n  '';\n}"
+        );
+        assert_eq!(parse_secrets(&updated), vec![token]);
+    }
+
+    #[test]
+    fn test_validate_secret_source() {
+        assert!(validate_secret_source(&SecretSource::File("a b/c".to_string())).is_ok());
+        assert!(validate_secret_source(&SecretSource::File("it's".to_string())).is_err());
+        assert!(
+            validate_secret_source(&SecretSource::Command("echo ${HOME}".to_string())).is_err()
+        );
+        assert!(validate_secret_source(&SecretSource::Command("echo ''".to_string())).is_err());
+        assert!(validate_secret_source(&SecretSource::Command(" ".to_string())).is_err());
+    }
+}
@@ -0,0 +1,322 @@
+//! # Services Section Parser
+//!
+//! Parser for the optional `services = { ... };` section in profile files.
+//!
+//! Each service is a single-line attribute set with any of `package`, `port`
+//! and `command`. The section is optional; a profile without it runs no
+//! services.
+//!
+//! ## Supported Syntax
+//!
+//! ```nix
+//! {pkgs, ...}: {
+//!   services = {
+//!     postgres = { package = pkgs.postgresql_16; port = 5432; };
+//!     worker = { command = "python worker.py"; };
+//!   };
+//!
+//!   shellHook = '' ... '';
+//! }
+//! ```
+
+use anyhow::{bail, Result};
+use nom::{
+    branch::alt,
+    bytes::complete::tag,
+    character::complete::{char, digit1},
+    combinator::{map, map_res},
+    multi::many0,
+    sequence::{delimited, preceded, terminated},
+    IResult, Parser,
+};
+
+use crate::flake::interfaces::services::Service;
+use crate::flake::nix_render::{nix_attr_key, nix_string};
+use crate::flake::parsers::utils::{
+    attribute_path_token, byte_offset, escaped_string_literal, find_top_level_attr, identifier,
+    multiws, ws,
+};
+
+/// Parsed `services` section with its location.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ServicesSection {
+    /// Services in declaration order
+    pub services: Vec<Service>,
+    /// Byte position where the section's line starts
+    pub start_pos: usize,
+    /// Byte position just after the terminating `;`
+    pub end_pos: usize,
+}
+
+enum ServiceAttr {
+    Package(String),
+    Port(u16),
+    Command(String),
+}
+
+/// Parse one attribute of a service: `package = pkgs.x;`, `port = 5432;` or
+/// `command = "...";`.
+fn service_attr(input: &str) -> IResult<&str, ServiceAttr> {
+    terminated(
+        alt((
+            map(
+                preceded(
+                    (tag("package"), ws, char('='), ws, tag("pkgs.")),
+                    attribute_path_token,
+                ),
+                |package: &str| ServiceAttr::Package(package.to_string()),
+            ),
+            map_res(
+                preceded((tag("port"), ws, char('='), ws), digit1),
+                |port: &str| port.parse().map(ServiceAttr::Port),
+            ),
+            map(
+                preceded((tag("command"), ws, char('='), ws), escaped_string_literal),
+                ServiceAttr::Command,
+            ),
+        )),
+        (ws, char(';'), multiws),
+    )
+    .parse(input)
+}
+
+/// Parse a service entry: `name = { ... };`.
+fn service_entry(input: &str) -> IResult<&str, Service> {
+    let (input, name) = identifier(input)?;
+    let (input, attrs) = delimited(
+        (ws, char('='), ws, char('{'), multiws),
+        many0(service_attr),
+        (char('}'), ws, char(';'), multiws),
+    )
+    .parse(input)?;
+
+    let mut service = Service {
+        name: name.to_string(),
+        package: None,
+        port: None,
+        command: None,
+    };
+    for attr in attrs {
+        match attr {
+            ServiceAttr::Package(package) => service.package = Some(package),
+            ServiceAttr::Port(port) => service.port = Some(port),
+            ServiceAttr::Command(command) => service.command = Some(command),
+        }
+    }
+    Ok((input, service))
+}
+
+fn service_set(input: &str) -> IResult<&str, Vec<Service>> {
+    delimited(
+        (char('{'), multiws),
+        many0(service_entry),
+        (char('}'), ws, char(';')),
+    )
+    .parse(input)
+}
+
+/// Parse the `services` section from profile file content.
+///
+/// Only a `services` attribute of the profile's top-level attribute set is
+/// recognized, so the word appearing in comments or scripts is ignored.
+///
+/// # Returns
+///
+/// `None` if the profile declares no services.
+///
+/// # Errors
+///
+/// Returns an error if a `services` attribute exists but is not in the form
+/// flk writes.
+pub fn parse_services_section(content: &str) -> Result<Option<ServicesSection>> {
+    let Some(pos) = find_top_level_attr(content, "services") else {
+        return Ok(None);
+    };
+    let line_start = content[..pos].rfind('\n').map_or(0, |i| i + 1);
+    let eq = pos + content[pos..].find('=').unwrap_or(0);
+    let (after_eq, _) = multiws(&content[eq + 1..]).unwrap_or(("", ""));
+    let Ok((remaining, services)) = service_set(after_eq) else {
+        bail!(
+            "'services' must map names to {{ package = pkgs.<name>; port = <n>; command = \"...\"; }}"
+        );
+    };
+
+    Ok(Some(ServicesSection {
+        services,
+        start_pos: line_start,
+        end_pos: byte_offset(content, remaining),
+    }))
+}
+
+/// Render a single service: `name = { package = pkgs.x; port = 1; };`.
+pub fn render_service(service: &Service) -> String {
+    let mut attrs = String::new();
+    if let Some(package) = &service.package {
+        attrs.push_str(&format!(" package = pkgs.{};", package));
+    }
+    if let Some(port) = service.port {
+        attrs.push_str(&format!(" port = {};", port));
+    }
+    if let Some(command) = &service.command {
+        attrs.push_str(&format!(" command = {};", nix_string(command)));
+    }
+    format!("{} = {{{} }};", nix_attr_key(&service.name), attrs)
+}
+
+/// Render a `services` attribute with one line per service.
+///
+/// The first line is not indented; following lines are indented relative to
+/// `indent`, the indentation of the `services` line itself.
+pub fn render_services(services: &[Service], indent: &str) -> String {
+    let mut out = String::from("services = {\n");
+    for service in services {
+        out.push_str(indent);
+        out.push_str("  ");
+        out.push_str(&render_service(service));
+        out.push('\n');
+    }
+    out.push_str(indent);
+    out.push_str("};");
+    out
+}
+
+/// Set the services of a profile, returning the modified content.
+///
+/// An existing section is replaced in place, an empty list removes it, and a
+/// new section is inserted before `shellHook`.
+///
+/// # Errors
+///
+/// Returns an error if the existing section is malformed or no `shellHook`
+/// exists to insert before.
+pub fn set_services(content: &str, services: &[Service]) -> Result<String> {
+    let existing = parse_services_section(content)?;
+
+    match existing {
+        Some(section) => {
+            let indent: String = content[section.start_pos..]
+                .chars()
+                .take_while(|c| *c == ' ' || *c == '\t')
+                .collect();
+            let mut result = String::new();
+            result.push_str(&content[..section.start_pos]);
+            if services.is_empty() {
+                let mut after = &content[section.end_pos..];
+                after = after.strip_prefix('\n').unwrap_or(after);
+                // Drop the blank line that separated the section from the next one
+                if result.ends_with("\n\n") {
+                    after = after.strip_prefix('\n').unwrap_or(after);
+                }
+                result.push_str(after);
+            } else {
+                result.push_str(&indent);
+                result.push_str(&render_services(services, &indent));
+                result.push_str(&content[section.end_pos..]);
+            }
+            Ok(result)
+        }
+        None if services.is_empty() => Ok(content.to_string()),
+        None => {
+            let Some(hook_pos) = content.find("shellHook =") else {
+                bail!("Could not find 'shellHook =' to insert 'services' before");
+            };
+            let line_start = content[..hook_pos].rfind('\n').map(|i| i + 1).unwrap_or(0);
+            let indent = &content[line_start..hook_pos];
+
+            let mut result = String::new();
+            result.push_str(&content[..line_start]);
+            result.push_str(indent);
+            result.push_str(&render_services(services, indent));
+            result.push_str("\n\n");
+            result.push_str(&content[line_start..]);
+            Ok(result)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CONTENT: &str = r#"{pkgs, ...}: {
+  description = "Backend";
+
+  packages = [
+    pkgs.git
+  ];
+
+  shellHook = ''
+    # services = { };
+  '';
+}
+"#;
+
+    fn service(
+        name: &str,
+        package: Option<&str>,
+        port: Option<u16>,
+        command: Option<&str>,
+    ) -> Service {
+        Service {
+            name: name.to_string(),
+            package: package.map(str::to_string),
+            port,
+            command: command.map(str::to_string),
+        }
+    }
+
+    #[test]
+    fn test_parse_services_section() {
+        assert_eq!(parse_services_section(CONTENT).unwrap(), None);
+
+        let content = CONTENT.replace(
+            "  shellHook",
+            "  services = {\n    postgres = { package = pkgs.postgresql_16; port = 5432; };\n    worker = {\n      command = \"celery -A \\\"app\\\" worker\";\n    };\n  };\n\n  shellHook",
+        );
+        let section = parse_services_section(&content).unwrap().unwrap();
+        assert_eq!(
+            section.services,
+            vec![
+                service("postgres", Some("postgresql_16"), Some(5432), None),
+                service("worker", None, None, Some("celery -A \"app\" worker")),
+            ]
+        );
+        assert!(content[section.start_pos..section.end_pos].ends_with("};"));
+
+        let invalid = CONTENT.replace("  shellHook", "  services = { db = 1; };\n  shellHook");
+        assert!(parse_services_section(&invalid).is_err());
+    }
+
+    #[test]
+    fn test_parse_services_ignores_script_assignments() {
+        let content = CONTENT.replace(
+            "    # services = { };",
+            "    services=\"web db\"\n    for s in $services; do echo \"''${s}\"; done",
+        );
+        assert_eq!(parse_services_section(&content).unwrap(), None);
+
+        let redis = service("redis", Some("redis"), Some(6379), None);
+        let added = set_services(&content, std::slice::from_ref(&redis)).unwrap();
+        let section = parse_services_section(&added).unwrap().unwrap();
+        assert_eq!(section.services, vec![redis]);
+        assert!(added.contains("    services=\"web db\"\n"));
+    }
+
+    #[test]
+    fn test_set_services_inserts_replaces_and_removes() {
+        let redis = service("redis", Some("redis"), Some(6379), None);
+        let added = set_services(CONTENT, std::slice::from_ref(&redis)).unwrap();
+        assert!(added.contains(
+            "  services = {\n    redis = { package = pkgs.redis; port = 6379; };\n  };\n\n  shellHook = ''"
+        ));
+
+        let worker = service("worker", None, None, Some("run ${x}"));
+        let replaced = set_services(&added, &[redis, worker.clone()]).unwrap();
+        assert!(replaced.contains("    worker = { command = \"run \\${x}\"; };\n"));
+        let parsed = parse_services_section(&replaced).unwrap().unwrap();
+        assert_eq!(parsed.services[1], worker);
+
+        let removed = set_services(&replaced, &[]).unwrap();
+        assert_eq!(removed, CONTENT);
+    }
+}
@@ -86,9 +86,78 @@ pub fn string_literal(input: &str) -> IResult<&str, &str> {
     delimited(char('"'), take_until("\""), char('"')).parse(input)
 }
 
+/// Parse a double-quoted Nix string, decoding its escape sequences.
+///
+/// Unlike [`string_literal`], escaped quotes do not end the string, and
+/// `\n`, `\t`, `\r`, `\"`, `\\` and `\$` are turned back into the
+/// characters they stand for.
+pub fn escaped_string_literal(input: &str) -> IResult<&str, String> {
+    let (rest, _) = char('"')(input)?;
+    let mut value = String::new();
+    let mut chars = rest.char_indices();
+
+    while let Some((i, ch)) = chars.next() {
+        match ch {
+            '"' => return Ok((&rest[i + 1..], value)),
+            '\\' => match chars.next() {
+                Some((_, 'n')) => value.push('\n'),
+                Some((_, 't')) => value.push('\t'),
+                Some((_, 'r')) => value.push('\r'),
+                Some((_, escaped)) => value.push(escaped),
+                None => break,
+            },
+            _ => value.push(ch),
+        }
+    }
+
+    Err(nom::Err::Error(nom::error::Error::new(
+        input,
+        nom::error::ErrorKind::Char,
+    )))
+}
+
 /// Parse a Nix multiline string (`'' ... ''`), returning the inner content.
+///
+/// The content is returned as written, so escapes such as `'''` and `''${`
+/// are kept and the string ends at the first unescaped `''`.
 pub fn multiline_string(input: &str) -> IResult<&str, &str> {
-    delimited(tag("''"), take_until("''"), tag("''")).parse(input)
+    let (_, _) = tag("''").parse(input)?;
+    match indented_string_end(input, 0) {
+        Some(end) => Ok((&input[end..], &input[2..end - 2])),
+        None => Err(nom::Err::Error(nom::error::Error::new(
+            input,
+            nom::error::ErrorKind::TakeUntil,
+        ))),
+    }
+}
+
+/// Normalize the content of a Nix indented string (`'' ... ''`).
+///
+/// Mirrors how Nix itself reads indented strings: a first line containing only
+/// whitespace is dropped, the common indentation of non-empty lines is removed,
+/// and trailing whitespace-only lines are trimmed.
+pub fn dedent_nix_string(content: &str) -> String {
+    let mut lines: Vec<&str> = content.lines().collect();
+
+    if lines.first().is_some_and(|line| line.trim().is_empty()) {
+        lines.remove(0);
+    }
+    while lines.last().is_some_and(|line| line.trim().is_empty()) {
+        lines.pop();
+    }
+
+    let indent = lines
+        .iter()
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| line.len() - line.trim_start().len())
+        .min()
+        .unwrap_or(0);
+
+    lines
+        .iter()
+        .map(|line| line.get(indent..).unwrap_or("").trim_end())
+        .collect::<Vec<_>>()
+        .join("\n")
 }
 
 /// Parse an inline comment starting with `#`.
@@ -129,108 +198,88 @@ pub fn byte_offset(original: &str, remaining: &str) -> usize {
     remaining.as_ptr() as usize - original.as_ptr() as usize
 }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-
-    #[test]
-    fn test_identifier() {
-        assert_eq!(identifier("rust-analyzer").unwrap().1, "rust-analyzer");
-        assert_eq!(identifier("my_var").unwrap().1, "my_var");
-    }
-
-    #[test]
-    fn test_attribute_path() {
-        assert_eq!(
-            attribute_path_token("rust-bin.stable.latest.default")
-                .unwrap()
-                .1,
-            "rust-bin.stable.latest.default"
-        );
-    }
-
-    #[test]
-    fn test_string_literal() {
-        assert_eq!(string_literal("\"hello world\"").unwrap().1, "hello world");
-    }
-
-    #[test]
-    fn test_inline_comment() {
-        assert_eq!(
-            inline_comment("# This is a comment").unwrap().1,
-            " This is a comment"
-        );
-    }
-
-    // ========================================================================
-    // PROFILE RESOLUTION TESTS
-    // ========================================================================
-
-    #[test]
-    fn test_normalize_profile_ref_simple_name() {
-        assert_eq!(normalize_profile_ref("rust"), Some("rust".to_string()));
-        assert_eq!(
-            normalize_profile_ref("my-profile"),
-            Some("my-profile".to_string())
-        );
-    }
-
-    #[test]
-    fn test_normalize_profile_ref_with_dot_hash_prefix() {
-        assert_eq!(normalize_profile_ref(".#rust"), Some("rust".to_string()));
-        assert_eq!(
-            normalize_profile_ref(".#my-profile"),
-            Some("my-profile".to_string())
-        );
-    }
-
-    #[test]
-    fn test_normalize_profile_ref_with_path_hash() {
-        assert_eq!(
-            normalize_profile_ref("/path/to/flake#rust"),
-            Some("rust".to_string())
-        );
-        assert_eq!(
-            normalize_profile_ref("github:user/repo#profile"),
-            Some("profile".to_string())
-        );
-    }
-
-    #[test]
-    fn test_normalize_profile_ref_empty_inputs() {
-        assert_eq!(normalize_profile_ref(""), None);
-        assert_eq!(normalize_profile_ref("   "), None);
-        assert_eq!(normalize_profile_ref("."), None);
-        assert_eq!(normalize_profile_ref(".#"), None);
-    }
-
-    #[test]
-    fn test_normalize_profile_ref_trims_whitespace() {
-        assert_eq!(normalize_profile_ref("  rust  "), Some("rust".to_string()));
-        assert_eq!(
-            normalize_profile_ref("  .#rust  "),
-            Some("rust".to_string())
-        );
+/// Find the end of a Nix indented string (`'' ... ''`) starting at `start`.
+///
+/// The escapes `'''`, `''$` and `''\` do not end the string. Returns the byte
+/// position just after the closing `''`.
+pub fn indented_string_end(content: &str, start: usize) -> Option<usize> {
+    let bytes = content.as_bytes();
+    let mut i = start + 2;
+    while i + 1 < bytes.len() {
+        if bytes[i] == b'\'' && bytes[i + 1] == b'\'' {
+            match bytes.get(i + 2) {
+                Some(b'\'') | Some(b'$') => i += 3,
+                Some(b'\\') => i += 4,
+                _ => return Some(i + 2),
+            }
+        } else {
+            i += 1;
+        }
     }
+    None
+}
 
-    #[test]
-    fn test_is_valid_profile_name_valid() {
-        assert!(is_valid_profile_name("rust"));
-        assert!(is_valid_profile_name("my-profile"));
-        assert!(is_valid_profile_name("profile_1"));
-        assert!(is_valid_profile_name("Profile123"));
+/// Find the end of a Nix double-quoted string starting at `start`.
+fn quoted_string_end(content: &str, start: usize) -> Option<usize> {
+    let bytes = content.as_bytes();
+    let mut i = start + 1;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'\\' => i += 2,
+            b'"' => return Some(i + 1),
+            _ => i += 1,
+        }
     }
+    None
+}
 
-    #[test]
-    fn test_is_valid_profile_name_invalid() {
-        assert!(!is_valid_profile_name(""));
-        assert!(!is_valid_profile_name("../etc"));
-        assert!(!is_valid_profile_name("path/traversal"));
-        assert!(!is_valid_profile_name("with spaces"));
-        assert!(!is_valid_profile_name("."));
-        assert!(!is_valid_profile_name(".."));
-        assert!(!is_valid_profile_name("pro\\file"));
+/// Find a top-level attribute `name = ...` of a profile's attribute set.
+///
+/// Returns the byte position of the name. Strings, comments and nested sets
+/// and lists are skipped, so the same word inside a `shellHook`, a command
+/// script or `containerConfig` does not match.
+pub fn find_top_level_attr(content: &str, name: &str) -> Option<usize> {
+    let bytes = content.as_bytes();
+    let is_ident = |b: u8| b.is_ascii_alphanumeric() || b == b'_' || b == b'-' || b == b'\'';
+    let mut depth = 0usize;
+    let mut i = 0;
+
+    while i < bytes.len() {
+        match bytes[i] {
+            b'#' => i = content[i..].find('\n').map_or(bytes.len(), |n| i + n),
+            b'/' if bytes.get(i + 1) == Some(&b'*') => {
+                i = content[i + 2..]
+                    .find("*/")
+                    .map_or(bytes.len(), |n| i + n + 4)
+            }
+            b'"' => i = quoted_string_end(content, i)?,
+            b'\'' if bytes.get(i + 1) == Some(&b'\'') => i = indented_string_end(content, i)?,
+            b'{' | b'[' | b'(' => {
+                depth += 1;
+                i += 1;
+            }
+            b'}' | b']' | b')' => {
+                depth = depth.saturating_sub(1);
+                i += 1;
+            }
+            _ if depth == 1
+                && bytes[i..].starts_with(name.as_bytes())
+                && (i == 0 || !is_ident(bytes[i - 1])) =>
+            {
+                let rest = &bytes[i + name.len()..];
+                let after = rest.trim_ascii_start();
+                if !rest.first().is_some_and(|b| is_ident(*b))
+                    && after.starts_with(b"=")
+                    && !after.starts_with(b"==")
+                {
+                    return Some(i);
+                }
+                i += name.len();
+            }
+            _ => i += 1,
+        }
     }
+    None
 }
 
 /// Get the default shell profile name from `.flk/default.nix`.
@@ -260,6 +309,9 @@ pub fn get_default_shell_profile() -> Result<String> {
     get_first_profile_name()
 }
 
+/// Default value of `maxCombinations` when `.flk/default.nix` does not set it.
+pub const DEFAULT_MAX_COMBINATIONS: usize = 3;
+
 /// Resolve which profile to use based on priority order.
 ///
 /// Resolution order:
@@ -268,24 +320,22 @@ pub fn get_default_shell_profile() -> Result<String> {
 /// 3. `defaultShell` attribute in `.flk/default.nix`
 /// 4. First available profile in `.flk/profiles/`
 ///
+/// Combined references such as `rust+python` are rejected, since the result
+/// names a single profile file to read or modify. Use [`resolve_profile_ref`]
+/// where a combination is acceptable.
+///
 /// # Arguments
 ///
 /// * `target` - Optional explicit profile from the `--profile` CLI flag
 pub fn resolve_profile(target: Option<String>) -> Result<String> {
-    let profile = if let Some(p) = target.and_then(|p| normalize_profile_ref(&p)) {
-        p
-    } else if let Ok(env_profile) = env::var("FLK_FLAKE_REF") {
-        match normalize_profile_ref(&env_profile) {
-            Some(p) => p,
-            None => {
-                return get_default_shell_profile()
-                    .context("Could not find default shell profile. Run 'flk init' to create one")
-            }
-        }
-    } else {
-        return get_default_shell_profile()
-            .context("Could not find default shell profile. Run 'flk init' to create one");
-    };
+    let profile = requested_profile(target)?;
+
+    if profile.contains('+') && is_valid_profile_ref(&profile) {
+        anyhow::bail!(
+            "'{}' combines several profiles, but this command works on a single profile. Pass one with --profile.",
+            profile
+        );
+    }
 
     // Validate profile name to prevent path traversal
     if !is_valid_profile_name(&profile) {
@@ -298,6 +348,100 @@ pub fn resolve_profile(target: Option<String>) -> Result<String> {
     Ok(profile)
 }
 
+/// Resolve a profile reference that may combine several profiles.
+///
+/// Uses the same resolution order as [`resolve_profile`], but also accepts
+/// references like `rust+python`, which select the combined shell generated by
+/// `profile-lib`. Every profile in the combination must exist, appear only once,
+/// and the combination may not exceed `maxCombinations`.
+///
+/// # Arguments
+///
+/// * `target` - Optional explicit profile from the `--profile` CLI flag
+pub fn resolve_profile_ref(target: Option<String>) -> Result<String> {
+    let profile_ref = requested_profile(target)?;
+
+    if !is_valid_profile_ref(&profile_ref) {
+        anyhow::bail!(
+            "Invalid profile reference '{}'. Use a profile name or several joined with '+', e.g. rust+python.",
+            profile_ref
+        );
+    }
+
+    let parts = split_profile_ref(&profile_ref);
+    if parts.len() > 1 {
+        for (i, part) in parts.iter().enumerate() {
+            if parts[..i].contains(part) {
+                anyhow::bail!(
+                    "Profile '{}' appears more than once in '{}'",
+                    part,
+                    profile_ref
+                );
+            }
+            if !PathBuf::from(".flk/profiles")
+                .join(format!("{}.nix", part))
+                .exists()
+            {
+                anyhow::bail!("Profile '{}' in '{}' does not exist", part, profile_ref);
+            }
+        }
+
+        let max = get_max_combinations()?;
+        if parts.len() > max {
+            anyhow::bail!(
+                "'{}' combines {} profiles, but maxCombinations is {}. Raise it with 'flk profile set-max-combinations {}'.",
+                profile_ref,
+                parts.len(),
+                max,
+                parts.len()
+            );
+        }
+    }
+
+    Ok(profile_ref)
+}
+
+fn requested_profile(target: Option<String>) -> Result<String> {
+    if let Some(p) = target.and_then(|p| normalize_profile_ref(&p)) {
+        return Ok(p);
+    }
+    if let Some(p) = env::var("FLK_FLAKE_REF")
+        .ok()
+        .and_then(|p| normalize_profile_ref(&p))
+    {
+        return Ok(p);
+    }
+    get_default_shell_profile()
+        .context("Could not find default shell profile. Run 'flk init' to create one")
+}
+
+/// Split a profile reference such as `rust+python` into its profile names.
+pub fn split_profile_ref(profile_ref: &str) -> Vec<&str> {
+    profile_ref.split('+').collect()
+}
+
+/// Validate a profile reference: one profile name, or several joined with `+`.
+pub fn is_valid_profile_ref(profile_ref: &str) -> bool {
+    split_profile_ref(profile_ref)
+        .iter()
+        .all(|part| is_valid_profile_name(part))
+}
+
+/// Get the `maxCombinations` setting from `.flk/default.nix`.
+///
+/// Falls back to [`DEFAULT_MAX_COMBINATIONS`] when the attribute is missing.
+pub fn get_max_combinations() -> Result<usize> {
+    let content = fs::read_to_string(".flk/default.nix")
+        .context("Failed to read .flk/default.nix. Have you run 'flk init'?")?;
+    let re = regex::Regex::new(r"maxCombinations\s*=\s*(\d+)\s*;").unwrap();
+    match re.captures(&content) {
+        Some(caps) => caps[1]
+            .parse()
+            .context("Invalid maxCombinations value in .flk/default.nix"),
+        None => Ok(DEFAULT_MAX_COMBINATIONS),
+    }
+}
+
 fn normalize_profile_ref(profile: &str) -> Option<String> {
     let trimmed = profile.trim();
     if trimmed.is_empty() || trimmed == "." || trimmed == ".#" {
@@ -333,6 +477,38 @@ pub fn is_valid_profile_name(name: &str) -> bool {
             .all(|c| c.is_alphanumeric() || c == '-' || c == '_')
 }
 
+/// Validate an environment variable name.
+///
+/// Names must start with a letter or underscore and contain only letters,
+/// numbers, and underscores.
+pub fn is_valid_env_var_name(name: &str) -> bool {
+    if name.is_empty() {
+        return false;
+    }
+
+    let first_char = name.chars().next().unwrap();
+    if !first_char.is_alphabetic() && first_char != '_' {
+        return false;
+    }
+
+    name.chars().all(|c| c.is_alphanumeric() || c == '_')
+}
+
+/// Validate a custom command name (letters, numbers, hyphens, underscores).
+pub fn is_valid_command_name(name: &str) -> bool {
+    !name.is_empty()
+        && name
+            .chars()
+            .all(|c| c.is_alphanumeric() || c == '-' || c == '_')
+        && !name.starts_with('-')
+}
+
+/// Validate a Nix attribute path such as `rust-bin.stable.latest.default`,
+/// as accepted by [`attribute_path_token`].
+pub fn is_valid_attribute_path(path: &str) -> bool {
+    matches!(attribute_path_token(path), Ok(("", _))) && path.split('.').all(|s| !s.is_empty())
+}
+
 /// Get first profile name from pofiles directory
 fn get_first_profile_name() -> Result<String> {
     let profiles = list_profiles()?;
@@ -366,3 +542,175 @@ pub fn list_profiles() -> Result<Vec<PathBuf>> {
         .map(|e| e.path())
         .collect())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_find_top_level_attr_skips_strings_and_nested_sets() {
+        let content = r#"{pkgs, ...}: let
+  extends = "let binding";
+in {
+  # extends = [ "comment" ];
+  containerConfig = { extends = 1; };
+  shellHook = ''
+    extends="x"
+    echo "''${HOME}" ''' extends = 2
+  '';
+  note = "extends = 3";
+  extends = [ "base" ];
+}
+"#;
+        let pos = find_top_level_attr(content, "extends").unwrap();
+        assert!(content[pos..].starts_with("extends = [ \"base\" ];"));
+        assert_eq!(find_top_level_attr(content, "packages"), None);
+        assert_eq!(find_top_level_attr("{ extendsMore = 1; }", "extends"), None);
+    }
+
+    #[test]
+    fn test_identifier() {
+        assert_eq!(identifier("rust-analyzer").unwrap().1, "rust-analyzer");
+        assert_eq!(identifier("my_var").unwrap().1, "my_var");
+    }
+
+    #[test]
+    fn test_attribute_path() {
+        assert_eq!(
+            attribute_path_token("rust-bin.stable.latest.default")
+                .unwrap()
+                .1,
+            "rust-bin.stable.latest.default"
+        );
+        assert!(is_valid_attribute_path("rust-bin.stable.latest.default"));
+        assert!(!is_valid_attribute_path("foo bar"));
+        assert!(!is_valid_attribute_path("foo;"));
+        assert!(!is_valid_attribute_path("foo..bar"));
+        assert!(!is_valid_attribute_path(""));
+    }
+
+    #[test]
+    fn test_string_literal() {
+        assert_eq!(string_literal("\"hello world\"").unwrap().1, "hello world");
+    }
+
+    #[test]
+    fn test_escaped_string_literal() {
+        let (rest, value) = escaped_string_literal(r#""say \"hi\"\n\${x} \\"; tail"#).unwrap();
+        assert_eq!(value, "say \"hi\"\n${x} \\");
+        assert_eq!(rest, "; tail");
+        assert!(escaped_string_literal(r#""unterminated"#).is_err());
+    }
+
+    #[test]
+    fn test_multiline_string_keeps_escapes() {
+        let input = "''\n  echo '''quoted''' ''${HOME} ''\\n\n'';\nrest";
+        assert_eq!(
+            multiline_string(input),
+            Ok((";\nrest", "\n  echo '''quoted''' ''${HOME} ''\\n\n"))
+        );
+        assert_eq!(multiline_string("'''';"), Ok((";", "")));
+        assert!(multiline_string("''unterminated").is_err());
+    }
+
+    #[test]
+    fn test_dedent_nix_string() {
+        assert_eq!(
+            dedent_nix_string("\n        echo hi\n          indented\n\n        done\n      "),
+            "echo hi\n  indented\n\ndone"
+        );
+        assert_eq!(dedent_nix_string("npm run dev"), "npm run dev");
+        assert_eq!(dedent_nix_string(""), "");
+    }
+
+    #[test]
+    fn test_inline_comment() {
+        assert_eq!(
+            inline_comment("# This is a comment").unwrap().1,
+            " This is a comment"
+        );
+    }
+
+    // ========================================================================
+    // PROFILE RESOLUTION TESTS
+    // ========================================================================
+
+    #[test]
+    fn test_normalize_profile_ref_simple_name() {
+        assert_eq!(normalize_profile_ref("rust"), Some("rust".to_string()));
+        assert_eq!(
+            normalize_profile_ref("my-profile"),
+            Some("my-profile".to_string())
+        );
+    }
+
+    #[test]
+    fn test_normalize_profile_ref_with_dot_hash_prefix() {
+        assert_eq!(normalize_profile_ref(".#rust"), Some("rust".to_string()));
+        assert_eq!(
+            normalize_profile_ref(".#my-profile"),
+            Some("my-profile".to_string())
+        );
+    }
+
+    #[test]
+    fn test_normalize_profile_ref_with_path_hash() {
+        assert_eq!(
+            normalize_profile_ref("/path/to/flake#rust"),
+            Some("rust".to_string())
+        );
+        assert_eq!(
+            normalize_profile_ref("github:user/repo#profile"),
+            Some("profile".to_string())
+        );
+    }
+
+    #[test]
+    fn test_normalize_profile_ref_empty_inputs() {
+        assert_eq!(normalize_profile_ref(""), None);
+        assert_eq!(normalize_profile_ref("   "), None);
+        assert_eq!(normalize_profile_ref("."), None);
+        assert_eq!(normalize_profile_ref(".#"), None);
+    }
+
+    #[test]
+    fn test_normalize_profile_ref_trims_whitespace() {
+        assert_eq!(normalize_profile_ref("  rust  "), Some("rust".to_string()));
+        assert_eq!(
+            normalize_profile_ref("  .#rust  "),
+            Some("rust".to_string())
+        );
+    }
+
+    #[test]
+    fn test_is_valid_profile_name_valid() {
+        assert!(is_valid_profile_name("rust"));
+        assert!(is_valid_profile_name("my-profile"));
+        assert!(is_valid_profile_name("profile_1"));
+        assert!(is_valid_profile_name("Profile123"));
+    }
+
+    #[test]
+    fn test_is_valid_profile_ref() {
+        assert!(is_valid_profile_ref("rust"));
+        assert!(is_valid_profile_ref("rust+python"));
+        assert!(is_valid_profile_ref("rust+python+node_20"));
+        assert!(!is_valid_profile_ref("rust+"));
+        assert!(!is_valid_profile_ref("+rust"));
+        assert!(!is_valid_profile_ref("rust++python"));
+        assert!(!is_valid_profile_ref("rust+../etc"));
+        assert_eq!(split_profile_ref("rust+python"), vec!["rust", "python"]);
+    }
+
+    #[test]
+    fn test_is_valid_profile_name_invalid() {
+        assert!(!is_valid_profile_name(""));
+        assert!(!is_valid_profile_name("../etc"));
+        assert!(!is_valid_profile_name("path/traversal"));
+        assert!(!is_valid_profile_name("with spaces"));
+        assert!(!is_valid_profile_name("."));
+        assert!(!is_valid_profile_name(".."));
+        assert!(!is_valid_profile_name("pro\\file"));
+        assert!(!is_valid_profile_name("rust+python"));
+    }
+}
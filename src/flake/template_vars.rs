@@ -0,0 +1,255 @@
+//! # Template Variables
+//!
+//! Profile templates can declare variables with a default value in comment
+//! lines, and use them anywhere in the template as `{{name}}`:
+//!
+//! ```nix
+//! # flk:var python_version = 313 -- Python version, as in pkgs.python313
+//! {pkgs, ...}: {
+//!   packages = [ pkgs.python{{python_version}} ];
+//! }
+//! ```
+//!
+//! Declarations are removed when the template is rendered. Values end up in
+//! Nix code, so they are restricted to letters, digits, `.`, `_`, `-` and `+`.
+//! Only declared variables are substituted; any other `{{...}}`, such as a
+//! Go template in a shell hook, is left as written.
+
+use anyhow::{bail, Context, Result};
+use regex::Regex;
+
+/// Marker starting a variable declaration in a template comment.
+const DECLARATION_MARKER: &str = "flk:var";
+
+fn declaration_re() -> Regex {
+    Regex::new(r"^\s*#\s*flk:var\s+(\S+)\s*=\s*(\S+)\s*(?:--\s*(.*?))?\s*$").unwrap()
+}
+
+fn placeholder_re() -> Regex {
+    Regex::new(r"\{\{\s*([A-Za-z0-9_]+)\s*\}\}").unwrap()
+}
+
+/// A variable declared by a template.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TemplateVariable {
+    /// Variable name, used as `{{name}}`
+    pub name: String,
+    /// Value used when none is given
+    pub default: String,
+    /// Optional explanation shown by `flk template show`
+    pub description: Option<String>,
+}
+
+fn is_declaration(line: &str) -> bool {
+    line.trim_start()
+        .strip_prefix('#')
+        .is_some_and(|rest| rest.trim_start().starts_with(DECLARATION_MARKER))
+}
+
+/// Check that a value can be substituted into a template.
+///
+/// # Errors
+///
+/// Returns an error if the value is empty or contains characters other than
+/// letters, digits, `.`, `_`, `-` and `+`.
+pub fn validate_value(name: &str, value: &str) -> Result<()> {
+    if !Regex::new(r"^[A-Za-z0-9._+-]+$").unwrap().is_match(value) {
+        bail!(
+            "Invalid value '{}' for template variable '{}': only letters, digits, '.', '_', '-' and '+' are allowed",
+            value,
+            name
+        );
+    }
+    Ok(())
+}
+
+/// Parse the variables declared by a template.
+///
+/// # Errors
+///
+/// Returns an error for malformed or duplicate declarations, invalid names and
+/// invalid defaults.
+pub fn parse_variables(content: &str) -> Result<Vec<TemplateVariable>> {
+    let declaration_re = declaration_re();
+    let name_re = Regex::new(r"^[a-z_][a-z0-9_]*$").unwrap();
+    let mut variables: Vec<TemplateVariable> = Vec::new();
+
+    for (index, line) in content.lines().enumerate() {
+        if !is_declaration(line) {
+            continue;
+        }
+        let caps = declaration_re.captures(line).with_context(|| {
+            format!(
+                "Malformed variable declaration on line {}: expected '# {} <name> = <default> [-- description]'",
+                index + 1,
+                DECLARATION_MARKER
+            )
+        })?;
+        let name = caps[1].to_string();
+        if !name_re.is_match(&name) {
+            bail!(
+                "Invalid template variable name '{}' on line {}: use lowercase letters, digits and '_'",
+                name,
+                index + 1
+            );
+        }
+        if variables.iter().any(|v| v.name == name) {
+            bail!("Template variable '{}' is declared twice", name);
+        }
+        let default = caps[2].to_string();
+        validate_value(&name, &default)?;
+
+        variables.push(TemplateVariable {
+            name,
+            default,
+            description: caps
+                .get(3)
+                .map(|m| m.as_str().to_string())
+                .filter(|d| !d.is_empty()),
+        });
+    }
+
+    Ok(variables)
+}
+
+/// Placeholders the template uses without declaring them, in order of first use.
+///
+/// Rendering leaves these untouched; `flk template add` warns about them since
+/// they are often misspelled variables.
+///
+/// # Errors
+///
+/// Returns an error if the declarations are invalid.
+pub fn undeclared_placeholders(content: &str) -> Result<Vec<String>> {
+    let variables = parse_variables(content)?;
+    let mut undeclared: Vec<String> = Vec::new();
+    for caps in placeholder_re().captures_iter(content) {
+        let name = &caps[1];
+        if !variables.iter().any(|v| v.name == name) && !undeclared.iter().any(|u| u == name) {
+            undeclared.push(name.to_string());
+        }
+    }
+    Ok(undeclared)
+}
+
+/// Parse a `key=value` assignment given on the command line.
+///
+/// # Errors
+///
+/// Returns an error if there is no `=` or the key is empty.
+pub fn parse_assignment(assignment: &str) -> Result<(String, String)> {
+    match assignment.split_once('=') {
+        Some((key, value)) if !key.trim().is_empty() => {
+            Ok((key.trim().to_string(), value.trim().to_string()))
+        }
+        _ => bail!(
+            "Invalid variable assignment '{}'. Use key=value, for example python_version=312",
+            assignment
+        ),
+    }
+}
+
+/// Render a template: substitute its variables and drop their declarations.
+///
+/// Declared variables take their value from `values`, or their default.
+/// Values for variables the template does not declare are ignored, so the
+/// same assignments can be applied to several templates, and placeholders it
+/// does not declare are kept as written.
+///
+/// # Errors
+///
+/// Returns an error if the declarations are invalid or a value is invalid.
+pub fn render_template(content: &str, values: &[(String, String)]) -> Result<String> {
+    let variables = parse_variables(content)?;
+
+    let mut resolved = Vec::new();
+    for variable in &variables {
+        let value = match values.iter().rev().find(|(k, _)| *k == variable.name) {
+            Some((_, value)) => {
+                validate_value(&variable.name, value)?;
+                value.as_str()
+            }
+            None => variable.default.as_str(),
+        };
+        resolved.push((variable.name.as_str(), value));
+    }
+
+    let mut rendered = String::with_capacity(content.len());
+    for line in content.split_inclusive('\n') {
+        if !is_declaration(line) {
+            rendered.push_str(line);
+        }
+    }
+
+    Ok(placeholder_re()
+        .replace_all(&rendered, |caps: &regex::Captures| {
+            resolved
+                .iter()
+                .find(|(name, _)| *name == &caps[1])
+                .map_or_else(|| caps[0].to_string(), |(_, value)| value.to_string())
+        })
+        .to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TEMPLATE: &str = "# flk:var python_version = 313 -- Python version\n# flk:var extra = ruff\n{pkgs, ...}: {\n  packages = [ pkgs.python{{python_version}} pkgs.{{ extra }} ];\n}\n";
+
+    #[test]
+    fn test_parse_variables() {
+        let variables = parse_variables(TEMPLATE).unwrap();
+        assert_eq!(variables.len(), 2);
+        assert_eq!(variables[0].name, "python_version");
+        assert_eq!(variables[0].default, "313");
+        assert_eq!(variables[0].description.as_deref(), Some("Python version"));
+        assert_eq!(variables[1].description, None);
+
+        assert!(parse_variables("# flk:var x = 1\n# flk:var x = 2\n").is_err());
+        assert!(parse_variables("# flk:var Bad = 1\n").is_err());
+        assert!(parse_variables("# flk:var x\n").is_err());
+        assert!(parse_variables("# flk:var x = \"a\"\n").is_err());
+    }
+
+    #[test]
+    fn test_render_template() {
+        let rendered = render_template(TEMPLATE, &[]).unwrap();
+        assert_eq!(
+            rendered,
+            "{pkgs, ...}: {\n  packages = [ pkgs.python313 pkgs.ruff ];\n}\n"
+        );
+
+        let values = vec![
+            ("python_version".to_string(), "312".to_string()),
+            ("unrelated".to_string(), "x".to_string()),
+        ];
+        assert!(render_template(TEMPLATE, &values)
+            .unwrap()
+            .contains("pkgs.python312 "));
+
+        let bad = vec![("extra".to_string(), "x; y".to_string())];
+        assert!(render_template(TEMPLATE, &bad).is_err());
+
+        // Undeclared placeholders are not template variables
+        let hook = format!(
+            "{}  # docker ps --format '{{{{ .Names }}}} {{{{missing}}}}'\n",
+            TEMPLATE
+        );
+        let rendered = render_template(&hook, &[]).unwrap();
+        assert!(rendered.contains("pkgs.python313 "));
+        assert!(rendered.ends_with("'{{ .Names }} {{missing}}'\n"));
+        assert_eq!(undeclared_placeholders(&hook).unwrap(), vec!["missing"]);
+        assert!(undeclared_placeholders(TEMPLATE).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_parse_assignment() {
+        assert_eq!(
+            parse_assignment("node_major=22").unwrap(),
+            ("node_major".to_string(), "22".to_string())
+        );
+        assert!(parse_assignment("node_major").is_err());
+        assert!(parse_assignment("=22").is_err());
+    }
+}
@@ -9,10 +9,18 @@
 //!   - [`flake::generator`] - Template-based flake generation
 //!   - [`flake::parsers`] - Nom-based parsers for Nix file sections
 //!   - [`flake::interfaces`] - Data structures representing flake components
+//!   - [`flake::inheritance`] - Profile `extends` resolution
+//!   - [`flake::diff`] - Profile comparison
 //!   - [`flake::nix_render`] - Safe Nix string/attribute rendering
+//!   - [`flake::template_vars`] - Profile template variables
+//!   - [`flake::channel`] - nixpkgs channel switching helpers
+//!
+//! - [`config`] - Layered user and project configuration
 //!
 //! - [`utils`] - Shared utilities
 //!   - [`utils::backup`] - Lock file backup and restore functionality
+//!   - [`utils::dotenv`] - `.env` file parsing and environment variable export formats
+//!   - [`utils::tasks`] - Task definitions read from `package.json`, Makefiles and justfiles
 //!   - [`utils::visual`] - Terminal output formatting and progress indicators
 //!
 //! ## Example
@@ -28,5 +36,6 @@
 //! let rust_profile = generate_flake("rust")?;
 //! ```
 
+pub mod config;
 pub mod flake;
 pub mod utils;
@@ -18,24 +18,44 @@
 //! - `search`/`deep-search` - Search nixpkgs for available packages
 //! - `command` - Manage custom shell commands
 //! - `env` - Manage environment variables
+//! - `service` - Manage background services such as databases
 //! - `lock` - Manage flake.lock backups and restoration
+//! - `channel` - Show or switch the nixpkgs branch
 //! - `activate` - Enter the development shell
+//! - `run` - Run a program or custom command in a profile's shell
 //! - `export` - Export configuration to Docker, Podman, or JSON
+//! - `import` - Recreate or update profiles from an exported JSON document
+//! - `container` - Run a profile's container image with the project mounted
 //! - `hook` - Generate shell integration hooks
 //! - `direnv` - Manage direnv integration
+//! - `doctor` - Diagnose Nix, shell integration and project setup problems
+//! - `check` - Lint profiles and pins for problems
+//! - `template` - List, show and install profile templates
+//! - `config` - Read and change user and project settings
 
 use anyhow::Result;
 use clap::{Parser, Subcommand};
+use std::path::PathBuf;
 
 mod commands;
 mod nix;
 
 use crate::commands::{
-    activate, add, command, completions, direnv, env,
-    export::{self, ExportType},
+    activate, add, channel,
+    check::{self, OutputFormat},
+    command::{self, CommandDetails},
+    completions, config, container, direnv, doctor,
+    env::{self, EnvFormat},
+    export::{self, ContainerRuntime, ExportType, ImageOptions},
     hook::{self, HookShell},
-    init, list, lock, profiles, remove, search, show, update,
+    import::{self, ImportType},
+    init, list, lock, profiles, remove, run, search,
+    service::{self, ServiceDefinition},
+    show, template, update,
 };
+use flk::config::Config;
+use flk::flake::interfaces::profiles::SecretSource;
+use flk::utils::visual::set_spinner_enabled;
 
 #[derive(Parser)]
 #[command(name = "flk")]
@@ -45,16 +65,32 @@ use crate::commands::{
 struct Cli {
     #[command(subcommand)]
     command: Commands,
+
+    /// When to use colored output (overrides the ui.color setting)
+    #[arg(long, global = true, value_name = "WHEN", value_parser = ["auto", "always", "never"])]
+    color: Option<String>,
 }
 
 #[derive(Subcommand)]
 enum Commands {
     /// Initialize a new flake.nix in the current directory
     Init {
-        /// Project type (rust, python, node, go, or generic)
+        /// Template to use (see `flk template list`); detected from the project files when omitted
         #[arg(short, long)]
         template: Option<String>,
 
+        /// Only create profiles for these detected languages (comma-separated)
+        #[arg(long, value_delimiter = ',', conflicts_with = "template")]
+        only: Vec<String>,
+
+        /// Also create an `all` profile combining every detected profile and make it the default
+        #[arg(long, conflicts_with = "template")]
+        combined: bool,
+
+        /// Set a template variable (key=value); can be repeated
+        #[arg(long = "set", value_name = "KEY=VALUE")]
+        vars: Vec<String>,
+
         /// Force overwrite if flake.nix already exists
         #[arg(short, long)]
         force: bool,
@@ -133,6 +169,18 @@ enum Commands {
         /// Target profile to manage
         #[arg(short = 'p', long)]
         profile: Option<String>,
+        /// Apply set, unset or rename to every profile at once
+        #[arg(long, conflicts_with = "profile")]
+        all_profiles: bool,
+    },
+
+    /// Manage background services such as databases
+    Service {
+        #[command(subcommand)]
+        action: ServiceAction,
+        /// Target profile, or a combination such as backend+worker
+        #[arg(short = 'p', long)]
+        profile: Option<String>,
     },
 
     /// Manage flake.lock file
@@ -141,6 +189,12 @@ enum Commands {
         action: LockAction,
     },
 
+    /// Show or switch the nixpkgs channel
+    Channel {
+        #[command(subcommand)]
+        action: ChannelAction,
+    },
+
     /// Generate and install shell completions
     Completions {
         /// Install the completions automatically
@@ -154,11 +208,23 @@ enum Commands {
 
     /// Reload the current shell environment
     Activate {
-        /// Target profile to activate
+        /// Target profile to activate, or a combination such as rust+python
         #[arg(short = 'p', long)]
         profile: Option<String>,
     },
 
+    /// Run a program or custom command in a profile's shell without entering it
+    Run {
+        /// Target profile, or a combination such as rust+python
+        #[arg(short = 'p', long)]
+        profile: Option<String>,
+        /// Custom command name or program to run
+        command: String,
+        /// Arguments passed to the command (after --)
+        #[arg(last = true)]
+        args: Vec<String>,
+    },
+
     /// Export flake configurations (Docker, JSON, etc.)
     Export {
         #[arg(short, long)]
@@ -166,6 +232,31 @@ enum Commands {
         /// Target profile to export
         #[arg(short = 'p', long)]
         profile: Option<String>,
+        #[command(flatten)]
+        image: ImageOptions,
+    },
+
+    /// Import profiles from an exported configuration
+    Import {
+        #[arg(value_enum)]
+        format: ImportType,
+        /// Path to the exported document (e.g. flake.json)
+        file: String,
+        /// Only import this profile from the document
+        #[arg(short = 'p', long)]
+        profile: Option<String>,
+        /// Merge into existing profiles, keeping entries not in the document
+        #[arg(long, conflicts_with = "replace")]
+        merge: bool,
+        /// Replace existing profiles so they match the document exactly
+        #[arg(long)]
+        replace: bool,
+    },
+
+    /// Run the exported container image of a profile
+    Container {
+        #[command(subcommand)]
+        action: ContainerAction,
     },
 
     /// Direnv integration
@@ -180,11 +271,36 @@ enum Commands {
         shell: HookShell,
     },
 
+    /// Diagnose common setup problems
+    Doctor,
+
+    /// Lint profiles, pins and the default shell for problems
+    Check {
+        /// Apply safe fixes (duplicates, unused pins, missing default shell)
+        #[arg(long)]
+        fix: bool,
+        /// Output format
+        #[arg(long, value_enum, default_value = "text")]
+        format: OutputFormat,
+    },
+
     /// Manage profiles
     Profile {
         #[command(subcommand)]
         action: ProfileAction,
     },
+
+    /// Manage profile templates
+    Template {
+        #[command(subcommand)]
+        action: TemplateAction,
+    },
+
+    /// Read and change settings in the user and project config files
+    Config {
+        #[command(subcommand)]
+        action: ConfigAction,
+    },
 }
 
 #[derive(Subcommand)]
@@ -201,6 +317,30 @@ enum CommandAction {
         /// Source from a file instead
         #[arg(short, long)]
         file: Option<String>,
+
+        #[command(flatten)]
+        details: CommandDetails,
+
+        /// Replace the command if it already exists
+        #[arg(long)]
+        force: bool,
+    },
+    /// Print a command's script
+    Show {
+        /// Command name
+        name: String,
+    },
+    /// Edit a command's script in $EDITOR
+    Edit {
+        /// Command name
+        name: String,
+    },
+    /// Rename a custom command
+    Rename {
+        /// Current name
+        old: String,
+        /// New name
+        new: String,
     },
     /// Remove a custom command from the dev shell
     Remove {
@@ -208,7 +348,64 @@ enum CommandAction {
         name: String,
     },
     /// List all custom commands
-    List,
+    List {
+        /// Show how commands depend on each other
+        #[arg(long)]
+        tree: bool,
+    },
+    /// Import tasks from a package.json, Makefile or justfile as commands
+    Import {
+        /// Task file to read
+        #[arg(long, value_name = "FILE")]
+        from: String,
+        /// Only import these tasks; '*' matches any text (comma-separated or repeated)
+        #[arg(long, value_name = "NAME", value_delimiter = ',')]
+        only: Vec<String>,
+    },
+}
+
+#[derive(Subcommand)]
+enum ServiceAction {
+    /// Add a service to the profile
+    Add {
+        /// Service name; postgres and redis are built in
+        name: String,
+
+        #[command(flatten)]
+        definition: ServiceDefinition,
+
+        /// Replace the service if it already exists
+        #[arg(long)]
+        force: bool,
+    },
+    /// Remove a service from the profile, keeping its state
+    Remove {
+        /// Service name
+        name: String,
+    },
+    /// Start services in the background
+    Up {
+        /// Services to start (default: all)
+        names: Vec<String>,
+    },
+    /// Stop running services
+    Down {
+        /// Services to stop (default: all)
+        names: Vec<String>,
+    },
+    /// Show which services are running
+    Status,
+    /// Print service logs
+    Logs {
+        /// Service to show (default: all)
+        name: Option<String>,
+        /// Number of lines to show from the end of each log
+        #[arg(short = 'n', long, default_value_t = 50)]
+        lines: usize,
+        /// Keep printing new output
+        #[arg(short, long)]
+        follow: bool,
+    },
 }
 
 #[derive(Subcommand)]
@@ -218,15 +415,69 @@ enum EnvAction {
         /// Variable name
         name: String,
         /// Variable value
-        value: String,
+        #[arg(required_unless_present = "secret", conflicts_with = "secret")]
+        value: Option<String>,
+        /// Store only where the value comes from; it is read when the shell starts
+        #[arg(long, requires = "secret_source")]
+        secret: bool,
+        /// Read the secret from this file
+        #[arg(
+            long,
+            value_name = "PATH",
+            group = "secret_source",
+            requires = "secret"
+        )]
+        from_file: Option<String>,
+        /// Read the secret from the output of this command, e.g. 'pass show api/token'
+        #[arg(
+            long,
+            value_name = "COMMAND",
+            group = "secret_source",
+            requires = "secret"
+        )]
+        from_cmd: Option<String>,
     },
-    /// Remove an environment variable
+    /// Remove an environment variable or secret
     Remove {
         /// Variable name
         name: String,
     },
+    /// Add an environment variable or change its value
+    Set {
+        /// Variable name
+        name: String,
+        /// Variable value
+        value: String,
+    },
+    /// Remove several environment variables or secrets at once
+    Unset {
+        /// Variable names
+        #[arg(required = true)]
+        names: Vec<String>,
+    },
+    /// Rename an environment variable or secret, keeping its value
+    Rename {
+        /// Current name
+        old: String,
+        /// New name
+        new: String,
+    },
     /// List all environment variables
     List,
+    /// Import environment variables from a .env file
+    Import {
+        /// Path to the .env file
+        file: String,
+        /// Replace the values of variables that already exist
+        #[arg(long)]
+        overwrite: bool,
+    },
+    /// Print environment variables as a .env file, JSON, or shell exports
+    Export {
+        /// Output format
+        #[arg(short, long, value_enum, default_value_t)]
+        format: EnvFormat,
+    },
 }
 #[derive(Subcommand)]
 enum LockAction {
@@ -242,6 +493,26 @@ enum LockAction {
         backup: String,
     },
 }
+
+#[derive(Subcommand)]
+enum ContainerAction {
+    /// Run the profile image with the project mounted at /workspace
+    Run {
+        /// Target profile to run
+        #[arg(short = 'p', long)]
+        profile: Option<String>,
+        /// Container runtime to use
+        #[arg(long, value_enum, default_value = "docker")]
+        runtime: ContainerRuntime,
+        /// Rebuild the image even if it is up to date
+        #[arg(long)]
+        rebuild: bool,
+        /// Command to run instead of the image default (after --)
+        #[arg(last = true)]
+        command: Vec<String>,
+    },
+}
+
 #[derive(Subcommand)]
 enum DirenvAction {
     /// Create .envrc with flake activation
@@ -257,9 +528,12 @@ enum ProfileAction {
     Add {
         /// Profile name
         name: String,
-        /// Template to use (base, rust, python, node, go, generic)
+        /// Template to use (see `flk template list`)
         #[arg(short, long)]
         template: Option<String>,
+        /// Set a template variable (key=value); can be repeated
+        #[arg(long = "set", value_name = "KEY=VALUE")]
+        vars: Vec<String>,
         /// Force overwrite if profile already exists
         #[arg(short, long)]
         force: bool,
@@ -269,6 +543,43 @@ enum ProfileAction {
         /// Profile name
         name: String,
     },
+    /// Rename a profile, updating the default profile and inheriting profiles
+    Rename {
+        /// Current profile name
+        old: String,
+        /// New profile name
+        new: String,
+    },
+    /// Copy a profile to a new name
+    Copy {
+        /// Profile to copy
+        source: String,
+        /// Name of the new profile
+        destination: String,
+        /// Force overwrite if the destination already exists
+        #[arg(short, long)]
+        force: bool,
+    },
+    /// Merge two profiles into one; the first profile wins on conflicts
+    Merge {
+        /// First profile
+        first: String,
+        /// Second profile
+        second: String,
+        /// Resulting profile (a new name or one of the two sources)
+        #[arg(long)]
+        into: String,
+    },
+    /// Compare the packages, env vars and commands of two profiles
+    Diff {
+        /// Profile to compare from
+        first: String,
+        /// Profile to compare to
+        second: String,
+        /// Output format
+        #[arg(long, value_enum, default_value = "text")]
+        format: OutputFormat,
+    },
     /// List all profiles
     List,
     /// Set default profile
@@ -276,17 +587,102 @@ enum ProfileAction {
         /// Profile name
         profile: String,
     },
+    /// Set how many profiles can be combined into one shell (e.g. rust+python)
+    SetMaxCombinations {
+        /// Maximum number of profiles per combination
+        value: usize,
+    },
+}
+
+#[derive(Subcommand)]
+enum TemplateAction {
+    /// List built-in, user and project templates
+    List,
+    /// Print the content of a template
+    Show {
+        /// Template name
+        name: String,
+    },
+    /// Install a profile file as a template
+    Add {
+        /// Path to the profile .nix file
+        path: PathBuf,
+        /// Template name (defaults to the file name)
+        #[arg(short, long)]
+        name: Option<String>,
+        /// Install into the project's .flk-templates/ instead of ~/.config/flk/templates
+        #[arg(long)]
+        project: bool,
+        /// Force overwrite if the template already exists
+        #[arg(short, long)]
+        force: bool,
+    },
+}
+
+#[derive(Subcommand)]
+enum ChannelAction {
+    /// Show the nixpkgs channel and the revision it is locked to
+    Show,
+    /// Follow another nixpkgs branch and relock
+    Set {
+        /// nixpkgs branch, e.g. nixos-unstable or nixos-25.11
+        channel: String,
+    },
+}
+
+#[derive(Subcommand)]
+enum ConfigAction {
+    /// Print the value of a setting
+    Get {
+        /// Setting key, e.g. ui.color
+        key: String,
+    },
+    /// Change a setting in the user config (or the project config with --project)
+    Set {
+        /// Setting key, e.g. ui.color
+        key: String,
+        /// New value
+        value: String,
+        /// Write .flk/config.toml instead of ~/.config/flk/config.toml
+        #[arg(long)]
+        project: bool,
+    },
+    /// List every setting with its value and origin
+    List,
 }
 
 fn main() -> Result<()> {
     let cli = Cli::parse();
 
+    // A broken config file must not prevent fixing it with `flk config`,
+    // which then runs with the defaults
+    let config = match Config::load() {
+        Ok(config) => config,
+        Err(_) if matches!(cli.command, Commands::Config { .. }) => {
+            Config::from_layers(None, None, |_| None)?
+        }
+        Err(e) => return Err(e),
+    };
+    let color = cli.color.as_deref().unwrap_or(config.color());
+    match color {
+        "always" => colored::control::set_override(true),
+        "never" => colored::control::set_override(false),
+        _ => {}
+    }
+    set_spinner_enabled(config.spinner());
+
     match cli.command {
-        Commands::Init { template, force } => {
-            init::run(template, force)?;
+        Commands::Init {
+            template,
+            only,
+            combined,
+            vars,
+            force,
+        } => {
+            init::run(template, only, combined, vars, force, &config)?;
         }
         Commands::Search { query, limit } => {
-            search::run_search(&query, limit)?;
+            search::run_search(&query, limit, &config)?;
         }
         Commands::DeepSearch { package } => {
             search::run_deep_search(&package)?;
@@ -302,40 +698,87 @@ fn main() -> Result<()> {
             version,
             profile,
         } => {
-            add::run_add(&package, version, profile)?;
+            add::run_add(&package, version, profile, &config)?;
         }
         Commands::Remove { package, profile } => {
             remove::run_remove(&package, profile)?;
         }
         Commands::Update { packages, show } => {
-            update::run_update(packages, show)?;
+            update::run_update(packages, show, &config)?;
         }
         Commands::Command { action, profile } => match action {
             CommandAction::Add {
                 name,
                 command,
                 file,
+                details,
+                force,
             } => {
                 let cmd = command.join(" ");
-                command::run_add(&name, &cmd, file, profile)?;
+                command::run_add(&name, &cmd, file, details, force, profile)?;
+            }
+            CommandAction::Show { name } => {
+                command::show(&name, profile)?;
+            }
+            CommandAction::Edit { name } => {
+                command::edit(&name, profile)?;
+            }
+            CommandAction::Rename { old, new } => {
+                command::rename(&old, &new, profile)?;
             }
             CommandAction::Remove { name } => {
                 command::run_remove(&name, profile)?;
             }
-            CommandAction::List => {
-                command::list(profile)?;
+            CommandAction::List { tree } => {
+                command::list(tree, profile)?;
+            }
+            CommandAction::Import { from, only } => {
+                command::import(&from, &only, profile)?;
             }
         },
-        Commands::Env { action, profile } => match action {
-            EnvAction::Add { name, value } => {
-                env::add(&name, &value, profile)?;
+        Commands::Env {
+            action,
+            profile,
+            all_profiles,
+        } => match action {
+            EnvAction::Set { name, value } => {
+                env::set(&name, &value, profile, all_profiles)?;
+            }
+            EnvAction::Unset { names } => {
+                env::unset(&names, profile, all_profiles)?;
             }
+            EnvAction::Rename { old, new } => {
+                env::rename(&old, &new, profile, all_profiles)?;
+            }
+            _ if all_profiles => {
+                anyhow::bail!("--all-profiles only applies to env set, unset and rename");
+            }
+            EnvAction::Add {
+                name,
+                value,
+                secret: _,
+                from_file,
+                from_cmd,
+            } => match (value, from_file, from_cmd) {
+                (Some(value), _, _) => env::add(&name, &value, profile)?,
+                (None, Some(path), _) => env::add_secret(&name, SecretSource::File(path), profile)?,
+                (None, None, Some(command)) => {
+                    env::add_secret(&name, SecretSource::Command(command), profile)?
+                }
+                (None, None, None) => unreachable!("clap requires a value or a secret source"),
+            },
             EnvAction::Remove { name } => {
                 env::remove(&name, profile)?;
             }
             EnvAction::List => {
                 env::list(profile)?;
             }
+            EnvAction::Import { file, overwrite } => {
+                env::import(&file, overwrite, profile)?;
+            }
+            EnvAction::Export { format } => {
+                env::export(format, profile)?;
+            }
         },
         Commands::Lock { action } => match action {
             LockAction::Show => {
@@ -345,7 +788,7 @@ fn main() -> Result<()> {
                 lock::history()?;
             }
             LockAction::Restore { backup } => {
-                lock::restore(&backup)?;
+                lock::restore(&backup, &config)?;
             }
         },
         Commands::Completions { install, shell } => {
@@ -354,9 +797,67 @@ fn main() -> Result<()> {
         Commands::Activate { profile } => {
             activate::run_activate(profile)?;
         }
-        Commands::Export { format, profile } => {
-            export::run_export(&format, profile)?;
+        Commands::Run {
+            profile,
+            command,
+            args,
+        } => {
+            run::run(&command, &args, profile)?;
+        }
+        Commands::Service { action, profile } => match action {
+            ServiceAction::Add {
+                name,
+                definition,
+                force,
+            } => {
+                service::add(&name, definition, force, profile)?;
+            }
+            ServiceAction::Remove { name } => {
+                service::remove(&name, profile)?;
+            }
+            ServiceAction::Up { names } => {
+                service::up(&names, profile)?;
+            }
+            ServiceAction::Down { names } => {
+                service::down(&names, profile)?;
+            }
+            ServiceAction::Status => {
+                service::status(profile)?;
+            }
+            ServiceAction::Logs {
+                name,
+                lines,
+                follow,
+            } => {
+                service::logs(name, lines, follow, profile)?;
+            }
+        },
+        Commands::Export {
+            format,
+            profile,
+            image,
+        } => {
+            export::run_export(&format, profile, &image, &config)?;
+        }
+        Commands::Import {
+            format,
+            file,
+            profile,
+            merge,
+            replace,
+        } => {
+            import::run_import(&format, &file, profile, merge, replace)?;
         }
+        Commands::Container { action } => match action {
+            ContainerAction::Run {
+                profile,
+                runtime,
+                rebuild,
+                command,
+            } => {
+                container::run(profile, runtime, rebuild, &command)?;
+            }
+        },
         Commands::Direnv { action } => match action {
             DirenvAction::Init => {
                 direnv::direnv_init()?;
@@ -371,23 +872,96 @@ fn main() -> Result<()> {
         Commands::Hook { shell } => {
             hook::run_hook(shell)?;
         }
+        Commands::Doctor => {
+            doctor::run_doctor()?;
+        }
+        Commands::Check { fix, format } => {
+            check::run_check(fix, format)?;
+        }
+        Commands::Channel { action } => match action {
+            ChannelAction::Show => {
+                channel::run_show()?;
+            }
+            ChannelAction::Set { channel } => {
+                channel::run_set(channel, &config)?;
+            }
+        },
+        Commands::Config { action } => match action {
+            ConfigAction::Get { key } => {
+                config::run_get(key)?;
+            }
+            ConfigAction::Set {
+                key,
+                value,
+                project,
+            } => {
+                config::run_set(key, value, project)?;
+            }
+            ConfigAction::List => {
+                config::run_list()?;
+            }
+        },
+        Commands::Template { action } => match action {
+            TemplateAction::List => {
+                template::run_list()?;
+            }
+            TemplateAction::Show { name } => {
+                template::run_show(name)?;
+            }
+            TemplateAction::Add {
+                path,
+                name,
+                project,
+                force,
+            } => {
+                template::run_add(path, name, project, force)?;
+            }
+        },
         Commands::Profile { action } => match action {
             ProfileAction::Add {
                 name,
                 template,
+                vars,
                 force,
             } => {
-                profiles::run_add(name, template, force)?;
+                profiles::run_add(name, template, vars, force, &config)?;
             }
             ProfileAction::Remove { name } => {
                 profiles::run_remove(name)?;
             }
+            ProfileAction::Rename { old, new } => {
+                profiles::run_rename(old, new)?;
+            }
+            ProfileAction::Copy {
+                source,
+                destination,
+                force,
+            } => {
+                profiles::run_copy(source, destination, force)?;
+            }
+            ProfileAction::Merge {
+                first,
+                second,
+                into,
+            } => {
+                profiles::run_merge(first, second, into)?;
+            }
+            ProfileAction::Diff {
+                first,
+                second,
+                format,
+            } => {
+                profiles::run_diff(first, second, format)?;
+            }
             ProfileAction::List => {
                 profiles::run_list()?;
             }
             ProfileAction::SetDefault { profile } => {
                 profiles::run_set_default(profile)?;
             }
+            ProfileAction::SetMaxCombinations { value } => {
+                profiles::run_set_max_combinations(value)?;
+            }
         },
     }
 
@@ -49,6 +49,39 @@ pub fn create_backup(file_path: &Path) -> Result<PathBuf> {
     Ok(backup_path)
 }
 
+/// Delete the oldest backups of a file, keeping the `keep` most recent ones.
+///
+/// Backups are ordered by the timestamp in their name. A `keep` of `0` keeps
+/// every backup.
+///
+/// # Returns
+///
+/// The paths of the deleted backups.
+pub fn prune_backups(file_path: &Path, keep: usize) -> Result<Vec<PathBuf>> {
+    let backup_dir = get_backup_dir()?;
+    if keep == 0 || !backup_dir.exists() {
+        return Ok(Vec::new());
+    }
+
+    let prefix = format!("{}.", file_path.file_name().unwrap().to_string_lossy());
+    let mut backups: Vec<PathBuf> = fs::read_dir(&backup_dir)
+        .context("Failed to read backup directory")?
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| entry.file_name().to_string_lossy().starts_with(&prefix))
+        .map(|entry| entry.path())
+        .collect();
+    backups.sort();
+
+    let excess = backups.len().saturating_sub(keep);
+    let removed: Vec<PathBuf> = backups.into_iter().take(excess).collect();
+    for backup in &removed {
+        fs::remove_file(backup)
+            .with_context(|| format!("Failed to remove old backup '{}'", backup.display()))?;
+    }
+
+    Ok(removed)
+}
+
 /// Ensure the `.flk` directory structure exists.
 ///
 /// Creates `.flk/backups/` if it doesn't exist.
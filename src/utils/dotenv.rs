@@ -0,0 +1,272 @@
+//! # Dotenv Utilities
+//!
+//! Read and write environment variables in the formats used outside of Nix:
+//! `.env` files, JSON objects, and `export` lines for POSIX shells.
+//!
+//! ## Supported `.env` Syntax
+//!
+//! ```text
+//! # Comments and blank lines are ignored
+//! export DATABASE_URL=postgres://localhost/app   # inline comment
+//! GREETING='single quotes keep $everything literal'
+//! MESSAGE="double quotes decode \n, \t, \" and \\"
+//! CERT="-----BEGIN CERTIFICATE-----
+//! spans several lines
+//! -----END CERTIFICATE-----"
+//! ```
+
+use anyhow::{bail, Context, Result};
+
+use crate::flake::interfaces::profiles::EnvVar;
+use crate::flake::parsers::utils::{byte_offset, is_valid_env_var_name};
+
+/// Characters that can appear in an unquoted `.env` value.
+fn is_bare_char(c: char) -> bool {
+    c.is_alphanumeric() || "_-./:@,+%=".contains(c)
+}
+
+/// Split off the first line, returning it and the text after its newline.
+fn split_line(input: &str) -> (&str, &str) {
+    match input.find('\n') {
+        Some(pos) => (input[..pos].trim_end_matches('\r'), &input[pos + 1..]),
+        None => (input.trim_end_matches('\r'), ""),
+    }
+}
+
+/// Parse a value, returning it and the input left after it.
+///
+/// `input` starts right after the `=`. Quoted values may span several lines;
+/// unquoted values end at the end of the line or at a `#` preceded by
+/// whitespace, so `COLOR=#fff` keeps its value.
+fn parse_value(input: &str) -> Option<(String, &str)> {
+    let quoted = input.trim_start_matches([' ', '\t']);
+
+    if let Some(rest) = quoted.strip_prefix('\'') {
+        let end = rest.find('\'')?;
+        return Some((rest[..end].to_string(), &rest[end + 1..]));
+    }
+
+    if let Some(rest) = quoted.strip_prefix('"') {
+        let mut value = String::new();
+        let mut chars = rest.char_indices();
+        while let Some((i, ch)) = chars.next() {
+            match ch {
+                '"' => return Some((value, &rest[i + 1..])),
+                '\\' => match chars.next()? {
+                    (_, 'n') => value.push('\n'),
+                    (_, 't') => value.push('\t'),
+                    (_, 'r') => value.push('\r'),
+                    (_, escaped @ ('"' | '\\' | '$' | '`')) => value.push(escaped),
+                    (_, other) => {
+                        value.push('\\');
+                        value.push(other);
+                    }
+                },
+                _ => value.push(ch),
+            }
+        }
+        return None;
+    }
+
+    let (line, _) = split_line(input);
+    let comment = line
+        .char_indices()
+        .find(|&(i, c)| c == '#' && line[..i].ends_with([' ', '\t']))
+        .map(|(i, _)| i)
+        .unwrap_or(line.len());
+    Some((line[..comment].trim().to_string(), &input[comment..]))
+}
+
+/// Parse the contents of a `.env` file.
+///
+/// Supports comments, blank lines, `export` prefixes, unquoted values with
+/// trailing comments, single-quoted literal values, and double-quoted values
+/// with escapes. Quoted values may span several lines. When a name appears
+/// twice, the last value wins.
+///
+/// # Errors
+///
+/// Returns an error naming the line if an entry has no `=`, an invalid
+/// variable name, an unterminated quote, or text after a quoted value.
+pub fn parse_dotenv(content: &str) -> Result<Vec<EnvVar>> {
+    let mut vars: Vec<EnvVar> = Vec::new();
+    let mut rest = content.strip_prefix('\u{feff}').unwrap_or(content);
+
+    while !rest.is_empty() {
+        let line_no = content[..content.len() - rest.len()].matches('\n').count() + 1;
+        let (line, next) = split_line(rest);
+        let trimmed = line.trim();
+        if trimmed.is_empty() || trimmed.starts_with('#') {
+            rest = next;
+            continue;
+        }
+
+        let assignment = trimmed
+            .strip_prefix("export")
+            .filter(|s| s.starts_with([' ', '\t']))
+            .map(str::trim_start)
+            .unwrap_or(trimmed);
+        let (name, value) = assignment
+            .split_once('=')
+            .with_context(|| format!("Line {}: expected NAME=value", line_no))?;
+        let name = name.trim_end();
+        if !is_valid_env_var_name(name) {
+            bail!(
+                "Line {}: invalid environment variable name '{}'",
+                line_no,
+                name
+            );
+        }
+
+        // Continue from the value in the full input so quotes can span lines
+        let (value, after) = parse_value(&rest[byte_offset(rest, value)..])
+            .with_context(|| format!("Line {}: unterminated quote in '{}'", line_no, name))?;
+
+        let (tail, next) = split_line(after);
+        let tail = tail.trim();
+        if !tail.is_empty() && !tail.starts_with('#') {
+            bail!(
+                "Line {}: unexpected text after the value of '{}': {}",
+                line_no,
+                name,
+                tail
+            );
+        }
+
+        match vars.iter_mut().find(|v| v.name == name) {
+            Some(existing) => existing.value = value,
+            None => vars.push(EnvVar::new(name.to_string(), value)),
+        }
+        rest = next;
+    }
+
+    Ok(vars)
+}
+
+/// Render variables as a `.env` file.
+///
+/// Values are left bare when that is unambiguous, single-quoted when they
+/// contain neither `'` nor a newline, and double-quoted with escapes
+/// otherwise, so [`parse_dotenv`] reads back the same values.
+pub fn render_dotenv(vars: &[EnvVar]) -> String {
+    let mut out = String::new();
+    for var in vars {
+        let value = &var.value;
+        let rendered = if !value.is_empty() && value.chars().all(is_bare_char) {
+            value.clone()
+        } else if !value.contains(['\'', '\n', '\r']) {
+            format!("'{}'", value)
+        } else {
+            let mut escaped = String::with_capacity(value.len() + 8);
+            for ch in value.chars() {
+                match ch {
+                    '\\' => escaped.push_str("\\\\"),
+                    '"' => escaped.push_str("\\\""),
+                    '$' => escaped.push_str("\\$"),
+                    '`' => escaped.push_str("\\`"),
+                    '\n' => escaped.push_str("\\n"),
+                    '\r' => escaped.push_str("\\r"),
+                    _ => escaped.push(ch),
+                }
+            }
+            format!("\"{}\"", escaped)
+        };
+        out.push_str(&format!("{}={}\n", var.name, rendered));
+    }
+    out
+}
+
+/// Render variables as a JSON object of names to values.
+///
+/// # Errors
+///
+/// Returns an error if serialization fails.
+pub fn render_json(vars: &[EnvVar]) -> Result<String> {
+    let object: serde_json::Map<String, serde_json::Value> = vars
+        .iter()
+        .map(|v| (v.name.clone(), serde_json::Value::String(v.value.clone())))
+        .collect();
+    serde_json::to_string_pretty(&object).context("Failed to serialize environment variables")
+}
+
+/// Render variables as `export` lines that a POSIX shell can `eval`.
+///
+/// Every value is single-quoted, so nothing in it is expanded.
+pub fn render_shell(vars: &[EnvVar]) -> String {
+    vars.iter()
+        .map(|v| format!("export {}='{}'\n", v.name, v.value.replace('\'', "'\\''")))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn var(name: &str, value: &str) -> EnvVar {
+        EnvVar::new(name.to_string(), value.to_string())
+    }
+
+    #[test]
+    fn test_parse_dotenv() {
+        let content = "# comment\n\
+            \n\
+            export DATABASE_URL=postgres://localhost/app   # inline comment\n\
+            COLOR=#fff\n\
+            EMPTY=\n\
+            SINGLE='keep $HOME and \\n literal'\n\
+            DOUBLE=\"say \\\"hi\\\"\\tnow\" # trailing\n\
+            MULTI=\"first\n\
+            second\"\n\
+            \texport  INDENTED = 'x'\r\n\
+            EMPTY=last wins\n";
+
+        let vars = parse_dotenv(content).unwrap();
+        assert_eq!(
+            vars,
+            vec![
+                var("DATABASE_URL", "postgres://localhost/app"),
+                var("COLOR", "#fff"),
+                var("EMPTY", "last wins"),
+                var("SINGLE", "keep $HOME and \\n literal"),
+                var("DOUBLE", "say \"hi\"\tnow"),
+                var("MULTI", "first\nsecond"),
+                var("INDENTED", "x"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_dotenv_errors() {
+        let err = parse_dotenv("A=1\n1BAD=2\n").unwrap_err();
+        assert!(err.to_string().contains("Line 2"));
+        assert!(parse_dotenv("NO_EQUALS\n").is_err());
+        assert!(parse_dotenv("OPEN=\"never closed\nB=2\n").is_err());
+        assert!(parse_dotenv("QUOTED='a' b\n").is_err());
+        assert!(parse_dotenv("export=1\n").is_ok());
+        assert_eq!(parse_dotenv("A= # note\n").unwrap()[0].value, "");
+    }
+
+    #[test]
+    fn test_render_round_trip() {
+        let vars = vec![
+            var("PLAIN", "postgres://localhost:5432/app"),
+            var("SPACES", "hello world $HOME"),
+            var("QUOTE", "it's \"quoted\"\nand `ticked` $HOME \\"),
+            var("EMPTY", ""),
+        ];
+
+        let dotenv = render_dotenv(&vars);
+        assert!(
+            dotenv.starts_with("PLAIN=postgres://localhost:5432/app\nSPACES='hello world $HOME'\n")
+        );
+        assert_eq!(parse_dotenv(&dotenv).unwrap(), vars);
+
+        assert_eq!(
+            render_shell(&vars[2..3]),
+            "export QUOTE='it'\\''s \"quoted\"\nand `ticked` $HOME \\'\n"
+        );
+
+        let json: serde_json::Value = serde_json::from_str(&render_json(&vars).unwrap()).unwrap();
+        assert_eq!(json["QUOTE"], vars[2].value);
+    }
+}
@@ -5,7 +5,11 @@
 //! ## Modules
 //!
 //! - [`backup`] - Lock file backup and restoration with timestamped archives
+//! - [`dotenv`] - `.env`, JSON, and shell formats for environment variables
+//! - [`tasks`] - Task definitions from `package.json`, Makefiles and justfiles
 //! - [`visual`] - Terminal output formatting, spinners, and progress indicators
 
 pub mod backup;
+pub mod dotenv;
+pub mod tasks;
 pub mod visual;
@@ -0,0 +1,528 @@
+//! # Task File Utilities
+//!
+//! Read task definitions that a project already keeps in `package.json`
+//! scripts, Makefile targets or justfile recipes, so they can become custom
+//! commands that call through to the original tool.
+//!
+//! ```text
+//! package.json   "scripts": { "test:unit": "vitest" }   ->  npm run test:unit -- "$@"
+//! Makefile       build: deps ## Build the app             ->  make build "$@"
+//! justfile       # Serve the docs                         ->  just serve "$@"
+//!                serve port='8080':
+//! ```
+
+use anyhow::{bail, Context, Result};
+use std::env;
+use std::path::Path;
+use std::process::Command;
+
+/// A file format that defines runnable tasks.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TaskSource {
+    /// `scripts` of a `package.json`, run with npm, pnpm, yarn or bun
+    PackageJson,
+    /// Explicit targets of a Makefile
+    Makefile,
+    /// Public recipes of a justfile
+    Justfile,
+}
+
+/// A task read from a task file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Task {
+    /// Name of the task as the tool knows it, e.g. `test:unit`
+    pub name: String,
+    /// Summary taken from the file, if it has one
+    pub description: Option<String>,
+    /// Argument synopsis, e.g. `<target> [port]`
+    pub usage: Option<String>,
+}
+
+impl TaskSource {
+    /// Detect the format from a file name.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file name is not a recognised task file.
+    pub fn from_path(path: &Path) -> Result<Self> {
+        let file_name = path
+            .file_name()
+            .map(|n| n.to_string_lossy().to_lowercase())
+            .unwrap_or_default();
+        match file_name.as_str() {
+            "package.json" => Ok(Self::PackageJson),
+            "makefile" | "gnumakefile" => Ok(Self::Makefile),
+            "justfile" | ".justfile" => Ok(Self::Justfile),
+            _ if file_name.ends_with(".mk") => Ok(Self::Makefile),
+            _ if file_name.ends_with(".just") => Ok(Self::Justfile),
+            _ => bail!(
+                "Cannot tell what kind of task file '{}' is. Use a package.json, Makefile or justfile.",
+                path.display()
+            ),
+        }
+    }
+
+    /// Parse the tasks defined in a file of this format.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a `package.json` is not valid JSON.
+    pub fn parse(self, content: &str) -> Result<Vec<Task>> {
+        match self {
+            Self::PackageJson => parse_package_json(content),
+            Self::Makefile => Ok(parse_makefile(content)),
+            Self::Justfile => Ok(parse_justfile(content)),
+        }
+    }
+
+    /// Build the script that runs `task` from the task file at `path`.
+    ///
+    /// Arguments given to the custom command are passed through. When the file
+    /// is not in the project root, the tool runs in a subshell that changes
+    /// into the file's directory, found from `root` (see [`project_root`]) so
+    /// the command works from any directory. `path` and `task` must pass
+    /// [`fits_command_script`].
+    pub fn call_script(self, path: &Path, task: &str, root: &str) -> String {
+        let dir = path
+            .parent()
+            .filter(|d| !d.as_os_str().is_empty() && *d != Path::new("."));
+        let file_name = path
+            .file_name()
+            .map(|n| n.to_string_lossy().to_string())
+            .unwrap_or_default();
+        let task = shell_word(task);
+
+        let call = match self {
+            Self::PackageJson => match package_manager(dir.unwrap_or(Path::new("."))) {
+                "npm" => format!("npm run {} -- \"$@\"", task),
+                manager => format!("{} run {} \"$@\"", manager, task),
+            },
+            Self::Makefile if ["Makefile", "makefile", "GNUmakefile"].contains(&&*file_name) => {
+                format!("make {} \"$@\"", task)
+            }
+            Self::Makefile => format!("make -f {} {} \"$@\"", shell_word(&file_name), task),
+            Self::Justfile if ["justfile", "Justfile", ".justfile"].contains(&&*file_name) => {
+                format!("just {} \"$@\"", task)
+            }
+            Self::Justfile => format!("just -f {} {} \"$@\"", shell_word(&file_name), task),
+        };
+
+        match dir {
+            Some(dir) if dir.is_absolute() => {
+                format!("(cd {} && {})", shell_word(&dir.to_string_lossy()), call)
+            }
+            Some(dir) => format!(
+                "(cd {}/{} && {})",
+                root,
+                shell_word(&dir.to_string_lossy()),
+                call
+            ),
+            None => call,
+        }
+    }
+}
+
+/// Whether `text` can be quoted into a command script.
+///
+/// Scripts are written into a Nix `''` string: a `'` would be quoted as
+/// `'\''`, whose `''` ends that string, and `${` starts Nix interpolation.
+pub fn fits_command_script(text: &str) -> bool {
+    !text.contains('\'') && !text.contains("${")
+}
+
+/// Shell expression for the project root, the current directory.
+///
+/// Inside a git repository the root is found when the command runs, with
+/// `git rev-parse --show-toplevel`, so it keeps working in other clones.
+/// Elsewhere it is the absolute path of the current directory.
+///
+/// # Errors
+///
+/// Returns an error if the current directory cannot be determined, or if its
+/// path cannot be quoted into a command script (see [`fits_command_script`]).
+pub fn project_root() -> Result<String> {
+    let prefix = Command::new("git")
+        .args(["rev-parse", "--show-prefix"])
+        .output()
+        .ok()
+        .filter(|output| output.status.success())
+        .map(|output| String::from_utf8_lossy(&output.stdout).trim().to_string());
+
+    match prefix {
+        Some(prefix) if prefix.is_empty() => Ok("\"$(git rev-parse --show-toplevel)\"".to_string()),
+        Some(prefix) => Ok(format!(
+            "\"$(git rev-parse --show-toplevel)\"/{}",
+            script_word(prefix.trim_end_matches('/'))?
+        )),
+        None => {
+            let dir = env::current_dir().context("Failed to read the current directory")?;
+            script_word(&dir.to_string_lossy())
+        }
+    }
+}
+
+/// Turn a task name into a valid custom command name.
+///
+/// Characters other than letters, digits, `-` and `_` become `-`, so
+/// `test:unit` becomes `test-unit`. Returns `None` if nothing usable is left.
+pub fn command_name(task: &str) -> Option<String> {
+    let name: String = task
+        .chars()
+        .map(|c| {
+            if c.is_alphanumeric() || c == '-' || c == '_' {
+                c
+            } else {
+                '-'
+            }
+        })
+        .collect();
+    let name = name.trim_matches('-');
+    (!name.is_empty()).then(|| name.to_string())
+}
+
+/// Pick the package manager from the lock file next to a `package.json`.
+fn package_manager(dir: &Path) -> &'static str {
+    [
+        ("pnpm-lock.yaml", "pnpm"),
+        ("yarn.lock", "yarn"),
+        ("bun.lockb", "bun"),
+        ("bun.lock", "bun"),
+    ]
+    .into_iter()
+    .find(|(lock, _)| dir.join(lock).exists())
+    .map(|(_, manager)| manager)
+    .unwrap_or("npm")
+}
+
+/// Quote a word for `sh` unless it only contains safe characters.
+fn shell_word(word: &str) -> String {
+    if !word.is_empty()
+        && word
+            .chars()
+            .all(|c| c.is_alphanumeric() || "-_./:@%+=,".contains(c))
+    {
+        word.to_string()
+    } else {
+        format!("'{}'", word.replace('\'', "'\\''"))
+    }
+}
+
+/// Quote a directory for a command script, rejecting it if it cannot be.
+fn script_word(dir: &str) -> Result<String> {
+    if !fits_command_script(dir) {
+        bail!(
+            "Cannot import tasks from '{}': paths containing ' or ${{ cannot be written into a command script",
+            dir
+        );
+    }
+    Ok(shell_word(dir))
+}
+
+/// Read the `scripts` object of a `package.json`.
+///
+/// The script text becomes the description, since `package.json` has no
+/// place for one.
+fn parse_package_json(content: &str) -> Result<Vec<Task>> {
+    let json: serde_json::Value =
+        serde_json::from_str(content).context("Failed to parse package.json")?;
+    let Some(scripts) = json.get("scripts").and_then(|s| s.as_object()) else {
+        return Ok(Vec::new());
+    };
+
+    Ok(scripts
+        .iter()
+        .map(|(name, script)| Task {
+            name: name.clone(),
+            description: script
+                .as_str()
+                .and_then(|s| s.lines().next())
+                .map(|s| s.trim().to_string())
+                .filter(|s| !s.is_empty()),
+            usage: None,
+        })
+        .collect())
+}
+
+/// Read the explicit targets of a Makefile.
+///
+/// Pattern rules, special targets such as `.PHONY`, targets built from
+/// variables and variable assignments are skipped. A `## comment` after the
+/// prerequisites becomes the description.
+fn parse_makefile(content: &str) -> Vec<Task> {
+    let mut tasks: Vec<Task> = Vec::new();
+    let mut in_define = false;
+
+    for line in content.lines() {
+        let trimmed = line.trim();
+        if in_define {
+            in_define = trimmed != "endef";
+            continue;
+        }
+        if trimmed.starts_with("define ") || trimmed == "define" {
+            in_define = true;
+            continue;
+        }
+        if line.starts_with(['\t', ' ', '#']) {
+            continue;
+        }
+
+        let Some((targets, rest)) = line.split_once(':') else {
+            continue;
+        };
+        if targets.contains('=') || rest.starts_with('=') || rest.starts_with(":=") {
+            continue;
+        }
+        let description = rest
+            .split_once("##")
+            .map(|(_, d)| d.trim().to_string())
+            .filter(|d| !d.is_empty());
+
+        for target in targets.split_whitespace() {
+            if target.starts_with('.')
+                || target.contains(['%', '$', '(', ')'])
+                || tasks.iter().any(|t| t.name == target)
+            {
+                continue;
+            }
+            tasks.push(Task {
+                name: target.to_string(),
+                description: description.clone(),
+                usage: None,
+            });
+        }
+    }
+
+    tasks
+}
+
+/// Read the public recipes of a justfile.
+///
+/// Recipes marked `[private]` or starting with `_` are skipped. A comment on
+/// the line before a recipe becomes its description, and the recipe's
+/// parameters become its usage.
+fn parse_justfile(content: &str) -> Vec<Task> {
+    let mut tasks = Vec::new();
+    let mut comment: Option<String> = None;
+    let mut private = false;
+
+    for line in content.lines() {
+        let trimmed = line.trim_end();
+        if trimmed.is_empty() || line.starts_with([' ', '\t']) {
+            comment = None;
+            private = false;
+            continue;
+        }
+        if let Some(text) = trimmed.strip_prefix('#') {
+            if !text.starts_with('!') {
+                comment = Some(text.trim().to_string()).filter(|c| !c.is_empty());
+            }
+            continue;
+        }
+        if let Some(attributes) = trimmed.strip_prefix('[') {
+            private |= attributes.contains("private");
+            continue;
+        }
+
+        let header = recipe_header(trimmed);
+        let (description, is_private) = (comment.take(), std::mem::take(&mut private));
+        let Some(header) = header else {
+            continue;
+        };
+        let mut words = header.split_whitespace();
+        let Some(name) = words.next().map(|n| n.trim_start_matches('@')) else {
+            continue;
+        };
+        if is_private
+            || name.starts_with('_')
+            || ["set", "alias", "export", "import", "mod"].contains(&name)
+        {
+            continue;
+        }
+
+        let params: Vec<String> = words.filter_map(recipe_param).collect();
+        tasks.push(Task {
+            name: name.to_string(),
+            description,
+            usage: (!params.is_empty()).then(|| params.join(" ")),
+        });
+    }
+
+    tasks
+}
+
+/// Return the part of a justfile line before the recipe's `:`, if the line
+/// starts a recipe rather than an assignment or setting.
+fn recipe_header(line: &str) -> Option<&str> {
+    let mut quote = None;
+    for (i, c) in line.char_indices() {
+        match (quote, c) {
+            (Some(q), c) if c == q => quote = None,
+            (Some(_), _) => {}
+            (None, '\'' | '"') => quote = Some(c),
+            (None, ':') => {
+                return (!line[i + 1..].starts_with('=')).then(|| &line[..i]);
+            }
+            _ => {}
+        }
+    }
+    None
+}
+
+/// Render a justfile parameter for a usage synopsis.
+///
+/// `name` becomes `<name>`, `name='x'` becomes `[name]`, `+name` becomes
+/// `<name>...` and `*name` becomes `[name...]`.
+fn recipe_param(param: &str) -> Option<String> {
+    let param = param.trim_start_matches('$');
+    let (param, variadic) = match param.strip_prefix(['+', '*']) {
+        Some(rest) => (rest, param.chars().next()),
+        None => (param, None),
+    };
+    let (name, has_default) = match param.split_once('=') {
+        Some((name, _)) => (name, true),
+        None => (param, false),
+    };
+    let name = name.trim_start_matches('$');
+    if name.is_empty() || !name.starts_with(|c: char| c.is_alphabetic() || c == '_') {
+        // Part of a quoted default value containing spaces
+        return None;
+    }
+    Some(match (variadic, has_default) {
+        (Some('+'), _) => format!("<{}>...", name),
+        (Some(_), _) => format!("[{}...]", name),
+        (None, true) => format!("[{}]", name),
+        (None, false) => format!("<{}>", name),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn task(name: &str, description: Option<&str>, usage: Option<&str>) -> Task {
+        Task {
+            name: name.to_string(),
+            description: description.map(str::to_string),
+            usage: usage.map(str::to_string),
+        }
+    }
+
+    #[test]
+    fn test_parse_package_json() {
+        let content = r#"{
+  "name": "app",
+  "scripts": { "dev": "vite", "test:unit": "vitest run" }
+}"#;
+        assert_eq!(
+            TaskSource::PackageJson.parse(content).unwrap(),
+            vec![
+                task("dev", Some("vite"), None),
+                task("test:unit", Some("vitest run"), None),
+            ]
+        );
+        assert!(TaskSource::PackageJson.parse("{}").unwrap().is_empty());
+        assert!(TaskSource::PackageJson.parse("{").is_err());
+    }
+
+    #[test]
+    fn test_parse_makefile() {
+        let content = "CC := gcc\n\
+            FLAGS = -O2\n\
+            .PHONY: build test\n\
+            \n\
+            build: src/main.c ## Build the binary\n\
+            \t$(CC) $(FLAGS) -o app src/main.c\n\
+            \n\
+            test lint: build\n\
+            \t./app --test\n\
+            %.o: %.c\n\
+            \t$(CC) -c $<\n\
+            define HELP\n\
+            fake: target\n\
+            endef\n\
+            build: extra\n";
+
+        assert_eq!(
+            TaskSource::Makefile.parse(content).unwrap(),
+            vec![
+                task("build", Some("Build the binary"), None),
+                task("test", None, None),
+                task("lint", None, None),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_justfile() {
+        let content = "set shell := [\"bash\", \"-c\"]\n\
+            version := \"1.0\"\n\
+            \n\
+            # Serve the docs\n\
+            serve port='8080' host=\"0.0.0.0:80\":\n\
+            \tmdbook serve --port {{port}}\n\
+            \n\
+            [private]\n\
+            helper:\n\
+            \techo hidden\n\
+            \n\
+            _internal:\n\
+            \techo hidden\n\
+            \n\
+            @test +files: build\n\
+            \tcargo test {{files}}\n\
+            \n\
+            build $target *flags:\n\
+            \tcargo build\n";
+
+        assert_eq!(
+            TaskSource::Justfile.parse(content).unwrap(),
+            vec![
+                task("serve", Some("Serve the docs"), Some("[port] [host]")),
+                task("test", None, Some("<files>...")),
+                task("build", None, Some("<target> [flags...]")),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_call_script_and_command_name() {
+        let path = Path::new("Makefile");
+        let root = "\"$(git rev-parse --show-toplevel)\"";
+        assert_eq!(
+            TaskSource::Makefile.call_script(path, "build", root),
+            "make build \"$@\""
+        );
+        assert_eq!(
+            TaskSource::Makefile.call_script(Path::new("tools/build.mk"), "all", root),
+            "(cd \"$(git rev-parse --show-toplevel)\"/tools && make -f build.mk all \"$@\")"
+        );
+        assert_eq!(
+            TaskSource::Makefile.call_script(Path::new("tools/build.mk"), "all", "'/srv/my app'"),
+            "(cd '/srv/my app'/tools && make -f build.mk all \"$@\")"
+        );
+        assert_eq!(
+            TaskSource::Justfile.call_script(Path::new("./justfile"), "serve", root),
+            "just serve \"$@\""
+        );
+        assert_eq!(
+            TaskSource::PackageJson.call_script(
+                Path::new("/nonexistent/web app/package.json"),
+                "test:unit",
+                root
+            ),
+            "(cd '/nonexistent/web app' && npm run test:unit -- \"$@\")"
+        );
+
+        assert_eq!(command_name("test:unit").as_deref(), Some("test-unit"));
+        assert_eq!(command_name("build"), Some("build".to_string()));
+        assert_eq!(command_name("::"), None);
+        assert!(fits_command_script("web app/package.json"));
+        assert!(!fits_command_script("it's/justfile"));
+        assert!(!fits_command_script("${HOME}/justfile"));
+        assert!(TaskSource::from_path(Path::new("README.md")).is_err());
+        assert_eq!(
+            TaskSource::from_path(Path::new("web/package.json")).unwrap(),
+            TaskSource::PackageJson
+        );
+    }
+}
@@ -9,6 +9,16 @@ use crate::flake::interfaces::profiles::Package;
 use anyhow::Result;
 use colored::Colorize;
 use indicatif::{ProgressBar, ProgressStyle};
+use std::sync::atomic::{AtomicBool, Ordering};
+
+static SPINNER_ENABLED: AtomicBool = AtomicBool::new(true);
+
+/// Enable or disable spinners for the rest of the process.
+///
+/// When disabled, [`with_spinner`] prints its message to stderr once instead.
+pub fn set_spinner_enabled(enabled: bool) {
+    SPINNER_ENABLED.store(enabled, Ordering::Relaxed);
+}
 
 /// Execute a function with a spinning progress indicator.
 ///
@@ -31,6 +41,11 @@ pub fn with_spinner<F, T>(message: &str, f: F) -> Result<T>
 where
     F: FnOnce() -> Result<T>,
 {
+    if !SPINNER_ENABLED.load(Ordering::Relaxed) {
+        eprintln!("{}", message);
+        return f();
+    }
+
     let spinner = ProgressBar::new_spinner();
     spinner.set_style(
         ProgressStyle::default_spinner()
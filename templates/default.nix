@@ -14,7 +14,7 @@ in
       defaultShell = "";
 
       profileFiles = builtins.readDir ./profiles;
-      profileDefinitions = builtins.listToAttrs (
+      rawProfiles = builtins.listToAttrs (
         map (file: {
           name = pkgs.lib.removeSuffix ".nix" file;
           value = import (./profiles + "/${file}") {inherit pkgs system;};
@@ -23,10 +23,159 @@ in
           (n: n != "default.nix" && pkgs.lib.hasSuffix ".nix" n)
           (builtins.attrNames profileFiles))
       );
+
+      # Merge a profile on top of the profiles it extends
+      mergeProfile = parent: child: let
+        childCommands = child.commands or [];
+        childNames = map (c: c.name) childCommands;
+      in
+        parent
+        // child
+        // {
+          packages = lib.unique ((parent.packages or []) ++ (child.packages or []));
+          envVars = (parent.envVars or {}) // (child.envVars or {});
+          services = (parent.services or {}) // (child.services or {});
+          commands =
+            (builtins.filter (c: !(builtins.elem c.name childNames)) (parent.commands or []))
+            ++ childCommands;
+          shellHook = lib.concatStringsSep "\n" (
+            builtins.filter (h: h != "") [(parent.shellHook or "") (child.shellHook or "")]
+          );
+        };
+
+      resolveProfile = stack: name: let
+        profile =
+          rawProfiles.${name}
+          or (throw "Profile '${lib.last stack}' extends '${name}', which does not exist");
+        parents = map (resolveProfile (stack ++ [name])) (profile.extends or []);
+      in
+        if builtins.elem name stack
+        then throw "Profile inheritance cycle: ${lib.concatStringsSep " -> " (stack ++ [name])}"
+        else removeAttrs (builtins.foldl' mergeProfile {} (parents ++ [profile])) ["extends"];
+
+      # __flk_run_deps runs a command's deps in order, stops at the first
+      # failure, runs a command shared by several deps only once and reports
+      # dependency cycles
+      commandDepsHook = ''
+        __flk_run_deps() {
+          local self="$1" dep status
+          shift
+          case " ''${__flk_stack-} " in
+            *" $self "*)
+              echo "flk: command dependency cycle: ''${__flk_stack// / -> } -> $self" >&2
+              return 1
+              ;;
+          esac
+          [ -n "''${__flk_stack-}" ] || local __flk_done=" "
+          local __flk_stack="''${__flk_stack:+$__flk_stack }$self"
+          for dep in "$@"; do
+            case "$__flk_done" in *" $dep "*) continue ;; esac
+            if ! declare -F "$dep" >/dev/null; then
+              echo "flk: $self depends on '$dep', which does not exist" >&2
+              return 127
+            fi
+            "$dep" || {
+              status=$?
+              echo "flk: $self stopped because $dep failed" >&2
+              return "$status"
+            }
+            __flk_done="$__flk_done$dep "
+          done
+        }
+      '';
+
+      # Start each command that has deps with a call to __flk_run_deps
+      withCommandDeps = profile: let
+        commands = profile.commands or [];
+        wrap = c:
+          if (c.deps or []) == []
+          then c
+          else c // {script = "__flk_run_deps ${lib.escapeShellArgs ([c.name] ++ c.deps)} || return $?\n" + c.script;};
+      in
+        if builtins.all (c: (c.deps or []) == []) commands
+        then profile
+        else
+          profile
+          // {
+            commands = map wrap commands;
+            shellHook = lib.concatStringsSep "\n" (
+              builtins.filter (h: h != "") [commandDepsHook (profile.shellHook or "")]
+            );
+          };
+
+      # Put each service's package on PATH; `flk service up` runs the
+      # services themselves, so profile-lib never sees them
+      withServices = profile: let
+        services = builtins.attrValues (profile.services or {});
+      in
+        removeAttrs profile ["services"]
+        // {
+          packages = lib.unique (
+            (profile.packages or []) ++ map (s: s.package) (builtins.filter (s: s ? package) services)
+          );
+        };
+
+      # Define a flk-help function listing the commands with their usage and
+      # description; profile-lib itself only needs each command's name and script
+      withCommandHelp = profile: let
+        commands = profile.commands or [];
+        signature = c: c.name + lib.optionalString ((c.usage or "") != "") " ${c.usage}";
+        width = builtins.foldl' lib.max 0 (map (c: builtins.stringLength (signature c)) commands);
+        helpLine = c: "  printf '  %-${toString width}s  %s\\n' ${lib.escapeShellArg (signature c)} ${lib.escapeShellArg (c.description or "")}";
+        helpHook = ''
+          flk-help() {
+            echo "Available commands:"
+          ${lib.concatMapStringsSep "\n" helpLine commands}
+          }
+          echo "Run flk-help to list the commands of this environment."
+        '';
+      in
+        if commands == []
+        then profile
+        else
+          profile
+          // {
+            commands = map (c: {inherit (c) name script;}) commands;
+            shellHook = lib.concatStringsSep "\n" (
+              builtins.filter (h: h != "") [helpHook (profile.shellHook or "")]
+            );
+          };
+
+      resolvedProfiles = builtins.mapAttrs (name: _: withServices (withCommandDeps (resolveProfile [] name))) rawProfiles;
+      profileDefinitions = builtins.mapAttrs (_: withCommandHelp) resolvedProfiles;
+
+      # Expose every command as apps.<system>.<profile>-<command> for
+      # `nix run`, with the profile's packages on PATH and its envVars set.
+      # The other commands are defined as functions so deps still work
+      commandApps = lib.concatMapAttrs (profileName: profile: let
+        commands = profile.commands or [];
+        envExports = lib.concatStrings (
+          lib.mapAttrsToList (n: v: "export ${n}=${lib.escapeShellArg (toString v)}\n") (profile.envVars or {})
+        );
+        functions = lib.concatMapStrings (c: "${c.name}() {\n${c.script}\n}\n") commands;
+        mkApp = c: let
+          app = pkgs.writeShellApplication {
+            name = "${profileName}-${c.name}";
+            runtimeInputs = profile.packages or [];
+            # Behave like the shell function: no strict mode, no shellcheck
+            bashOptions = [];
+            checkPhase = ''${pkgs.stdenv.shellDryRun} "$target"'';
+            text = envExports + commandDepsHook + functions + ''${c.name} "$@"'';
+          };
+        in {
+          type = "app";
+          program = "${app}/bin/${profileName}-${c.name}";
+          meta.description = c.description or "flk command ${c.name} of the ${profileName} profile";
+        };
+      in
+        builtins.listToAttrs (map (c: lib.nameValuePair "${profileName}-${c.name}" (mkApp c)) commands))
+      resolvedProfiles;
     in
-      profileLib.mkProfileOutputs {
+      lib.recursiveUpdate
+      (profileLib.mkProfileOutputs {
         inherit profileDefinitions;
         maxCombinations = 3;
-      }
+      })
+      {apps = commandApps;}
       // lib.optionalAttrs (defaultShell != "") {inherit defaultShell;}
   )
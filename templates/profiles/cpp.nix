@@ -0,0 +1,35 @@
+{pkgs, ...}: let
+in {
+  description = "C/C++ development environment";
+
+  packages = [
+    pkgs.gcc
+    pkgs.clang-tools
+    pkgs.cmake
+    pkgs.meson
+    pkgs.ninja
+    pkgs.pkg-config
+    pkgs.gnumake
+    pkgs.ccache
+  ];
+
+  envVars = {
+    LANG = "en_US.UTF-8";
+    LC_ALL = "en_US.UTF-8";
+    CMAKE_EXPORT_COMPILE_COMMANDS = "ON";
+    CMAKE_GENERATOR = "Ninja";
+  };
+
+  commands = [];
+
+  shellHook = ''
+    echo "⚙️  C/C++ development environment ready!"
+    echo "Compiler: $(cc --version | head -n 1)"
+
+    # Custom commands will be added here
+  '';
+
+  containerConfig = {
+    Cmd = ["${pkgs.bashInteractive}/bin/bash"];
+  };
+}
@@ -0,0 +1,33 @@
+# flk:var dotnet_version = 8 -- .NET SDK major version (9 for pkgs.dotnet-sdk_9)
+{pkgs, ...}: let
+in {
+  description = ".NET development environment";
+
+  packages = [
+    pkgs.dotnet-sdk_{{dotnet_version}}
+    pkgs.csharp-ls
+  ];
+
+  envVars = {
+    LANG = "en_US.UTF-8";
+    LC_ALL = "en_US.UTF-8";
+    DOTNET_CLI_TELEMETRY_OPTOUT = "1";
+    DOTNET_NOLOGO = "1";
+  };
+
+  commands = [];
+
+  shellHook = ''
+    echo "🟣 .NET development environment ready!"
+    echo ".NET SDK version: $(dotnet --version)"
+
+    # Tools installed with 'dotnet tool install' live here
+    export PATH="$HOME/.dotnet/tools:$PATH"
+
+    # Custom commands will be added here
+  '';
+
+  containerConfig = {
+    Cmd = ["${pkgs.bashInteractive}/bin/bash"];
+  };
+}
@@ -0,0 +1,35 @@
+{pkgs, ...}: let
+in {
+  description = "Elixir development environment";
+
+  packages = [
+    pkgs.elixir
+    pkgs.erlang
+    pkgs.elixir-ls
+  ] ++ pkgs.lib.optionals pkgs.stdenv.isLinux [ pkgs.inotify-tools ];
+
+  envVars = {
+    LANG = "en_US.UTF-8";
+    LC_ALL = "en_US.UTF-8";
+    ERL_AFLAGS = "-kernel shell_history enabled";
+  };
+
+  commands = [];
+
+  shellHook = ''
+    echo "💧 Elixir development environment ready!"
+    echo "Elixir version: $(elixir --short-version)"
+
+    # Keep Mix and Hex archives inside the project
+    export MIX_HOME="$PWD/.nix-mix"
+    export HEX_HOME="$PWD/.nix-hex"
+    mkdir -p "$MIX_HOME" "$HEX_HOME"
+    export PATH="$MIX_HOME/bin:$MIX_HOME/escripts:$HEX_HOME/bin:$PATH"
+
+    # Custom commands will be added here
+  '';
+
+  containerConfig = {
+    Cmd = ["${pkgs.bashInteractive}/bin/bash"];
+  };
+}
@@ -0,0 +1,32 @@
+{pkgs, ...}: let
+in {
+  description = "Haskell development environment";
+
+  packages = [
+    pkgs.ghc
+    pkgs.cabal-install
+    pkgs.stack
+    pkgs.haskell-language-server
+    pkgs.hlint
+    pkgs.ormolu
+    pkgs.zlib
+  ];
+
+  envVars = {
+    LANG = "en_US.UTF-8";
+    LC_ALL = "en_US.UTF-8";
+  };
+
+  commands = [];
+
+  shellHook = ''
+    echo "λ Haskell development environment ready!"
+    echo "GHC version: $(ghc --numeric-version)"
+
+    # Custom commands will be added here
+  '';
+
+  containerConfig = {
+    Cmd = ["${pkgs.bashInteractive}/bin/bash"];
+  };
+}
@@ -0,0 +1,33 @@
+# flk:var jdk_version = 21 -- JDK version (17 for pkgs.jdk17)
+{pkgs, ...}: let
+in {
+  description = "Java/Kotlin development environment";
+
+  packages = [
+    pkgs.jdk{{jdk_version}}
+    pkgs.gradle
+    pkgs.maven
+    pkgs.kotlin
+    pkgs.jdt-language-server
+    pkgs.kotlin-language-server
+  ];
+
+  envVars = {
+    LANG = "en_US.UTF-8";
+    LC_ALL = "en_US.UTF-8";
+    JAVA_TOOL_OPTIONS = "-Dfile.encoding=UTF-8";
+  };
+
+  commands = [];
+
+  shellHook = ''
+    echo "☕ Java/Kotlin development environment ready!"
+    echo "Java version: $(java -version 2>&1 | head -n 1)"
+
+    # Custom commands will be added here
+  '';
+
+  containerConfig = {
+    Cmd = ["${pkgs.bashInteractive}/bin/bash"];
+  };
+}
@@ -1,9 +1,10 @@
+# flk:var node_major = 25 -- Node.js major version (22 for pkgs.nodejs_22)
 {pkgs, ...}: let
 in {
   description = "Node.js development environment";
 
   packages = [
-    pkgs.nodejs_25
+    pkgs.nodejs_{{node_major}}
     pkgs.nodePackages.npm
     pkgs.nodePackages.pnpm
     pkgs.yarn
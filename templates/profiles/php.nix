@@ -0,0 +1,32 @@
+# flk:var php_version = 83 -- PHP version without dots (84 for pkgs.php84)
+{pkgs, ...}: let
+in {
+  description = "PHP development environment";
+
+  packages = [
+    pkgs.php{{php_version}}
+    pkgs.php{{php_version}}Packages.composer
+    pkgs.phpactor
+  ];
+
+  envVars = {
+    LANG = "en_US.UTF-8";
+    LC_ALL = "en_US.UTF-8";
+  };
+
+  commands = [];
+
+  shellHook = ''
+    echo "🐘 PHP development environment ready!"
+    echo "PHP version: $(php --version | head -n 1)"
+
+    # Make tools installed by composer available
+    export PATH="$PWD/vendor/bin:$PATH"
+
+    # Custom commands will be added here
+  '';
+
+  containerConfig = {
+    Cmd = ["${pkgs.bashInteractive}/bin/bash"];
+  };
+}
@@ -1,10 +1,11 @@
+# flk:var python_version = 313 -- Python version as used in nixpkgs attribute names (312 for pkgs.python312)
 {pkgs, ...}: let
 in {
   description = "Python development environment";
 
   packages = [
-    pkgs.python313
-    pkgs.python313Packages.pip
+    pkgs.python{{python_version}}
+    pkgs.python{{python_version}}Packages.pip
     pkgs.poetry
     pkgs.virtualenv
     pkgs.black
@@ -0,0 +1,34 @@
+# flk:var ruby_version = 3_3 -- Ruby version with underscores (3_4 for pkgs.ruby_3_4)
+{pkgs, ...}: let
+in {
+  description = "Ruby development environment";
+
+  packages = [
+    pkgs.ruby_{{ruby_version}}
+    pkgs.bundler
+    pkgs.solargraph
+    pkgs.rubocop
+  ];
+
+  envVars = {
+    LANG = "en_US.UTF-8";
+    LC_ALL = "en_US.UTF-8";
+    BUNDLE_PATH = "vendor/bundle";
+  };
+
+  commands = [];
+
+  shellHook = ''
+    echo "💎 Ruby development environment ready!"
+    echo "Ruby version: $(ruby --version)"
+
+    # Make gem executables installed by bundler available
+    export PATH="$PWD/bin:$PATH"
+
+    # Custom commands will be added here
+  '';
+
+  containerConfig = {
+    Cmd = ["${pkgs.bashInteractive}/bin/bash"];
+  };
+}
@@ -0,0 +1,30 @@
+{pkgs, ...}: let
+in {
+  description = "Zig development environment";
+
+  packages = [
+    pkgs.zig
+    pkgs.zls
+  ];
+
+  envVars = {
+    LANG = "en_US.UTF-8";
+    LC_ALL = "en_US.UTF-8";
+  };
+
+  commands = [];
+
+  shellHook = ''
+    echo "⚡ Zig development environment ready!"
+    echo "Zig version: $(zig version)"
+
+    # Keep the build cache inside the project
+    export ZIG_GLOBAL_CACHE_DIR="$PWD/.zig-cache/global"
+
+    # Custom commands will be added here
+  '';
+
+  containerConfig = {
+    Cmd = ["${pkgs.bashInteractive}/bin/bash"];
+  };
+}
@@ -187,6 +187,390 @@ fn test_init_with_go_template() {
     assert!(content.contains("Go development environment"));
 }
 
+#[test]
+fn test_init_detects_more_languages() {
+    for (marker, template, label) in [
+        ("build.gradle.kts", "java", "Java/Kotlin"),
+        ("CMakeLists.txt", "cpp", "C/C++"),
+        ("build.zig", "zig", "Zig"),
+        ("my-app.cabal", "haskell", "Haskell"),
+        ("mix.exs", "elixir", "Elixir"),
+        ("Gemfile", "ruby", "Ruby"),
+        ("composer.json", "php", "PHP"),
+        ("App.csproj", "dotnet", ".NET"),
+    ] {
+        let temp_dir = TempDir::new().unwrap();
+        fs::write(temp_dir.path().join(marker), "").unwrap();
+
+        flk_cmd()
+            .current_dir(temp_dir.path())
+            .arg("init")
+            .assert()
+            .success()
+            .stdout(predicate::str::contains(format!(
+                "Detected {} project",
+                label
+            )));
+
+        assert!(temp_dir
+            .path()
+            .join(format!(".flk/profiles/{}.nix", template))
+            .exists());
+    }
+}
+
+#[test]
+fn test_init_polyglot_project() {
+    let temp_dir = TempDir::new().unwrap();
+    let root = temp_dir.path();
+    fs::write(root.join("Cargo.toml"), "").unwrap();
+    fs::create_dir_all(root.join("frontend")).unwrap();
+    fs::write(root.join("frontend/package.json"), "{}").unwrap();
+    fs::create_dir_all(root.join("services/api")).unwrap();
+    fs::write(root.join("services/api/go.mod"), "").unwrap();
+    fs::create_dir_all(root.join("node_modules/dep")).unwrap();
+    fs::write(root.join("node_modules/dep/pyproject.toml"), "").unwrap();
+
+    flk_cmd()
+        .current_dir(root)
+        .arg("init")
+        .assert()
+        .success()
+        .stdout(contains("Detected Rust project (.)"))
+        .stdout(contains("Detected Node.js project (frontend)"))
+        .stdout(contains("Detected Go project (services/api)"))
+        .stdout(contains("Default profile: rust"));
+
+    for profile in ["rust", "node", "go"] {
+        assert!(root.join(format!(".flk/profiles/{}.nix", profile)).exists());
+    }
+    assert!(!root.join(".flk/profiles/python.nix").exists());
+    let helper = fs::read_to_string(root.join(".flk/default.nix")).unwrap();
+    assert!(helper.contains("defaultShell = \"rust\";"));
+
+    // --only limits the set, --combined adds a profile extending the others
+    flk_cmd()
+        .current_dir(root)
+        .args(["init", "--force", "--only", "node,go", "--combined"])
+        .assert()
+        .success()
+        .stdout(contains("Initializing flake for node, go projects"))
+        .stdout(contains("Default profile: all"));
+
+    let all = fs::read_to_string(root.join(".flk/profiles/all.nix")).unwrap();
+    assert!(all.contains(r#"extends = [ "node" "go" ];"#));
+    let helper = fs::read_to_string(root.join(".flk/default.nix")).unwrap();
+    assert!(helper.contains("defaultShell = \"all\";"));
+
+    flk_cmd()
+        .current_dir(root)
+        .args(["init", "--force", "--only", "cobol"])
+        .assert()
+        .failure()
+        .stderr(contains("Unknown language 'cobol' for --only"));
+}
+
+#[test]
+fn test_template_variables() {
+    let temp_dir = TempDir::new().unwrap();
+    let root = temp_dir.path();
+
+    flk_cmd()
+        .current_dir(root)
+        .args(["init", "-t", "python", "--set", "python_version=312"])
+        .assert()
+        .success();
+    let profile = fs::read_to_string(root.join(".flk/profiles/python.nix")).unwrap();
+    assert!(profile.contains("pkgs.python312\n"));
+    assert!(profile.contains("pkgs.python312Packages.pip"));
+    assert!(!profile.contains("flk:var"));
+    assert!(!profile.contains("{{"));
+
+    flk_cmd()
+        .current_dir(root)
+        .args(["profile", "add", "web", "-t", "node"])
+        .assert()
+        .success();
+    let profile = fs::read_to_string(root.join(".flk/profiles/web.nix")).unwrap();
+    assert!(profile.contains("pkgs.nodejs_25"));
+
+    flk_cmd()
+        .current_dir(root)
+        .args([
+            "profile",
+            "add",
+            "api",
+            "-t",
+            "node",
+            "--set",
+            "nod_major=22",
+        ])
+        .assert()
+        .failure()
+        .stderr(contains("Unknown template variable 'nod_major'"))
+        .stderr(contains("node_major"));
+
+    flk_cmd()
+        .current_dir(root)
+        .args([
+            "profile",
+            "add",
+            "api",
+            "-t",
+            "node",
+            "--set",
+            "node_major=22;",
+        ])
+        .assert()
+        .failure()
+        .stderr(contains("Invalid value '22;'"));
+
+    flk_cmd()
+        .current_dir(root)
+        .args(["template", "show", "python"])
+        .assert()
+        .success()
+        .stderr(contains("python_version"))
+        .stderr(contains("(default: 313)"));
+
+    // Undeclared placeholders are kept as written, with a warning
+    fs::write(
+        root.join("hooked.nix"),
+        "{pkgs, ...}: {\n  packages = [];\n  envVars = {};\n  commands = [];\n  shellHook = ''\n    docker ps --format '{{.Names}}' {{tool}}\n  '';\n}\n",
+    )
+    .unwrap();
+    flk_cmd()
+        .current_dir(root)
+        .args(["template", "add", "hooked.nix", "--project"])
+        .assert()
+        .success()
+        .stdout(contains("'{{tool}}' is not a declared variable"));
+    flk_cmd()
+        .current_dir(root)
+        .args(["profile", "add", "hooked", "--template", "hooked"])
+        .assert()
+        .success();
+    let hooked = fs::read_to_string(root.join(".flk/profiles/hooked.nix")).unwrap();
+    assert!(hooked.contains("docker ps --format '{{.Names}}' {{tool}}"));
+}
+
+#[test]
+fn test_config_layers() {
+    let temp_dir = TempDir::new().unwrap();
+    let root = temp_dir.path().join("project");
+    let config_home = temp_dir.path().join("config");
+    fs::create_dir_all(&root).unwrap();
+    let flk = || {
+        let mut cmd = flk_cmd();
+        cmd.current_dir(&root)
+            .env("XDG_CONFIG_HOME", &config_home)
+            .env_remove("FLK_TEMPLATE_DEFAULT");
+        cmd
+    };
+
+    flk()
+        .args(["config", "set", "template.default", "go"])
+        .assert()
+        .success()
+        .stdout(contains("Set template.default = go"));
+    assert!(fs::read_to_string(config_home.join("flk/config.toml"))
+        .unwrap()
+        .contains("[template]\ndefault = \"go\""));
+
+    flk()
+        .args([
+            "config",
+            "set",
+            "nix.channel",
+            "nixos-unstable",
+            "--project",
+        ])
+        .assert()
+        .failure()
+        .stderr(contains("No .flk directory found"));
+    flk()
+        .args(["config", "set", "ui.color", "sometimes"])
+        .assert()
+        .failure()
+        .stderr(contains("expected one of auto, always, never"));
+    flk()
+        .args(["config", "get", "ui.colour"])
+        .assert()
+        .failure()
+        .stderr(contains("Unknown config key 'ui.colour'"));
+
+    // The user's default template is used when nothing is detected
+    flk()
+        .arg("init")
+        .assert()
+        .success()
+        .stdout(contains("using go template"));
+    assert!(root.join(".flk/profiles/go.nix").exists());
+
+    flk()
+        .args(["config", "set", "template.default", "node", "--project"])
+        .assert()
+        .success();
+    flk()
+        .args(["config", "get", "template.default"])
+        .assert()
+        .success()
+        .stdout("node\n")
+        .stderr(contains("from project (.flk/config.toml)"));
+    flk().args(["profile", "add", "web"]).assert().success();
+    assert!(fs::read_to_string(root.join(".flk/profiles/web.nix"))
+        .unwrap()
+        .contains("Node.js development environment"));
+
+    // Environment variables win over both files
+    flk()
+        .env("FLK_TEMPLATE_DEFAULT", "python")
+        .args(["config", "list"])
+        .assert()
+        .success()
+        .stdout(contains(
+            "template.default = python [env (FLK_TEMPLATE_DEFAULT)]",
+        ))
+        .stdout(contains("nix.channel = nixos-25.11 [default]"));
+    flk()
+        .env("FLK_TEMPLATE_DEFAULT", "python")
+        .args(["config", "set", "template.default", "rust"])
+        .assert()
+        .success()
+        .stdout(contains(
+            "template.default is overridden by env (FLK_TEMPLATE_DEFAULT)",
+        ));
+
+    // The project channel is used for new flakes
+    flk()
+        .args([
+            "config",
+            "set",
+            "nix.channel",
+            "nixos-unstable",
+            "--project",
+        ])
+        .assert()
+        .success();
+    flk().args(["init", "--force"]).assert().success();
+    assert!(fs::read_to_string(root.join("flake.nix"))
+        .unwrap()
+        .contains("github:NixOS/nixpkgs/nixos-unstable"));
+
+    // A broken config is reported, but can still be inspected
+    fs::write(root.join(".flk/config.toml"), "[ui]\nspinner = \"maybe\"\n").unwrap();
+    flk()
+        .args(["profile", "list"])
+        .assert()
+        .failure()
+        .stderr(contains("expected true or false"));
+    flk()
+        .args(["config", "set", "ui.spinner", "false", "--project"])
+        .assert()
+        .success();
+    flk().args(["profile", "list"]).assert().success();
+}
+
+#[cfg(unix)]
+#[test]
+fn test_channel_set() {
+    let temp_dir = TempDir::new().unwrap();
+    let root = temp_dir.path().join("project");
+    let fake_bin_dir = temp_dir.path().join("bin");
+    let fake_nix_path = fake_bin_dir.join("nix");
+    fs::create_dir_all(&root).unwrap();
+    fs::create_dir_all(&fake_bin_dir).unwrap();
+
+    // Locks nixpkgs to "newrev" and reports versions for whichever revision is evaluated
+    fs::write(
+        &fake_nix_path,
+        r#"#!/bin/sh
+case "$1" in
+  --version) echo "nix (Nix) 2.30.0" ;;
+  flake)
+    sed 's/oldrev/newrev/' flake.lock > flake.lock.new && mv flake.lock.new flake.lock ;;
+  eval)
+    case "$*" in
+      *oldrev*) echo '{"git":"2.44.0","old-tool":"1.0","ripgrep":"14.1.0"}' ;;
+      *) echo '{"git":"2.44.0","old-tool":null,"ripgrep":"14.1.1"}' ;;
+    esac ;;
+esac
+"#,
+    )
+    .unwrap();
+    make_executable(&fake_nix_path);
+
+    let flk = || {
+        let mut cmd = flk_cmd();
+        cmd.current_dir(&root)
+            .env("XDG_CONFIG_HOME", temp_dir.path().join("config"))
+            .env("PATH", prepend_path(&fake_bin_dir));
+        cmd
+    };
+
+    flk().arg("init").assert().success();
+    let profile_path = root.join(".flk/profiles/generic.nix");
+    let profile = fs::read_to_string(&profile_path).unwrap().replace(
+        "# User packages",
+        "# User packages\n    pkgs.git\n    pkgs.old-tool\n    pkgs.ripgrep",
+    );
+    fs::write(&profile_path, profile).unwrap();
+    fs::write(
+        root.join("flake.lock"),
+        r#"{"nodes":{"nixpkgs":{"locked":{"lastModified":1700000000,"owner":"NixOS","repo":"nixpkgs","rev":"oldrev","type":"github"}},"root":{"inputs":{"nixpkgs":"nixpkgs"}}},"root":"root","version":7}"#,
+    )
+    .unwrap();
+
+    flk()
+        .args(["channel", "show"])
+        .assert()
+        .success()
+        .stdout(contains("nixpkgs channel: nixos-25.11"))
+        .stdout(contains("oldrev (2023-11-14)"));
+
+    flk()
+        .args(["channel", "set", "unstable"])
+        .assert()
+        .failure()
+        .stderr(contains("Invalid channel 'unstable'"));
+    flk()
+        .args(["channel", "set", "nixos-25.11"])
+        .assert()
+        .success()
+        .stdout(contains("Already following nixos-25.11"));
+
+    flk()
+        .args(["channel", "set", "nixos-unstable"])
+        .assert()
+        .success()
+        .stdout(contains(
+            "Switched nixpkgs from nixos-25.11 to nixos-unstable",
+        ))
+        .stdout(contains("Created backup"))
+        .stdout(contains("generic"))
+        .stdout(contains("old-tool 1.0 → no longer available"))
+        .stdout(contains("ripgrep 14.1.0 → 14.1.1"))
+        .stdout(contains("git 2.44.0").not());
+
+    let flake = fs::read_to_string(root.join("flake.nix")).unwrap();
+    assert!(flake.contains("\n    nixpkgs.url = \"github:NixOS/nixpkgs/nixos-unstable\";\n"));
+    assert!(fs::read_to_string(root.join("flake.lock"))
+        .unwrap()
+        .contains("newrev"));
+    // Only release channels move the stable pin source
+    assert!(fs::read_to_string(root.join(".flk/pins.nix"))
+        .unwrap()
+        .contains("nixos-25.05"));
+
+    flk()
+        .args(["channel", "set", "nixos-26.05"])
+        .assert()
+        .success();
+    assert!(fs::read_to_string(root.join(".flk/pins.nix"))
+        .unwrap()
+        .contains("stable = \"github:NixOS/nixpkgs/nixos-26.05\";"));
+}
+
 #[test]
 fn test_init_force_overwrite() {
     let temp_dir = TempDir::new().unwrap();
@@ -523,6 +907,7 @@ fn test_hook_shells_include_shell_command() {
         .stdout(contains("export FLK_PROFILE=\".#$profile\""))
         .stdout(contains("_flk_exec_nix_develop \".#$profile\""))
         .stdout(contains("[ \"$f\" -nt \"$stamp_path\" ] && return 1"))
+        .stdout(contains("$(_flk_profile_files \"$profile\")"))
         .stdout(contains(".nix-profile-"))
         .stdout(contains(".stamp"));
 
@@ -554,6 +939,7 @@ fn test_hook_shells_include_shell_command() {
         .stdout(contains("set -gx FLK_PROFILE"))
         .stdout(contains("set -l flk_shell"))
         .stdout(contains("test \"$f\" -nt \"$stamp_path\"; and return 1"))
+        .stdout(contains("string split + -- $argv[1]"))
         .stdout(contains(".nix-profile-"))
         .stdout(contains(".stamp"));
 }
@@ -611,6 +997,91 @@ fn test_activate_uses_shell_fallback_and_profile_gc_root() {
     );
 }
 
+#[cfg(unix)]
+#[test]
+fn test_activate_profile_combination() {
+    let temp_dir = TempDir::new().unwrap();
+    let fake_bin_dir = temp_dir.path().join("bin");
+    let fake_nix_path = fake_bin_dir.join("nix");
+    let log_path = temp_dir.path().join("nix-args.log");
+
+    fs::create_dir_all(&fake_bin_dir).unwrap();
+    fs::write(
+        &fake_nix_path,
+        "#!/bin/sh\nprintf '%s\\n' \"$@\" > \"$FAKE_NIX_LOG\"\n",
+    )
+    .unwrap();
+    make_executable(&fake_nix_path);
+
+    flk_cmd()
+        .current_dir(temp_dir.path())
+        .arg("init")
+        .assert()
+        .success();
+    flk_cmd()
+        .current_dir(temp_dir.path())
+        .args(["profile", "add", "backend"])
+        .assert()
+        .success();
+
+    flk_cmd()
+        .current_dir(temp_dir.path())
+        .env("PATH", prepend_path(&fake_bin_dir))
+        .env("FAKE_NIX_LOG", &log_path)
+        .env_remove("SHELL")
+        .args(["activate", "--profile", ".#generic+backend"])
+        .assert()
+        .success();
+
+    let args = fs::read_to_string(&log_path).unwrap();
+    let args: Vec<&str> = args.lines().collect();
+    assert_eq!(
+        &args[..5],
+        &[
+            "develop",
+            ".#generic+backend",
+            "--impure",
+            "--profile",
+            ".flk/.nix-profile-generic+backend",
+        ]
+    );
+
+    flk_cmd()
+        .current_dir(temp_dir.path())
+        .env("PATH", prepend_path(&fake_bin_dir))
+        .args(["activate", "--profile", "generic+missing"])
+        .assert()
+        .failure()
+        .stderr(contains(
+            "Profile 'missing' in 'generic+missing' does not exist",
+        ));
+
+    flk_cmd()
+        .current_dir(temp_dir.path())
+        .args(["profile", "set-max-combinations", "1"])
+        .assert()
+        .success();
+    let default_nix = fs::read_to_string(temp_dir.path().join(".flk/default.nix")).unwrap();
+    assert!(default_nix.contains("maxCombinations = 1;"));
+
+    flk_cmd()
+        .current_dir(temp_dir.path())
+        .env("PATH", prepend_path(&fake_bin_dir))
+        .args(["activate", "--profile", "generic+backend"])
+        .assert()
+        .failure()
+        .stderr(contains("maxCombinations is 1"));
+
+    // Commands that edit a profile need a single one
+    flk_cmd()
+        .current_dir(temp_dir.path())
+        .env("FLK_FLAKE_REF", ".#generic+backend")
+        .arg("list")
+        .assert()
+        .failure()
+        .stderr(contains("combines several profiles"));
+}
+
 #[cfg(unix)]
 #[test]
 fn test_activate_reuses_fresh_profile_cache() {
@@ -751,47 +1222,173 @@ fn test_activate_treats_cache_fresh_when_stamp_equals_input_mtime() {
 
 #[cfg(unix)]
 #[test]
-fn test_activate_profile_cache_with_real_nix_when_available() {
-    if !real_nix_tests_enabled() {
-        eprintln!(
-            "skipping test_activate_profile_cache_with_real_nix_when_available: set RUN_REAL_NIX_TESTS=1 and ensure nix flakes support is available"
-        );
-        return;
-    }
-
+fn test_run_custom_command_and_exit_code() {
     let temp_dir = TempDir::new().unwrap();
-    let profile_path = temp_dir.path().join(".flk/.nix-profile-generic");
+    let fake_bin_dir = temp_dir.path().join("bin");
+    let fake_nix_path = fake_bin_dir.join("nix");
+    let log_path = temp_dir.path().join("nix-args.log");
     let stamp_path = temp_dir.path().join(".flk/.nix-profile-generic.stamp");
 
+    // Log the arguments, then run whatever follows -c like nix develop would
+    fs::create_dir_all(&fake_bin_dir).unwrap();
+    fs::write(
+        &fake_nix_path,
+        "#!/bin/sh\nprintf '%s\\n' \"$@\" > \"$FAKE_NIX_LOG\"\n\
+         while [ \"$1\" != \"-c\" ]; do shift; done\nshift\nexec \"$@\"\n",
+    )
+    .unwrap();
+    make_executable(&fake_nix_path);
+
     flk_cmd()
         .current_dir(temp_dir.path())
         .arg("init")
         .assert()
         .success();
 
-    let true_bin = true_binary();
-
     flk_cmd()
         .current_dir(temp_dir.path())
-        .env("SHELL", &true_bin)
-        .args(["activate", "--profile", "generic"])
+        .args(["command", "add", "greet", "echo \"hello $1\""])
         .assert()
         .success();
 
-    assert!(
-        profile_path.exists(),
-        "expected first activation to create cached profile at {}",
-        profile_path.display()
-    );
-    assert!(
-        stamp_path.exists(),
-        "expected first activation to create cache stamp at {}",
-        stamp_path.display()
-    );
-
-    let first_stamp_mtime = fs::metadata(&stamp_path).unwrap().modified().unwrap();
-
-    // Second activation should reuse the cached profile (stamp unchanged).
+    flk_cmd()
+        .current_dir(temp_dir.path())
+        .env("PATH", prepend_path(&fake_bin_dir))
+        .env("FAKE_NIX_LOG", &log_path)
+        .args(["run", "-p", "generic", "greet", "--", "world"])
+        .assert()
+        .success()
+        .stdout("hello world\n");
+
+    let args: Vec<String> = fs::read_to_string(&log_path)
+        .unwrap()
+        .lines()
+        .map(ToOwned::to_owned)
+        .collect();
+    assert_eq!(
+        &args[..8],
+        [
+            "develop",
+            ".#generic",
+            "--impure",
+            "--profile",
+            ".flk/.nix-profile-generic",
+            "-c",
+            "/bin/sh",
+            "-c",
+        ]
+    );
+    assert_eq!(&args[9..12], ["sh", "bash", "-c"]);
+    assert_eq!(args.last().unwrap(), "world");
+
+    // Scripts are function bodies, as in the dev shell
+    flk_cmd()
+        .current_dir(temp_dir.path())
+        .args(["command", "add", "early"])
+        .arg("local x=\"$1\"\necho \"got $x\"\nreturn 4\necho unreachable")
+        .assert()
+        .success();
+    flk_cmd()
+        .current_dir(temp_dir.path())
+        .env("PATH", prepend_path(&fake_bin_dir))
+        .env("FAKE_NIX_LOG", &log_path)
+        .args(["run", "-p", "generic", "early", "--", "it"])
+        .assert()
+        .code(4)
+        .stdout("got it\n");
+
+    flk_cmd()
+        .current_dir(temp_dir.path())
+        .args(["command", "add", "hello", "${pkgs.hello}/bin/hello"])
+        .assert()
+        .success();
+    flk_cmd()
+        .current_dir(temp_dir.path())
+        .env("PATH", prepend_path(&fake_bin_dir))
+        .env("FAKE_NIX_LOG", &log_path)
+        .args(["run", "-p", "generic", "hello"])
+        .assert()
+        .failure()
+        .stderr(contains("'hello' uses Nix interpolation"));
+
+    // A fresh cache is entered directly, and the exit code passes through
+    fs::write(temp_dir.path().join(".flk/.nix-profile-generic"), "cached").unwrap();
+    fs::write(&stamp_path, "").unwrap();
+    set_modified_time(
+        &stamp_path,
+        std::time::SystemTime::now() + std::time::Duration::from_secs(60),
+    );
+
+    flk_cmd()
+        .current_dir(temp_dir.path())
+        .env("PATH", prepend_path(&fake_bin_dir))
+        .env("FAKE_NIX_LOG", &log_path)
+        .args(["run", "-p", "generic", "sh", "--", "-c", "exit 3"])
+        .assert()
+        .code(3);
+
+    let args: Vec<String> = fs::read_to_string(&log_path)
+        .unwrap()
+        .lines()
+        .map(ToOwned::to_owned)
+        .collect();
+    assert_eq!(
+        args,
+        vec![
+            "develop",
+            ".flk/.nix-profile-generic",
+            "--impure",
+            "-c",
+            "sh",
+            "-c",
+            "exit 3",
+        ]
+    );
+}
+
+#[cfg(unix)]
+#[test]
+fn test_activate_profile_cache_with_real_nix_when_available() {
+    if !real_nix_tests_enabled() {
+        eprintln!(
+            "skipping test_activate_profile_cache_with_real_nix_when_available: set RUN_REAL_NIX_TESTS=1 and ensure nix flakes support is available"
+        );
+        return;
+    }
+
+    let temp_dir = TempDir::new().unwrap();
+    let profile_path = temp_dir.path().join(".flk/.nix-profile-generic");
+    let stamp_path = temp_dir.path().join(".flk/.nix-profile-generic.stamp");
+
+    flk_cmd()
+        .current_dir(temp_dir.path())
+        .arg("init")
+        .assert()
+        .success();
+
+    let true_bin = true_binary();
+
+    flk_cmd()
+        .current_dir(temp_dir.path())
+        .env("SHELL", &true_bin)
+        .args(["activate", "--profile", "generic"])
+        .assert()
+        .success();
+
+    assert!(
+        profile_path.exists(),
+        "expected first activation to create cached profile at {}",
+        profile_path.display()
+    );
+    assert!(
+        stamp_path.exists(),
+        "expected first activation to create cache stamp at {}",
+        stamp_path.display()
+    );
+
+    let first_stamp_mtime = fs::metadata(&stamp_path).unwrap().modified().unwrap();
+
+    // Second activation should reuse the cached profile (stamp unchanged).
     flk_cmd()
         .current_dir(temp_dir.path())
         .env("SHELL", &true_bin)
@@ -995,178 +1592,539 @@ fn test_profile_add_list_set_default_remove() {
 }
 
 #[test]
-fn test_profile_name_validation() {
+fn test_profile_extends_inherits_and_detects_cycles() {
     let temp_dir = TempDir::new().unwrap();
+    let profiles = temp_dir.path().join(".flk/profiles");
 
     flk_cmd()
         .current_dir(temp_dir.path())
         .arg("init")
         .assert()
         .success();
-
-    // Invalid names should fail
     flk_cmd()
         .current_dir(temp_dir.path())
-        .arg("profile")
-        .arg("add")
-        .arg("../bad")
+        .args(["profile", "add", "backend"])
         .assert()
-        .failure()
-        .stderr(predicate::str::contains("Invalid profile name"));
+        .success();
+
+    let generic = fs::read_to_string(profiles.join("generic.nix")).unwrap();
+    fs::write(
+        profiles.join("generic.nix"),
+        generic.replacen("packages = [", "packages = [\n    pkgs.git", 1),
+    )
+    .unwrap();
+    let backend = fs::read_to_string(profiles.join("backend.nix")).unwrap();
+    fs::write(
+        profiles.join("backend.nix"),
+        backend.replacen(
+            "  packages = [",
+            "  extends = [ \"generic\" ];\n\n  packages = [\n    pkgs.jq",
+            1,
+        ),
+    )
+    .unwrap();
 
     flk_cmd()
         .current_dir(temp_dir.path())
-        .arg("profile")
-        .arg("add")
-        .arg("has spaces")
+        .args(["list", "--profile", "backend"])
         .assert()
-        .failure()
-        .stderr(predicate::str::contains("Invalid profile name"));
-}
-
-#[test]
-fn test_path_traversal_prevention() {
-    let temp_dir = TempDir::new().unwrap();
+        .success()
+        .stdout(predicate::str::contains("git (from generic)"))
+        .stdout(predicate::str::contains("jq"));
 
     flk_cmd()
         .current_dir(temp_dir.path())
-        .arg("init")
+        .args(["command", "add", "greet", "echo hello"])
         .assert()
         .success();
+    flk_cmd()
+        .current_dir(temp_dir.path())
+        .args(["command", "--profile", "backend", "list"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("(from generic)"))
+        .stdout(predicate::str::contains("nix run .#backend-greet"));
 
-    // Path traversal via --profile flag should be rejected
     flk_cmd()
         .current_dir(temp_dir.path())
-        .arg("add")
-        .arg("ripgrep")
-        .arg("--profile")
-        .arg("../../../tmp/malicious")
+        .arg("show")
         .assert()
-        .failure()
-        .stderr(predicate::str::contains("Invalid profile name"));
+        .success()
+        .stdout(predicate::str::contains("Extends:"))
+        .stdout(predicate::str::contains("(from generic)"));
 
-    // Path traversal via env command should also be rejected
     flk_cmd()
         .current_dir(temp_dir.path())
-        .arg("env")
-        .arg("--profile")
-        .arg("../secret")
-        .arg("list")
+        .args(["profile", "set-default", "backend"])
+        .assert()
+        .success();
+    flk_cmd()
+        .current_dir(temp_dir.path())
+        .args(["profile", "remove", "generic"])
         .assert()
         .failure()
-        .stderr(predicate::str::contains("Invalid profile name"));
+        .stderr(predicate::str::contains("extended by: backend"));
+
+    // Close the loop: generic -> backend -> generic
+    let generic = fs::read_to_string(profiles.join("generic.nix")).unwrap();
+    fs::write(
+        profiles.join("generic.nix"),
+        generic.replacen(
+            "  packages = [",
+            "  extends = [ \"backend\" ];\n  packages = [",
+            1,
+        ),
+    )
+    .unwrap();
 
-    // Path traversal via profile remove should be rejected
     flk_cmd()
         .current_dir(temp_dir.path())
-        .arg("profile")
-        .arg("remove")
-        .arg("../../../etc/passwd")
+        .arg("show")
         .assert()
         .failure()
-        .stderr(predicate::str::contains("Invalid profile name"));
+        .stderr(predicate::str::contains("Profile inheritance cycle"));
 
-    // Path traversal via profile set-default should be rejected
     flk_cmd()
         .current_dir(temp_dir.path())
-        .arg("profile")
-        .arg("set-default")
-        .arg("../../../tmp/malicious")
+        .arg("check")
         .assert()
         .failure()
-        .stderr(predicate::str::contains("Invalid profile name"));
+        .stdout(predicate::str::contains("extends-cycle"));
 }
 
 #[test]
-fn test_add_package_to_specific_profile() {
+fn test_profile_rename_copy_merge() {
     let temp_dir = TempDir::new().unwrap();
+    let flk_dir = temp_dir.path().join(".flk");
+    let profiles = flk_dir.join("profiles");
 
     flk_cmd()
         .current_dir(temp_dir.path())
         .arg("init")
         .assert()
         .success();
-
-    // Create a second profile
     flk_cmd()
         .current_dir(temp_dir.path())
-        .arg("profile")
-        .arg("add")
-        .arg("rust")
-        .arg("--template")
-        .arg("rust")
+        .args(["profile", "add", "tools"])
         .assert()
         .success();
-
-    // Add package to the rust profile specifically
     flk_cmd()
         .current_dir(temp_dir.path())
-        .arg("add")
-        .arg("ripgrep")
-        .arg("--profile")
-        .arg("rust")
+        .args(["profile", "set-default", "generic"])
         .assert()
         .success();
 
-    // Verify package is in rust profile
-    let rust_profile = fs::read_to_string(temp_dir.path().join(".flk/profiles/rust.nix")).unwrap();
-    assert!(rust_profile.contains("ripgrep"));
+    let generic = fs::read_to_string(profiles.join("generic.nix")).unwrap();
+    fs::write(
+        profiles.join("generic.nix"),
+        generic.replacen("packages = [", "packages = [\n    pkgs.git", 1),
+    )
+    .unwrap();
+    let tools = fs::read_to_string(profiles.join("tools.nix")).unwrap();
+    fs::write(
+        profiles.join("tools.nix"),
+        tools
+            .replacen(
+                "  packages = [",
+                "  extends = [ \"generic\" ];\n\n  packages = [\n    pkgs.jq",
+                1,
+            )
+            .replacen("LANG = \"en_US.UTF-8\";", "LANG = \"C\";", 1),
+    )
+    .unwrap();
+    for cache in [
+        ".nix-profile-generic",
+        ".nix-profile-generic.stamp",
+        ".nix-profile-generic-1-link",
+        ".nix-profile-tools+generic",
+        ".nix-profile-tools",
+    ] {
+        fs::write(flk_dir.join(cache), "").unwrap();
+    }
 
-    // Verify package is NOT in generic profile
-    let generic_profile =
-        fs::read_to_string(temp_dir.path().join(".flk/profiles/generic.nix")).unwrap();
-    assert!(!generic_profile.contains("ripgrep"));
-}
+    flk_cmd()
+        .current_dir(temp_dir.path())
+        .args(["profile", "rename", "generic", "base"])
+        .assert()
+        .success()
+        .stdout(contains("Removed 4 cached environment file(s)"));
 
-#[test]
-fn test_env_operations_on_specific_profile() {
-    let temp_dir = TempDir::new().unwrap();
+    assert!(!profiles.join("generic.nix").exists());
+    assert!(profiles.join("base.nix").exists());
+    assert!(fs::read_to_string(flk_dir.join("default.nix"))
+        .unwrap()
+        .contains("defaultShell = \"base\";"));
+    assert!(fs::read_to_string(profiles.join("tools.nix"))
+        .unwrap()
+        .contains("extends = [ \"base\" ];"));
+    assert!(flk_dir.join(".nix-profile-tools").exists());
 
     flk_cmd()
         .current_dir(temp_dir.path())
-        .arg("init")
+        .args(["profile", "copy", "base", "tools"])
         .assert()
-        .success();
-
-    // Create a second profile
+        .failure()
+        .stderr(contains(
+            "Profile tools already exists! Use --force to overwrite.",
+        ));
     flk_cmd()
         .current_dir(temp_dir.path())
-        .arg("profile")
-        .arg("add")
-        .arg("dev")
-        .arg("--template")
-        .arg("base")
+        .args(["profile", "copy", "base", "scratch"])
         .assert()
         .success();
+    assert_eq!(
+        fs::read_to_string(profiles.join("scratch.nix")).unwrap(),
+        fs::read_to_string(profiles.join("base.nix")).unwrap()
+    );
 
-    // Add env var to dev profile
     flk_cmd()
         .current_dir(temp_dir.path())
-        .arg("env")
-        .arg("--profile")
-        .arg("dev")
-        .arg("add")
-        .arg("MY_VAR")
-        .arg("my_value")
+        .args(["profile", "merge", "base", "tools", "--into", "all"])
         .assert()
-        .success();
+        .success()
+        .stdout(contains("Merged base and tools into all"))
+        .stdout(contains("env LANG: kept \"en_US.UTF-8\", dropped \"C\""));
 
-    // Verify env var is in dev profile
-    let dev_profile = fs::read_to_string(temp_dir.path().join(".flk/profiles/dev.nix")).unwrap();
-    assert!(dev_profile.contains("MY_VAR"));
+    let merged = fs::read_to_string(profiles.join("all.nix")).unwrap();
+    assert!(merged.contains("pkgs.git"));
+    assert!(merged.contains("pkgs.jq"));
+    assert!(merged.contains("LANG = \"en_US.UTF-8\";"));
+    assert!(merged.contains("extends = [ \"base\" ];"));
 
-    // List env vars on specific profile
     flk_cmd()
         .current_dir(temp_dir.path())
-        .arg("env")
-        .arg("--profile")
-        .arg("dev")
-        .arg("list")
+        .args(["profile", "merge", "base", "tools", "--into", "scratch"])
         .assert()
-        .success()
-        .stdout(predicate::str::contains("MY_VAR"));
-}
-
+        .failure()
+        .stderr(contains(
+            "Profile scratch already exists! Choose another name or remove it first.",
+        ));
+    flk_cmd()
+        .current_dir(temp_dir.path())
+        .args(["profile", "rename", "scratch", "all"])
+        .assert()
+        .failure()
+        .stderr(contains(
+            "Profile all already exists! Choose another name or remove it first.",
+        ));
+}
+
+#[test]
+fn test_profile_diff_text_and_json() {
+    let temp_dir = TempDir::new().unwrap();
+    let profiles = temp_dir.path().join(".flk/profiles");
+
+    flk_cmd()
+        .current_dir(temp_dir.path())
+        .arg("init")
+        .assert()
+        .success();
+    flk_cmd()
+        .current_dir(temp_dir.path())
+        .args(["profile", "copy", "generic", "other"])
+        .assert()
+        .success();
+
+    flk_cmd()
+        .current_dir(temp_dir.path())
+        .args(["profile", "diff", "generic", "other"])
+        .assert()
+        .success()
+        .stdout(contains("Profiles have the same packages"));
+
+    let other = fs::read_to_string(profiles.join("other.nix")).unwrap();
+    fs::write(
+        profiles.join("other.nix"),
+        other
+            .replacen(
+                "packages = [",
+                "packages = [\n    pkgs.\"ripgrep@14.1.0\"",
+                1,
+            )
+            .replacen("LANG = \"en_US.UTF-8\";", "LANG = \"C\";", 1),
+    )
+    .unwrap();
+
+    flk_cmd()
+        .current_dir(temp_dir.path())
+        .args(["profile", "diff", "generic", "other"])
+        .assert()
+        .success()
+        .stdout(contains("ripgrep"))
+        .stdout(contains("LANG"));
+
+    let output = flk_cmd()
+        .current_dir(temp_dir.path())
+        .args(["profile", "diff", "generic", "other", "--format", "json"])
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let diff: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(diff["packages"]["added"][0]["name"], "ripgrep");
+    assert_eq!(diff["packages"]["added"][0]["version"], "14.1.0");
+    assert_eq!(diff["env_vars"]["changed"][0]["name"], "LANG");
+    assert_eq!(diff["env_vars"]["changed"][0]["to"], "C");
+    assert!(diff["commands"]["changed"].as_array().unwrap().is_empty());
+}
+
+#[test]
+fn test_user_and_project_templates() {
+    let temp_dir = TempDir::new().unwrap();
+    let config_dir = temp_dir.path().join("config");
+    let project = temp_dir.path().join("project");
+    fs::create_dir_all(&project).unwrap();
+
+    let template = temp_dir.path().join("stack.nix");
+    fs::write(
+        &template,
+        "{pkgs, ...}: {\n  description = \"Company stack\";\n\n  packages = [\n    pkgs.jq\n  ];\n\n  envVars = {};\n\n  commands = [];\n}\n",
+    )
+    .unwrap();
+    fs::write(temp_dir.path().join("broken.nix"), "{ description = 1; }").unwrap();
+
+    let cmd = || {
+        let mut cmd = flk_cmd();
+        cmd.current_dir(&project)
+            .env("XDG_CONFIG_HOME", &config_dir);
+        cmd
+    };
+
+    cmd()
+        .args(["init", "--template", "stack"])
+        .assert()
+        .failure()
+        .stderr(contains("Unknown template 'stack'"));
+    assert!(!project.join("flake.nix").exists());
+    cmd()
+        .args(["init", "--template", "../stack"])
+        .assert()
+        .failure()
+        .stderr(contains("Invalid template name '../stack'"));
+    assert!(!project.join("flake.nix").exists());
+
+    cmd()
+        .args(["template", "add"])
+        .arg(temp_dir.path().join("broken.nix"))
+        .assert()
+        .failure()
+        .stderr(contains("not a valid profile template"));
+
+    cmd()
+        .args(["template", "add"])
+        .arg(&template)
+        .assert()
+        .success();
+    assert!(config_dir.join("flk/templates/stack.nix").exists());
+
+    cmd()
+        .args(["init", "--template", "stack"])
+        .assert()
+        .success();
+    assert!(fs::read_to_string(project.join(".flk/profiles/stack.nix"))
+        .unwrap()
+        .contains("Company stack"));
+
+    // A project template overrides the user and built-in ones
+    cmd()
+        .args(["template", "add", "--project", "--name", "rust"])
+        .arg(&template)
+        .assert()
+        .success();
+    cmd()
+        .args(["template", "list"])
+        .assert()
+        .success()
+        .stdout(contains("stack [user"))
+        .stdout(contains(
+            "rust [project (.flk-templates/rust.nix)] (overrides built-in)",
+        ));
+    cmd()
+        .args(["template", "show", "rust"])
+        .assert()
+        .success()
+        .stdout(contains("Company stack"));
+
+    cmd()
+        .args(["template", "add"])
+        .arg(&template)
+        .assert()
+        .failure()
+        .stderr(contains("already exists"));
+}
+
+#[test]
+fn test_profile_name_validation() {
+    let temp_dir = TempDir::new().unwrap();
+
+    flk_cmd()
+        .current_dir(temp_dir.path())
+        .arg("init")
+        .assert()
+        .success();
+
+    // Invalid names should fail
+    flk_cmd()
+        .current_dir(temp_dir.path())
+        .arg("profile")
+        .arg("add")
+        .arg("../bad")
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("Invalid profile name"));
+
+    flk_cmd()
+        .current_dir(temp_dir.path())
+        .arg("profile")
+        .arg("add")
+        .arg("has spaces")
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("Invalid profile name"));
+}
+
+#[test]
+fn test_path_traversal_prevention() {
+    let temp_dir = TempDir::new().unwrap();
+
+    flk_cmd()
+        .current_dir(temp_dir.path())
+        .arg("init")
+        .assert()
+        .success();
+
+    // Path traversal via --profile flag should be rejected
+    flk_cmd()
+        .current_dir(temp_dir.path())
+        .arg("add")
+        .arg("ripgrep")
+        .arg("--profile")
+        .arg("../../../tmp/malicious")
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("Invalid profile name"));
+
+    // Path traversal via env command should also be rejected
+    flk_cmd()
+        .current_dir(temp_dir.path())
+        .arg("env")
+        .arg("--profile")
+        .arg("../secret")
+        .arg("list")
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("Invalid profile name"));
+
+    // Path traversal via profile remove should be rejected
+    flk_cmd()
+        .current_dir(temp_dir.path())
+        .arg("profile")
+        .arg("remove")
+        .arg("../../../etc/passwd")
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("Invalid profile name"));
+
+    // Path traversal via profile set-default should be rejected
+    flk_cmd()
+        .current_dir(temp_dir.path())
+        .arg("profile")
+        .arg("set-default")
+        .arg("../../../tmp/malicious")
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("Invalid profile name"));
+}
+
+#[test]
+fn test_add_package_to_specific_profile() {
+    let temp_dir = TempDir::new().unwrap();
+
+    flk_cmd()
+        .current_dir(temp_dir.path())
+        .arg("init")
+        .assert()
+        .success();
+
+    // Create a second profile
+    flk_cmd()
+        .current_dir(temp_dir.path())
+        .arg("profile")
+        .arg("add")
+        .arg("rust")
+        .arg("--template")
+        .arg("rust")
+        .assert()
+        .success();
+
+    // Add package to the rust profile specifically
+    flk_cmd()
+        .current_dir(temp_dir.path())
+        .arg("add")
+        .arg("ripgrep")
+        .arg("--profile")
+        .arg("rust")
+        .assert()
+        .success();
+
+    // Verify package is in rust profile
+    let rust_profile = fs::read_to_string(temp_dir.path().join(".flk/profiles/rust.nix")).unwrap();
+    assert!(rust_profile.contains("ripgrep"));
+
+    // Verify package is NOT in generic profile
+    let generic_profile =
+        fs::read_to_string(temp_dir.path().join(".flk/profiles/generic.nix")).unwrap();
+    assert!(!generic_profile.contains("ripgrep"));
+}
+
+#[test]
+fn test_env_operations_on_specific_profile() {
+    let temp_dir = TempDir::new().unwrap();
+
+    flk_cmd()
+        .current_dir(temp_dir.path())
+        .arg("init")
+        .assert()
+        .success();
+
+    // Create a second profile
+    flk_cmd()
+        .current_dir(temp_dir.path())
+        .arg("profile")
+        .arg("add")
+        .arg("dev")
+        .arg("--template")
+        .arg("base")
+        .assert()
+        .success();
+
+    // Add env var to dev profile
+    flk_cmd()
+        .current_dir(temp_dir.path())
+        .arg("env")
+        .arg("--profile")
+        .arg("dev")
+        .arg("add")
+        .arg("MY_VAR")
+        .arg("my_value")
+        .assert()
+        .success();
+
+    // Verify env var is in dev profile
+    let dev_profile = fs::read_to_string(temp_dir.path().join(".flk/profiles/dev.nix")).unwrap();
+    assert!(dev_profile.contains("MY_VAR"));
+
+    // List env vars on specific profile
+    flk_cmd()
+        .current_dir(temp_dir.path())
+        .arg("env")
+        .arg("--profile")
+        .arg("dev")
+        .arg("list")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("MY_VAR"));
+}
+
 #[test]
 fn test_export_json() {
     let temp_dir = TempDir::new().unwrap();
@@ -1179,17 +2137,697 @@ fn test_export_json() {
 
     flk_cmd()
         .current_dir(temp_dir.path())
-        .arg("export")
-        .arg("--format")
-        .arg("json")
+        .arg("export")
+        .arg("--format")
+        .arg("json")
+        .assert()
+        .success();
+
+    let json_path = temp_dir.path().join("flake.json");
+    assert!(json_path.exists());
+
+    let json_content = fs::read_to_string(json_path).unwrap();
+    assert!(json_content.contains("profiles"));
+}
+
+#[cfg(unix)]
+fn write_fake_image_tools(bin_dir: &Path, nix_script: &str, docker_script: &str) {
+    fs::create_dir_all(bin_dir).unwrap();
+    let nix_path = bin_dir.join("nix");
+    let docker_path = bin_dir.join("docker");
+    fs::write(&nix_path, nix_script).unwrap();
+    fs::write(&docker_path, docker_script).unwrap();
+    make_executable(&nix_path);
+    make_executable(&docker_path);
+}
+
+#[cfg(unix)]
+const FAKE_NIX_BUILD: &str = "#!/bin/sh\nprintf '%s\\n' \"$*\" >> \"$FAKE_LOG\"\nif [ \"$1\" = build ]; then echo archive > .flk/result; fi\n";
+
+#[cfg(unix)]
+#[test]
+fn test_export_docker_output_saves_archive_without_loading() {
+    let temp_dir = TempDir::new().unwrap();
+    let bin_dir = temp_dir.path().join("bin");
+    let log_path = temp_dir.path().join("tools.log");
+    write_fake_image_tools(
+        &bin_dir,
+        FAKE_NIX_BUILD,
+        "#!/bin/sh\necho \"docker $*\" >> \"$FAKE_LOG\"\nexit 1\n",
+    );
+
+    flk_cmd()
+        .current_dir(temp_dir.path())
+        .arg("init")
+        .assert()
+        .success();
+
+    flk_cmd()
+        .current_dir(temp_dir.path())
+        .env("PATH", prepend_path(&bin_dir))
+        .env("FAKE_LOG", &log_path)
+        .args(["export", "--format", "docker", "--output", "image.tar"])
+        .assert()
+        .success()
+        .stdout(contains("Saved image archive to"));
+
+    let archive = fs::read_to_string(temp_dir.path().join("image.tar")).unwrap();
+    assert_eq!(archive, "archive\n");
+
+    let log = fs::read_to_string(&log_path).unwrap();
+    assert!(log.contains("build .#docker-generic --out-link .flk/result --impure"));
+    assert!(!log.contains("docker "));
+}
+
+#[cfg(unix)]
+#[test]
+fn test_export_docker_sets_container_config_and_tags_image() {
+    let temp_dir = TempDir::new().unwrap();
+    let bin_dir = temp_dir.path().join("bin");
+    let log_path = temp_dir.path().join("tools.log");
+    write_fake_image_tools(
+        &bin_dir,
+        FAKE_NIX_BUILD,
+        "#!/bin/sh\necho \"docker $*\" >> \"$FAKE_LOG\"\nif [ \"$1\" = load ]; then echo 'Loaded image: generic:abc123'; fi\n",
+    );
+
+    flk_cmd()
+        .current_dir(temp_dir.path())
+        .arg("init")
+        .assert()
+        .success();
+
+    flk_cmd()
+        .current_dir(temp_dir.path())
+        .env("PATH", prepend_path(&bin_dir))
+        .env("FAKE_LOG", &log_path)
+        .args([
+            "export",
+            "--format",
+            "docker",
+            "--image-name",
+            "myapp",
+            "--tag",
+            "1.0",
+            "--port",
+            "8080",
+            "--port",
+            "53/udp",
+            "--workdir",
+            "/app",
+            "--entrypoint",
+            "sh -c 'npm start'",
+        ])
+        .assert()
+        .success()
+        .stdout(contains("Loaded image"))
+        .stdout(contains("myapp:1.0"));
+
+    let log = fs::read_to_string(&log_path).unwrap();
+    assert!(log.contains("docker load -i .flk/result"));
+    assert!(log.contains("docker tag generic:abc123 myapp:1.0"));
+
+    let profile = fs::read_to_string(temp_dir.path().join(".flk/profiles/generic.nix")).unwrap();
+    assert!(profile.contains("ExposedPorts = { \"8080/tcp\" = {}; \"53/udp\" = {}; };"));
+    assert!(profile.contains("WorkingDir = \"/app\";"));
+    assert!(profile.contains("Entrypoint = [\"sh\" \"-c\" \"npm start\"];"));
+    assert!(profile.contains("Cmd = "));
+}
+
+#[cfg(unix)]
+#[test]
+fn test_export_docker_reports_build_failure() {
+    let temp_dir = TempDir::new().unwrap();
+    let bin_dir = temp_dir.path().join("bin");
+    let log_path = temp_dir.path().join("tools.log");
+    write_fake_image_tools(
+        &bin_dir,
+        "#!/bin/sh\necho 'error: attribute missing' >&2\nexit 1\n",
+        "#!/bin/sh\necho \"docker $*\" >> \"$FAKE_LOG\"\n",
+    );
+
+    flk_cmd()
+        .current_dir(temp_dir.path())
+        .arg("init")
+        .assert()
+        .success();
+    let profile_path = temp_dir.path().join(".flk/profiles/generic.nix");
+    let original = fs::read_to_string(&profile_path).unwrap();
+
+    flk_cmd()
+        .current_dir(temp_dir.path())
+        .env("PATH", prepend_path(&bin_dir))
+        .env("FAKE_LOG", &log_path)
+        .args(["export", "--format", "docker", "--port", "8080"])
+        .assert()
+        .failure()
+        .stderr(contains("Failed to build Docker image"))
+        .stderr(contains("error: attribute missing"));
+
+    assert!(!log_path.exists());
+    // The containerConfig change is undone when the build fails
+    let profile = fs::read_to_string(profile_path).unwrap();
+    assert_eq!(profile, original);
+}
+
+#[cfg(unix)]
+#[test]
+fn test_export_docker_reports_load_failure() {
+    let temp_dir = TempDir::new().unwrap();
+    let bin_dir = temp_dir.path().join("bin");
+    let log_path = temp_dir.path().join("tools.log");
+    write_fake_image_tools(
+        &bin_dir,
+        FAKE_NIX_BUILD,
+        "#!/bin/sh\necho 'Cannot connect to the Docker daemon' >&2\nexit 1\n",
+    );
+
+    flk_cmd()
+        .current_dir(temp_dir.path())
+        .arg("init")
+        .assert()
+        .success();
+
+    flk_cmd()
+        .current_dir(temp_dir.path())
+        .env("PATH", prepend_path(&bin_dir))
+        .env("FAKE_LOG", &log_path)
+        .args(["export", "--format", "docker"])
+        .assert()
+        .failure()
+        .stderr(contains("'docker load' failed"))
+        .stderr(contains("Cannot connect to the Docker daemon"));
+}
+
+#[cfg(unix)]
+#[test]
+fn test_container_run_rebuilds_only_when_stale() {
+    let temp_dir = TempDir::new().unwrap();
+    let bin_dir = temp_dir.path().join("bin");
+    let log_path = temp_dir.path().join("tools.log");
+    write_fake_image_tools(
+        &bin_dir,
+        FAKE_NIX_BUILD,
+        "#!/bin/sh\necho \"docker $*\" >> \"$FAKE_LOG\"\nif [ \"$1\" = load ]; then echo 'Loaded image: generic:abc123'; fi\nif [ \"$1\" = run ]; then exit 3; fi\n",
+    );
+
+    flk_cmd()
+        .current_dir(temp_dir.path())
+        .arg("init")
+        .assert()
+        .success();
+
+    let run_container = || {
+        flk_cmd()
+            .current_dir(temp_dir.path())
+            .env("PATH", prepend_path(&bin_dir))
+            .env("FAKE_LOG", &log_path)
+            .args(["container", "run", "--", "echo", "hi"])
+            .assert()
+            .code(3)
+    };
+    let builds = || {
+        fs::read_to_string(&log_path)
+            .unwrap()
+            .lines()
+            .filter(|line| line.starts_with("build "))
+            .count()
+    };
+
+    run_container().stdout(contains("Loaded image"));
+    assert_eq!(builds(), 1);
+
+    let log = fs::read_to_string(&log_path).unwrap();
+    let run_line = log
+        .lines()
+        .find(|line| line.starts_with("docker run"))
+        .unwrap();
+    assert!(run_line.contains(&format!(
+        "-v {}:/workspace -w /workspace --user ",
+        temp_dir.path().display()
+    )));
+    assert!(run_line.contains("-e LANG=en_US.UTF-8"));
+    assert!(run_line.ends_with("generic:abc123 echo hi"));
+
+    run_container().stdout(contains("Using cached image"));
+    assert_eq!(builds(), 1);
+
+    let stamp = temp_dir.path().join(".flk/.image-docker-generic.stamp");
+    set_modified_time(
+        &stamp,
+        std::time::SystemTime::now() - std::time::Duration::from_secs(60),
+    );
+    flk_cmd()
+        .current_dir(temp_dir.path())
+        .args(["env", "add", "NEW_VAR", "1"])
+        .assert()
+        .success();
+
+    run_container().stdout(contains("Loaded image"));
+    assert_eq!(builds(), 2);
+}
+
+#[cfg(unix)]
+fn doctor_cmd(temp_dir: &TempDir, flakes: &str) -> assert_cmd::Command {
+    let bin_dir = temp_dir.path().join("bin");
+    let home_dir = temp_dir.path().join("home");
+    fs::create_dir_all(&bin_dir).unwrap();
+    fs::create_dir_all(&home_dir).unwrap();
+    let nix_path = bin_dir.join("nix");
+    fs::write(
+        &nix_path,
+        format!(
+            "#!/bin/sh\nif [ \"$1\" = --version ]; then echo 'nix (Nix) 2.24.0'; exit 0; fi\nif [ \"$1\" = config ]; then echo '{}'; exit 0; fi\nexit 1\n",
+            flakes
+        ),
+    )
+    .unwrap();
+    make_executable(&nix_path);
+    fs::write(home_dir.join(".bashrc"), "eval \"$(flk hook bash)\"\n").unwrap();
+
+    let mut cmd = flk_cmd();
+    cmd.current_dir(temp_dir.path())
+        .env("PATH", prepend_path(&bin_dir))
+        .env("HOME", &home_dir)
+        .env("SHELL", "/bin/bash")
+        .env("GIT_CEILING_DIRECTORIES", temp_dir.path().parent().unwrap())
+        .arg("doctor");
+    cmd
+}
+
+#[cfg(unix)]
+#[test]
+fn test_doctor_passes_for_healthy_project() {
+    let temp_dir = TempDir::new().unwrap();
+
+    flk_cmd()
+        .current_dir(temp_dir.path())
+        .arg("init")
+        .assert()
+        .success();
+
+    doctor_cmd(&temp_dir, "flakes nix-command")
+        .assert()
+        .success()
+        .stdout(contains("Nix is installed (nix (Nix) 2.24.0)"))
+        .stdout(contains("Flakes and nix-command are enabled"))
+        .stdout(contains("flk hook is installed"))
+        .stdout(contains("Profile generic parses"));
+}
+
+#[cfg(unix)]
+#[test]
+fn test_doctor_reports_broken_setup() {
+    let temp_dir = TempDir::new().unwrap();
+
+    flk_cmd()
+        .current_dir(temp_dir.path())
+        .arg("init")
+        .assert()
+        .success();
+    fs::write(
+        temp_dir.path().join(".flk/profiles/broken.nix"),
+        "{pkgs, ...}: {\n  description = \"no sections\";\n}\n",
+    )
+    .unwrap();
+    fs::write(temp_dir.path().join(".flk/.nix-profile-generic"), "").unwrap();
+
+    std::process::Command::new("git")
+        .arg("init")
+        .arg("-q")
+        .current_dir(temp_dir.path())
+        .status()
+        .unwrap();
+
+    doctor_cmd(&temp_dir, "nix-command")
+        .assert()
+        .failure()
+        .stdout(contains("Flakes are not enabled"))
+        .stdout(contains("experimental-features = nix-command flakes"))
+        .stdout(contains("Files not tracked by git"))
+        .stdout(contains("git add --intent-to-add flake.nix"))
+        .stdout(contains("broken").and(contains("cannot be parsed")))
+        .stdout(contains("generic").and(contains("has a stale cache")))
+        .stderr(contains("problem(s) found"));
+}
+
+fn write_lint_fixture(root: &Path) {
+    fs::write(
+        root.join(".flk/profiles/generic.nix"),
+        r#"{pkgs, ...}: {
+  description = "Lint fixture";
+
+  packages = [
+    pkgs.git
+    pkgs.curl
+    pkgs.git
+    pkgs."ripgrep@14.1.0"
+    pkgs."jq@1.7.1"
+  ];
+
+  envVars = {
+    GOOD_NAME = "1";
+    BAD-NAME = "2";
+  };
+
+  commands = [
+    {
+      name = "cd";
+      script = ''
+        echo shadowed
+      '';
+    }
+  ];
+
+  shellHook = ''
+    echo ready
+  '';
+}
+"#,
+    )
+    .unwrap();
+
+    fs::write(
+        root.join(".flk/pins.nix"),
+        r#"{
+  sources = {
+    stable = "github:NixOS/nixpkgs/nixos-25.05";
+    pkgs-aaa111 = "github:NixOS/nixpkgs/aaa111";
+    pkgs-bbb222 = "github:NixOS/nixpkgs/bbb222";
+  };
+
+  pinnedPackages = {
+    pkgs-aaa111 = [
+      {
+        pkg = "jq";
+        name = "jq@1.7.1";
+      }
+      {
+        pkg = "fd";
+        name = "fd@9.0.0";
+      }
+    ];
+  };
+}
+"#,
+    )
+    .unwrap();
+
+    let importer = fs::read_to_string(root.join(".flk/default.nix")).unwrap();
+    fs::write(
+        root.join(".flk/default.nix"),
+        importer.replace("defaultShell = \"\";", "defaultShell = \"missing\";"),
+    )
+    .unwrap();
+}
+
+#[test]
+fn test_check_reports_project_problems() {
+    let temp_dir = TempDir::new().unwrap();
+
+    flk_cmd()
+        .current_dir(temp_dir.path())
+        .arg("init")
+        .assert()
+        .success();
+
+    flk_cmd()
+        .current_dir(temp_dir.path())
+        .arg("check")
+        .assert()
+        .success()
+        .stdout(contains("No problems found"));
+
+    write_lint_fixture(temp_dir.path());
+
+    flk_cmd()
+        .current_dir(temp_dir.path())
+        .arg("check")
+        .assert()
+        .failure()
+        .stdout(contains("warning[duplicate-package]"))
+        .stdout(contains("error[missing-pin]"))
+        .stdout(contains("ripgrep@14.1.0"))
+        .stdout(contains("warning[unused-pin]"))
+        .stdout(contains("fd@9.0.0"))
+        .stdout(contains("warning[unused-source]"))
+        .stdout(contains("pkgs-bbb222"))
+        .stdout(contains("error[invalid-env-name]"))
+        .stdout(contains("warning[shadowed-builtin]"))
+        .stdout(contains("error[missing-default-shell]"))
+        .stdout(contains("stable").not())
+        .stderr(contains("flk check found"));
+
+    let output = flk_cmd()
+        .current_dir(temp_dir.path())
+        .args(["check", "--format", "json"])
+        .output()
+        .unwrap();
+    assert!(!output.status.success());
+    let report: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(report["errors"], 3);
+    assert_eq!(report["warnings"], 4);
+    assert!(report["findings"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .any(|f| f["code"] == "missing-pin" && f["fixable"] == false));
+}
+
+#[test]
+fn test_check_fix_repairs_safe_problems() {
+    let temp_dir = TempDir::new().unwrap();
+
+    flk_cmd()
+        .current_dir(temp_dir.path())
+        .arg("init")
+        .assert()
+        .success();
+    write_lint_fixture(temp_dir.path());
+
+    flk_cmd()
+        .current_dir(temp_dir.path())
+        .args(["check", "--fix"])
+        .assert()
+        .failure()
+        .stdout(contains("Fixed: removed duplicate package 'git'"))
+        .stdout(contains("Fixed: removed unused pin 'fd@9.0.0'"))
+        .stdout(contains("Fixed: removed unused source 'pkgs-bbb222'"))
+        .stdout(contains("Fixed: reset defaultShell"))
+        .stdout(contains("duplicate-package").not())
+        .stdout(contains("unused-pin").not())
+        .stdout(contains("error[missing-pin]"));
+
+    let profile = fs::read_to_string(temp_dir.path().join(".flk/profiles/generic.nix")).unwrap();
+    assert_eq!(profile.matches("pkgs.git").count(), 1);
+    assert!(profile.contains("    pkgs.curl\n    pkgs.\"ripgrep@14.1.0\""));
+
+    let pins = fs::read_to_string(temp_dir.path().join(".flk/pins.nix")).unwrap();
+    assert!(pins.contains("jq@1.7.1"));
+    assert!(pins.contains("stable"));
+    assert!(!pins.contains("fd@9.0.0"));
+    assert!(!pins.contains("pkgs-bbb222"));
+
+    let importer = fs::read_to_string(temp_dir.path().join(".flk/default.nix")).unwrap();
+    assert!(importer.contains("defaultShell = \"\";"));
+}
+
+#[test]
+fn test_export_rejects_invalid_image_options() {
+    let temp_dir = TempDir::new().unwrap();
+
+    flk_cmd()
+        .current_dir(temp_dir.path())
+        .arg("init")
+        .assert()
+        .success();
+
+    flk_cmd()
+        .current_dir(temp_dir.path())
+        .args(["export", "--format", "json", "--tag", "v1"])
+        .assert()
+        .failure()
+        .stderr(contains("only be used with the docker and podman formats"));
+
+    flk_cmd()
+        .current_dir(temp_dir.path())
+        .args(["export", "--format", "docker", "--image-name", "My App"])
+        .assert()
+        .failure()
+        .stderr(contains("Invalid image name"));
+
+    flk_cmd()
+        .current_dir(temp_dir.path())
+        .args(["export", "--format", "docker", "--port", "70000"])
+        .assert()
+        .failure()
+        .stderr(contains("Invalid port"));
+}
+
+#[test]
+fn test_import_json_round_trip() {
+    let temp_dir = TempDir::new().unwrap();
+
+    flk_cmd()
+        .current_dir(temp_dir.path())
+        .arg("init")
+        .assert()
+        .success();
+
+    flk_cmd()
+        .current_dir(temp_dir.path())
+        .args(["env", "add", "MY_VAR", "hello"])
+        .assert()
+        .success();
+
+    flk_cmd()
+        .current_dir(temp_dir.path())
+        .args(["command", "add", "greet", "echo hello"])
+        .assert()
+        .success();
+
+    flk_cmd()
+        .current_dir(temp_dir.path())
+        .args(["export", "--format", "json"])
+        .assert()
+        .success();
+
+    let profile_path = temp_dir.path().join(".flk/profiles/generic.nix");
+    fs::remove_file(&profile_path).unwrap();
+
+    flk_cmd()
+        .current_dir(temp_dir.path())
+        .args(["import", "json", "flake.json"])
+        .assert()
+        .success()
+        .stdout(contains("Created profile"));
+
+    let content = fs::read_to_string(&profile_path).unwrap();
+    assert!(content.contains("MY_VAR = \"hello\";"));
+    assert!(content.contains("name = \"greet\";"));
+    assert!(content.contains("echo hello"));
+
+    // Importing over an existing profile requires an explicit mode
+    flk_cmd()
+        .current_dir(temp_dir.path())
+        .args(["import", "json", "flake.json"])
+        .assert()
+        .failure()
+        .stderr(contains("already exists"));
+
+    flk_cmd()
+        .current_dir(temp_dir.path())
+        .args(["import", "json", "flake.json", "--merge"])
+        .assert()
+        .success()
+        .stdout(contains("already up to date"));
+}
+
+#[test]
+fn test_import_json_merge_and_replace() {
+    let temp_dir = TempDir::new().unwrap();
+
+    flk_cmd()
+        .current_dir(temp_dir.path())
+        .arg("init")
+        .assert()
+        .success();
+
+    flk_cmd()
+        .current_dir(temp_dir.path())
+        .args(["env", "add", "LOCAL_ONLY", "1"])
+        .assert()
+        .success();
+
+    fs::write(
+        temp_dir.path().join("import.json"),
+        r#"{
+  "profiles": [
+    {
+      "name": "generic",
+      "packages": [{ "name": "ripgrep" }],
+      "env_vars": [{ "name": "FROM_JSON", "value": "yes" }]
+    },
+    {
+      "name": "tools",
+      "packages": [{ "name": "jq", "version": "1.7.1" }]
+    }
+  ]
+}"#,
+    )
+    .unwrap();
+
+    flk_cmd()
+        .current_dir(temp_dir.path())
+        .args(["import", "json", "import.json", "--merge"])
+        .assert()
+        .success()
+        .stdout(contains("Created profile"))
+        .stdout(contains("jq@1.7.1 is pinned but has no entry"));
+
+    let generic = fs::read_to_string(temp_dir.path().join(".flk/profiles/generic.nix")).unwrap();
+    assert!(generic.contains("pkgs.ripgrep"));
+    assert!(generic.contains("FROM_JSON = \"yes\";"));
+    assert!(generic.contains("LOCAL_ONLY = \"1\";"));
+
+    let tools = fs::read_to_string(temp_dir.path().join(".flk/profiles/tools.nix")).unwrap();
+    assert!(tools.contains("pkgs.\"jq@1.7.1\""));
+    assert!(!tools.contains("LANG"));
+
+    flk_cmd()
+        .current_dir(temp_dir.path())
+        .args([
+            "import",
+            "json",
+            "import.json",
+            "--profile",
+            "generic",
+            "--replace",
+        ])
+        .assert()
+        .success();
+
+    let generic = fs::read_to_string(temp_dir.path().join(".flk/profiles/generic.nix")).unwrap();
+    assert!(!generic.contains("LOCAL_ONLY"));
+    assert!(generic.contains("FROM_JSON = \"yes\";"));
+}
+
+#[test]
+fn test_import_json_rejects_invalid_documents() {
+    let temp_dir = TempDir::new().unwrap();
+
+    flk_cmd()
+        .current_dir(temp_dir.path())
+        .arg("init")
+        .assert()
+        .success();
+
+    fs::write(
+        temp_dir.path().join("bad.json"),
+        r#"{ "profiles": [{ "name": "../escape" }] }"#,
+    )
+    .unwrap();
+
+    flk_cmd()
+        .current_dir(temp_dir.path())
+        .args(["import", "json", "bad.json"])
+        .assert()
+        .failure()
+        .stderr(contains("Invalid profile name"));
+
+    flk_cmd()
+        .current_dir(temp_dir.path())
+        .args(["import", "json", "bad.json", "--merge", "--replace"])
         .assert()
-        .success();
-
-    let json_path = temp_dir.path().join("flake.json");
-    assert!(json_path.exists());
+        .failure();
+}
 
-    let json_content = fs::read_to_string(json_path).unwrap();
-    assert!(json_content.contains("profiles"));
+#[test]
+fn test_flake_json_schema_is_valid_json() {
+    let schema: serde_json::Value =
+        serde_json::from_str(include_str!("../schemas/flake.schema.json")).unwrap();
+    assert_eq!(schema["required"][0], "profiles");
+    assert!(schema["$defs"]["profile"]["properties"]["shell_hook"].is_object());
 }
 
 #[test]
@@ -1213,7 +2851,7 @@ fn test_profile_directory_isolation() {
         .collect();
 
     // Should have rust. nix and possibly default.nix
-    assert!(entries.len() >= 1);
+    assert!(!entries.is_empty());
     assert!(profiles_dir.join("rust.nix").exists());
 }
 
@@ -1372,107 +3010,564 @@ fn test_lock_show_displays_locked_inputs() {
     .unwrap();
 
     flk_cmd()
-        .current_dir(temp_dir.path())
-        .args(["lock", "show"])
+        .current_dir(temp_dir.path())
+        .args(["lock", "show"])
+        .assert()
+        .success()
+        .stdout(contains("Flake Lock File Information"))
+        .stdout(contains("Lock Version:"))
+        .stdout(contains("flake-utils"))
+        .stdout(contains("nixpkgs"))
+        .stdout(contains("1234567890ab"))
+        .stdout(contains("abcdef123456"));
+}
+
+#[test]
+fn test_lock_history_lists_available_backups() {
+    let temp_dir = TempDir::new().unwrap();
+    let backup_dir = temp_dir.path().join(".flk/backups");
+    fs::create_dir_all(&backup_dir).unwrap();
+    fs::write(backup_dir.join("flake.lock.2025-01-27_14-30-00"), "old").unwrap();
+    fs::write(backup_dir.join("flake.lock.latest-manual"), "new").unwrap();
+
+    flk_cmd()
+        .current_dir(temp_dir.path())
+        .args(["lock", "history"])
+        .assert()
+        .success()
+        .stdout(contains("Lock File Backup History"))
+        .stdout(contains("2025-01-27_14-30-00"))
+        .stdout(contains("latest-manual"));
+}
+
+#[test]
+fn test_lock_restore_replaces_current_lock_file() {
+    let temp_dir = TempDir::new().unwrap();
+    let backup_dir = temp_dir.path().join(".flk/backups");
+    fs::create_dir_all(&backup_dir).unwrap();
+    fs::write(temp_dir.path().join("flake.lock"), "{\"version\":1}").unwrap();
+    fs::write(backup_dir.join("flake.lock.snapshot"), "{\"version\":2}").unwrap();
+
+    flk_cmd()
+        .current_dir(temp_dir.path())
+        .args(["lock", "restore", "snapshot"])
+        .assert()
+        .success()
+        .stdout(contains("Lock file restored successfully"));
+
+    assert_eq!(
+        fs::read_to_string(temp_dir.path().join("flake.lock")).unwrap(),
+        "{\"version\":2}"
+    );
+
+    let backup_count = fs::read_dir(&backup_dir)
+        .unwrap()
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| {
+            entry
+                .file_name()
+                .to_string_lossy()
+                .starts_with("flake.lock.")
+        })
+        .count();
+    assert!(backup_count >= 2);
+}
+
+// --- command.rs success & error paths ---
+
+#[test]
+fn test_command_add_list_remove() {
+    let temp_dir = TempDir::new().unwrap();
+    flk_cmd()
+        .current_dir(temp_dir.path())
+        .arg("init")
+        .assert()
+        .success();
+
+    flk_cmd()
+        .current_dir(temp_dir.path())
+        .args(["command", "add", "greet", "echo hello"])
+        .assert()
+        .success()
+        .stdout(contains("Command 'greet' added successfully"));
+
+    flk_cmd()
+        .current_dir(temp_dir.path())
+        .args(["command", "list"])
+        .assert()
+        .success()
+        .stdout(contains("greet"))
+        .stdout(contains("nix run .#generic-greet"));
+
+    flk_cmd()
+        .current_dir(temp_dir.path())
+        .args(["command", "remove", "greet"])
+        .assert()
+        .success()
+        .stdout(contains("Command 'greet' removed successfully"));
+
+    flk_cmd()
+        .current_dir(temp_dir.path())
+        .args(["command", "list"])
+        .assert()
+        .success()
+        .stdout(contains("No commands found"));
+}
+
+#[test]
+fn test_command_description_and_usage() {
+    let temp_dir = TempDir::new().unwrap();
+    let root = temp_dir.path();
+    flk_cmd().current_dir(root).arg("init").assert().success();
+
+    flk_cmd()
+        .current_dir(root)
+        .args([
+            "command",
+            "add",
+            "serve",
+            "--description",
+            "Serve the \"docs\"",
+            "--usage",
+            "[port]",
+            "mdbook serve -p $1",
+        ])
+        .assert()
+        .success();
+
+    let profile = fs::read_to_string(root.join(".flk/profiles/generic.nix")).unwrap();
+    assert!(profile.contains(r#"description = "Serve the \"docs\"";"#));
+    assert!(profile.contains(r#"usage = "[port]";"#));
+
+    flk_cmd()
+        .current_dir(root)
+        .args(["command", "list"])
+        .assert()
+        .success()
+        .stdout(contains("serve [port]"))
+        .stdout(contains("Serve the \"docs\""))
+        .stdout(contains("nix run .#generic-serve -- [port]"));
+
+    flk_cmd()
+        .current_dir(root)
+        .args(["command", "add", "bad", "--usage", "a\nb", "echo"])
+        .assert()
+        .failure()
+        .stderr(contains("single line"));
+}
+
+#[test]
+fn test_command_show_edit_rename_force() {
+    let temp_dir = TempDir::new().unwrap();
+    let root = temp_dir.path();
+    flk_cmd().current_dir(root).arg("init").assert().success();
+    let profile = root.join(".flk/profiles/generic.nix");
+
+    flk_cmd()
+        .current_dir(root)
+        .args(["command", "add", "-d", "Say hello", "greet", "echo hello"])
+        .assert()
+        .success();
+    flk_cmd()
+        .current_dir(root)
+        .args(["command", "add", "other", "echo other"])
+        .assert()
+        .success();
+    flk_cmd()
+        .current_dir(root)
+        .args(["command", "add", "greet", "echo hi"])
+        .assert()
+        .failure()
+        .stderr(contains("--force"));
+
+    // --force replaces the script in place and keeps the description
+    flk_cmd()
+        .current_dir(root)
+        .args(["command", "add", "--force", "greet", "echo hello world"])
+        .assert()
+        .success()
+        .stdout(contains("replaced"));
+    let content = fs::read_to_string(&profile).unwrap();
+    assert!(content.find("greet").unwrap() < content.find("other").unwrap());
+    assert!(content.contains(r#"description = "Say hello";"#));
+
+    flk_cmd()
+        .current_dir(root)
+        .args(["command", "show", "greet"])
+        .assert()
+        .success()
+        .stdout(contains("Say hello"))
+        .stdout(contains("echo hello world"));
+
+    flk_cmd()
+        .current_dir(root)
+        .args(["command", "edit", "greet"])
+        .env("VISUAL", "sed -i s/world/there/")
+        .assert()
+        .success()
+        .stdout(contains("updated successfully"));
+    assert!(fs::read_to_string(&profile)
+        .unwrap()
+        .contains("echo hello there"));
+    flk_cmd()
+        .current_dir(root)
+        .args(["command", "edit", "greet"])
+        .env("VISUAL", "true")
+        .assert()
+        .success()
+        .stdout(contains("was not changed"));
+    flk_cmd()
+        .current_dir(root)
+        .args(["command", "edit", "greet"])
+        .env("VISUAL", "false")
+        .assert()
+        .failure()
+        .stderr(contains("nothing was saved"));
+
+    flk_cmd()
+        .current_dir(root)
+        .args(["command", "rename", "greet", "other"])
+        .assert()
+        .failure()
+        .stderr(contains("already exists"));
+    flk_cmd()
+        .current_dir(root)
+        .args(["command", "rename", "greet", "hello"])
+        .assert()
+        .success();
+    flk_cmd()
+        .current_dir(root)
+        .args(["command", "show", "hello"])
+        .assert()
+        .success()
+        .stdout(contains("echo hello there"));
+    flk_cmd()
+        .current_dir(root)
+        .args(["command", "show", "greet"])
+        .assert()
+        .failure();
+}
+
+#[test]
+fn test_command_import_from_task_files() {
+    let temp_dir = TempDir::new().unwrap();
+    let root = temp_dir.path();
+
+    flk_cmd().current_dir(root).arg("init").assert().success();
+    fs::write(
+        root.join("package.json"),
+        r#"{ "scripts": { "dev": "vite", "test:unit": "vitest run", "lint": "eslint ." } }"#,
+    )
+    .unwrap();
+    fs::create_dir_all(root.join("docs")).unwrap();
+    fs::write(
+        root.join("docs/justfile"),
+        "# Serve the docs\nserve port='3000':\n\tmdbook serve --port {{port}}\n",
+    )
+    .unwrap();
+
+    flk_cmd()
+        .current_dir(root)
+        .args(["command", "add", "dev", "echo mine"])
+        .assert()
+        .success();
+
+    flk_cmd()
+        .current_dir(root)
+        .args([
+            "command",
+            "import",
+            "--from",
+            "package.json",
+            "--only",
+            "dev,test*,nope",
+        ])
+        .assert()
+        .success()
+        .stdout(contains("Skipping 'dev': command already exists"))
+        .stdout(contains("No task matches 'nope'"))
+        .stdout(contains("Imported 1 command(s), 1 skipped"));
+
+    flk_cmd()
+        .current_dir(root)
+        .args(["command", "import", "--from", "docs/justfile"])
+        .assert()
+        .success();
+
+    let profile = fs::read_to_string(root.join(".flk/profiles/generic.nix")).unwrap();
+    assert!(profile.contains("name = \"test-unit\";"));
+    assert!(profile.contains("description = \"vitest run\";"));
+    assert!(profile.contains("npm run test:unit -- \"$@\""));
+    assert!(profile.contains("echo mine"));
+    assert!(!profile.contains("name = \"lint\";"));
+    assert!(profile.contains("usage = \"[port]\";"));
+    // Outside git the directory is found from the absolute project path
+    let project = fs::canonicalize(root).unwrap();
+    assert!(profile.contains(&format!(
+        "(cd {}/docs && just serve \"$@\")",
+        project.display()
+    )));
+
+    // Inside git it is found from the repository root when the command runs
+    let git_init = std::process::Command::new("git")
+        .arg("init")
+        .arg("-q")
+        .current_dir(root)
+        .status();
+    if git_init.is_ok_and(|status| status.success()) {
+        flk_cmd()
+            .current_dir(root)
+            .args(["command", "remove", "serve"])
+            .assert()
+            .success();
+        flk_cmd()
+            .current_dir(root)
+            .args(["command", "import", "--from", "docs/justfile"])
+            .assert()
+            .success();
+        let profile = fs::read_to_string(root.join(".flk/profiles/generic.nix")).unwrap();
+        assert!(
+            profile.contains("(cd \"$(git rev-parse --show-toplevel)\"/docs && just serve \"$@\")")
+        );
+    }
+
+    // A quote in the path would end the profile's Nix string
+    fs::create_dir_all(root.join("it's")).unwrap();
+    fs::write(root.join("it's/justfile"), "build:\n\techo build\n").unwrap();
+    let before = fs::read_to_string(root.join(".flk/profiles/generic.nix")).unwrap();
+    flk_cmd()
+        .current_dir(root)
+        .args(["command", "import", "--from", "it's/justfile"])
+        .assert()
+        .failure()
+        .stderr(contains("paths containing ' or ${ cannot be written"));
+    assert_eq!(
+        fs::read_to_string(root.join(".flk/profiles/generic.nix")).unwrap(),
+        before
+    );
+
+    flk_cmd()
+        .current_dir(root)
+        .args(["command", "import", "--from", "README.md"])
+        .assert()
+        .failure()
+        .stderr(contains("Cannot tell what kind of task file"));
+}
+
+#[cfg(unix)]
+#[test]
+fn test_command_deps() {
+    let temp_dir = TempDir::new().unwrap();
+    let root = temp_dir.path();
+    let fake_bin_dir = root.join("bin");
+    let fake_nix_path = fake_bin_dir.join("nix");
+
+    fs::create_dir_all(&fake_bin_dir).unwrap();
+    fs::write(
+        &fake_nix_path,
+        "#!/bin/sh\nwhile [ \"$1\" != \"-c\" ]; do shift; done\nshift\nexec \"$@\"\n",
+    )
+    .unwrap();
+    make_executable(&fake_nix_path);
+
+    flk_cmd().current_dir(root).arg("init").assert().success();
+    for args in [
+        vec!["command", "add", "setup", "echo setup"],
+        vec!["command", "add", "--dep", "setup", "lint", "echo lint"],
+        vec![
+            "command",
+            "add",
+            "--dep",
+            "setup",
+            "test",
+            "echo \"test $1\"",
+        ],
+        vec![
+            "command",
+            "add",
+            "--dep",
+            "lint,test",
+            "ci",
+            "echo \"ci $1\"",
+        ],
+    ] {
+        flk_cmd().current_dir(root).args(args).assert().success();
+    }
+
+    let profile = fs::read_to_string(root.join(".flk/profiles/generic.nix")).unwrap();
+    assert!(profile.contains("deps = [ \"lint\" \"test\" ];"));
+
+    flk_cmd()
+        .current_dir(root)
+        .args(["command", "list", "--tree"])
+        .assert()
+        .success()
+        .stdout(contains(
+            "ci\n├── lint\n│   └── setup\n└── test\n    └── setup\n",
+        ));
+
+    flk_cmd()
+        .current_dir(root)
+        .args(["command", "add", "--dep", "format", "fmt", "echo fmt"])
+        .assert()
+        .failure()
+        .stderr(contains("'fmt' depends on 'format', which does not exist"));
+    flk_cmd()
+        .current_dir(root)
+        .args([
+            "command",
+            "add",
+            "--force",
+            "--dep",
+            "ci",
+            "setup",
+            "echo setup",
+        ])
+        .assert()
+        .failure()
+        .stderr(contains("cycle: setup -> ci -> lint -> setup"));
+
+    // Dependencies run first, each once, and only the command gets the arguments
+    flk_cmd()
+        .current_dir(root)
+        .env("PATH", prepend_path(&fake_bin_dir))
+        .args(["run", "ci", "--", "now"])
         .assert()
         .success()
-        .stdout(contains("Flake Lock File Information"))
-        .stdout(contains("Lock Version:"))
-        .stdout(contains("flake-utils"))
-        .stdout(contains("nixpkgs"))
-        .stdout(contains("1234567890ab"))
-        .stdout(contains("abcdef123456"));
-}
+        .stdout("setup\nlint\ntest \nci now\n");
 
-#[test]
-fn test_lock_history_lists_available_backups() {
-    let temp_dir = TempDir::new().unwrap();
-    let backup_dir = temp_dir.path().join(".flk/backups");
-    fs::create_dir_all(&backup_dir).unwrap();
-    fs::write(backup_dir.join("flake.lock.2025-01-27_14-30-00"), "old").unwrap();
-    fs::write(backup_dir.join("flake.lock.latest-manual"), "new").unwrap();
+    flk_cmd()
+        .current_dir(root)
+        .args(["command", "rename", "lint", "check"])
+        .assert()
+        .success()
+        .stdout(contains("Updated the deps of: ci"));
+    let profile = fs::read_to_string(root.join(".flk/profiles/generic.nix")).unwrap();
+    assert!(profile.contains("deps = [ \"check\" \"test\" ];"));
 
     flk_cmd()
-        .current_dir(temp_dir.path())
-        .args(["lock", "history"])
+        .current_dir(root)
+        .args(["command", "remove", "setup"])
         .assert()
         .success()
-        .stdout(contains("Lock File Backup History"))
-        .stdout(contains("2025-01-27_14-30-00"))
-        .stdout(contains("latest-manual"));
+        .stdout(contains("check, test still list 'setup' in their deps"));
+    flk_cmd()
+        .current_dir(root)
+        .arg("check")
+        .assert()
+        .failure()
+        .stdout(contains("'check' depends on 'setup', which does not exist"));
 }
 
 #[test]
-fn test_lock_restore_replaces_current_lock_file() {
+fn test_service_lifecycle() {
     let temp_dir = TempDir::new().unwrap();
-    let backup_dir = temp_dir.path().join(".flk/backups");
-    fs::create_dir_all(&backup_dir).unwrap();
-    fs::write(temp_dir.path().join("flake.lock"), "{\"version\":1}").unwrap();
-    fs::write(backup_dir.join("flake.lock.snapshot"), "{\"version\":2}").unwrap();
+    let root = temp_dir.path();
+    let fake_bin_dir = root.join("bin");
+    let fake_nix_path = fake_bin_dir.join("nix");
+
+    fs::create_dir_all(&fake_bin_dir).unwrap();
+    fs::write(
+        &fake_nix_path,
+        "#!/bin/sh\nwhile [ \"$1\" != \"-c\" ]; do shift; done\nshift\nexec \"$@\"\n",
+    )
+    .unwrap();
+    make_executable(&fake_nix_path);
 
+    flk_cmd().current_dir(root).arg("init").assert().success();
     flk_cmd()
-        .current_dir(temp_dir.path())
-        .args(["lock", "restore", "snapshot"])
+        .current_dir(root)
+        .args(["service", "add", "postgres", "--version", "16"])
         .assert()
-        .success()
-        .stdout(contains("Lock file restored successfully"));
+        .success();
+    flk_cmd()
+        .current_dir(root)
+        .args(["service", "add", "worker", "--port", "8123"])
+        .args(["--command", "echo \"worker on $PORT\"; exec sleep 30"])
+        .assert()
+        .success();
+    flk_cmd()
+        .current_dir(root)
+        .args(["service", "add", "nginx"])
+        .assert()
+        .failure()
+        .stderr(contains("--command"));
+    let before = fs::read_to_string(root.join(".flk/profiles/generic.nix")).unwrap();
+    flk_cmd()
+        .current_dir(root)
+        .args(["service", "add", "queue", "--package", "foo bar"])
+        .args(["--command", "sleep 100"])
+        .assert()
+        .failure()
+        .stderr(contains("Invalid package 'foo bar'"));
 
-    assert_eq!(
-        fs::read_to_string(temp_dir.path().join("flake.lock")).unwrap(),
-        "{\"version\":2}"
-    );
+    let profile = fs::read_to_string(root.join(".flk/profiles/generic.nix")).unwrap();
+    assert_eq!(profile, before);
+    assert!(profile.contains("postgres = { package = pkgs.postgresql_16; port = 5432; };"));
+    assert!(profile.contains("worker = { port = 8123; command = "));
+    let default_nix = fs::read_to_string(root.join(".flk/default.nix")).unwrap();
+    assert!(default_nix.contains("removeAttrs profile [\"services\"]"));
 
-    let backup_count = fs::read_dir(&backup_dir)
+    flk_cmd()
+        .current_dir(root)
+        .env("PATH", prepend_path(&fake_bin_dir))
+        .args(["service", "up", "worker"])
+        .assert()
+        .success()
+        .stdout(contains("Started worker"));
+    let state = root.join(".flk/state/generic/worker");
+    assert!(state.join("pid").exists());
+    let gitignore = fs::read_to_string(root.join(".gitignore")).unwrap();
+    assert_eq!(gitignore.matches(".flk/state/").count(), 1);
+    assert!(fs::read_to_string(state.join("run.sh"))
         .unwrap()
-        .filter_map(|entry| entry.ok())
-        .filter(|entry| {
-            entry
-                .file_name()
-                .to_string_lossy()
-                .starts_with("flake.lock.")
-        })
-        .count();
-    assert!(backup_count >= 2);
-}
+        .contains("exec sleep 30"));
 
-// --- command.rs success & error paths ---
-
-#[test]
-fn test_command_add_list_remove() {
-    let temp_dir = TempDir::new().unwrap();
     flk_cmd()
-        .current_dir(temp_dir.path())
-        .arg("init")
+        .current_dir(root)
+        .args(["service", "status"])
         .assert()
-        .success();
-
+        .success()
+        .stdout(contains("running"))
+        .stdout(contains("stopped"));
     flk_cmd()
-        .current_dir(temp_dir.path())
-        .args(["command", "add", "greet", "echo hello"])
+        .current_dir(root)
+        .args(["service", "logs", "worker"])
         .assert()
         .success()
-        .stdout(contains("Command 'greet' added successfully"));
+        .stdout(contains("worker on 8123"));
 
     flk_cmd()
-        .current_dir(temp_dir.path())
-        .args(["command", "list"])
+        .current_dir(root)
+        .args(["service", "down", "worker"])
         .assert()
         .success()
-        .stdout(contains("greet"));
+        .stdout(contains("Stopped worker"));
+    assert!(!state.join("pid").exists());
 
+    // A service that exits right away makes up fail
     flk_cmd()
-        .current_dir(temp_dir.path())
-        .args(["command", "remove", "greet"])
+        .current_dir(root)
+        .args(["service", "add", "broken", "--command", "exit 3"])
         .assert()
-        .success()
-        .stdout(contains("Command 'greet' removed successfully"));
+        .success();
+    flk_cmd()
+        .current_dir(root)
+        .env("PATH", prepend_path(&fake_bin_dir))
+        .args(["service", "up", "broken"])
+        .assert()
+        .failure()
+        .stdout(contains("exited right away"));
+    let gitignore = fs::read_to_string(root.join(".gitignore")).unwrap();
+    assert_eq!(gitignore.matches(".flk/state/").count(), 1);
 
     flk_cmd()
-        .current_dir(temp_dir.path())
-        .args(["command", "list"])
+        .current_dir(root)
+        .args(["service", "remove", "postgres"])
         .assert()
-        .success()
-        .stdout(contains("No commands found"));
+        .success();
+    let profile = fs::read_to_string(root.join(".flk/profiles/generic.nix")).unwrap();
+    assert!(!profile.contains("postgres"));
 }
 
 #[test]
@@ -1581,6 +3676,333 @@ fn test_env_add_and_remove() {
         .stdout(predicate::str::contains("MY_VAR").not());
 }
 
+#[test]
+fn test_env_secrets() {
+    let temp_dir = TempDir::new().unwrap();
+    let root = temp_dir.path();
+    flk_cmd().current_dir(root).arg("init").assert().success();
+    fs::create_dir_all(root.join(".secrets")).unwrap();
+    fs::write(root.join(".secrets/token"), "s3cr3t\n").unwrap();
+
+    flk_cmd()
+        .current_dir(root)
+        .args(["env", "add", "--secret", "API_TOKEN", "--from-file"])
+        .arg(".secrets/token")
+        .assert()
+        .success()
+        .stdout(contains("will be resolved when the shell starts"));
+    flk_cmd()
+        .current_dir(root)
+        .args([
+            "env",
+            "add",
+            "--secret",
+            "GH_TOKEN",
+            "--from-cmd",
+            "gh auth token",
+        ])
+        .assert()
+        .success();
+
+    flk_cmd()
+        .current_dir(root)
+        .args(["env", "add", "API_TOKEN", "plain"])
+        .assert()
+        .failure()
+        .stderr(contains("already exists"));
+    flk_cmd()
+        .current_dir(root)
+        .args(["env", "add", "OTHER", "--from-file", ".secrets/token"])
+        .assert()
+        .failure();
+    flk_cmd()
+        .current_dir(root)
+        .args(["env", "add", "--secret", "OTHER", "value"])
+        .assert()
+        .failure();
+    flk_cmd()
+        .current_dir(root)
+        .args([
+            "env",
+            "add",
+            "--secret",
+            "OTHER",
+            "--from-cmd",
+            "echo ${HOME}",
+        ])
+        .assert()
+        .failure()
+        .stderr(contains("cannot contain"));
+
+    // Only the reference is stored, and the hook line resolves it
+    let profile = fs::read_to_string(root.join(".flk/profiles/generic.nix")).unwrap();
+    assert!(!profile.contains("s3cr3t"));
+    let line = profile
+        .lines()
+        .find(|l| l.contains("export API_TOKEN="))
+        .unwrap();
+    let output = std::process::Command::new("sh")
+        .current_dir(root)
+        .arg("-c")
+        .arg(format!("{}\necho \"$API_TOKEN\"", line))
+        .output()
+        .unwrap();
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "s3cr3t\n");
+
+    flk_cmd()
+        .current_dir(root)
+        .args(["env", "list"])
+        .assert()
+        .success()
+        .stdout(contains("API_TOKEN = ********"))
+        .stdout(contains("secret from file .secrets/token"))
+        .stdout(contains("secret from command `gh auth token`"))
+        .stdout(contains("s3cr3t").not());
+    flk_cmd()
+        .current_dir(root)
+        .arg("show")
+        .assert()
+        .success()
+        .stdout(contains("Secrets:"))
+        .stdout(contains("GH_TOKEN = ********"));
+
+    flk_cmd()
+        .current_dir(root)
+        .args(["export", "--format", "json"])
+        .assert()
+        .success();
+    let json = fs::read_to_string(root.join("flake.json")).unwrap();
+    assert!(json.contains("\"file\": \".secrets/token\""));
+    assert!(!json.contains("s3cr3t"));
+
+    flk_cmd()
+        .current_dir(root)
+        .args(["env", "remove", "API_TOKEN"])
+        .assert()
+        .success();
+    flk_cmd()
+        .current_dir(root)
+        .args(["env", "list"])
+        .assert()
+        .success()
+        .stdout(contains("API_TOKEN").not())
+        .stdout(contains("GH_TOKEN"));
+}
+
+#[test]
+fn test_env_dotenv_import_export() {
+    let temp_dir = TempDir::new().unwrap();
+    let root = temp_dir.path();
+    flk_cmd().current_dir(root).arg("init").assert().success();
+
+    fs::write(
+        root.join(".env.example"),
+        "# Local settings\n\
+         export DATABASE_URL=postgres://localhost/app # dev database\n\
+         GREETING='Hello ${USER}, it\"s \"fine\"'\n\
+         CERT=\"line one\nline two\"\n",
+    )
+    .unwrap();
+
+    flk_cmd()
+        .current_dir(root)
+        .args(["env", "import", ".env.example"])
+        .assert()
+        .success()
+        .stdout(contains("3 added, 0 updated"));
+
+    let profile = fs::read_to_string(root.join(".flk/profiles/generic.nix")).unwrap();
+    assert!(profile.contains(r#"DATABASE_URL = "postgres://localhost/app";"#));
+    assert!(profile.contains(r#"GREETING = "Hello \${USER}, it\"s \"fine\"";"#));
+    assert!(profile.contains(r#"CERT = "line one\nline two";"#));
+
+    // Existing values are kept unless --overwrite is given
+    fs::write(root.join(".env"), "DATABASE_URL=postgres://db/app\nNEW=1\n").unwrap();
+    flk_cmd()
+        .current_dir(root)
+        .args(["env", "import", ".env"])
+        .assert()
+        .success()
+        .stdout(contains("--overwrite"))
+        .stdout(contains("1 added, 0 updated"));
+    flk_cmd()
+        .current_dir(root)
+        .args(["env", "import", ".env", "--overwrite"])
+        .assert()
+        .success()
+        .stdout(contains("0 added, 1 updated"));
+
+    fs::write(root.join("bad.env"), "OK=1\n2BAD=x\n").unwrap();
+    flk_cmd()
+        .current_dir(root)
+        .args(["env", "import", "bad.env"])
+        .assert()
+        .failure()
+        .stderr(contains("Line 2"));
+
+    // Nix interpolation cannot be exported as a plain value
+    let profile_path = root.join(".flk/profiles/generic.nix");
+    let profile = fs::read_to_string(&profile_path).unwrap();
+    fs::write(
+        &profile_path,
+        profile.replacen(
+            "DATABASE_URL =",
+            "OPENSSL_LIB = \"${pkgs.openssl.out}/lib\";\n    DATABASE_URL =",
+            1,
+        ),
+    )
+    .unwrap();
+
+    let output = flk_cmd()
+        .current_dir(root)
+        .args(["env", "export"])
+        .output()
+        .unwrap();
+    let dotenv = String::from_utf8(output.stdout).unwrap();
+    assert!(String::from_utf8(output.stderr)
+        .unwrap()
+        .contains("Variables using Nix interpolation are not exported: OPENSSL_LIB"));
+    assert!(!dotenv.contains("OPENSSL_LIB"));
+    assert!(dotenv.contains("DATABASE_URL=postgres://db/app\n"));
+    assert!(dotenv.contains("CERT=\"line one\\nline two\"\n"));
+    assert!(!dotenv.contains("OK="));
+
+    flk_cmd()
+        .current_dir(root)
+        .args(["env", "export", "--format", "json"])
+        .assert()
+        .success()
+        .stdout(contains(r#""GREETING": "Hello ${USER}, it\"s \"fine\"""#))
+        .stdout(contains("OPENSSL_LIB").not());
+
+    // Shell exports keep every value literal
+    let output = flk_cmd()
+        .current_dir(root)
+        .args(["env", "export", "--format", "shell"])
+        .output()
+        .unwrap();
+    let exports = String::from_utf8(output.stdout).unwrap();
+    let output = std::process::Command::new("sh")
+        .arg("-c")
+        .arg(format!("{}printf '%s' \"$GREETING\"", exports))
+        .output()
+        .unwrap();
+    assert_eq!(
+        String::from_utf8_lossy(&output.stdout),
+        "Hello ${USER}, it\"s \"fine\""
+    );
+}
+
+#[test]
+fn test_env_set_unset_rename() {
+    let temp_dir = TempDir::new().unwrap();
+    let root = temp_dir.path();
+    flk_cmd().current_dir(root).arg("init").assert().success();
+    flk_cmd()
+        .current_dir(root)
+        .args(["profile", "add", "dev", "--template", "base"])
+        .assert()
+        .success();
+    let generic = root.join(".flk/profiles/generic.nix");
+    let dev = root.join(".flk/profiles/dev.nix");
+
+    flk_cmd()
+        .current_dir(root)
+        .args(["env", "-p", "generic", "set", "MODE", "debug"])
+        .assert()
+        .success()
+        .stdout(contains("Added MODE = debug"));
+    flk_cmd()
+        .current_dir(root)
+        .args(["env", "-p", "generic", "set", "MODE", "release"])
+        .assert()
+        .success()
+        .stdout(contains("Updated MODE = release"));
+    let content = fs::read_to_string(&generic).unwrap();
+    assert!(content.contains(r#"MODE = "release";"#));
+    assert!(!content.contains("debug"));
+
+    flk_cmd()
+        .current_dir(root)
+        .args(["env", "--all-profiles", "set", "SHARED", "1"])
+        .assert()
+        .success();
+    flk_cmd()
+        .current_dir(root)
+        .args(["env", "--all-profiles", "rename", "SHARED", "COMMON"])
+        .assert()
+        .success()
+        .stdout(contains("in profile dev"))
+        .stdout(contains("in profile generic"));
+    for path in [&generic, &dev] {
+        let content = fs::read_to_string(path).unwrap();
+        assert!(content.contains(r#"COMMON = "1";"#));
+        assert!(!content.contains("SHARED"));
+    }
+
+    // A missing name aborts the whole change
+    flk_cmd()
+        .current_dir(root)
+        .args(["env", "-p", "generic", "unset", "MODE", "MISSING"])
+        .assert()
+        .failure()
+        .stderr(contains("nothing was removed"));
+    assert!(fs::read_to_string(&generic).unwrap().contains("MODE ="));
+
+    flk_cmd()
+        .current_dir(root)
+        .args(["env", "--all-profiles", "unset", "MODE", "COMMON"])
+        .assert()
+        .success();
+    for path in [&generic, &dev] {
+        let content = fs::read_to_string(path).unwrap();
+        assert!(!content.contains("MODE ="));
+        assert!(!content.contains("COMMON"));
+    }
+
+    // Secrets keep their source when renamed
+    flk_cmd()
+        .current_dir(root)
+        .args([
+            "env",
+            "-p",
+            "generic",
+            "add",
+            "--secret",
+            "TOKEN",
+            "--from-cmd",
+            "gh auth token",
+        ])
+        .assert()
+        .success();
+    flk_cmd()
+        .current_dir(root)
+        .args(["env", "-p", "generic", "rename", "TOKEN", "GH_TOKEN"])
+        .assert()
+        .success();
+    assert!(fs::read_to_string(&generic)
+        .unwrap()
+        .contains(r#"export GH_TOKEN="$(gh auth token)" # flk:secret"#));
+    flk_cmd()
+        .current_dir(root)
+        .args(["env", "-p", "generic", "set", "GH_TOKEN", "plain"])
+        .assert()
+        .failure()
+        .stderr(contains("is a secret"));
+
+    flk_cmd()
+        .current_dir(root)
+        .args(["env", "--all-profiles", "add", "X", "1"])
+        .assert()
+        .failure()
+        .stderr(contains("only applies to env set, unset and rename"));
+    flk_cmd()
+        .current_dir(root)
+        .args(["env", "--all-profiles", "--profile", "dev", "set", "X", "1"])
+        .assert()
+        .failure();
+}
+
 #[test]
 fn test_env_add_nonexistent_profile() {
     let temp_dir = TempDir::new().unwrap();
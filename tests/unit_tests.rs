@@ -161,6 +161,29 @@ pkgs = import nixpkgs {
         assert!(entry.script.contains("cargo build --release"));
     }
 
+    #[test]
+    fn test_set_entry_and_rename_command() {
+        use flk::flake::interfaces::shellhooks::ShellHookEntry;
+
+        let mut section = parse_shell_hook_section(PROFILE_CONTENT).unwrap();
+        let names: Vec<String> = section.entries.iter().map(|e| e.name.clone()).collect();
+
+        // Replacing keeps the command in its original position
+        assert!(section.set_entry(ShellHookEntry::new(&names[0], "echo replaced")));
+        assert_eq!(section.entries[0].script, "echo replaced");
+        assert!(!section.set_entry(ShellHookEntry::new("brand_new", "echo new")));
+        assert_eq!(section.entries.last().unwrap().name, "brand_new");
+
+        section.entries.last_mut().unwrap().deps = vec![names[0].clone()];
+        section.rename_command(&names[0], "renamed").unwrap();
+        assert_eq!(section.entries[0].name, "renamed");
+        assert_eq!(section.entries[0].script, "echo replaced");
+        assert_eq!(section.entries.last().unwrap().deps, vec!["renamed"]);
+        assert_eq!(section.dependents("renamed"), vec!["brand_new"]);
+        assert!(section.rename_command("renamed", "brand_new").is_err());
+        assert!(section.rename_command("missing", "other").is_err());
+    }
+
     #[test]
     fn test_remove_command() {
         let mut section = parse_shell_hook_section(PROFILE_CONTENT).unwrap();
@@ -200,7 +223,7 @@ pkgs = import nixpkgs {
         let section = parse_env_vars_section(PROFILE_CONTENT).unwrap();
         let result = section.add_env_var(PROFILE_CONTENT, "QUOTED", r#"value"with"quotes"#);
         assert!(result.contains("QUOTED"));
-        assert!(result.contains(r#"value"with"quotes"#));
+        assert!(result.contains(r#"QUOTED = "value\"with\"quotes";"#));
     }
 
     #[test]
@@ -499,10 +522,32 @@ mod generator_tests {
     }
 
     #[test]
-    fn test_unknown_template_defaults_to_generic() {
-        // Test that unknown templates fall back to generic
-        let flake = flake::generator::generate_flake("unknown").unwrap();
-        assert!(flake.contains("Generic Development Environment"));
+    fn test_generate_language_flakes() {
+        for (template, description) in [
+            ("java", "Java/Kotlin development environment"),
+            ("cpp", "C/C++ development environment"),
+            ("zig", "Zig development environment"),
+            ("haskell", "Haskell development environment"),
+            ("elixir", "Elixir development environment"),
+            ("ruby", "Ruby development environment"),
+            ("php", "PHP development environment"),
+            ("dotnet", ".NET development environment"),
+        ] {
+            let flake = flake::generator::generate_flake(template).unwrap();
+            assert!(flake.contains(description), "{}", template);
+            let profile = flk::flake::parsers::profile::parse_profile(template, &flake).unwrap();
+            assert!(!profile.packages.is_empty(), "{}", template);
+        }
+    }
+
+    #[test]
+    fn test_unknown_template_is_an_error() {
+        // Unknown templates no longer fall back to generic
+        let err = flake::generator::generate_flake("unknown")
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains("Unknown template 'unknown'"));
+        assert!(err.contains("rust"));
     }
 
     #[test]
@@ -539,7 +584,10 @@ mod generator_tests {
 
     #[test]
     fn test_all_templates_are_valid_nix() {
-        let templates = vec!["rust", "python", "node", "go", "generic"];
+        let templates = vec![
+            "rust", "python", "node", "go", "java", "cpp", "zig", "haskell", "elixir", "ruby",
+            "php", "dotnet", "generic",
+        ];
         for template in templates {
             let flake = flake::generator::generate_flake(template).unwrap();
             // Basic validation: contains key Nix syntax
@@ -550,7 +598,10 @@ mod generator_tests {
 
     #[test]
     fn test_all_templates_have_env_vars_section() {
-        let templates = vec!["rust", "python", "node", "go", "generic"];
+        let templates = vec![
+            "rust", "python", "node", "go", "java", "cpp", "zig", "haskell", "elixir", "ruby",
+            "php", "dotnet", "generic",
+        ];
         for template in templates {
             let flake = flake::generator::generate_flake(template).unwrap();
             assert!(flake.contains("envVars"));
@@ -559,7 +610,10 @@ mod generator_tests {
 
     #[test]
     fn test_all_templates_have_shell_hook() {
-        let templates = vec!["rust", "python", "node", "go", "generic"];
+        let templates = vec![
+            "rust", "python", "node", "go", "java", "cpp", "zig", "haskell", "elixir", "ruby",
+            "php", "dotnet", "generic",
+        ];
         for template in templates {
             let flake = flake::generator::generate_flake(template).unwrap();
             assert!(flake.contains("shellHook"));